@@ -0,0 +1,28 @@
+create table blocks (
+    height bigint primary key,
+    hash text not null unique check (hash ~ '^[a-fA-F0-9]{64}'),
+    prev_hash text not null check (prev_hash ~ '^[a-fA-F0-9]{64}'),
+    created_at timestamp not null default now()
+);
+
+create table watched_deposit_scripts (
+    script_pubkey text primary key,
+    created_at timestamp not null default now()
+);
+
+create table deposit_utxos (
+    outpoint text primary key,
+    script_pubkey text not null,
+    amount bigint not null,
+    block_hash text not null references blocks (hash) on delete cascade,
+    created_at timestamp not null default now()
+);
+
+create table bridge_utxos (
+    outpoint text primary key,
+    amount bigint not null,
+    block_hash text not null references blocks (hash) on delete cascade,
+    spending_txid text check (spending_txid ~ '^[a-fA-F0-9]{64}'),
+    spending_block_hash text references blocks (hash),
+    created_at timestamp not null default now()
+);
@@ -0,0 +1,49 @@
+create table new_deposit_requests (
+    id serial primary key, 
+    start_utxo text not null unique,
+    recovery_taproot_address text not null,
+    evm_address text not null check (evm_address ~ '^[a-fA-F0-9]{40}'),
+    amount bigint not null check (amount > 0),
+    created_at timestamp not null default now()
+);
+
+CREATE SEQUENCE start_from_zero MINVALUE 0 START 0;
+
+create table deposit_move_txs (
+    id INTEGER primary key default nextval('start_from_zero'),
+    start_utxo text not null,
+    recovery_taproot_address text not null,
+    evm_address text not null check (evm_address ~ '^[a-fA-F0-9]{40}'),
+    move_txid text not null check (move_txid ~ '^[a-fA-F0-9]{64}'),
+    move_vout INTEGER not null default 0,
+    created_at timestamp not null default now(),
+    unique (move_txid, move_vout)
+);
+
+create table deposit_lifecycle (
+    start_utxo text primary key,
+    recovery_taproot_address text not null,
+    evm_address text not null check (evm_address ~ '^[a-fA-F0-9]{40}'),
+    amount bigint not null check (amount > 0),
+    status text not null check (status in ('requested', 'presigns_collected', 'move_broadcast', 'move_confirmed', 'minted')),
+    move_tx text,
+    move_txid text check (move_txid ~ '^[a-fA-F0-9]{64}'),
+    deposit_block_hash text check (deposit_block_hash ~ '^[a-fA-F0-9]{64}'),
+    move_block_hash text check (move_block_hash ~ '^[a-fA-F0-9]{64}'),
+    reorged boolean not null default false,
+    created_at timestamp not null default now(),
+    updated_at timestamp not null default now()
+);
+
+create table withdrawal_txs (
+    idx INTEGER primary key,
+    bridge_fund_txid text not null check (bridge_fund_txid ~ '^[a-fA-F0-9]{64}'),
+    withdrawal_address text not null,
+    withdrawal_tx text not null,
+    withdrawal_txid text not null check (withdrawal_txid ~ '^[a-fA-F0-9]{64}'),
+    status text not null check (status in ('broadcast', 'confirmed')),
+    block_hash text check (block_hash ~ '^[a-fA-F0-9]{64}'),
+    reorged boolean not null default false,
+    created_at timestamp not null default now(),
+    updated_at timestamp not null default now()
+);
@@ -0,0 +1,13 @@
+create table withdrawal_sigs (
+    idx INTEGER primary key,
+    bridge_fund_txid text not
+     null check (bridge_fund_txid ~ '^[a-fA-F0-9]{64}'),
+    sig text not null check (sig ~ '^[a-fA-F0-9]{128}'),
+    created_at timestamp not null default now()
+);
+
+create table musig2_withdrawals (
+    idx INTEGER primary key,
+    bridge_fund_txid text not null check (bridge_fund_txid ~ '^[a-fA-F0-9]{64}'),
+    created_at timestamp not null default now()
+);
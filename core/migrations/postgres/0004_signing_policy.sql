@@ -0,0 +1,33 @@
+create table signing_policy (
+    id boolean primary key default true check (id),
+    daily_limit bigint check (daily_limit >= 0),
+    evm_address_daily_limit bigint check (evm_address_daily_limit >= 0),
+    max_outstanding_withdrawals INTEGER check (max_outstanding_withdrawals >= 0),
+    approval_threshold bigint check (approval_threshold >= 0),
+    updated_at timestamp not null default now()
+);
+
+create table policy_addresses (
+    list text not null check (list in ('allow', 'deny')),
+    kind text not null check (kind in ('evm_address', 'recovery_address')),
+    address text not null,
+    created_at timestamp not null default now(),
+    primary key (list, kind, address)
+);
+
+create table signed_deposits (
+    start_utxo text primary key,
+    evm_address text not null check (evm_address ~ '^[a-fA-F0-9]{40}'),
+    amount bigint not null check (amount > 0),
+    created_at timestamp not null default now()
+);
+
+create table deposit_approvals (
+    start_utxo text primary key,
+    recovery_taproot_address text not null,
+    evm_address text not null check (evm_address ~ '^[a-fA-F0-9]{40}'),
+    amount bigint not null check (amount > 0),
+    status text not null check (status in ('pending', 'approved', 'rejected')),
+    created_at timestamp not null default now(),
+    updated_at timestamp not null default now()
+);
@@ -0,0 +1,42 @@
+-- Brings the tables that scripts/schema.sql created to 0002_operator.
+
+-- Requests could be made more than once for the same deposit.
+delete from new_deposit_requests a using new_deposit_requests b
+    where a.start_utxo = b.start_utxo and a.id > b.id;
+alter table new_deposit_requests add unique (start_utxo);
+-- Every deposit was of 1 BTC, before deposit denominations.
+alter table new_deposit_requests add column amount bigint not null default 100000000 check (amount > 0);
+alter table new_deposit_requests alter column amount drop default;
+
+-- Move txs had a single deposit, which was moved to the first output.
+alter table deposit_move_txs drop constraint deposit_move_txs_move_txid_key;
+alter table deposit_move_txs add column move_vout INTEGER not null default 0;
+alter table deposit_move_txs add unique (move_txid, move_vout);
+
+create table deposit_lifecycle (
+    start_utxo text primary key,
+    recovery_taproot_address text not null,
+    evm_address text not null check (evm_address ~ '^[a-fA-F0-9]{40}'),
+    amount bigint not null check (amount > 0),
+    status text not null check (status in ('requested', 'presigns_collected', 'move_broadcast', 'move_confirmed', 'minted')),
+    move_tx text,
+    move_txid text check (move_txid ~ '^[a-fA-F0-9]{64}'),
+    deposit_block_hash text check (deposit_block_hash ~ '^[a-fA-F0-9]{64}'),
+    move_block_hash text check (move_block_hash ~ '^[a-fA-F0-9]{64}'),
+    reorged boolean not null default false,
+    created_at timestamp not null default now(),
+    updated_at timestamp not null default now()
+);
+
+create table withdrawal_txs (
+    idx INTEGER primary key,
+    bridge_fund_txid text not null check (bridge_fund_txid ~ '^[a-fA-F0-9]{64}'),
+    withdrawal_address text not null,
+    withdrawal_tx text not null,
+    withdrawal_txid text not null check (withdrawal_txid ~ '^[a-fA-F0-9]{64}'),
+    status text not null check (status in ('broadcast', 'confirmed')),
+    block_hash text check (block_hash ~ '^[a-fA-F0-9]{64}'),
+    reorged boolean not null default false,
+    created_at timestamp not null default now(),
+    updated_at timestamp not null default now()
+);
@@ -0,0 +1,7 @@
+-- Brings the tables that scripts/schema.sql created to 0003_verifier.
+
+create table musig2_withdrawals (
+    idx INTEGER primary key,
+    bridge_fund_txid text not null check (bridge_fund_txid ~ '^[a-fA-F0-9]{64}'),
+    created_at timestamp not null default now()
+);
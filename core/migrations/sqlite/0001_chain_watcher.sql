@@ -0,0 +1,28 @@
+create table blocks (
+    height integer primary key,
+    hash text not null unique check (length(hash) = 64 and hash not glob '*[^a-fA-F0-9]*'),
+    prev_hash text not null check (length(prev_hash) = 64 and prev_hash not glob '*[^a-fA-F0-9]*'),
+    created_at text not null default current_timestamp
+);
+
+create table watched_deposit_scripts (
+    script_pubkey text primary key,
+    created_at text not null default current_timestamp
+);
+
+create table deposit_utxos (
+    outpoint text primary key,
+    script_pubkey text not null,
+    amount integer not null,
+    block_hash text not null references blocks (hash) on delete cascade,
+    created_at text not null default current_timestamp
+);
+
+create table bridge_utxos (
+    outpoint text primary key,
+    amount integer not null,
+    block_hash text not null references blocks (hash) on delete cascade,
+    spending_txid text check (length(spending_txid) = 64 and spending_txid not glob '*[^a-fA-F0-9]*'),
+    spending_block_hash text references blocks (hash),
+    created_at text not null default current_timestamp
+);
@@ -0,0 +1,48 @@
+create table new_deposit_requests (
+    id integer primary key autoincrement,
+    start_utxo text not null unique,
+    recovery_taproot_address text not null,
+    evm_address text not null check (length(evm_address) = 40 and evm_address not glob '*[^a-fA-F0-9]*'),
+    amount integer not null check (amount > 0),
+    created_at text not null default current_timestamp
+);
+
+-- Ids start from zero, they are given by the insert query.
+create table deposit_move_txs (
+    id integer primary key check (id >= 0),
+    start_utxo text not null,
+    recovery_taproot_address text not null,
+    evm_address text not null check (length(evm_address) = 40 and evm_address not glob '*[^a-fA-F0-9]*'),
+    move_txid text not null check (length(move_txid) = 64 and move_txid not glob '*[^a-fA-F0-9]*'),
+    move_vout integer not null default 0,
+    created_at text not null default current_timestamp,
+    unique (move_txid, move_vout)
+);
+
+create table deposit_lifecycle (
+    start_utxo text primary key,
+    recovery_taproot_address text not null,
+    evm_address text not null check (length(evm_address) = 40 and evm_address not glob '*[^a-fA-F0-9]*'),
+    amount integer not null check (amount > 0),
+    status text not null check (status in ('requested', 'presigns_collected', 'move_broadcast', 'move_confirmed', 'minted')),
+    move_tx text,
+    move_txid text check (length(move_txid) = 64 and move_txid not glob '*[^a-fA-F0-9]*'),
+    deposit_block_hash text check (length(deposit_block_hash) = 64 and deposit_block_hash not glob '*[^a-fA-F0-9]*'),
+    move_block_hash text check (length(move_block_hash) = 64 and move_block_hash not glob '*[^a-fA-F0-9]*'),
+    reorged boolean not null default false,
+    created_at text not null default current_timestamp,
+    updated_at text not null default current_timestamp
+);
+
+create table withdrawal_txs (
+    idx integer primary key,
+    bridge_fund_txid text not null check (length(bridge_fund_txid) = 64 and bridge_fund_txid not glob '*[^a-fA-F0-9]*'),
+    withdrawal_address text not null,
+    withdrawal_tx text not null,
+    withdrawal_txid text not null check (length(withdrawal_txid) = 64 and withdrawal_txid not glob '*[^a-fA-F0-9]*'),
+    status text not null check (status in ('broadcast', 'confirmed')),
+    block_hash text check (length(block_hash) = 64 and block_hash not glob '*[^a-fA-F0-9]*'),
+    reorged boolean not null default false,
+    created_at text not null default current_timestamp,
+    updated_at text not null default current_timestamp
+);
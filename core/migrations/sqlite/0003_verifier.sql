@@ -0,0 +1,12 @@
+create table withdrawal_sigs (
+    idx integer primary key,
+    bridge_fund_txid text not null check (length(bridge_fund_txid) = 64 and bridge_fund_txid not glob '*[^a-fA-F0-9]*'),
+    sig text not null check (length(sig) = 128 and sig not glob '*[^a-fA-F0-9]*'),
+    created_at text not null default current_timestamp
+);
+
+create table musig2_withdrawals (
+    idx integer primary key,
+    bridge_fund_txid text not null check (length(bridge_fund_txid) = 64 and bridge_fund_txid not glob '*[^a-fA-F0-9]*'),
+    created_at text not null default current_timestamp
+);
@@ -0,0 +1,33 @@
+create table signing_policy (
+    id boolean primary key default true check (id),
+    daily_limit integer check (daily_limit >= 0),
+    evm_address_daily_limit integer check (evm_address_daily_limit >= 0),
+    max_outstanding_withdrawals integer check (max_outstanding_withdrawals >= 0),
+    approval_threshold integer check (approval_threshold >= 0),
+    updated_at text not null default current_timestamp
+);
+
+create table policy_addresses (
+    list text not null check (list in ('allow', 'deny')),
+    kind text not null check (kind in ('evm_address', 'recovery_address')),
+    address text not null,
+    created_at text not null default current_timestamp,
+    primary key (list, kind, address)
+);
+
+create table signed_deposits (
+    start_utxo text primary key,
+    evm_address text not null check (length(evm_address) = 40 and evm_address not glob '*[^a-fA-F0-9]*'),
+    amount integer not null check (amount > 0),
+    created_at text not null default current_timestamp
+);
+
+create table deposit_approvals (
+    start_utxo text primary key,
+    recovery_taproot_address text not null,
+    evm_address text not null check (length(evm_address) = 40 and evm_address not glob '*[^a-fA-F0-9]*'),
+    amount integer not null check (amount > 0),
+    status text not null check (status in ('pending', 'approved', 'rejected')),
+    created_at text not null default current_timestamp,
+    updated_at text not null default current_timestamp
+);
@@ -0,0 +1,6 @@
+create table withdrawal_ledger (
+    idx integer primary key,
+    bridge_utxo text not null unique,
+    withdrawal_address text not null,
+    created_at text not null default current_timestamp
+);
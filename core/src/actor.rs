@@ -1,63 +1,103 @@
+use crate::config::BridgeConfig;
 use crate::errors::BridgeError;
+use crate::musig2::{
+    KeyAggContext, MuSigAggNonce, MuSigPartialSignature, MuSigPubNonce, MuSigSecNonce,
+};
+use crate::signer::{self, InMemorySigner, Signer};
 use crate::transaction_builder::CreateTxOutputs;
 use crate::utils;
 use bitcoin::sighash::SighashCache;
 use bitcoin::taproot::LeafVersion;
 use bitcoin::{
     hashes::Hash,
-    secp256k1::{ecdsa, schnorr, Keypair, Message, SecretKey, XOnlyPublicKey},
+    secp256k1::{ecdsa, schnorr, SecretKey, XOnlyPublicKey},
     Address, TapSighash, TapTweakHash,
 };
 use bitcoin::{TapLeafHash, TapNodeHash, TapSighashType, TxOut};
+use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub struct Actor {
-    keypair: Keypair,
-    secret_key: SecretKey,
+    signer: Arc<dyn Signer>,
     pub xonly_public_key: XOnlyPublicKey,
     pub address: Address,
 }
 
 impl Actor {
+    /// Creates an actor that signs with `sk`, which is kept in memory.
     pub fn new(sk: SecretKey, network: bitcoin::Network) -> Self {
-        let keypair = Keypair::from_secret_key(&utils::SECP, &sk);
-        let (xonly, _parity) = XOnlyPublicKey::from_keypair(&keypair);
+        Self::with_signer(Arc::new(InMemorySigner::new(sk)), network)
+    }
+
+    pub fn with_signer(signer: Arc<dyn Signer>, network: bitcoin::Network) -> Self {
+        let xonly = signer.xonly_public_key();
         let address = Address::p2tr(&utils::SECP, xonly, None, network);
 
         Actor {
-            keypair,
-            secret_key: keypair.secret_key(),
+            signer,
             xonly_public_key: xonly,
             address,
         }
     }
 
+    /// Creates an actor with the signer that is selected in config. See
+    /// [`signer::from_config`].
+    pub fn from_config(config: &BridgeConfig) -> Result<Self, BridgeError> {
+        Ok(Self::with_signer(
+            signer::from_config(config)?,
+            config.network,
+        ))
+    }
+
     pub fn sign_with_tweak(
         &self,
         sighash: TapSighash,
         merkle_root: Option<TapNodeHash>,
     ) -> Result<schnorr::Signature, BridgeError> {
-        Ok(utils::SECP.sign_schnorr(
-            &Message::from_digest_slice(sighash.as_byte_array()).expect("should be hash"),
-            &self.keypair.add_xonly_tweak(
-                &utils::SECP,
-                &TapTweakHash::from_key_and_tweak(self.xonly_public_key, merkle_root).to_scalar(),
-            )?,
-        ))
+        self.signer.sign_schnorr(
+            sighash.to_byte_array(),
+            Some(TapTweakHash::from_key_and_tweak(
+                self.xonly_public_key,
+                merkle_root,
+            )),
+        )
     }
 
-    pub fn sign(&self, sighash: TapSighash) -> schnorr::Signature {
-        utils::SECP.sign_schnorr(
-            &Message::from_digest_slice(sighash.as_byte_array()).expect("should be hash"),
-            &self.keypair,
-        )
+    pub fn sign(&self, sighash: TapSighash) -> Result<schnorr::Signature, BridgeError> {
+        self.signer.sign_schnorr(sighash.to_byte_array(), None)
     }
 
-    pub fn sign_ecdsa(&self, data: [u8; 32]) -> ecdsa::Signature {
-        utils::SECP.sign_ecdsa(
-            &Message::from_digest_slice(&data).expect("should be hash"),
-            &self.secret_key,
-        )
+    /// Signs a 32 byte digest of a message that is not a transaction.
+    pub fn sign_digest(&self, digest: [u8; 32]) -> Result<schnorr::Signature, BridgeError> {
+        self.signer.sign_schnorr(digest, None)
+    }
+
+    /// Generates a MuSig2 nonce pair for signing `sighash` with the aggregated
+    /// key.
+    pub fn musig2_nonce(
+        &self,
+        key_agg_ctx: &KeyAggContext,
+        sighash: TapSighash,
+    ) -> Result<(MuSigSecNonce, MuSigPubNonce), BridgeError> {
+        self.signer
+            .musig2_nonce(key_agg_ctx, sighash.as_byte_array())
+    }
+
+    /// Creates a MuSig2 partial signature for `sighash`, using the secret nonce
+    /// generated by [`Actor::musig2_nonce`].
+    pub fn musig2_partial_sign(
+        &self,
+        key_agg_ctx: &KeyAggContext,
+        sec_nonce: MuSigSecNonce,
+        agg_nonce: &MuSigAggNonce,
+        sighash: TapSighash,
+    ) -> Result<MuSigPartialSignature, BridgeError> {
+        self.signer
+            .musig2_partial_sign(key_agg_ctx, sec_nonce, agg_nonce, sighash.as_byte_array())
+    }
+
+    pub fn sign_ecdsa(&self, data: [u8; 32]) -> Result<ecdsa::Signature, BridgeError> {
+        self.signer.sign_ecdsa(data)
     }
 
     pub fn sign_taproot_script_spend_tx(
@@ -74,7 +114,7 @@ impl Actor {
             TapLeafHash::from_script(spend_script, LeafVersion::TapScript),
             bitcoin::sighash::TapSighashType::Default,
         )?;
-        Ok(self.sign(sig_hash))
+        self.sign(sig_hash)
     }
 
     pub fn sighash_taproot_script_spend(
@@ -117,7 +157,7 @@ impl Actor {
             ),
             bitcoin::sighash::TapSighashType::Default,
         )?;
-        Ok(self.sign(sig_hash))
+        self.sign(sig_hash)
     }
 
     pub fn sign_taproot_pubkey_spend_tx(
@@ -171,6 +211,6 @@ impl Actor {
             ),
             bitcoin::sighash::TapSighashType::Default,
         )?;
-        Ok(self.sign_with_tweak(sig_hash, None).unwrap())
+        self.sign_with_tweak(sig_hash, None)
     }
 }
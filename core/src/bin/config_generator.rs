@@ -48,7 +48,7 @@ fn main() {
 
     for i in 0..num_verifiers {
         let mut new_config = BridgeConfig {
-            secret_key: secret_keys[i],
+            secret_key: Some(secret_keys[i]),
             verifiers_public_keys: public_keys.clone(),
             num_verifiers,
             port: ports[i],
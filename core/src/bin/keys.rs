@@ -0,0 +1,223 @@
+//! Manages encrypted keystores of operator and verifiers and serves them as
+//! remote signers. Keystore password is read from the
+//! `CLEMENTINE_KEYSTORE_PASSWORD` environment variable or a password file.
+
+use clap::{Parser, Subcommand};
+use clementine_core::config::BridgeConfig;
+use clementine_core::errors::BridgeError;
+use clementine_core::signer::{self, InMemorySigner, Keystore, Signer, KEYSTORE_PASSWORD_ENV};
+use clementine_core::utils::SECP;
+use secp256k1::SecretKey;
+use std::io::BufRead;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::exit;
+use std::str::FromStr;
+use std::sync::Arc;
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Cli {
+    /// File to read the keystore password from, instead of the
+    /// `CLEMENTINE_KEYSTORE_PASSWORD` environment variable
+    #[clap(long, value_parser, global = true)]
+    password_file: Option<PathBuf>,
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Generates a new key into a keystore
+    Generate {
+        /// Path of the new keystore
+        keystore: PathBuf,
+    },
+    /// Imports a hex secret key from stdin, or the `secret_key` of a
+    /// configuration file, into a keystore
+    Import {
+        /// Path of the new keystore
+        keystore: PathBuf,
+        /// TOML formatted configuration file to import the key of
+        #[clap(long, value_parser)]
+        config_file: Option<PathBuf>,
+    },
+    /// Prints the secret key of a keystore in hex
+    Export {
+        /// Path of the keystore
+        keystore: PathBuf,
+    },
+    /// Replaces the key of a keystore with a new one. Old keystore is kept
+    /// next to it, with its public key appended to its name
+    Rotate {
+        /// Path of the keystore
+        keystore: PathBuf,
+    },
+    /// Prints x-only public keys of keystores, in the order they are given,
+    /// as `verifiers_public_keys` of configuration files
+    PublicKeys {
+        /// Paths of the keystores, operator's keystore last
+        #[clap(required = true)]
+        keystores: Vec<PathBuf>,
+    },
+    /// Signs with a keystore for the operator or verifier that has
+    /// `remote_signer_socket` set to the socket, until it is stopped
+    Serve {
+        /// Path of the keystore
+        #[clap(long, value_parser)]
+        keystore: PathBuf,
+        /// Path of the Unix socket to listen on, which must not exist
+        #[clap(long, value_parser)]
+        socket: PathBuf,
+    },
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    if let Err(e) = run(cli) {
+        eprintln!("{}", e);
+        exit(1);
+    }
+}
+
+fn run(cli: Cli) -> Result<(), BridgeError> {
+    let password_file = cli.password_file;
+    let password = || read_password(password_file.as_deref());
+
+    match cli.command {
+        Command::Generate { keystore } => {
+            let secret_key = SecretKey::new(&mut secp256k1::rand::thread_rng());
+            create_keystore(&keystore, &secret_key, &password()?)
+        }
+        Command::Import {
+            keystore,
+            config_file,
+        } => {
+            let secret_key = match config_file {
+                Some(config_file) => BridgeConfig::try_parse_file(config_file)?
+                    .secret_key
+                    .ok_or(BridgeError::ConfigError(
+                        "Configuration file has no secret_key".to_string(),
+                    ))?,
+                None => {
+                    let mut line = String::new();
+                    std::io::stdin()
+                        .lock()
+                        .read_line(&mut line)
+                        .map_err(|e| BridgeError::SignerError(e.to_string()))?;
+
+                    SecretKey::from_str(line.trim())?
+                }
+            };
+
+            create_keystore(&keystore, &secret_key, &password()?)
+        }
+        Command::Export { keystore } => {
+            let secret_key = Keystore::read(&keystore)?.decrypt(&password()?)?;
+            println!("{}", hex::encode(secret_key.secret_bytes()));
+
+            Ok(())
+        }
+        Command::Rotate { keystore } => {
+            let password = password()?;
+            let old_keystore = Keystore::read(&keystore)?;
+            // Wrong passwords are noticed before anything is changed.
+            old_keystore.decrypt(&password)?;
+
+            let mut backup = keystore.clone().into_os_string();
+            backup.push(format!(".{}", old_keystore.xonly_public_key));
+            old_keystore.write(Path::new(&backup))?;
+            eprintln!("Old keystore is kept in {}", Path::new(&backup).display());
+
+            let secret_key = SecretKey::new(&mut secp256k1::rand::thread_rng());
+            Keystore::encrypt(&secret_key, &password)?.write(&keystore)?;
+            eprintln!(
+                "Update verifiers_public_keys of every operator and verifier configuration: {} is replaced with {}",
+                old_keystore.xonly_public_key,
+                secret_key.x_only_public_key(&SECP).0
+            );
+            println!("{}", secret_key.x_only_public_key(&SECP).0);
+
+            Ok(())
+        }
+        Command::PublicKeys { keystores } => {
+            let public_keys = keystores
+                .iter()
+                .map(|keystore| Ok(Keystore::read(keystore)?.xonly_public_key))
+                .collect::<Result<Vec<_>, BridgeError>>()?;
+
+            println!(
+                "verifiers_public_keys = {}",
+                toml::Value::from(
+                    public_keys
+                        .iter()
+                        .map(|pk| pk.to_string())
+                        .collect::<Vec<String>>()
+                )
+            );
+
+            Ok(())
+        }
+        Command::Serve { keystore, socket } => {
+            let signer = InMemorySigner::from_keystore(&keystore, &password()?)?;
+            serve(signer, &socket)
+        }
+    }
+}
+
+/// Answers remote signer requests on a new Unix socket at `path`, which only
+/// the owner can connect to.
+fn serve(signer: InMemorySigner, path: &Path) -> Result<(), BridgeError> {
+    let runtime = tokio::runtime::Runtime::new().map_err(BridgeError::ServerError)?;
+
+    runtime.block_on(async {
+        let listener = tokio::net::UnixListener::bind(path).map_err(BridgeError::ServerError)?;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+            .map_err(BridgeError::ServerError)?;
+        eprintln!(
+            "Signing with {} on {}",
+            signer.xonly_public_key(),
+            path.display()
+        );
+
+        signer::serve(listener, Arc::new(signer)).await
+    })
+}
+
+/// Writes a new keystore and prints its public key. Existing keystores are
+/// not overwritten.
+fn create_keystore(path: &Path, secret_key: &SecretKey, password: &str) -> Result<(), BridgeError> {
+    if path.exists() {
+        return Err(BridgeError::SignerError(format!(
+            "{} already exists",
+            path.display()
+        )));
+    }
+
+    let keystore = Keystore::encrypt(secret_key, password)?;
+    keystore.write(path)?;
+    println!("{}", keystore.xonly_public_key);
+
+    Ok(())
+}
+
+fn read_password(password_file: Option<&Path>) -> Result<String, BridgeError> {
+    match password_file {
+        Some(path) => std::fs::read_to_string(path)
+            .map(|password| password.trim_end_matches(['\r', '\n']).to_string())
+            .map_err(|e| {
+                BridgeError::SignerError(format!(
+                    "Can't read password file {}: {}",
+                    path.display(),
+                    e
+                ))
+            }),
+        None => std::env::var(KEYSTORE_PASSWORD_ENV).map_err(|_| {
+            BridgeError::ConfigError(format!(
+                "Set {} or give --password-file",
+                KEYSTORE_PASSWORD_ENV
+            ))
+        }),
+    }
+}
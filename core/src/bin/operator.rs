@@ -1,8 +1,15 @@
+use clementine_core::database::migrations::Role;
 use clementine_core::{cli, extended_rpc::ExtendedRpc, servers::create_operator_server};
+use std::process::exit;
 
 #[tokio::main]
 async fn main() {
-    let config = cli::get_configuration();
+    let (config, args) = cli::get_configuration_and_args();
+    if args.command == Some(cli::Command::Migrate) {
+        cli::migrate(config, &[Role::Operator]).await;
+        return;
+    }
+
     let verifier_endpoints = config.verifier_endpoints.clone().unwrap();
     let rpc = ExtendedRpc::<bitcoincore_rpc::Client>::new(
         config.bitcoin_rpc_url.clone(),
@@ -10,10 +17,11 @@ async fn main() {
         config.bitcoin_rpc_password.clone(),
     );
 
-    create_operator_server(config, rpc, verifier_endpoints)
-        .await
-        .unwrap()
-        .1
-        .stopped()
-        .await;
+    match create_operator_server(config, rpc, verifier_endpoints).await {
+        Ok((_, handle)) => handle.stopped().await,
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(1);
+        }
+    }
 }
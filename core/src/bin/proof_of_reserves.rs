@@ -0,0 +1,26 @@
+use clementine_core::{cli, extended_rpc::ExtendedRpc, proof_of_reserves};
+use std::process::exit;
+
+#[tokio::main]
+async fn main() {
+    let config = cli::get_configuration();
+    let rpc = ExtendedRpc::<bitcoincore_rpc::Client>::new(
+        config.bitcoin_rpc_url.clone(),
+        config.bitcoin_rpc_user.clone(),
+        config.bitcoin_rpc_password.clone(),
+    );
+
+    let attestation = proof_of_reserves::create_attestation(config, rpc)
+        .await
+        .unwrap();
+    println!("{}", serde_json::to_string_pretty(&attestation).unwrap());
+
+    if !attestation.report.is_balanced() {
+        eprintln!(
+            "Reserves are {} sats, but {} sats are expected",
+            attestation.report.total,
+            attestation.report.deposited - attestation.report.withdrawn
+        );
+        exit(1);
+    }
+}
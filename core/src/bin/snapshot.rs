@@ -0,0 +1,102 @@
+//! Exports the database of an operator or a verifier to a signed snapshot and
+//! imports it into a fresh database. Node should be stopped while its
+//! database is exported.
+
+use clap::{Parser, Subcommand, ValueEnum};
+use clementine_core::actor::Actor;
+use clementine_core::config::BridgeConfig;
+use clementine_core::database::common::Database;
+use clementine_core::database::migrations::Role;
+use clementine_core::database::snapshot::{self, Snapshot, SnapshotFormat};
+use clementine_core::errors::BridgeError;
+use std::path::PathBuf;
+use std::process::exit;
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Cli {
+    /// TOML formatted configuration file of the node
+    config_file: PathBuf,
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Exports the database to a snapshot, signed with the node key
+    Export {
+        /// Role of the node
+        #[clap(long, value_enum)]
+        role: RoleArg,
+        /// Path of the snapshot
+        output: PathBuf,
+        #[clap(long, value_enum, default_value = "json")]
+        format: FormatArg,
+    },
+    /// Imports a snapshot of this node into an empty database
+    Import {
+        /// Path of the snapshot, in JSON or CBOR
+        input: PathBuf,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum RoleArg {
+    Operator,
+    Verifier,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum FormatArg {
+    Json,
+    Cbor,
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+
+    if let Err(e) = run(cli).await {
+        eprintln!("{}", e);
+        exit(1);
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), BridgeError> {
+    let config = BridgeConfig::try_parse_file(cli.config_file)?;
+    let actor = Actor::from_config(&config)?;
+    let db = Database::new(config).await?;
+
+    match cli.command {
+        Command::Export {
+            role,
+            output,
+            format,
+        } => {
+            let role = match role {
+                RoleArg::Operator => Role::Operator,
+                RoleArg::Verifier => Role::Verifier,
+            };
+            let format = match format {
+                FormatArg::Json => SnapshotFormat::Json,
+                FormatArg::Cbor => SnapshotFormat::Cbor,
+            };
+
+            let snapshot = snapshot::export(&db, role, &actor).await?;
+            std::fs::write(&output, snapshot.encode(format)?)
+                .map_err(|e| BridgeError::InvalidSnapshot(e.to_string()))?;
+            eprintln!("Exported database to {}", output.display());
+        }
+        Command::Import { input } => {
+            let bytes =
+                std::fs::read(&input).map_err(|e| BridgeError::InvalidSnapshot(e.to_string()))?;
+            let snapshot = Snapshot::decode(&bytes)?;
+
+            snapshot::import(&db, &snapshot, &actor.xonly_public_key).await?;
+            eprintln!("Imported {}", input.display());
+        }
+    }
+    db.close().await;
+
+    Ok(())
+}
@@ -1,5 +1,4 @@
 use bitcoin::Address;
-use clementine_circuits::constants::BRIDGE_AMOUNT_SATS;
 use clementine_core::config::BridgeConfig;
 use clementine_core::transaction_builder::TransactionBuilder;
 use clementine_core::EVMAddress;
@@ -8,20 +7,28 @@ fn main() {
     let config = BridgeConfig::new();
 
     let secp = bitcoin::secp256k1::Secp256k1::new();
-    let (xonly_pk, _) = config.secret_key.public_key(&secp).x_only_public_key();
+    let (xonly_pk, _) = config
+        .secret_key
+        .unwrap()
+        .public_key(&secp)
+        .x_only_public_key();
     let address = Address::p2tr(&secp, xonly_pk, None, config.network);
-    let tx_builder = TransactionBuilder::new(config.verifiers_public_keys.clone(), config.network);
+    let tx_builder = TransactionBuilder::from_config(&config).unwrap();
     let evm_address: EVMAddress = EVMAddress([1u8; 20]);
-    let deposit_address = tx_builder
-        .generate_deposit_address(
-            address.as_unchecked(),
-            &evm_address,
-            BRIDGE_AMOUNT_SATS,
-            config.user_takes_after,
-        )
-        .unwrap();
 
     println!("EVM Address: {:?}", hex::encode(evm_address.0));
     println!("User: {:?}", xonly_pk.to_string());
-    println!("Deposit address: {:?}", deposit_address);
+
+    for amount in config.deposit_denominations.iter() {
+        let deposit_address = tx_builder
+            .generate_deposit_address(
+                address.as_unchecked(),
+                &evm_address,
+                *amount,
+                config.user_takes_after,
+            )
+            .unwrap();
+
+        println!("Deposit address for {} sats: {:?}", amount, deposit_address);
+    }
 }
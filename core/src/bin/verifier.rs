@@ -1,18 +1,26 @@
+use clementine_core::database::migrations::Role;
 use clementine_core::{cli, extended_rpc::ExtendedRpc, servers::create_verifier_server};
+use std::process::exit;
 
 #[tokio::main]
 async fn main() {
-    let config = cli::get_configuration();
+    let (config, args) = cli::get_configuration_and_args();
+    if args.command == Some(cli::Command::Migrate) {
+        cli::migrate(config, &[Role::Verifier]).await;
+        return;
+    }
+
     let rpc = ExtendedRpc::<bitcoincore_rpc::Client>::new(
         config.bitcoin_rpc_url.clone(),
         config.bitcoin_rpc_user.clone(),
         config.bitcoin_rpc_password.clone(),
     );
 
-    create_verifier_server(config, rpc)
-        .await
-        .unwrap()
-        .1
-        .stopped()
-        .await;
+    match create_verifier_server(config, rpc).await {
+        Ok((_, handle)) => handle.stopped().await,
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(1);
+        }
+    }
 }
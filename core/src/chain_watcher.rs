@@ -0,0 +1,267 @@
+//! # Chain Watcher
+//!
+//! Chain watcher follows the best chain block by block and indexes bridge
+//! related UTXOs into the database. Every change is published as a
+//! `ChainEvent`, so that operator and verifiers can act on them without
+//! polling Bitcoin themselves.
+//!
+//! Blocks that leave the best chain are rolled back from the index, newest
+//! first, before new blocks are indexed.
+
+use crate::config::BridgeConfig;
+use crate::database::common::{Database, IndexedBlock};
+use crate::errors::BridgeError;
+use crate::extended_rpc::ExtendedRpc;
+use crate::transaction_builder::TransactionBuilder;
+use bitcoin::{Amount, Block, BlockHash, OutPoint, ScriptBuf, Txid};
+use bitcoin_mock_rpc::RpcApiWrapper;
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+/// Interval for chain watcher to check for new blocks, in seconds.
+pub const CHAIN_WATCHER_POLL_INTERVAL_SECS: u64 = 5;
+
+/// Maximum number of events that a slow subscriber can lag behind.
+const CHAIN_EVENT_CAPACITY: usize = 1024;
+
+/// Events published by the chain watcher.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChainEvent {
+    /// A block is added to the best chain and indexed.
+    BlockConnected { height: u64, hash: BlockHash },
+    /// A block left the best chain and everything indexed from it is rolled
+    /// back.
+    BlockDisconnected { height: u64, hash: BlockHash },
+    /// Deposit address of a deposit request received funds.
+    DepositReceived {
+        outpoint: OutPoint,
+        amount: Amount,
+        block_hash: BlockHash,
+    },
+    /// Bridge address received funds, most likely by a move tx.
+    BridgeUtxoCreated {
+        outpoint: OutPoint,
+        amount: Amount,
+        block_hash: BlockHash,
+    },
+    /// A bridge UTXO is spent.
+    BridgeUtxoSpent {
+        outpoint: OutPoint,
+        spending_txid: Txid,
+        block_hash: BlockHash,
+    },
+}
+
+#[derive(Debug)]
+pub struct ChainWatcher<R>
+where
+    R: RpcApiWrapper,
+{
+    rpc: ExtendedRpc<R>,
+    db: Database,
+    bridge_script_pubkey: ScriptBuf,
+    /// Height to start indexing from, if nothing is indexed yet. Current tip
+    /// is used if not set.
+    start_height: Option<u64>,
+    events: broadcast::Sender<ChainEvent>,
+}
+
+impl<R> ChainWatcher<R>
+where
+    R: RpcApiWrapper,
+{
+    pub async fn new(config: BridgeConfig, rpc: ExtendedRpc<R>) -> Result<Self, BridgeError> {
+        let db = Database::new(config.clone()).await?;
+        let transaction_builder = TransactionBuilder::from_config(&config)?;
+        let (bridge_address, _) = transaction_builder.generate_bridge_address()?;
+        let (events, _) = broadcast::channel(CHAIN_EVENT_CAPACITY);
+
+        Ok(Self {
+            rpc,
+            db,
+            bridge_script_pubkey: bridge_address.script_pubkey(),
+            start_height: config.chain_watcher_start_height,
+            events,
+        })
+    }
+
+    /// Returns a new receiver for chain events. Only events published after
+    /// this call are received.
+    pub fn subscribe(&self) -> broadcast::Receiver<ChainEvent> {
+        self.events.subscribe()
+    }
+
+    /// Brings the index up to the current best chain: Rolls back blocks that
+    /// are not in the best chain anymore and indexes new blocks.
+    pub async fn sync(&self) -> Result<(), BridgeError> {
+        let tip = self.rpc.get_block_count()?;
+        let mut last_block = self.db.get_last_indexed_block().await?;
+
+        while let Some((height, hash)) = last_block {
+            if height <= tip && self.rpc.get_block_hash(height)? == hash {
+                break;
+            }
+
+            tracing::warn!("Block {} at height {} left the best chain", hash, height);
+            self.db.disconnect_block(hash).await?;
+            self.publish(ChainEvent::BlockDisconnected { height, hash });
+
+            last_block = self.db.get_last_indexed_block().await?;
+        }
+
+        let mut height = match last_block {
+            Some((height, _)) => height + 1,
+            None => self.start_height.unwrap_or(tip),
+        };
+        while height <= tip {
+            let hash = self.rpc.get_block_hash(height)?;
+            let block = self.rpc.get_block(&hash)?;
+
+            // Best chain changed while syncing. It will be handled in the next
+            // call.
+            if let Some((_, last_hash)) = last_block {
+                if block.header.prev_blockhash != last_hash {
+                    return Ok(());
+                }
+            }
+
+            self.connect_block(height, &block).await?;
+
+            last_block = Some((height, hash));
+            height += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Indexes a block and publishes everything found in it.
+    async fn connect_block(&self, height: u64, block: &Block) -> Result<(), BridgeError> {
+        let watched_deposit_scripts = self.db.get_watched_deposit_scripts().await?;
+        let mut bridge_utxos: HashSet<OutPoint> = self
+            .db
+            .get_unspent_bridge_utxos()
+            .await?
+            .into_iter()
+            .map(|(outpoint, _)| outpoint)
+            .collect();
+
+        let mut indexed_block = IndexedBlock {
+            height,
+            hash: block.block_hash(),
+            prev_hash: block.header.prev_blockhash,
+            deposit_utxos: Vec::new(),
+            bridge_utxos: Vec::new(),
+            spent_bridge_utxos: Vec::new(),
+        };
+
+        for tx in block.txdata.iter() {
+            let txid = tx.compute_txid();
+
+            for input in tx.input.iter() {
+                if bridge_utxos.remove(&input.previous_output) {
+                    indexed_block
+                        .spent_bridge_utxos
+                        .push((input.previous_output, txid));
+                }
+            }
+
+            for (vout, output) in tx.output.iter().enumerate() {
+                let outpoint = OutPoint {
+                    txid,
+                    vout: vout as u32,
+                };
+
+                if output.script_pubkey == self.bridge_script_pubkey {
+                    indexed_block.bridge_utxos.push((outpoint, output.value));
+                    // Can be spent in the same block.
+                    bridge_utxos.insert(outpoint);
+                } else if watched_deposit_scripts.contains(&output.script_pubkey) {
+                    indexed_block.deposit_utxos.push((
+                        outpoint,
+                        output.script_pubkey.clone(),
+                        output.value,
+                    ));
+                }
+            }
+        }
+
+        self.db.connect_block(&indexed_block).await?;
+
+        let block_hash = indexed_block.hash;
+        for (outpoint, _, amount) in indexed_block.deposit_utxos {
+            self.publish(ChainEvent::DepositReceived {
+                outpoint,
+                amount,
+                block_hash,
+            });
+        }
+        for (outpoint, amount) in indexed_block.bridge_utxos {
+            self.publish(ChainEvent::BridgeUtxoCreated {
+                outpoint,
+                amount,
+                block_hash,
+            });
+        }
+        for (outpoint, spending_txid) in indexed_block.spent_bridge_utxos {
+            self.publish(ChainEvent::BridgeUtxoSpent {
+                outpoint,
+                spending_txid,
+                block_hash,
+            });
+        }
+        self.publish(ChainEvent::BlockConnected {
+            height,
+            hash: block_hash,
+        });
+
+        Ok(())
+    }
+
+    fn publish(&self, event: ChainEvent) {
+        tracing::debug!("Chain event: {:?}", event);
+
+        // Sending only fails if there are no subscribers, which is fine.
+        let _ = self.events.send(event);
+    }
+
+    /// Syncs with the best chain forever, once in every `interval_secs`
+    /// seconds.
+    pub async fn run(self, interval_secs: u64) {
+        loop {
+            if let Err(e) = self.sync().await {
+                tracing::error!("Chain watcher failed to sync: {}", e);
+            }
+
+            tokio::time::sleep(Duration::from_secs(interval_secs)).await;
+        }
+    }
+}
+
+/// A change of the chain tip, as seen by a subscriber.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TipChange {
+    Connected(BlockHash),
+    Disconnected(BlockHash),
+    /// Subscriber lagged behind and missed some events, so any block might
+    /// have been connected or disconnected.
+    Unknown,
+}
+
+/// Waits until the chain tip changes. Returns `None` if chain watcher is gone
+/// and no more events will be received.
+pub async fn wait_for_tip_change(
+    events: &mut broadcast::Receiver<ChainEvent>,
+) -> Option<TipChange> {
+    loop {
+        match events.recv().await {
+            Ok(ChainEvent::BlockConnected { hash, .. }) => return Some(TipChange::Connected(hash)),
+            Ok(ChainEvent::BlockDisconnected { hash, .. }) => {
+                return Some(TipChange::Disconnected(hash))
+            }
+            Ok(_) => {}
+            Err(broadcast::error::RecvError::Lagged(_)) => return Some(TipChange::Unknown),
+            Err(broadcast::error::RecvError::Closed) => return None,
+        }
+    }
+}
@@ -4,8 +4,10 @@
 //! for easy generation of help messages and handling arguments.
 
 use crate::config::BridgeConfig;
+use crate::database::common::Database;
+use crate::database::migrations::Role;
 use crate::errors::BridgeError;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use std::env;
 use std::ffi::OsString;
 use std::path::PathBuf;
@@ -17,6 +19,14 @@ use std::process::exit;
 pub struct Args {
     /// TOML formatted configuration file.
     pub config_file: PathBuf,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Applies pending database migrations and exits.
+    Migrate,
 }
 
 /// Parse all the command line arguments and generate a `BridgeConfig`.
@@ -44,6 +54,11 @@ where
 /// Prints help + error messages and kills process on error. This will not panic
 /// intentionally, just to print a user friendly message and not a trace.
 pub fn get_configuration() -> BridgeConfig {
+    get_configuration_and_args().0
+}
+
+/// Same as [`get_configuration`], but also returns the parsed cli arguments.
+pub fn get_configuration_and_args() -> (BridgeConfig, Args) {
     let args = match parse() {
         Ok(c) => c,
         Err(e) => {
@@ -52,8 +67,36 @@ pub fn get_configuration() -> BridgeConfig {
         }
     };
 
-    match get_configuration_from(args) {
-        Ok(c) => c,
+    match get_configuration_from(args.clone()) {
+        Ok(c) => (c, args),
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(1);
+        }
+    }
+}
+
+/// Applies pending database migrations of `roles` and prints them.
+///
+/// # Exits
+///
+/// Prints the error message and kills process on error.
+pub async fn migrate(config: BridgeConfig, roles: &[Role]) {
+    let migrate = || async {
+        let database = Database::new(config).await?;
+        let applied = database.migrate(roles).await?;
+        database.close().await;
+
+        Ok::<_, BridgeError>(applied)
+    };
+
+    match migrate().await {
+        Ok(applied) if applied.is_empty() => println!("Database is up to date"),
+        Ok(applied) => {
+            for migration in applied {
+                println!("Applied {}", migration.name);
+            }
+        }
         Err(e) => {
             eprintln!("{}", e);
             exit(1);
@@ -72,7 +115,7 @@ pub fn get_configuration_from(args: Args) -> Result<BridgeConfig, BridgeError> {
 
 #[cfg(test)]
 mod tests {
-    use super::parse_from;
+    use super::{parse_from, Command};
     use crate::errors::BridgeError;
 
     /// With help message flag, we should see the help message. Shocking.
@@ -92,6 +135,15 @@ mod tests {
         }
     }
 
+    #[test]
+    fn migrate_command() {
+        let args = parse_from(vec!["clementine-core", "config.toml"]).unwrap();
+        assert_eq!(args.command, None);
+
+        let args = parse_from(vec!["clementine-core", "config.toml", "migrate"]).unwrap();
+        assert_eq!(args.command, Some(Command::Migrate));
+    }
+
     /// With version flag, we should see the program version read from
     /// `Cargo.toml`.
     #[test]
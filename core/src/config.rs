@@ -12,6 +12,7 @@
 
 use crate::errors::BridgeError;
 use bitcoin::{Network, XOnlyPublicKey};
+use clementine_circuits::constants::BRIDGE_AMOUNT_SATS;
 use secp256k1::SecretKey;
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
@@ -31,8 +32,10 @@ pub struct BridgeConfig {
     pub port: u16,
     /// Bitcoin network to work on.
     pub network: Network,
-    /// Secret key for the operator or the verifier.
-    pub secret_key: SecretKey,
+    /// Secret key for the operator or the verifier. Use `keystore_path` or
+    /// `remote_signer_socket` instead, to keep it out of the configuration
+    /// file.
+    pub secret_key: Option<SecretKey>,
     /// Verifiers public keys, including operator's.
     pub verifiers_public_keys: Vec<XOnlyPublicKey>,
     /// Number of verifiers.
@@ -53,6 +56,12 @@ pub struct BridgeConfig {
     pub all_secret_keys: Option<Vec<SecretKey>>,
     /// Verifier endpoints.
     pub verifier_endpoints: Option<Vec<String>>,
+    /// Storage backend of the operator or the verifier.
+    #[serde(default)]
+    pub db_backend: DatabaseBackend,
+    /// Directory of SQLite databases, which keeps the database in
+    /// `<db_name>.sqlite3`. Used if `db_backend` is `sqlite`.
+    pub db_dir: Option<PathBuf>,
     /// PostgreSQL database host address.
     pub db_host: String,
     /// PostgreSQL database port.
@@ -63,6 +72,94 @@ pub struct BridgeConfig {
     pub db_password: String,
     /// PostgreSQL database name.
     pub db_name: String,
+    /// Use a MuSig2 aggregated key for bridge and deposit scripts, instead of
+    /// N-of-N signatures.
+    #[serde(default)]
+    pub musig2: bool,
+    /// Apply pending database migrations on start. Operator and verifier
+    /// refuse to start with pending migrations if this is not set, until they
+    /// are applied with the `migrate` subcommand.
+    #[serde(default)]
+    pub db_auto_migrate: bool,
+    /// Block height for chain watcher to start indexing from, if database has
+    /// no blocks indexed. Current tip is used if not given.
+    pub chain_watcher_start_height: Option<u64>,
+    /// Confirmation target in blocks, for fee rate estimation.
+    pub fee_rate_conf_target: Option<u16>,
+    /// Fee rate in sat/vB to use if Bitcoin can't estimate one.
+    pub fallback_fee_rate: Option<u32>,
+    /// Maximum fee rate in sat/vB that can be paid while bumping fees.
+    pub max_fee_rate: Option<u32>,
+    /// Deposit amounts in sats that are accepted by the bridge. Every
+    /// operator and verifier must use the same set.
+    #[serde(default = "default_deposit_denominations")]
+    pub deposit_denominations: Vec<u64>,
+    /// Queue deposits and move them in a single tx once this many deposits
+    /// are queued. Deposits are moved one by one if neither this nor
+    /// `deposit_batch_interval_secs` is set.
+    pub deposit_batch_size: Option<usize>,
+    /// Move queued deposits in a single tx at least once in this many
+    /// seconds.
+    pub deposit_batch_interval_secs: Option<u64>,
+    /// JSON-RPC endpoint of the rollup node. Verifiers refuse to sign
+    /// withdrawals if it is not set.
+    pub rollup_rpc_url: Option<String>,
+    /// PEM certificate chain of the server. Server uses TLS if this and
+    /// `tls_key_path` are set.
+    pub tls_cert_path: Option<PathBuf>,
+    /// PEM private key of the server certificate.
+    pub tls_key_path: Option<PathBuf>,
+    /// PEM CA certificates that clients must present a certificate signed
+    /// by. Verifiers set this to accept only the operator.
+    pub tls_client_ca_path: Option<PathBuf>,
+    /// PEM CA certificates that servers' certificates are checked against.
+    /// System roots are used if not set.
+    pub tls_ca_path: Option<PathBuf>,
+    /// PEM client certificate chain, that operator presents to verifiers.
+    pub tls_client_cert_path: Option<PathBuf>,
+    /// PEM private key of the client certificate.
+    pub tls_client_key_path: Option<PathBuf>,
+    /// Timeout of operator's requests to a verifier, in seconds.
+    pub verifier_timeout_secs: Option<u64>,
+    /// Number of times a request to a verifier is retried after a timeout or
+    /// a connection failure.
+    pub verifier_retries: Option<u32>,
+    /// Delay before the first retry of a request to a verifier, in
+    /// milliseconds. It is doubled on every retry.
+    pub verifier_retry_backoff_ms: Option<u64>,
+    /// Time between operator's health checks of verifiers, in seconds.
+    pub verifier_health_check_interval_secs: Option<u64>,
+    /// Port of verifier's admin RPC server, which manages the signing
+    /// policy. It only listens on localhost and is not started if this is not
+    /// set.
+    pub admin_port: Option<u16>,
+    /// Port of the Prometheus metrics endpoint, on `host`. Metrics are not
+    /// served if it is not set.
+    pub metrics_port: Option<u16>,
+    /// Encrypted keystore file to sign with. Its password is read from the
+    /// `CLEMENTINE_KEYSTORE_PASSWORD` environment variable.
+    pub keystore_path: Option<PathBuf>,
+    /// Unix socket of a remote signer to sign with, so that the secret key
+    /// is kept out of this process.
+    pub remote_signer_socket: Option<PathBuf>,
+}
+
+/// Database that operator or verifier keeps its state in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DatabaseBackend {
+    /// PostgreSQL server at `db_host`.
+    #[default]
+    Postgres,
+    /// Embedded SQLite database in `db_dir`.
+    Sqlite,
+    /// Database in memory, which is lost when the process exits. Meant for
+    /// tests.
+    Memory,
+}
+
+fn default_deposit_denominations() -> Vec<u64> {
+    vec![BRIDGE_AMOUNT_SATS]
 }
 
 impl BridgeConfig {
@@ -127,7 +224,7 @@ impl Default for BridgeConfig {
             tracing_debug: "debug".to_string(),
             host: "127.0.0.1".to_string(),
             port: 3030,
-            secret_key: SecretKey::new(&mut secp256k1::rand::thread_rng()),
+            secret_key: Some(SecretKey::new(&mut secp256k1::rand::thread_rng())),
             verifiers_public_keys: vec![],
             num_verifiers: 4,
             min_relay_fee: 289,
@@ -139,11 +236,37 @@ impl Default for BridgeConfig {
             bitcoin_rpc_password: "admin".to_string(),
             all_secret_keys: None,
             verifier_endpoints: None,
+            db_backend: DatabaseBackend::Postgres,
+            db_dir: None,
             db_host: "127.0.0.1".to_string(),
             db_port: 5432,
             db_user: "postgres".to_string(),
             db_password: "postgres".to_string(),
             db_name: "postgres".to_string(),
+            musig2: false,
+            db_auto_migrate: false,
+            chain_watcher_start_height: None,
+            fee_rate_conf_target: None,
+            fallback_fee_rate: None,
+            max_fee_rate: None,
+            deposit_denominations: default_deposit_denominations(),
+            deposit_batch_size: None,
+            deposit_batch_interval_secs: None,
+            rollup_rpc_url: None,
+            tls_cert_path: None,
+            tls_key_path: None,
+            tls_client_ca_path: None,
+            tls_ca_path: None,
+            tls_client_cert_path: None,
+            tls_client_key_path: None,
+            verifier_timeout_secs: None,
+            verifier_retries: None,
+            verifier_retry_backoff_ms: None,
+            verifier_health_check_interval_secs: None,
+            admin_port: None,
+            metrics_port: None,
+            keystore_path: None,
+            remote_signer_socket: None,
         }
     }
 }
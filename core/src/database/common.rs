@@ -1,47 +1,315 @@
 //! # Common Database Operations
 //!
-//! Common database operations for both operator and verifier. This module
-//! directly talks with PostgreSQL. It is expected that PostgreSQL is properly
-//! installed and configured.
-
+//! Common database operations for both operator and verifier. Queries are
+//! run by the [`Storage`] backend that is selected in the configuration.
+
+use super::memory::MemoryStorage;
+use super::migrations::Role;
+use super::postgres::PostgresStorage;
+use super::sqlite::SqliteStorage;
+use crate::config::DatabaseBackend;
+use crate::policy::SigningPolicy;
+use crate::traits::storage::Storage;
 use crate::EVMAddress;
 use crate::{config::BridgeConfig, errors::BridgeError};
 use bitcoin::address::NetworkUnchecked;
-use bitcoin::{Address, OutPoint, Txid};
-use sqlx::{Pool, Postgres};
-use std::fs;
+use bitcoin::consensus::encode::deserialize_hex;
+use bitcoin::{Address, Amount, BlockHash, OutPoint, ScriptBuf, Transaction, Txid};
+use serde::{Deserialize, Serialize};
+use sqlx::pool::PoolConnection;
+use sqlx::Pool;
+use std::ops::{Deref, DerefMut};
 use std::str::FromStr;
+use std::sync::Arc;
+use tokio::sync::{Mutex, MutexGuard};
+
+/// Steps of a deposit, in the order operator goes through them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DepositStatus {
+    /// Deposit request is accepted but presigns are not collected yet.
+    Requested,
+    /// Move tx is signed by every verifier but not broadcasted yet.
+    PresignsCollected,
+    /// Move tx is broadcasted.
+    MoveBroadcast,
+    /// Move tx has enough confirmations.
+    MoveConfirmed,
+    /// Deposit is minted on the rollup.
+    Minted,
+}
 
+impl DepositStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DepositStatus::Requested => "requested",
+            DepositStatus::PresignsCollected => "presigns_collected",
+            DepositStatus::MoveBroadcast => "move_broadcast",
+            DepositStatus::MoveConfirmed => "move_confirmed",
+            DepositStatus::Minted => "minted",
+        }
+    }
+}
+
+impl FromStr for DepositStatus {
+    type Err = BridgeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "requested" => Ok(DepositStatus::Requested),
+            "presigns_collected" => Ok(DepositStatus::PresignsCollected),
+            "move_broadcast" => Ok(DepositStatus::MoveBroadcast),
+            "move_confirmed" => Ok(DepositStatus::MoveConfirmed),
+            "minted" => Ok(DepositStatus::Minted),
+            _ => Err(BridgeError::InvalidDepositStatus(s.to_string())),
+        }
+    }
+}
+
+/// A deposit's row in the lifecycle table.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DepositRecord {
+    pub start_utxo: OutPoint,
+    pub recovery_taproot_address: Address<NetworkUnchecked>,
+    pub evm_address: EVMAddress,
+    /// Deposited amount in sats, one of the deposit denominations.
+    pub amount: u64,
+    pub status: DepositStatus,
+    /// Fully signed move tx. Set after presigns are collected.
+    pub move_tx: Option<Transaction>,
+    /// Block that the deposit UTXO is confirmed in.
+    pub deposit_block_hash: Option<BlockHash>,
+    /// Block that the move tx is confirmed in.
+    pub move_block_hash: Option<BlockHash>,
+    /// Set if a block that this deposit depends on left the best chain.
+    pub reorged: bool,
+}
+
+/// A validated deposit request, waiting in the queue to be moved in a batch.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DepositRequest {
+    pub start_utxo: OutPoint,
+    pub recovery_taproot_address: Address<NetworkUnchecked>,
+    pub evm_address: EVMAddress,
+    /// Deposited amount in sats, one of the deposit denominations.
+    pub amount: u64,
+}
+
+/// A move tx of a deposit, with the deposit index it is saved at.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MoveTxRecord {
+    pub idx: usize,
+    pub start_utxo: OutPoint,
+    pub recovery_taproot_address: Address<NetworkUnchecked>,
+    pub evm_address: EVMAddress,
+    pub move_txid: Txid,
+    pub move_vout: u32,
+}
+
+/// Steps of a withdrawal, after it is signed and broadcasted by operator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WithdrawalStatus {
+    /// Withdrawal tx is broadcasted.
+    Broadcast,
+    /// Withdrawal tx has enough confirmations.
+    Confirmed,
+}
+
+impl WithdrawalStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WithdrawalStatus::Broadcast => "broadcast",
+            WithdrawalStatus::Confirmed => "confirmed",
+        }
+    }
+}
+
+impl FromStr for WithdrawalStatus {
+    type Err = BridgeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "broadcast" => Ok(WithdrawalStatus::Broadcast),
+            "confirmed" => Ok(WithdrawalStatus::Confirmed),
+            _ => Err(BridgeError::InvalidWithdrawalStatus(s.to_string())),
+        }
+    }
+}
+
+/// A withdrawal tx that is broadcasted by operator.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WithdrawalRecord {
+    pub idx: usize,
+    pub bridge_fund_txid: Txid,
+    pub withdrawal_address: Address<NetworkUnchecked>,
+    pub withdrawal_tx: Transaction,
+    pub status: WithdrawalStatus,
+    /// Block that the withdrawal tx is confirmed in.
+    pub block_hash: Option<BlockHash>,
+    /// Set if the block that this withdrawal was confirmed in left the best
+    /// chain.
+    pub reorged: bool,
+}
+
+/// A withdrawal in the verifier's ledger. Withdrawal index and bridge UTXO
+/// are bound to each other and to the withdrawal address once the withdrawal
+/// is signed, so that a bridge UTXO is never signed to be spent twice.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WithdrawalLedgerEntry {
+    pub idx: usize,
+    pub bridge_utxo: OutPoint,
+    pub withdrawal_address: Address<NetworkUnchecked>,
+}
+
+/// A block and the bridge related outputs and inputs found in it.
+#[derive(Clone, Debug)]
+pub struct IndexedBlock {
+    pub height: u64,
+    pub hash: BlockHash,
+    pub prev_hash: BlockHash,
+    /// Outputs paying to a watched deposit script.
+    pub deposit_utxos: Vec<(OutPoint, ScriptBuf, Amount)>,
+    /// Outputs paying to the bridge address.
+    pub bridge_utxos: Vec<(OutPoint, Amount)>,
+    /// Bridge UTXOs spent in this block, with their spending txid.
+    pub spent_bridge_utxos: Vec<(OutPoint, Txid)>,
+}
+
+pub(super) fn decode_error<E: std::error::Error + Send + Sync + 'static>(e: E) -> BridgeError {
+    BridgeError::DatabaseError(sqlx::Error::Decode(Box::new(e)))
+}
+
+fn decode_block_hash(block_hash: Option<String>) -> Result<Option<BlockHash>, BridgeError> {
+    match block_hash {
+        Some(block_hash) => Ok(Some(
+            BlockHash::from_str(&block_hash).map_err(decode_error)?,
+        )),
+        None => Ok(None),
+    }
+}
+
+pub(super) type DepositRecordRow = (
+    String,
+    String,
+    String,
+    String,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    bool,
+    i64,
+);
+
+pub(super) const DEPOSIT_RECORD_COLUMNS: &str = "start_utxo, recovery_taproot_address, evm_address, status, move_tx, deposit_block_hash, move_block_hash, reorged, amount";
+
+impl TryFrom<DepositRecordRow> for DepositRecord {
+    type Error = BridgeError;
+
+    fn try_from(row: DepositRecordRow) -> Result<Self, Self::Error> {
+        let start_utxo = OutPoint::from_str(&row.0).map_err(decode_error)?;
+        let recovery_taproot_address = Address::from_str(&row.1).map_err(decode_error)?;
+        let evm_address = serde_json::from_str(&format!("\"{}\"", row.2)).map_err(decode_error)?;
+        let status = DepositStatus::from_str(&row.3)?;
+        let move_tx = match row.4 {
+            Some(tx) => Some(deserialize_hex(&tx).map_err(decode_error)?),
+            None => None,
+        };
+
+        Ok(DepositRecord {
+            start_utxo,
+            recovery_taproot_address,
+            evm_address,
+            amount: row.8 as u64,
+            status,
+            move_tx,
+            deposit_block_hash: decode_block_hash(row.5)?,
+            move_block_hash: decode_block_hash(row.6)?,
+            reorged: row.7,
+        })
+    }
+}
+
+pub(super) type DepositRequestRow = (String, String, String, i64);
+
+impl TryFrom<DepositRequestRow> for DepositRequest {
+    type Error = BridgeError;
+
+    fn try_from(row: DepositRequestRow) -> Result<Self, Self::Error> {
+        Ok(DepositRequest {
+            start_utxo: OutPoint::from_str(&row.0).map_err(decode_error)?,
+            recovery_taproot_address: Address::from_str(&row.1).map_err(decode_error)?,
+            evm_address: serde_json::from_str(&format!("\"{}\"", row.2)).map_err(decode_error)?,
+            amount: row.3 as u64,
+        })
+    }
+}
+
+pub(super) type WithdrawalRecordRow = (i32, String, String, String, String, Option<String>, bool);
+
+pub(super) const WITHDRAWAL_RECORD_COLUMNS: &str =
+    "idx, bridge_fund_txid, withdrawal_address, withdrawal_tx, status, block_hash, reorged";
+
+impl TryFrom<WithdrawalRecordRow> for WithdrawalRecord {
+    type Error = BridgeError;
+
+    fn try_from(row: WithdrawalRecordRow) -> Result<Self, Self::Error> {
+        Ok(WithdrawalRecord {
+            idx: row.0 as usize,
+            bridge_fund_txid: Txid::from_str(&row.1).map_err(decode_error)?,
+            withdrawal_address: Address::from_str(&row.2).map_err(decode_error)?,
+            withdrawal_tx: deserialize_hex(&row.3).map_err(decode_error)?,
+            status: WithdrawalStatus::from_str(&row.4)?,
+            block_hash: decode_block_hash(row.5)?,
+            reorged: row.6,
+        })
+    }
+}
+
+pub(super) type WithdrawalLedgerRow = (i32, String, String);
+
+impl TryFrom<WithdrawalLedgerRow> for WithdrawalLedgerEntry {
+    type Error = BridgeError;
+
+    fn try_from(row: WithdrawalLedgerRow) -> Result<Self, Self::Error> {
+        Ok(WithdrawalLedgerEntry {
+            idx: row.0 as usize,
+            bridge_utxo: OutPoint::from_str(&row.1).map_err(decode_error)?,
+            withdrawal_address: Address::from_str(&row.2).map_err(decode_error)?,
+        })
+    }
+}
+
+pub(super) type SigningPolicyRow = (Option<i64>, Option<i64>, Option<i32>, Option<i64>);
+
+impl From<SigningPolicyRow> for SigningPolicy {
+    fn from(row: SigningPolicyRow) -> Self {
+        SigningPolicy {
+            daily_limit: row.0.map(|limit| limit as u64),
+            evm_address_daily_limit: row.1.map(|limit| limit as u64),
+            max_outstanding_withdrawals: row.2.map(|max| max as usize),
+            approval_threshold: row.3.map(|threshold| threshold as u64),
+        }
+    }
+}
+
+/// Storage of the operator or the verifier, with the backend in config.
 #[derive(Clone, Debug)]
 pub struct Database {
-    connection: Pool<Postgres>,
+    storage: Arc<dyn Storage>,
 }
 
 impl Database {
     /// Returns a `Database` after establishing a connection to database.
     /// Returns error if database is not available.
     pub async fn new(config: BridgeConfig) -> Result<Self, BridgeError> {
-        let url = "postgresql://".to_owned()
-            + config.db_host.as_str()
-            + ":"
-            + config.db_port.to_string().as_str()
-            + "?dbname="
-            + config.db_name.as_str()
-            + "&user="
-            + config.db_user.as_str()
-            + "&password="
-            + config.db_password.as_str();
-        tracing::debug!("Connecting database: {}", url);
-
-        match sqlx::PgPool::connect(url.as_str()).await {
-            Ok(c) => Ok(Self { connection: c }),
-            Err(e) => Err(BridgeError::DatabaseError(e)),
-        }
-    }
+        let storage: Arc<dyn Storage> = match config.db_backend {
+            DatabaseBackend::Postgres => Arc::new(PostgresStorage::new(&config).await?),
+            DatabaseBackend::Sqlite => Arc::new(SqliteStorage::new(&config).await?),
+            DatabaseBackend::Memory => Arc::new(MemoryStorage::new(&config)),
+        };
 
-    /// Closes database connection.
-    pub async fn close(&self) {
-        self.connection.close().await;
+        Ok(Self { storage })
     }
 
     /// Drops the given database if it exists.
@@ -49,20 +317,19 @@ impl Database {
         config: BridgeConfig,
         database_name: &str,
     ) -> Result<(), BridgeError> {
-        let url = "postgresql://".to_owned()
-            + config.db_user.as_str()
-            + ":"
-            + config.db_password.as_str()
-            + "@"
-            + config.db_host.as_str();
-        let conn = sqlx::PgPool::connect(url.as_str()).await?;
-
-        let query = format!("DROP DATABASE IF EXISTS {database_name}");
-        sqlx::query(&query).execute(&conn).await?;
-
-        conn.close().await;
-
-        Ok(())
+        match config.db_backend {
+            DatabaseBackend::Postgres => {
+                PostgresStorage::drop_database(&config, database_name).await
+            }
+            DatabaseBackend::Sqlite => {
+                SqliteStorage::drop_database(&SqliteStorage::database_path(&config, database_name)?)
+            }
+            DatabaseBackend::Memory => {
+                MemoryStorage::drop_database(database_name);
+
+                Ok(())
+            }
+        }
     }
 
     /// Creates a new database with given name. A new database connection should
@@ -76,180 +343,168 @@ impl Database {
         config: BridgeConfig,
         database_name: &str,
     ) -> Result<BridgeConfig, BridgeError> {
-        let url = "postgresql://".to_owned()
-            + config.db_user.as_str()
-            + ":"
-            + config.db_password.as_str()
-            + "@"
-            + config.db_host.as_str();
-        let conn = sqlx::PgPool::connect(url.as_str()).await?;
-
-        Database::drop_database(config.clone(), database_name).await?;
-
-        let query = format!(
-            "CREATE DATABASE {} WITH OWNER {}",
-            database_name, config.db_user
-        );
-        sqlx::query(&query).execute(&conn).await?;
-
-        conn.close().await;
+        match config.db_backend {
+            DatabaseBackend::Postgres => {
+                PostgresStorage::create_database(&config, database_name).await?
+            }
+            // SQLite and in-memory databases are created when they are
+            // opened.
+            DatabaseBackend::Sqlite | DatabaseBackend::Memory => {
+                Database::drop_database(config.clone(), database_name).await?
+            }
+        }
 
-        let config = BridgeConfig {
+        Ok(BridgeConfig {
             db_name: database_name.to_string(),
             ..config
-        };
-
-        Ok(config)
+        })
     }
 
-    /// Runs given SQL file to database. Database connection must be established
-    /// before calling this function.
-    pub async fn run_sql_file(&self, sql_file: &str) -> Result<(), BridgeError> {
-        let contents = fs::read_to_string(sql_file).unwrap();
-
-        sqlx::raw_sql(contents.as_str())
-            .execute(&self.connection)
-            .await?;
+    /// Applies pending migrations of `roles` if `auto_migrate` is set.
+    /// Otherwise, fails if there are any.
+    pub async fn check_migrations(
+        &self,
+        roles: &[Role],
+        auto_migrate: bool,
+    ) -> Result<(), BridgeError> {
+        if auto_migrate {
+            self.migrate(roles).await?;
 
-        Ok(())
-    }
+            return Ok(());
+        }
 
-    /// Starts a database transaction.
-    ///
-    /// Return value can be used for committing changes. If not committed,
-    /// database will rollback every operation done after that call.
-    pub async fn begin_transaction(
-        &self,
-    ) -> Result<sqlx::Transaction<'_, sqlx::Postgres>, BridgeError> {
-        match self.connection.begin().await {
-            Ok(t) => Ok(t),
-            Err(e) => Err(BridgeError::DatabaseError(e)),
+        let pending = self.get_pending_migrations(roles).await?;
+        if !pending.is_empty() {
+            return Err(BridgeError::MigrationError(format!(
+                "{} migrations are pending, run the migrate subcommand or set db_auto_migrate",
+                pending.len()
+            )));
         }
-    }
 
-    pub async fn add_new_deposit_request(
-        &self,
-        start_utxo: OutPoint,
-        recovery_taproot_address: Address<NetworkUnchecked>,
-        evm_address: EVMAddress,
-    ) -> Result<(), BridgeError> {
-        let start_utxo = start_utxo.to_string();
-        let recovery_taproot_address = serde_json::to_string(&recovery_taproot_address)
-            .unwrap()
-            .trim_matches('"')
-            .to_owned();
-        let evm_address = serde_json::to_string(&evm_address)
-            .unwrap()
-            .trim_matches('"')
-            .to_owned();
+        Ok(())
+    }
+}
 
-        sqlx::query("INSERT INTO new_deposit_requests (start_utxo, recovery_taproot_address, evm_address) VALUES ($1, $2, $3);")
-            .bind(start_utxo)
-            .bind(recovery_taproot_address)
-            .bind(evm_address)
-            .fetch_all(&self.connection)
-            .await?;
+impl Deref for Database {
+    type Target = dyn Storage;
 
-        Ok(())
+    fn deref(&self) -> &Self::Target {
+        self.storage.as_ref()
     }
+}
 
-    pub async fn get_deposit_tx(&self, idx: usize) -> Result<Txid, BridgeError> {
-        let qr: (String,) = sqlx::query_as("SELECT move_txid FROM deposit_move_txs WHERE id = $1;")
-            .bind(idx as i64)
-            .fetch_one(&self.connection)
-            .await?;
+/// Connection that the queries of a SQL backend run on.
+#[derive(Debug)]
+pub(super) enum SqlConnection<DB: sqlx::Database> {
+    /// Every query runs on a connection of the pool and is committed on its
+    /// own.
+    Pool(Pool<DB>),
+    /// Queries run in a transaction that is started with
+    /// [`Storage::begin_transaction`], one at a time.
+    Transaction(Mutex<sqlx::Transaction<'static, DB>>),
+}
 
-        match Txid::from_str(qr.0.as_str()) {
-            Ok(c) => Ok(c),
-            Err(e) => Err(BridgeError::DatabaseError(sqlx::Error::Decode(Box::new(e)))),
+impl<DB: sqlx::Database> SqlConnection<DB> {
+    /// Returns the connection that the next query runs on. Queries that use
+    /// a connection of a transaction wait until the previous one releases it.
+    pub(super) async fn acquire(&self) -> Result<SqlConnectionGuard<'_, DB>, BridgeError> {
+        match self {
+            SqlConnection::Pool(pool) => Ok(SqlConnectionGuard::Pool(pool.acquire().await?)),
+            SqlConnection::Transaction(transaction) => {
+                Ok(SqlConnectionGuard::Transaction(transaction.lock().await))
+            }
         }
     }
 
-    pub async fn get_next_deposit_index(&self) -> Result<usize, BridgeError> {
-        let qr: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM deposit_move_txs;")
-            .fetch_one(&self.connection)
-            .await?;
+    /// Starts a transaction on the pool. Transactions can't be nested.
+    pub(super) async fn begin(&self) -> Result<Self, BridgeError> {
+        match self {
+            SqlConnection::Pool(pool) => {
+                Ok(SqlConnection::Transaction(Mutex::new(pool.begin().await?)))
+            }
+            SqlConnection::Transaction(_) => Err(BridgeError::DatabaseError(
+                sqlx::Error::Protocol("Transactions can't be nested".to_owned()),
+            )),
+        }
+    }
 
-        Ok(qr.0 as usize)
+    /// Closes the pool. Transactions leave it to the storage that started
+    /// them.
+    pub(super) async fn close(&self) {
+        if let SqlConnection::Pool(pool) = self {
+            pool.close().await;
+        }
     }
 
-    pub async fn insert_move_txid(
-        &self,
-        start_utxo: OutPoint,
-        recovery_taproot_address: Address<NetworkUnchecked>,
-        evm_address: EVMAddress,
-        move_txid: Txid,
-    ) -> Result<(), BridgeError> {
-        sqlx::query("INSERT INTO deposit_move_txs (start_utxo, recovery_taproot_address, evm_address, move_txid) VALUES ($1, $2, $3, $4);")
-            .bind(start_utxo.to_string())
-            .bind(serde_json::to_string(&recovery_taproot_address).unwrap().trim_matches('"'))
-            .bind(serde_json::to_string(&evm_address).unwrap().trim_matches('"'))
-            .bind(move_txid.to_string())
-            .fetch_all(&self.connection)
-            .await?;
+    /// Commits the transaction. Queries on the pool are already committed.
+    pub(super) async fn commit(self) -> Result<(), BridgeError> {
+        match self {
+            SqlConnection::Pool(_) => Ok(()),
+            SqlConnection::Transaction(transaction) => {
+                Ok(transaction.into_inner().commit().await?)
+            }
+        }
+    }
 
-        Ok(())
+    /// Rolls back the transaction. Queries on the pool can't be rolled back.
+    pub(super) async fn rollback(self) -> Result<(), BridgeError> {
+        match self {
+            SqlConnection::Pool(_) => Err(BridgeError::DatabaseError(sqlx::Error::Protocol(
+                "Queries outside of a transaction can't be rolled back".to_owned(),
+            ))),
+            SqlConnection::Transaction(transaction) => {
+                Ok(transaction.into_inner().rollback().await?)
+            }
+        }
     }
+}
 
-    pub async fn get_move_txid(
-        &self,
-        start_utxo: OutPoint,
-        recovery_taproot_address: Address<NetworkUnchecked>,
-        evm_address: EVMAddress,
-    ) -> Result<Txid, BridgeError> {
-        let qr: (String,) = sqlx::query_as("SELECT (move_txid) FROM deposit_move_txs WHERE start_utxo = $1 AND recovery_taproot_address = $2 AND evm_address = $3;")
-            .bind(start_utxo.to_string())
-            .bind(serde_json::to_string(&recovery_taproot_address).unwrap().trim_matches('"'))
-            .bind(serde_json::to_string(&evm_address).unwrap().trim_matches('"'))
-            .fetch_one(&self.connection)
-            .await?;
-
-        let move_txid = Txid::from_str(&qr.0).unwrap();
-        Ok(move_txid)
-    }
-
-    pub async fn save_withdrawal_sig(
-        &self,
-        idx: usize,
-        bridge_fund_txid: Txid,
-        sig: secp256k1::schnorr::Signature,
-    ) -> Result<(), BridgeError> {
-        sqlx::query(
-            "INSERT INTO withdrawal_sigs (idx, bridge_fund_txid, sig) VALUES ($1, $2, $3);",
-        )
-        .bind(idx as i64)
-        .bind(bridge_fund_txid.to_string())
-        .bind(sig.to_string())
-        .fetch_all(&self.connection)
-        .await?;
+/// Connection that is returned by [`SqlConnection::acquire`].
+pub(super) enum SqlConnectionGuard<'a, DB: sqlx::Database> {
+    Pool(PoolConnection<DB>),
+    Transaction(MutexGuard<'a, sqlx::Transaction<'static, DB>>),
+}
 
-        Ok(())
-    }
+impl<DB: sqlx::Database> Deref for SqlConnectionGuard<'_, DB> {
+    type Target = DB::Connection;
 
-    pub async fn get_withdrawal_sig_by_idx(
-        &self,
-        idx: usize,
-    ) -> Result<(Txid, secp256k1::schnorr::Signature), BridgeError> {
-        let qr: (String, String) =
-            sqlx::query_as("SELECT bridge_fund_txid, sig FROM withdrawal_sigs WHERE idx = $1;")
-                .bind(idx as i64)
-                .fetch_one(&self.connection)
-                .await?;
+    fn deref(&self) -> &Self::Target {
+        match self {
+            SqlConnectionGuard::Pool(connection) => connection,
+            SqlConnectionGuard::Transaction(transaction) => transaction,
+        }
+    }
+}
 
-        let bridge_fund_txid = Txid::from_str(&qr.0).unwrap();
-        let sig = secp256k1::schnorr::Signature::from_str(&qr.1).unwrap();
-        Ok((bridge_fund_txid, sig))
+impl<DB: sqlx::Database> DerefMut for SqlConnectionGuard<'_, DB> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            SqlConnectionGuard::Pool(connection) => connection,
+            SqlConnectionGuard::Transaction(transaction) => transaction,
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Database;
+    use super::{
+        Database, DepositRequest, DepositStatus, IndexedBlock, WithdrawalLedgerEntry,
+        WithdrawalStatus,
+    };
+    use crate::database::migrations::Role;
+    use crate::database::operator::OperatorDB;
+    use crate::errors::BridgeError;
+    use crate::policy::{
+        AddressList, ApprovalStatus, ListedAddress, PolicyAddress, SignedDeposit, SigningPolicy,
+    };
     use crate::{
-        config::BridgeConfig, create_test_config, create_test_config_with_thread_name,
-        mock::common, transaction_builder::TransactionBuilder, EVMAddress,
+        actor::Actor, config::BridgeConfig, create_test_config,
+        create_test_config_with_thread_name, mock::common, transaction_builder::TransactionBuilder,
+        EVMAddress,
     };
-    use bitcoin::{Address, Amount, OutPoint, ScriptBuf, TxOut, XOnlyPublicKey};
+    use bitcoin::hashes::Hash;
+    use bitcoin::{Address, Amount, BlockHash, OutPoint, ScriptBuf, TxOut, Txid, XOnlyPublicKey};
+    use clementine_circuits::constants::BRIDGE_AMOUNT_SATS;
     use secp256k1::{schnorr::Signature, Secp256k1};
     use std::thread;
 
@@ -310,6 +565,7 @@ mod tests {
                 OutPoint::null(),
                 address.as_unchecked().clone(),
                 EVMAddress([0u8; 20]),
+                BRIDGE_AMOUNT_SATS,
             )
             .await
             .unwrap();
@@ -343,6 +599,837 @@ mod tests {
         assert_eq!(txid, read_txid);
         assert_eq!(signature, read_signature);
     }
+
+    #[tokio::test]
+    async fn withdrawal_ledger() {
+        let config = create_test_config!("withdrawal_ledger", "test_config.toml");
+        let db = Database::new(config.clone()).await.unwrap();
+
+        let address = Address::p2tr(
+            &Secp256k1::new(),
+            *crate::utils::UNSPENDABLE_XONLY_PUBKEY,
+            None,
+            config.network,
+        );
+        let entry = WithdrawalLedgerEntry {
+            idx: 0x45,
+            bridge_utxo: OutPoint::new(Txid::from_byte_array([0x45; 32]), 0),
+            withdrawal_address: address.as_unchecked().clone(),
+        };
+        db.add_withdrawal_ledger_entry(&entry).await.unwrap();
+        db.add_withdrawal_ledger_entry(&entry).await.unwrap();
+
+        // Entries with a known index or bridge UTXO are not added.
+        let other_utxo = OutPoint::new(Txid::from_byte_array([0x46; 32]), 1);
+        db.add_withdrawal_ledger_entry(&WithdrawalLedgerEntry {
+            bridge_utxo: other_utxo,
+            ..entry.clone()
+        })
+        .await
+        .unwrap();
+        db.add_withdrawal_ledger_entry(&WithdrawalLedgerEntry {
+            idx: 0x46,
+            ..entry.clone()
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            db.get_withdrawal_ledger().await.unwrap(),
+            vec![entry.clone()]
+        );
+
+        assert_eq!(
+            db.get_withdrawal_ledger_entries(0x46, entry.bridge_utxo)
+                .await
+                .unwrap(),
+            vec![entry.clone()]
+        );
+        assert_eq!(
+            db.get_withdrawal_ledger_entries(0x45, other_utxo)
+                .await
+                .unwrap(),
+            vec![entry.clone()]
+        );
+        assert!(db
+            .get_withdrawal_ledger_entries(0x46, other_utxo)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Batch withdrawals are only in the ledger, and they are signed.
+        let batch_entry = WithdrawalLedgerEntry {
+            idx: 0x47,
+            bridge_utxo: OutPoint::new(Txid::from_byte_array([0x47; 32]), 1),
+            withdrawal_address: address.as_unchecked().clone(),
+        };
+        assert!(!db.is_withdrawal_idx_signed(0x47).await.unwrap());
+        db.add_withdrawal_ledger_entry(&batch_entry).await.unwrap();
+        assert!(db.is_withdrawal_idx_signed(0x47).await.unwrap());
+        assert!(db
+            .is_withdrawal_signed(batch_entry.bridge_utxo)
+            .await
+            .unwrap());
+        // Other outputs of the same tx are not signed.
+        assert!(!db
+            .is_withdrawal_signed(OutPoint::new(batch_entry.bridge_utxo.txid, 0))
+            .await
+            .unwrap());
+
+        // A direct withdrawal is both signed and in the ledger, but it is
+        // outstanding once.
+        let signature = Actor::new(config.secret_key.unwrap(), config.network)
+            .sign_digest([0x45; 32])
+            .unwrap();
+        db.save_withdrawal_sig(entry.idx, entry.bridge_utxo.txid, signature)
+            .await
+            .unwrap();
+        assert_eq!(db.get_outstanding_withdrawal_count().await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn deposit_lifecycle() {
+        let config = create_test_config!("deposit_lifecycle", "test_config.toml");
+        let db = Database::new(config.clone()).await.unwrap();
+
+        let secp = Secp256k1::new();
+        let xonly_public_key = XOnlyPublicKey::from_slice(&[
+            0x78u8, 0x19u8, 0x90u8, 0xd7u8, 0xe2u8, 0x11u8, 0x8cu8, 0xc3u8, 0x61u8, 0xa9u8, 0x3au8,
+            0x6fu8, 0xccu8, 0x54u8, 0xceu8, 0x61u8, 0x1du8, 0x6du8, 0xf3u8, 0x81u8, 0x68u8, 0xd6u8,
+            0xb1u8, 0xedu8, 0xfbu8, 0x55u8, 0x65u8, 0x35u8, 0xf2u8, 0x20u8, 0x0cu8, 0x4b,
+        ])
+        .unwrap();
+        let address = Address::p2tr(&secp, xonly_public_key, None, config.network);
+        let start_utxo = OutPoint::null();
+        let evm_address = EVMAddress([0x45u8; 20]);
+
+        db.start_deposit(
+            start_utxo,
+            address.as_unchecked().clone(),
+            evm_address,
+            BRIDGE_AMOUNT_SATS,
+        )
+        .await
+        .unwrap();
+        // Starting an already started deposit must not reset it.
+        db.set_deposit_status(start_utxo, DepositStatus::MoveConfirmed)
+            .await
+            .unwrap();
+        db.start_deposit(
+            start_utxo,
+            address.as_unchecked().clone(),
+            evm_address,
+            BRIDGE_AMOUNT_SATS,
+        )
+        .await
+        .unwrap();
+        let record = db.get_deposit_record(start_utxo).await.unwrap();
+        assert_eq!(record.status, DepositStatus::MoveConfirmed);
+        assert_eq!(record.evm_address, evm_address);
+        assert_eq!(record.amount, BRIDGE_AMOUNT_SATS);
+        assert_eq!(record.recovery_taproot_address, *address.as_unchecked());
+        assert!(record.move_tx.is_none());
+        assert!(db.get_unfinished_deposits(0).await.unwrap().is_empty());
+
+        let unminted = db.get_unminted_deposits(0).await.unwrap();
+        assert_eq!(unminted.len(), 1);
+        assert_eq!(unminted[0].start_utxo, start_utxo);
+        db.set_deposit_status(start_utxo, DepositStatus::Minted)
+            .await
+            .unwrap();
+        assert!(db.get_unminted_deposits(0).await.unwrap().is_empty());
+
+        let txout = TxOut {
+            value: Amount::from_sat(0x45),
+            script_pubkey: ScriptBuf::new(),
+        };
+        let move_tx = TransactionBuilder::create_btc_tx(vec![], vec![txout]);
+        db.set_deposit_presigned(start_utxo, &move_tx)
+            .await
+            .unwrap();
+
+        let unfinished = db.get_unfinished_deposits(0).await.unwrap();
+        assert_eq!(unfinished.len(), 1);
+        assert_eq!(unfinished[0].status, DepositStatus::PresignsCollected);
+        assert_eq!(unfinished[0].move_tx, Some(move_tx));
+
+        // Recently updated deposits are assumed to be in progress.
+        assert!(db.get_unfinished_deposits(3600).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn transactions() {
+        let config = create_test_config!("transactions", "test_config.toml");
+        let db = Database::new(config.clone()).await.unwrap();
+
+        let address = Address::p2tr(
+            &Secp256k1::new(),
+            *crate::utils::UNSPENDABLE_XONLY_PUBKEY,
+            None,
+            config.network,
+        );
+        let evm_address = EVMAddress([0x45u8; 20]);
+        let start_utxo = OutPoint::new(Txid::from_byte_array([0x45u8; 32]), 0);
+        let move_txid = Txid::from_byte_array([0x46u8; 32]);
+        db.start_deposit(
+            start_utxo,
+            address.as_unchecked().clone(),
+            evm_address,
+            BRIDGE_AMOUNT_SATS,
+        )
+        .await
+        .unwrap();
+
+        // Calls in a transaction see its changes but others don't, until it
+        // is committed.
+        let transaction = db.begin_transaction().await.unwrap();
+        assert!(transaction.begin_transaction().await.is_err());
+        transaction
+            .insert_move_txid(
+                start_utxo,
+                address.as_unchecked().clone(),
+                evm_address,
+                move_txid,
+                0,
+            )
+            .await
+            .unwrap();
+        transaction
+            .set_deposit_status(start_utxo, DepositStatus::MoveBroadcast)
+            .await
+            .unwrap();
+        assert_eq!(
+            transaction
+                .get_move_txid(start_utxo, address.as_unchecked().clone(), evm_address)
+                .await
+                .unwrap(),
+            move_txid
+        );
+        transaction.rollback().await.unwrap();
+        assert!(db
+            .get_move_txid(start_utxo, address.as_unchecked().clone(), evm_address)
+            .await
+            .is_err());
+        assert_eq!(
+            db.get_deposit_record(start_utxo).await.unwrap().status,
+            DepositStatus::Requested
+        );
+
+        // Dropping a transaction rolls it back.
+        let transaction = db.begin_transaction().await.unwrap();
+        transaction
+            .insert_move_txid(
+                start_utxo,
+                address.as_unchecked().clone(),
+                evm_address,
+                move_txid,
+                0,
+            )
+            .await
+            .unwrap();
+        drop(transaction);
+        assert!(db
+            .get_move_txid(start_utxo, address.as_unchecked().clone(), evm_address)
+            .await
+            .is_err());
+
+        let transaction = db.begin_transaction().await.unwrap();
+        transaction
+            .insert_move_txid(
+                start_utxo,
+                address.as_unchecked().clone(),
+                evm_address,
+                move_txid,
+                0,
+            )
+            .await
+            .unwrap();
+        transaction
+            .set_deposit_status(start_utxo, DepositStatus::MoveBroadcast)
+            .await
+            .unwrap();
+        transaction.commit().await.unwrap();
+        assert_eq!(
+            db.get_move_txid(start_utxo, address.as_unchecked().clone(), evm_address)
+                .await
+                .unwrap(),
+            move_txid
+        );
+        assert_eq!(
+            db.get_deposit_record(start_utxo).await.unwrap().status,
+            DepositStatus::MoveBroadcast
+        );
+    }
+
+    #[tokio::test]
+    async fn deposit_batch_queue() {
+        let config = create_test_config!("deposit_batch_queue", "test_config.toml");
+        let db = Database::new(config.clone()).await.unwrap();
+
+        let address = Address::p2tr(
+            &crate::utils::SECP,
+            *crate::utils::UNSPENDABLE_XONLY_PUBKEY,
+            None,
+            config.network,
+        );
+        let start_utxos: Vec<OutPoint> = (0..3)
+            .map(|vout| OutPoint {
+                txid: Txid::all_zeros(),
+                vout,
+            })
+            .collect();
+
+        for start_utxo in start_utxos.iter() {
+            db.start_deposit(
+                *start_utxo,
+                address.as_unchecked().clone(),
+                EVMAddress([0x45u8; 20]),
+                BRIDGE_AMOUNT_SATS,
+            )
+            .await
+            .unwrap();
+            db.add_new_deposit_request(
+                *start_utxo,
+                address.as_unchecked().clone(),
+                EVMAddress([0x45u8; 20]),
+                BRIDGE_AMOUNT_SATS,
+            )
+            .await
+            .unwrap();
+        }
+        // Queueing an already queued deposit is a no-op.
+        db.add_new_deposit_request(
+            start_utxos[0],
+            address.as_unchecked().clone(),
+            EVMAddress([0x45u8; 20]),
+            BRIDGE_AMOUNT_SATS,
+        )
+        .await
+        .unwrap();
+        assert_eq!(db.get_new_deposit_request_count().await.unwrap(), 3);
+
+        let requests = db.get_new_deposit_requests(2).await.unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].start_utxo, start_utxos[0]);
+        assert_eq!(requests[1].start_utxo, start_utxos[1]);
+        assert_eq!(requests[1].amount, BRIDGE_AMOUNT_SATS);
+
+        let txout = TxOut {
+            value: Amount::from_sat(0x45),
+            script_pubkey: ScriptBuf::new(),
+        };
+        let move_tx = TransactionBuilder::create_btc_tx(vec![], vec![txout]);
+        db.set_deposits_presigned(&start_utxos[..2], &move_tx)
+            .await
+            .unwrap();
+
+        let requests = db.get_new_deposit_requests(10).await.unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].start_utxo, start_utxos[2]);
+        for start_utxo in start_utxos[..2].iter() {
+            let record = db.get_deposit_record(*start_utxo).await.unwrap();
+            assert_eq!(record.status, DepositStatus::PresignsCollected);
+            assert_eq!(record.move_tx, Some(move_tx.clone()));
+        }
+
+        db.remove_new_deposit_request(start_utxos[2]).await.unwrap();
+        assert_eq!(db.get_new_deposit_request_count().await.unwrap(), 0);
+
+        // Deposits of a batch share the move tx, each with its own output.
+        let move_txid = move_tx.compute_txid();
+        for (vout, start_utxo) in start_utxos[..2].iter().enumerate() {
+            db.insert_move_txid(
+                *start_utxo,
+                address.as_unchecked().clone(),
+                EVMAddress([0x45u8; 20]),
+                move_txid,
+                vout as u32,
+            )
+            .await
+            .unwrap();
+        }
+        let idx = db.get_next_deposit_index().await.unwrap() - 1;
+        assert_eq!(
+            db.get_bridge_utxo(idx).await.unwrap(),
+            OutPoint {
+                txid: move_txid,
+                vout: 1
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn query_records() {
+        let config = create_test_config!("query_records", "test_config.toml");
+        let db = Database::new(config.clone()).await.unwrap();
+
+        let address = Address::p2tr(
+            &crate::utils::SECP,
+            *crate::utils::UNSPENDABLE_XONLY_PUBKEY,
+            None,
+            config.network,
+        );
+        let start_utxos: Vec<OutPoint> = (0..3)
+            .map(|vout| OutPoint {
+                txid: Txid::all_zeros(),
+                vout,
+            })
+            .collect();
+        let evm_addresses = [
+            EVMAddress([0x45u8; 20]),
+            EVMAddress([0x46u8; 20]),
+            EVMAddress([0x45u8; 20]),
+        ];
+        for (start_utxo, evm_address) in start_utxos.iter().zip(evm_addresses) {
+            db.start_deposit(
+                *start_utxo,
+                address.as_unchecked().clone(),
+                evm_address,
+                BRIDGE_AMOUNT_SATS,
+            )
+            .await
+            .unwrap();
+        }
+
+        let txout = TxOut {
+            value: Amount::from_sat(0x45),
+            script_pubkey: ScriptBuf::new(),
+        };
+        let move_tx = TransactionBuilder::create_btc_tx(vec![], vec![txout]);
+        db.set_deposits_presigned(&start_utxos[1..], &move_tx)
+            .await
+            .unwrap();
+
+        let deposits = db.list_deposit_records(None, None, 0, 10).await.unwrap();
+        assert_eq!(deposits.len(), 3);
+        let deposits = db.list_deposit_records(None, None, 1, 1).await.unwrap();
+        assert_eq!(deposits.len(), 1);
+        assert_eq!(deposits[0].start_utxo, start_utxos[1]);
+
+        let deposits = db
+            .list_deposit_records(Some(EVMAddress([0x45u8; 20])), None, 0, 10)
+            .await
+            .unwrap();
+        assert_eq!(deposits.len(), 2);
+        assert_eq!(deposits[0].start_utxo, start_utxos[0]);
+        assert_eq!(deposits[1].start_utxo, start_utxos[2]);
+
+        let deposits = db
+            .list_deposit_records(
+                Some(EVMAddress([0x45u8; 20])),
+                Some(DepositStatus::PresignsCollected),
+                0,
+                10,
+            )
+            .await
+            .unwrap();
+        assert_eq!(deposits.len(), 1);
+        assert_eq!(deposits[0].start_utxo, start_utxos[2]);
+
+        let deposits = db
+            .get_deposit_records_by_move_txid(move_tx.compute_txid())
+            .await
+            .unwrap();
+        assert_eq!(deposits.len(), 2);
+        assert_eq!(deposits[0].start_utxo, start_utxos[1]);
+        assert_eq!(deposits[1].start_utxo, start_utxos[2]);
+
+        for idx in 0..3 {
+            db.save_withdrawal_tx(
+                idx,
+                Txid::from_byte_array([idx as u8; 32]),
+                address.as_unchecked().clone(),
+                &move_tx,
+            )
+            .await
+            .unwrap();
+        }
+        db.set_withdrawal_confirmed(1, BlockHash::all_zeros())
+            .await
+            .unwrap();
+
+        let withdrawals = db.list_withdrawal_records(None, 1, 10).await.unwrap();
+        assert_eq!(withdrawals.len(), 2);
+        assert_eq!(withdrawals[0].idx, 1);
+        let withdrawals = db
+            .list_withdrawal_records(Some(WithdrawalStatus::Broadcast), 0, 10)
+            .await
+            .unwrap();
+        assert_eq!(
+            withdrawals.iter().map(|w| w.idx).collect::<Vec<_>>(),
+            vec![0, 2]
+        );
+        let withdrawals = db.get_confirmed_withdrawals().await.unwrap();
+        assert_eq!(withdrawals.len(), 1);
+        assert_eq!(withdrawals[0].idx, 1);
+
+        assert!(db.get_moved_deposits().await.unwrap().is_empty());
+        db.set_deposit_status(start_utxos[1], DepositStatus::MoveConfirmed)
+            .await
+            .unwrap();
+        db.set_deposit_status(start_utxos[2], DepositStatus::Minted)
+            .await
+            .unwrap();
+        assert_eq!(db.get_moved_deposits().await.unwrap().len(), 2);
+        assert_eq!(db.get_pending_record_counts().await.unwrap(), (1, 2));
+
+        assert_eq!(db.get_bridge_balance().await.unwrap(), (Amount::ZERO, 0));
+        let block = IndexedBlock {
+            height: 100,
+            hash: BlockHash::from_byte_array([1; 32]),
+            prev_hash: BlockHash::from_byte_array([0; 32]),
+            deposit_utxos: vec![],
+            bridge_utxos: vec![
+                (start_utxos[0], Amount::from_sat(BRIDGE_AMOUNT_SATS)),
+                (start_utxos[1], Amount::from_sat(BRIDGE_AMOUNT_SATS)),
+            ],
+            spent_bridge_utxos: vec![],
+        };
+        db.connect_block(&block).await.unwrap();
+        assert_eq!(
+            db.get_bridge_balance().await.unwrap(),
+            (Amount::from_sat(2 * BRIDGE_AMOUNT_SATS), 2)
+        );
+    }
+
+    #[tokio::test]
+    async fn rollback_records_in_block() {
+        let config = create_test_config!("rollback_records_in_block", "test_config.toml");
+        let db = Database::new(config.clone()).await.unwrap();
+
+        let secp = Secp256k1::new();
+        let xonly_public_key = XOnlyPublicKey::from_slice(&[
+            0x78u8, 0x19u8, 0x90u8, 0xd7u8, 0xe2u8, 0x11u8, 0x8cu8, 0xc3u8, 0x61u8, 0xa9u8, 0x3au8,
+            0x6fu8, 0xccu8, 0x54u8, 0xceu8, 0x61u8, 0x1du8, 0x6du8, 0xf3u8, 0x81u8, 0x68u8, 0xd6u8,
+            0xb1u8, 0xedu8, 0xfbu8, 0x55u8, 0x65u8, 0x35u8, 0xf2u8, 0x20u8, 0x0cu8, 0x4b,
+        ])
+        .unwrap();
+        let address = Address::p2tr(&secp, xonly_public_key, None, config.network);
+        let deposit_block_hash = BlockHash::from_byte_array([1; 32]);
+        let move_block_hash = BlockHash::from_byte_array([2; 32]);
+
+        let start_utxo = OutPoint::null();
+        db.start_deposit(
+            start_utxo,
+            address.as_unchecked().clone(),
+            EVMAddress([0u8; 20]),
+            BRIDGE_AMOUNT_SATS,
+        )
+        .await
+        .unwrap();
+        db.set_deposit_block_hash(start_utxo, deposit_block_hash)
+            .await
+            .unwrap();
+        db.set_deposit_confirmed(start_utxo, move_block_hash)
+            .await
+            .unwrap();
+
+        let txout = TxOut {
+            value: Amount::from_sat(0x45),
+            script_pubkey: ScriptBuf::new(),
+        };
+        let withdrawal_tx = TransactionBuilder::create_btc_tx(vec![], vec![txout]);
+        db.save_withdrawal_tx(
+            0,
+            Txid::from_byte_array([0x45; 32]),
+            address.as_unchecked().clone(),
+            &withdrawal_tx,
+        )
+        .await
+        .unwrap();
+        db.set_withdrawal_confirmed(0, move_block_hash)
+            .await
+            .unwrap();
+
+        let block_hashes = db.get_record_block_hashes().await.unwrap();
+        assert_eq!(block_hashes.len(), 2);
+
+        // Move tx and withdrawal tx are confirmed in the disconnected block.
+        assert_eq!(
+            db.rollback_deposits_in_block(move_block_hash)
+                .await
+                .unwrap(),
+            vec![start_utxo]
+        );
+        assert_eq!(
+            db.rollback_withdrawals_in_block(move_block_hash)
+                .await
+                .unwrap(),
+            vec![0]
+        );
+
+        let deposit = db.get_deposit_record(start_utxo).await.unwrap();
+        assert_eq!(deposit.status, DepositStatus::MoveBroadcast);
+        assert_eq!(deposit.deposit_block_hash, Some(deposit_block_hash));
+        assert_eq!(deposit.move_block_hash, None);
+        assert!(deposit.reorged);
+
+        let withdrawal = db.get_withdrawal_record(0).await.unwrap();
+        assert_eq!(withdrawal.status, WithdrawalStatus::Broadcast);
+        assert_eq!(withdrawal.block_hash, None);
+        assert!(withdrawal.reorged);
+        assert_eq!(withdrawal.withdrawal_tx, withdrawal_tx);
+
+        // Deposit UTXO's block leaving the best chain should not change status.
+        assert_eq!(
+            db.rollback_deposits_in_block(deposit_block_hash)
+                .await
+                .unwrap(),
+            vec![start_utxo]
+        );
+        let deposit = db.get_deposit_record(start_utxo).await.unwrap();
+        assert_eq!(deposit.status, DepositStatus::MoveBroadcast);
+        assert_eq!(deposit.deposit_block_hash, None);
+        assert!(db.get_record_block_hashes().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn migrations() {
+        let config = common::get_test_config("test_config.toml").unwrap();
+        let config = Database::create_database(config, "migrations")
+            .await
+            .unwrap();
+
+        // Nodes don't start with pending migrations.
+        assert!(matches!(
+            OperatorDB::new(BridgeConfig {
+                db_auto_migrate: false,
+                ..config.clone()
+            })
+            .await,
+            Err(BridgeError::MigrationError(_))
+        ));
+
+        let db = Database::new(config).await.unwrap();
+
+        assert_eq!(
+            db.get_pending_migrations(&[Role::Operator])
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+        assert!(matches!(
+            db.check_migrations(&[Role::Operator], false).await,
+            Err(BridgeError::MigrationError(_))
+        ));
+        db.check_migrations(&[Role::Operator], true).await.unwrap();
+        db.check_migrations(&[Role::Operator], false).await.unwrap();
+
+        // Operator doesn't have verifier's tables.
+        assert!(db.get_signing_policy().await.is_err());
+
+        let applied = db.migrate(&[Role::Verifier]).await.unwrap();
+        assert_eq!(
+            applied.iter().map(|m| m.version).collect::<Vec<_>>(),
+            vec![3, 4, 5]
+        );
+        assert!(db
+            .migrate(&[Role::Operator, Role::Verifier])
+            .await
+            .unwrap()
+            .is_empty());
+        db.get_signing_policy().await.unwrap();
+
+        db.close().await;
+    }
+
+    #[tokio::test]
+    async fn signing_policy_records() {
+        let config = create_test_config!("signing_policy_records", "test_config.toml");
+        let db = Database::new(config.clone()).await.unwrap();
+
+        assert_eq!(
+            db.get_signing_policy().await.unwrap(),
+            SigningPolicy::default()
+        );
+        let policy = SigningPolicy {
+            daily_limit: Some(BRIDGE_AMOUNT_SATS),
+            max_outstanding_withdrawals: Some(1),
+            ..SigningPolicy::default()
+        };
+        db.set_signing_policy(&policy).await.unwrap();
+        db.set_signing_policy(&policy).await.unwrap();
+        assert_eq!(db.get_signing_policy().await.unwrap(), policy);
+
+        let listed = ListedAddress {
+            list: AddressList::Deny,
+            address: PolicyAddress::EvmAddress(EVMAddress([0x45; 20])),
+        };
+        db.add_policy_address(&listed).await.unwrap();
+        db.add_policy_address(&listed).await.unwrap();
+        assert_eq!(
+            db.get_policy_addresses().await.unwrap(),
+            vec![listed.clone()]
+        );
+        assert!(db.remove_policy_address(&listed).await.unwrap());
+        assert!(!db.remove_policy_address(&listed).await.unwrap());
+
+        let deposit = DepositRequest {
+            start_utxo: OutPoint {
+                txid: Txid::from_byte_array([0x45; 32]),
+                vout: 1,
+            },
+            recovery_taproot_address: Address::p2tr(
+                &crate::utils::SECP,
+                *crate::utils::UNSPENDABLE_XONLY_PUBKEY,
+                None,
+                config.network,
+            )
+            .as_unchecked()
+            .clone(),
+            evm_address: EVMAddress([0x45; 20]),
+            amount: BRIDGE_AMOUNT_SATS,
+        };
+        db.add_signed_deposits(&[SignedDeposit::from(&deposit)])
+            .await
+            .unwrap();
+        db.add_signed_deposits(&[SignedDeposit::from(&deposit)])
+            .await
+            .unwrap();
+        let signed_deposits = db.get_signed_deposits(60).await.unwrap();
+        assert_eq!(signed_deposits.len(), 1);
+        assert_eq!(signed_deposits[0].start_utxo, deposit.start_utxo);
+        assert_eq!(signed_deposits[0].evm_address, deposit.evm_address);
+
+        assert_eq!(
+            db.get_deposit_approval_status(deposit.start_utxo)
+                .await
+                .unwrap(),
+            None
+        );
+        db.add_deposit_approval(&deposit).await.unwrap();
+        db.add_deposit_approval(&deposit).await.unwrap();
+        let approvals = db
+            .list_deposit_approvals(Some(ApprovalStatus::Pending))
+            .await
+            .unwrap();
+        assert_eq!(approvals.len(), 1);
+        assert_eq!(approvals[0].deposit.start_utxo, deposit.start_utxo);
+        db.set_deposit_approval_status(deposit.start_utxo, ApprovalStatus::Approved)
+            .await
+            .unwrap();
+        assert_eq!(
+            db.get_deposit_approval_status(deposit.start_utxo)
+                .await
+                .unwrap(),
+            Some(ApprovalStatus::Approved)
+        );
+        // Only pending deposits can be approved or rejected.
+        assert!(db
+            .set_deposit_approval_status(deposit.start_utxo, ApprovalStatus::Rejected)
+            .await
+            .is_err());
+        assert!(db
+            .list_deposit_approvals(Some(ApprovalStatus::Pending))
+            .await
+            .unwrap()
+            .is_empty());
+
+        let bridge_utxo = OutPoint {
+            txid: Txid::from_byte_array([0x46; 32]),
+            vout: 0,
+        };
+        let signature = Actor::new(config.secret_key.unwrap(), config.network)
+            .sign_digest([0x45; 32])
+            .unwrap();
+        assert!(!db.is_withdrawal_idx_signed(0).await.unwrap());
+        db.save_withdrawal_sig(0, bridge_utxo.txid, signature)
+            .await
+            .unwrap();
+        assert!(db.is_withdrawal_idx_signed(0).await.unwrap());
+        assert_eq!(db.get_outstanding_withdrawal_count().await.unwrap(), 1);
+
+        let block = IndexedBlock {
+            height: 100,
+            hash: BlockHash::from_byte_array([1; 32]),
+            prev_hash: BlockHash::from_byte_array([0; 32]),
+            deposit_utxos: vec![],
+            bridge_utxos: vec![(bridge_utxo, Amount::from_sat(BRIDGE_AMOUNT_SATS))],
+            spent_bridge_utxos: vec![],
+        };
+        db.connect_block(&block).await.unwrap();
+        assert_eq!(db.get_outstanding_withdrawal_count().await.unwrap(), 1);
+
+        let block = IndexedBlock {
+            height: 101,
+            hash: BlockHash::from_byte_array([2; 32]),
+            prev_hash: block.hash,
+            deposit_utxos: vec![],
+            bridge_utxos: vec![],
+            spent_bridge_utxos: vec![(bridge_utxo, Txid::from_byte_array([0x47; 32]))],
+        };
+        db.connect_block(&block).await.unwrap();
+        assert_eq!(db.get_outstanding_withdrawal_count().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn connect_disconnect_block() {
+        let config = create_test_config!("connect_disconnect_block", "test_config.toml");
+        let db = Database::new(config).await.unwrap();
+
+        assert_eq!(db.get_last_indexed_block().await.unwrap(), None);
+
+        let deposit_script = ScriptBuf::from_bytes(vec![0x51]);
+        db.add_watched_deposit_script(&deposit_script)
+            .await
+            .unwrap();
+        db.add_watched_deposit_script(&deposit_script)
+            .await
+            .unwrap();
+        assert!(db
+            .get_watched_deposit_scripts()
+            .await
+            .unwrap()
+            .contains(&deposit_script));
+
+        let bridge_utxo = OutPoint {
+            txid: Txid::from_byte_array([0x45; 32]),
+            vout: 0,
+        };
+        let first_block = IndexedBlock {
+            height: 100,
+            hash: BlockHash::from_byte_array([1; 32]),
+            prev_hash: BlockHash::from_byte_array([0; 32]),
+            deposit_utxos: vec![(
+                OutPoint {
+                    txid: Txid::from_byte_array([0x46; 32]),
+                    vout: 1,
+                },
+                deposit_script,
+                Amount::from_sat(BRIDGE_AMOUNT_SATS),
+            )],
+            bridge_utxos: vec![(bridge_utxo, Amount::from_sat(BRIDGE_AMOUNT_SATS))],
+            spent_bridge_utxos: vec![],
+        };
+        let second_block = IndexedBlock {
+            height: 101,
+            hash: BlockHash::from_byte_array([2; 32]),
+            prev_hash: first_block.hash,
+            deposit_utxos: vec![],
+            bridge_utxos: vec![],
+            spent_bridge_utxos: vec![(bridge_utxo, Txid::from_byte_array([0x47; 32]))],
+        };
+
+        db.connect_block(&first_block).await.unwrap();
+        db.connect_block(&second_block).await.unwrap();
+        assert_eq!(
+            db.get_last_indexed_block().await.unwrap(),
+            Some((101, second_block.hash))
+        );
+        assert!(db.get_unspent_bridge_utxos().await.unwrap().is_empty());
+
+        // Disconnecting the spending block should make bridge UTXO unspent
+        // again.
+        db.disconnect_block(second_block.hash).await.unwrap();
+        assert_eq!(
+            db.get_unspent_bridge_utxos().await.unwrap(),
+            vec![(bridge_utxo, Amount::from_sat(BRIDGE_AMOUNT_SATS))]
+        );
+
+        db.disconnect_block(first_block.hash).await.unwrap();
+        assert!(db.get_unspent_bridge_utxos().await.unwrap().is_empty());
+        assert_eq!(db.get_last_indexed_block().await.unwrap(), None);
+    }
 }
 
 #[cfg(poc)]
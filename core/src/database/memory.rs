@@ -0,0 +1,1602 @@
+//! # In-memory Storage
+//!
+//! [`Storage`] implementation that keeps everything in memory, so that tests
+//! can run operator and verifiers without a database server. Databases are
+//! kept by name until they are dropped or the process exits, so every actor
+//! in a process opens its own database with `db_name`, like the other
+//! backends.
+//!
+//! Migrations don't have any SQL for this backend but they are still
+//! recorded, and tables of a migration can't be used before it is applied.
+
+use super::common::{
+    DepositRecord, DepositRequest, DepositStatus, IndexedBlock, MoveTxRecord,
+    WithdrawalLedgerEntry, WithdrawalRecord, WithdrawalStatus,
+};
+use super::migrations::{self, Migration, Role};
+use crate::config::{BridgeConfig, DatabaseBackend};
+use crate::errors::BridgeError;
+use crate::policy::{ApprovalStatus, DepositApproval, ListedAddress, SignedDeposit, SigningPolicy};
+use crate::traits::storage::{Storage, StorageTransaction};
+use crate::EVMAddress;
+use bitcoin::address::NetworkUnchecked;
+use bitcoin::{Address, Amount, BlockHash, OutPoint, ScriptBuf, Transaction, Txid};
+use jsonrpsee::core::async_trait;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, MutexGuard, OwnedMutexGuard};
+
+lazy_static::lazy_static! {
+    /// Every in-memory database of this process, by name.
+    static ref DATABASES: std::sync::Mutex<HashMap<String, Arc<Mutex<Tables>>>> =
+        std::sync::Mutex::new(HashMap::new());
+}
+
+// Versions of the migrations that create the tables.
+const CHAIN_WATCHER: i64 = 1;
+const OPERATOR: i64 = 2;
+const VERIFIER: i64 = 3;
+const SIGNING_POLICY: i64 = 4;
+const WITHDRAWAL_LEDGER: i64 = 5;
+
+fn query_error(message: String) -> BridgeError {
+    BridgeError::DatabaseError(sqlx::Error::Protocol(message))
+}
+
+fn not_found() -> BridgeError {
+    BridgeError::DatabaseError(sqlx::Error::RowNotFound)
+}
+
+#[derive(Clone, Debug)]
+struct MoveTxRow {
+    start_utxo: OutPoint,
+    recovery_taproot_address: Address<NetworkUnchecked>,
+    evm_address: EVMAddress,
+    move_txid: Txid,
+    move_vout: u32,
+}
+
+#[derive(Clone, Debug)]
+struct DepositRow {
+    record: DepositRecord,
+    updated_at: Instant,
+}
+
+#[derive(Clone, Debug)]
+struct WithdrawalRow {
+    record: WithdrawalRecord,
+    updated_at: Instant,
+}
+
+#[derive(Clone, Debug)]
+struct BridgeUtxoRow {
+    outpoint: OutPoint,
+    amount: Amount,
+    block_hash: BlockHash,
+    spending_txid: Option<Txid>,
+    spending_block_hash: Option<BlockHash>,
+}
+
+/// Every table of a database. Rows that are listed in the order they are
+/// created are kept in that order.
+#[derive(Clone, Debug, Default)]
+struct Tables {
+    /// Versions and checksums of applied migrations.
+    schema_migrations: Vec<(i64, String)>,
+    /// Hashes and previous hashes of blocks, by height.
+    blocks: BTreeMap<u64, (BlockHash, BlockHash)>,
+    watched_deposit_scripts: HashSet<ScriptBuf>,
+    deposit_utxos: Vec<(OutPoint, ScriptBuf, Amount, BlockHash)>,
+    bridge_utxos: Vec<BridgeUtxoRow>,
+    new_deposit_requests: Vec<DepositRequest>,
+    /// Move txs, by deposit index.
+    deposit_move_txs: Vec<MoveTxRow>,
+    deposit_lifecycle: Vec<DepositRow>,
+    withdrawal_txs: BTreeMap<usize, WithdrawalRow>,
+    withdrawal_sigs: BTreeMap<usize, (Txid, secp256k1::schnorr::Signature)>,
+    musig2_withdrawals: BTreeMap<usize, Txid>,
+    signing_policy: Option<SigningPolicy>,
+    policy_addresses: Vec<ListedAddress>,
+    signed_deposits: Vec<(SignedDeposit, Instant)>,
+    deposit_approvals: Vec<DepositApproval>,
+    withdrawal_ledger: BTreeMap<usize, WithdrawalLedgerEntry>,
+}
+
+impl Tables {
+    /// Fails like a query on a missing table if any of the migrations that
+    /// create the used tables is not applied.
+    fn require(&self, versions: &[i64]) -> Result<(), BridgeError> {
+        for version in versions {
+            if !self.schema_migrations.iter().any(|(v, _)| v == version) {
+                return Err(query_error(format!(
+                    "Tables of migration {} do not exist",
+                    version
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn deposit(&self, start_utxo: OutPoint) -> Option<&DepositRow> {
+        self.deposit_lifecycle
+            .iter()
+            .find(|row| row.record.start_utxo == start_utxo)
+    }
+
+    /// Updates the deposit with given start UTXO, if there is one.
+    fn update_deposit(&mut self, start_utxo: OutPoint, update: impl FnOnce(&mut DepositRecord)) {
+        if let Some(row) = self
+            .deposit_lifecycle
+            .iter_mut()
+            .find(|row| row.record.start_utxo == start_utxo)
+        {
+            update(&mut row.record);
+            row.updated_at = Instant::now();
+        }
+    }
+
+    /// Returns deposits that match `filter`, in the order they are requested.
+    fn deposits(&self, filter: impl Fn(&DepositRow) -> bool) -> Vec<DepositRecord> {
+        self.deposit_lifecycle
+            .iter()
+            .filter(|row| filter(row))
+            .map(|row| row.record.clone())
+            .collect()
+    }
+
+    /// Returns withdrawals that match `filter`, in the order of their
+    /// indexes.
+    fn withdrawals(&self, filter: impl Fn(&WithdrawalRow) -> bool) -> Vec<WithdrawalRecord> {
+        self.withdrawal_txs
+            .values()
+            .filter(|row| filter(row))
+            .map(|row| row.record.clone())
+            .collect()
+    }
+}
+
+fn is_idle(updated_at: Instant, idle_secs: u64) -> bool {
+    updated_at.elapsed() >= Duration::from_secs(idle_secs)
+}
+
+#[derive(Debug)]
+pub struct MemoryStorage {
+    tables: MemoryConnection,
+}
+
+/// Tables that the calls of a [`MemoryStorage`] use.
+#[derive(Debug)]
+enum MemoryConnection {
+    /// Tables of the database, which every call locks for itself.
+    Database(Arc<Mutex<Tables>>),
+    /// Tables of the database, which a transaction keeps locked until it
+    /// ends.
+    Transaction(Mutex<TransactionTables>),
+}
+
+/// Tables that are locked by a transaction, with their copy from the start
+/// of it. Tables are restored to that copy unless the transaction is
+/// committed.
+#[derive(Debug)]
+struct TransactionTables {
+    tables: OwnedMutexGuard<Tables>,
+    snapshot: Option<Box<Tables>>,
+}
+
+impl Drop for TransactionTables {
+    fn drop(&mut self) {
+        if let Some(snapshot) = self.snapshot.take() {
+            *self.tables = *snapshot;
+        }
+    }
+}
+
+/// Tables that are returned by [`MemoryStorage::lock`].
+enum TablesGuard<'a> {
+    Database(MutexGuard<'a, Tables>),
+    Transaction(MutexGuard<'a, TransactionTables>),
+}
+
+impl Deref for TablesGuard<'_> {
+    type Target = Tables;
+
+    fn deref(&self) -> &Tables {
+        match self {
+            TablesGuard::Database(tables) => tables,
+            TablesGuard::Transaction(transaction) => &transaction.tables,
+        }
+    }
+}
+
+impl DerefMut for TablesGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Tables {
+        match self {
+            TablesGuard::Database(tables) => tables,
+            TablesGuard::Transaction(transaction) => &mut transaction.tables,
+        }
+    }
+}
+
+impl MemoryStorage {
+    /// Opens the database of `db_name`, creating it if it doesn't exist.
+    pub fn new(config: &BridgeConfig) -> Self {
+        tracing::debug!("Opening in-memory database: {}", config.db_name);
+
+        let tables = DATABASES
+            .lock()
+            .unwrap()
+            .entry(config.db_name.clone())
+            .or_default()
+            .clone();
+
+        Self {
+            tables: MemoryConnection::Database(tables),
+        }
+    }
+
+    /// Deletes the database with given name, if it exists. Storages that
+    /// already opened it keep using its tables.
+    pub fn drop_database(database_name: &str) {
+        DATABASES.lock().unwrap().remove(database_name);
+    }
+
+    /// Locks the tables for a call. Calls wait for the transactions of the
+    /// database to end, like the other calls.
+    async fn lock(&self) -> TablesGuard<'_> {
+        match &self.tables {
+            MemoryConnection::Database(tables) => TablesGuard::Database(tables.lock().await),
+            MemoryConnection::Transaction(transaction) => {
+                TablesGuard::Transaction(transaction.lock().await)
+            }
+        }
+    }
+
+    async fn read<T>(
+        &self,
+        versions: &[i64],
+        query: impl FnOnce(&Tables) -> Result<T, BridgeError>,
+    ) -> Result<T, BridgeError> {
+        let tables = self.lock().await;
+        tables.require(versions)?;
+
+        query(&tables)
+    }
+
+    /// Runs `query` on a copy of the tables and keeps its changes only if it
+    /// succeeds, so that every call is atomic.
+    async fn write<T>(
+        &self,
+        versions: &[i64],
+        query: impl FnOnce(&mut Tables) -> Result<T, BridgeError>,
+    ) -> Result<T, BridgeError> {
+        let mut tables = self.lock().await;
+        tables.require(versions)?;
+
+        let mut changed = tables.clone();
+        let res = query(&mut changed)?;
+        *tables = changed;
+
+        Ok(res)
+    }
+}
+
+#[async_trait]
+impl StorageTransaction for MemoryStorage {
+    async fn commit(self: Box<Self>) -> Result<(), BridgeError> {
+        if let MemoryConnection::Transaction(transaction) = self.tables {
+            transaction.into_inner().snapshot = None;
+        }
+
+        Ok(())
+    }
+
+    async fn rollback(self: Box<Self>) -> Result<(), BridgeError> {
+        match self.tables {
+            MemoryConnection::Database(_) => Err(query_error(
+                "Calls outside of a transaction can't be rolled back".to_owned(),
+            )),
+            // Tables are restored when the transaction is dropped.
+            MemoryConnection::Transaction(_) => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl Storage for MemoryStorage {
+    async fn close(&self) {}
+
+    async fn run_sql_file(&self, sql_file: &str) -> Result<(), BridgeError> {
+        Err(query_error(format!(
+            "In-memory databases can't run SQL file {}",
+            sql_file
+        )))
+    }
+
+    async fn get_pending_migrations(
+        &self,
+        roles: &[Role],
+    ) -> Result<Vec<&'static Migration>, BridgeError> {
+        self.read(&[], |tables| {
+            migrations::pending(&tables.schema_migrations, roles, DatabaseBackend::Memory)
+        })
+        .await
+    }
+
+    async fn migrate(&self, roles: &[Role]) -> Result<Vec<&'static Migration>, BridgeError> {
+        self.write(&[], |tables| {
+            let pending =
+                migrations::pending(&tables.schema_migrations, roles, DatabaseBackend::Memory)?;
+
+            for migration in pending.iter() {
+                tracing::info!("Applying migration {}", migration.name);
+
+                tables.schema_migrations.push((
+                    migration.version,
+                    migration.checksum(DatabaseBackend::Memory),
+                ));
+            }
+
+            Ok(pending)
+        })
+        .await
+    }
+
+    async fn begin_transaction(&self) -> Result<Box<dyn StorageTransaction>, BridgeError> {
+        match &self.tables {
+            MemoryConnection::Database(tables) => {
+                let tables = tables.clone().lock_owned().await;
+                let snapshot = Some(Box::new(tables.clone()));
+
+                Ok(Box::new(Self {
+                    tables: MemoryConnection::Transaction(Mutex::new(TransactionTables {
+                        tables,
+                        snapshot,
+                    })),
+                }))
+            }
+            MemoryConnection::Transaction(_) => {
+                Err(query_error("Transactions can't be nested".to_owned()))
+            }
+        }
+    }
+
+    async fn add_new_deposit_request(
+        &self,
+        start_utxo: OutPoint,
+        recovery_taproot_address: Address<NetworkUnchecked>,
+        evm_address: EVMAddress,
+        amount: u64,
+    ) -> Result<(), BridgeError> {
+        self.write(&[OPERATOR], |tables| {
+            if !tables
+                .new_deposit_requests
+                .iter()
+                .any(|request| request.start_utxo == start_utxo)
+            {
+                tables.new_deposit_requests.push(DepositRequest {
+                    start_utxo,
+                    recovery_taproot_address,
+                    evm_address,
+                    amount,
+                });
+            }
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get_new_deposit_requests(
+        &self,
+        limit: usize,
+    ) -> Result<Vec<DepositRequest>, BridgeError> {
+        self.read(&[OPERATOR], |tables| {
+            Ok(tables
+                .new_deposit_requests
+                .iter()
+                .take(limit)
+                .cloned()
+                .collect())
+        })
+        .await
+    }
+
+    async fn get_new_deposit_request_count(&self) -> Result<usize, BridgeError> {
+        self.read(&[OPERATOR], |tables| Ok(tables.new_deposit_requests.len()))
+            .await
+    }
+
+    async fn remove_new_deposit_request(&self, start_utxo: OutPoint) -> Result<(), BridgeError> {
+        self.write(&[OPERATOR], |tables| {
+            tables
+                .new_deposit_requests
+                .retain(|request| request.start_utxo != start_utxo);
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get_deposit_tx(&self, idx: usize) -> Result<Txid, BridgeError> {
+        self.read(&[OPERATOR], |tables| {
+            tables
+                .deposit_move_txs
+                .get(idx)
+                .map(|row| row.move_txid)
+                .ok_or_else(not_found)
+        })
+        .await
+    }
+
+    async fn get_bridge_utxo(&self, idx: usize) -> Result<OutPoint, BridgeError> {
+        self.read(&[OPERATOR], |tables| {
+            tables
+                .deposit_move_txs
+                .get(idx)
+                .map(|row| OutPoint::new(row.move_txid, row.move_vout))
+                .ok_or_else(not_found)
+        })
+        .await
+    }
+
+    async fn get_next_deposit_index(&self) -> Result<usize, BridgeError> {
+        self.read(&[OPERATOR], |tables| Ok(tables.deposit_move_txs.len()))
+            .await
+    }
+
+    async fn insert_move_txid(
+        &self,
+        start_utxo: OutPoint,
+        recovery_taproot_address: Address<NetworkUnchecked>,
+        evm_address: EVMAddress,
+        move_txid: Txid,
+        move_vout: u32,
+    ) -> Result<(), BridgeError> {
+        self.write(&[OPERATOR], |tables| {
+            if tables
+                .deposit_move_txs
+                .iter()
+                .any(|row| row.move_txid == move_txid && row.move_vout == move_vout)
+            {
+                return Err(query_error(format!(
+                    "Move tx output {}:{} is already saved",
+                    move_txid, move_vout
+                )));
+            }
+
+            tables.deposit_move_txs.push(MoveTxRow {
+                start_utxo,
+                recovery_taproot_address,
+                evm_address,
+                move_txid,
+                move_vout,
+            });
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get_move_txid(
+        &self,
+        start_utxo: OutPoint,
+        recovery_taproot_address: Address<NetworkUnchecked>,
+        evm_address: EVMAddress,
+    ) -> Result<Txid, BridgeError> {
+        self.read(&[OPERATOR], |tables| {
+            tables
+                .deposit_move_txs
+                .iter()
+                .find(|row| {
+                    row.start_utxo == start_utxo
+                        && row.recovery_taproot_address == recovery_taproot_address
+                        && row.evm_address == evm_address
+                })
+                .map(|row| row.move_txid)
+                .ok_or_else(not_found)
+        })
+        .await
+    }
+
+    async fn get_move_txs(&self) -> Result<Vec<MoveTxRecord>, BridgeError> {
+        self.read(&[OPERATOR], |tables| {
+            Ok(tables
+                .deposit_move_txs
+                .iter()
+                .enumerate()
+                .map(|(idx, row)| MoveTxRecord {
+                    idx,
+                    start_utxo: row.start_utxo,
+                    recovery_taproot_address: row.recovery_taproot_address.clone(),
+                    evm_address: row.evm_address,
+                    move_txid: row.move_txid,
+                    move_vout: row.move_vout,
+                })
+                .collect())
+        })
+        .await
+    }
+
+    async fn save_withdrawal_sig(
+        &self,
+        idx: usize,
+        bridge_fund_txid: Txid,
+        sig: secp256k1::schnorr::Signature,
+    ) -> Result<(), BridgeError> {
+        self.write(&[VERIFIER], |tables| {
+            if tables.withdrawal_sigs.contains_key(&idx) {
+                return Err(query_error(format!(
+                    "Withdrawal signature {} is already saved",
+                    idx
+                )));
+            }
+
+            tables.withdrawal_sigs.insert(idx, (bridge_fund_txid, sig));
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get_withdrawal_sig_by_idx(
+        &self,
+        idx: usize,
+    ) -> Result<(Txid, secp256k1::schnorr::Signature), BridgeError> {
+        self.read(&[VERIFIER], |tables| {
+            tables
+                .withdrawal_sigs
+                .get(&idx)
+                .copied()
+                .ok_or_else(not_found)
+        })
+        .await
+    }
+
+    async fn get_withdrawal_sigs(
+        &self,
+    ) -> Result<Vec<(usize, Txid, secp256k1::schnorr::Signature)>, BridgeError> {
+        self.read(&[VERIFIER], |tables| {
+            Ok(tables
+                .withdrawal_sigs
+                .iter()
+                .map(|(idx, (bridge_fund_txid, sig))| (*idx, *bridge_fund_txid, *sig))
+                .collect())
+        })
+        .await
+    }
+
+    async fn save_musig2_withdrawal(
+        &self,
+        idx: usize,
+        bridge_fund_txid: Txid,
+    ) -> Result<(), BridgeError> {
+        self.write(&[VERIFIER], |tables| {
+            tables
+                .musig2_withdrawals
+                .entry(idx)
+                .or_insert(bridge_fund_txid);
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get_musig2_withdrawal_txid(&self, idx: usize) -> Result<Txid, BridgeError> {
+        self.read(&[VERIFIER], |tables| {
+            tables
+                .musig2_withdrawals
+                .get(&idx)
+                .copied()
+                .ok_or_else(not_found)
+        })
+        .await
+    }
+
+    async fn get_musig2_withdrawals(&self) -> Result<Vec<(usize, Txid)>, BridgeError> {
+        self.read(&[VERIFIER], |tables| {
+            Ok(tables
+                .musig2_withdrawals
+                .iter()
+                .map(|(idx, bridge_fund_txid)| (*idx, *bridge_fund_txid))
+                .collect())
+        })
+        .await
+    }
+
+    async fn start_deposit(
+        &self,
+        start_utxo: OutPoint,
+        recovery_taproot_address: Address<NetworkUnchecked>,
+        evm_address: EVMAddress,
+        amount: u64,
+    ) -> Result<(), BridgeError> {
+        self.write(&[OPERATOR], |tables| {
+            if tables.deposit(start_utxo).is_none() {
+                tables.deposit_lifecycle.push(DepositRow {
+                    record: DepositRecord {
+                        start_utxo,
+                        recovery_taproot_address,
+                        evm_address,
+                        amount,
+                        status: DepositStatus::Requested,
+                        move_tx: None,
+                        deposit_block_hash: None,
+                        move_block_hash: None,
+                        reorged: false,
+                    },
+                    updated_at: Instant::now(),
+                });
+            }
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn set_deposit_presigned(
+        &self,
+        start_utxo: OutPoint,
+        move_tx: &Transaction,
+    ) -> Result<(), BridgeError> {
+        self.write(&[OPERATOR], |tables| {
+            tables.update_deposit(start_utxo, |record| {
+                record.status = DepositStatus::PresignsCollected;
+                record.move_tx = Some(move_tx.clone());
+            });
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn set_deposits_presigned(
+        &self,
+        start_utxos: &[OutPoint],
+        move_tx: &Transaction,
+    ) -> Result<(), BridgeError> {
+        self.write(&[OPERATOR], |tables| {
+            for start_utxo in start_utxos {
+                tables.update_deposit(*start_utxo, |record| {
+                    record.status = DepositStatus::PresignsCollected;
+                    record.move_tx = Some(move_tx.clone());
+                });
+                tables
+                    .new_deposit_requests
+                    .retain(|request| request.start_utxo != *start_utxo);
+            }
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn set_deposit_status(
+        &self,
+        start_utxo: OutPoint,
+        status: DepositStatus,
+    ) -> Result<(), BridgeError> {
+        self.write(&[OPERATOR], |tables| {
+            tables.update_deposit(start_utxo, |record| record.status = status);
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get_deposit_record(&self, start_utxo: OutPoint) -> Result<DepositRecord, BridgeError> {
+        self.read(&[OPERATOR], |tables| {
+            tables
+                .deposit(start_utxo)
+                .map(|row| row.record.clone())
+                .ok_or_else(not_found)
+        })
+        .await
+    }
+
+    async fn restore_deposit_record(&self, record: &DepositRecord) -> Result<(), BridgeError> {
+        self.write(&[OPERATOR], |tables| {
+            if tables.deposit(record.start_utxo).is_some() {
+                return Err(query_error(format!(
+                    "Deposit {} is already known",
+                    record.start_utxo
+                )));
+            }
+
+            tables.deposit_lifecycle.push(DepositRow {
+                record: record.clone(),
+                updated_at: Instant::now(),
+            });
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get_moved_deposits(&self) -> Result<Vec<DepositRecord>, BridgeError> {
+        self.read(&[OPERATOR], |tables| {
+            Ok(tables.deposits(|row| {
+                matches!(
+                    row.record.status,
+                    DepositStatus::MoveConfirmed | DepositStatus::Minted
+                )
+            }))
+        })
+        .await
+    }
+
+    async fn list_deposit_records(
+        &self,
+        evm_address: Option<EVMAddress>,
+        status: Option<DepositStatus>,
+        offset: u64,
+        limit: u64,
+    ) -> Result<Vec<DepositRecord>, BridgeError> {
+        self.read(&[OPERATOR], |tables| {
+            Ok(tables
+                .deposits(|row| {
+                    evm_address.is_none_or(|evm_address| row.record.evm_address == evm_address)
+                        && status.is_none_or(|status| row.record.status == status)
+                })
+                .into_iter()
+                .skip(offset as usize)
+                .take(limit as usize)
+                .collect())
+        })
+        .await
+    }
+
+    async fn get_deposit_records_by_move_txid(
+        &self,
+        move_txid: Txid,
+    ) -> Result<Vec<DepositRecord>, BridgeError> {
+        self.read(&[OPERATOR], |tables| {
+            Ok(tables.deposits(|row| {
+                row.record
+                    .move_tx
+                    .as_ref()
+                    .is_some_and(|move_tx| move_tx.compute_txid() == move_txid)
+            }))
+        })
+        .await
+    }
+
+    async fn get_unfinished_deposits(
+        &self,
+        idle_secs: u64,
+    ) -> Result<Vec<DepositRecord>, BridgeError> {
+        self.read(&[OPERATOR], |tables| {
+            Ok(tables.deposits(|row| {
+                matches!(
+                    row.record.status,
+                    DepositStatus::Requested
+                        | DepositStatus::PresignsCollected
+                        | DepositStatus::MoveBroadcast
+                ) && is_idle(row.updated_at, idle_secs)
+            }))
+        })
+        .await
+    }
+
+    async fn get_unminted_deposits(
+        &self,
+        idle_secs: u64,
+    ) -> Result<Vec<DepositRecord>, BridgeError> {
+        self.read(&[OPERATOR], |tables| {
+            Ok(tables.deposits(|row| {
+                row.record.status == DepositStatus::MoveConfirmed
+                    && is_idle(row.updated_at, idle_secs)
+            }))
+        })
+        .await
+    }
+
+    async fn set_deposit_block_hash(
+        &self,
+        start_utxo: OutPoint,
+        block_hash: BlockHash,
+    ) -> Result<(), BridgeError> {
+        self.write(&[OPERATOR], |tables| {
+            tables.update_deposit(start_utxo, |record| {
+                record.deposit_block_hash = Some(block_hash)
+            });
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn set_deposit_confirmed(
+        &self,
+        start_utxo: OutPoint,
+        move_block_hash: BlockHash,
+    ) -> Result<(), BridgeError> {
+        self.write(&[OPERATOR], |tables| {
+            tables.update_deposit(start_utxo, |record| {
+                record.status = DepositStatus::MoveConfirmed;
+                record.move_block_hash = Some(move_block_hash);
+            });
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn rollback_deposits_in_block(
+        &self,
+        block_hash: BlockHash,
+    ) -> Result<Vec<OutPoint>, BridgeError> {
+        self.write(&[OPERATOR], |tables| {
+            let mut start_utxos = Vec::new();
+
+            for row in tables.deposit_lifecycle.iter_mut() {
+                let record = &mut row.record;
+                let moved_in_block = record.move_block_hash == Some(block_hash);
+                let deposited_in_block = record.deposit_block_hash == Some(block_hash);
+
+                if moved_in_block {
+                    if matches!(
+                        record.status,
+                        DepositStatus::MoveConfirmed | DepositStatus::Minted
+                    ) {
+                        record.status = DepositStatus::MoveBroadcast;
+                    }
+                    record.move_block_hash = None;
+                }
+                if deposited_in_block {
+                    record.deposit_block_hash = None;
+                }
+                if moved_in_block || deposited_in_block {
+                    record.reorged = true;
+                    row.updated_at = Instant::now();
+                    start_utxos.push(record.start_utxo);
+                }
+            }
+
+            start_utxos.sort();
+
+            Ok(start_utxos)
+        })
+        .await
+    }
+
+    async fn save_withdrawal_tx(
+        &self,
+        idx: usize,
+        bridge_fund_txid: Txid,
+        withdrawal_address: Address<NetworkUnchecked>,
+        withdrawal_tx: &Transaction,
+    ) -> Result<(), BridgeError> {
+        self.write(&[OPERATOR], |tables| {
+            tables
+                .withdrawal_txs
+                .entry(idx)
+                .or_insert_with(|| WithdrawalRow {
+                    record: WithdrawalRecord {
+                        idx,
+                        bridge_fund_txid,
+                        withdrawal_address,
+                        withdrawal_tx: withdrawal_tx.clone(),
+                        status: WithdrawalStatus::Broadcast,
+                        block_hash: None,
+                        reorged: false,
+                    },
+                    updated_at: Instant::now(),
+                });
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get_withdrawal_record(&self, idx: usize) -> Result<WithdrawalRecord, BridgeError> {
+        self.read(&[OPERATOR], |tables| {
+            tables
+                .withdrawal_txs
+                .get(&idx)
+                .map(|row| row.record.clone())
+                .ok_or_else(not_found)
+        })
+        .await
+    }
+
+    async fn restore_withdrawal_record(
+        &self,
+        record: &WithdrawalRecord,
+    ) -> Result<(), BridgeError> {
+        self.write(&[OPERATOR], |tables| {
+            if tables.withdrawal_txs.contains_key(&record.idx) {
+                return Err(query_error(format!(
+                    "Withdrawal {} is already saved",
+                    record.idx
+                )));
+            }
+
+            tables.withdrawal_txs.insert(
+                record.idx,
+                WithdrawalRow {
+                    record: record.clone(),
+                    updated_at: Instant::now(),
+                },
+            );
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get_confirmed_withdrawals(&self) -> Result<Vec<WithdrawalRecord>, BridgeError> {
+        self.read(&[OPERATOR], |tables| {
+            Ok(tables.withdrawals(|row| row.record.status == WithdrawalStatus::Confirmed))
+        })
+        .await
+    }
+
+    async fn list_withdrawal_records(
+        &self,
+        status: Option<WithdrawalStatus>,
+        offset: u64,
+        limit: u64,
+    ) -> Result<Vec<WithdrawalRecord>, BridgeError> {
+        self.read(&[OPERATOR], |tables| {
+            Ok(tables
+                .withdrawals(|row| status.is_none_or(|status| row.record.status == status))
+                .into_iter()
+                .skip(offset as usize)
+                .take(limit as usize)
+                .collect())
+        })
+        .await
+    }
+
+    async fn get_pending_record_counts(&self) -> Result<(usize, usize), BridgeError> {
+        self.read(&[OPERATOR], |tables| {
+            let deposits = tables
+                .deposit_lifecycle
+                .iter()
+                .filter(|row| {
+                    matches!(
+                        row.record.status,
+                        DepositStatus::Requested
+                            | DepositStatus::PresignsCollected
+                            | DepositStatus::MoveBroadcast
+                    )
+                })
+                .count();
+            let withdrawals = tables
+                .withdrawal_txs
+                .values()
+                .filter(|row| row.record.status == WithdrawalStatus::Broadcast)
+                .count();
+
+            Ok((deposits, withdrawals))
+        })
+        .await
+    }
+
+    async fn get_unconfirmed_withdrawals(
+        &self,
+        idle_secs: u64,
+    ) -> Result<Vec<WithdrawalRecord>, BridgeError> {
+        self.read(&[OPERATOR], |tables| {
+            Ok(tables.withdrawals(|row| {
+                row.record.status == WithdrawalStatus::Broadcast
+                    && is_idle(row.updated_at, idle_secs)
+            }))
+        })
+        .await
+    }
+
+    async fn set_withdrawal_confirmed(
+        &self,
+        idx: usize,
+        block_hash: BlockHash,
+    ) -> Result<(), BridgeError> {
+        self.write(&[OPERATOR], |tables| {
+            if let Some(row) = tables.withdrawal_txs.get_mut(&idx) {
+                row.record.status = WithdrawalStatus::Confirmed;
+                row.record.block_hash = Some(block_hash);
+                row.updated_at = Instant::now();
+            }
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn rollback_withdrawals_in_block(
+        &self,
+        block_hash: BlockHash,
+    ) -> Result<Vec<usize>, BridgeError> {
+        self.write(&[OPERATOR], |tables| {
+            let mut idxs = Vec::new();
+
+            for row in tables.withdrawal_txs.values_mut() {
+                if row.record.block_hash == Some(block_hash) {
+                    row.record.status = WithdrawalStatus::Broadcast;
+                    row.record.block_hash = None;
+                    row.record.reorged = true;
+                    row.updated_at = Instant::now();
+                    idxs.push(row.record.idx);
+                }
+            }
+
+            Ok(idxs)
+        })
+        .await
+    }
+
+    async fn get_record_block_hashes(&self) -> Result<HashSet<BlockHash>, BridgeError> {
+        self.read(&[OPERATOR], |tables| {
+            Ok(tables
+                .deposit_lifecycle
+                .iter()
+                .flat_map(|row| [row.record.deposit_block_hash, row.record.move_block_hash])
+                .chain(
+                    tables
+                        .withdrawal_txs
+                        .values()
+                        .map(|row| row.record.block_hash),
+                )
+                .flatten()
+                .collect())
+        })
+        .await
+    }
+
+    async fn get_last_indexed_block(&self) -> Result<Option<(u64, BlockHash)>, BridgeError> {
+        self.read(&[CHAIN_WATCHER], |tables| {
+            Ok(tables
+                .blocks
+                .last_key_value()
+                .map(|(height, (hash, _))| (*height, *hash)))
+        })
+        .await
+    }
+
+    async fn connect_block(&self, block: &IndexedBlock) -> Result<(), BridgeError> {
+        self.write(&[CHAIN_WATCHER], |tables| {
+            if tables.blocks.contains_key(&block.height)
+                || tables.blocks.values().any(|(hash, _)| *hash == block.hash)
+            {
+                return Err(query_error(format!(
+                    "Block {} at height {} conflicts with an indexed block",
+                    block.hash, block.height
+                )));
+            }
+            tables
+                .blocks
+                .insert(block.height, (block.hash, block.prev_hash));
+
+            for (outpoint, script_pubkey, amount) in block.deposit_utxos.iter() {
+                if tables.deposit_utxos.iter().any(|utxo| utxo.0 == *outpoint) {
+                    return Err(query_error(format!(
+                        "Deposit UTXO {} is already indexed",
+                        outpoint
+                    )));
+                }
+                tables
+                    .deposit_utxos
+                    .push((*outpoint, script_pubkey.clone(), *amount, block.hash));
+            }
+
+            for (outpoint, amount) in block.bridge_utxos.iter() {
+                if tables
+                    .bridge_utxos
+                    .iter()
+                    .any(|utxo| utxo.outpoint == *outpoint)
+                {
+                    return Err(query_error(format!(
+                        "Bridge UTXO {} is already indexed",
+                        outpoint
+                    )));
+                }
+                tables.bridge_utxos.push(BridgeUtxoRow {
+                    outpoint: *outpoint,
+                    amount: *amount,
+                    block_hash: block.hash,
+                    spending_txid: None,
+                    spending_block_hash: None,
+                });
+            }
+
+            for (outpoint, spending_txid) in block.spent_bridge_utxos.iter() {
+                for utxo in tables
+                    .bridge_utxos
+                    .iter_mut()
+                    .filter(|utxo| utxo.outpoint == *outpoint)
+                {
+                    utxo.spending_txid = Some(*spending_txid);
+                    utxo.spending_block_hash = Some(block.hash);
+                }
+            }
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn disconnect_block(&self, block_hash: BlockHash) -> Result<(), BridgeError> {
+        self.write(&[CHAIN_WATCHER], |tables| {
+            for utxo in tables
+                .bridge_utxos
+                .iter_mut()
+                .filter(|utxo| utxo.spending_block_hash == Some(block_hash))
+            {
+                utxo.spending_txid = None;
+                utxo.spending_block_hash = None;
+            }
+
+            // Deposit and bridge UTXOs created in this block are deleted with
+            // it.
+            tables.blocks.retain(|_, (hash, _)| *hash != block_hash);
+            tables.deposit_utxos.retain(|utxo| utxo.3 != block_hash);
+            tables
+                .bridge_utxos
+                .retain(|utxo| utxo.block_hash != block_hash);
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn add_watched_deposit_script(
+        &self,
+        script_pubkey: &ScriptBuf,
+    ) -> Result<(), BridgeError> {
+        self.write(&[CHAIN_WATCHER], |tables| {
+            tables.watched_deposit_scripts.insert(script_pubkey.clone());
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get_watched_deposit_scripts(&self) -> Result<HashSet<ScriptBuf>, BridgeError> {
+        self.read(&[CHAIN_WATCHER], |tables| {
+            Ok(tables.watched_deposit_scripts.clone())
+        })
+        .await
+    }
+
+    async fn get_unspent_bridge_utxos(&self) -> Result<Vec<(OutPoint, Amount)>, BridgeError> {
+        self.read(&[CHAIN_WATCHER], |tables| {
+            Ok(tables
+                .bridge_utxos
+                .iter()
+                .filter(|utxo| utxo.spending_txid.is_none())
+                .map(|utxo| (utxo.outpoint, utxo.amount))
+                .collect())
+        })
+        .await
+    }
+
+    async fn get_bridge_balance(&self) -> Result<(Amount, usize), BridgeError> {
+        let unspent = self.get_unspent_bridge_utxos().await?;
+
+        Ok((
+            unspent.iter().map(|(_, amount)| *amount).sum(),
+            unspent.len(),
+        ))
+    }
+
+    async fn is_withdrawal_signed(&self, bridge_utxo: OutPoint) -> Result<bool, BridgeError> {
+        self.read(&[VERIFIER, WITHDRAWAL_LEDGER], |tables| {
+            Ok((bridge_utxo.vout == 0
+                && (tables
+                    .withdrawal_sigs
+                    .values()
+                    .any(|(txid, _)| *txid == bridge_utxo.txid)
+                    || tables
+                        .musig2_withdrawals
+                        .values()
+                        .any(|txid| *txid == bridge_utxo.txid)))
+                || tables
+                    .withdrawal_ledger
+                    .values()
+                    .any(|entry| entry.bridge_utxo == bridge_utxo))
+        })
+        .await
+    }
+
+    async fn get_signing_policy(&self) -> Result<SigningPolicy, BridgeError> {
+        self.read(&[SIGNING_POLICY], |tables| {
+            Ok(tables.signing_policy.clone().unwrap_or_default())
+        })
+        .await
+    }
+
+    async fn set_signing_policy(&self, policy: &SigningPolicy) -> Result<(), BridgeError> {
+        self.write(&[SIGNING_POLICY], |tables| {
+            tables.signing_policy = Some(policy.clone());
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get_policy_addresses(&self) -> Result<Vec<ListedAddress>, BridgeError> {
+        self.read(&[SIGNING_POLICY], |tables| {
+            Ok(tables.policy_addresses.clone())
+        })
+        .await
+    }
+
+    async fn add_policy_address(&self, listed: &ListedAddress) -> Result<(), BridgeError> {
+        self.write(&[SIGNING_POLICY], |tables| {
+            if !tables.policy_addresses.contains(listed) {
+                tables.policy_addresses.push(listed.clone());
+            }
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn remove_policy_address(&self, listed: &ListedAddress) -> Result<bool, BridgeError> {
+        self.write(&[SIGNING_POLICY], |tables| {
+            let len = tables.policy_addresses.len();
+            tables.policy_addresses.retain(|address| address != listed);
+
+            Ok(tables.policy_addresses.len() < len)
+        })
+        .await
+    }
+
+    async fn add_signed_deposits(&self, deposits: &[SignedDeposit]) -> Result<(), BridgeError> {
+        self.write(&[SIGNING_POLICY], |tables| {
+            for deposit in deposits {
+                if !tables
+                    .signed_deposits
+                    .iter()
+                    .any(|(signed, _)| signed.start_utxo == deposit.start_utxo)
+                {
+                    tables.signed_deposits.push((*deposit, Instant::now()));
+                }
+            }
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get_signed_deposits(
+        &self,
+        window_secs: u64,
+    ) -> Result<Vec<SignedDeposit>, BridgeError> {
+        self.read(&[SIGNING_POLICY], |tables| {
+            Ok(tables
+                .signed_deposits
+                .iter()
+                .filter(|(_, created_at)| created_at.elapsed() < Duration::from_secs(window_secs))
+                .map(|(signed, _)| *signed)
+                .collect())
+        })
+        .await
+    }
+
+    async fn add_deposit_approval(&self, deposit: &DepositRequest) -> Result<(), BridgeError> {
+        self.write(&[SIGNING_POLICY], |tables| {
+            if !tables
+                .deposit_approvals
+                .iter()
+                .any(|approval| approval.deposit.start_utxo == deposit.start_utxo)
+            {
+                tables.deposit_approvals.push(DepositApproval {
+                    deposit: deposit.clone(),
+                    status: ApprovalStatus::Pending,
+                });
+            }
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get_deposit_approval_status(
+        &self,
+        start_utxo: OutPoint,
+    ) -> Result<Option<ApprovalStatus>, BridgeError> {
+        self.read(&[SIGNING_POLICY], |tables| {
+            Ok(tables
+                .deposit_approvals
+                .iter()
+                .find(|approval| approval.deposit.start_utxo == start_utxo)
+                .map(|approval| approval.status))
+        })
+        .await
+    }
+
+    async fn set_deposit_approval_status(
+        &self,
+        start_utxo: OutPoint,
+        status: ApprovalStatus,
+    ) -> Result<(), BridgeError> {
+        self.write(&[SIGNING_POLICY], |tables| {
+            match tables.deposit_approvals.iter_mut().find(|approval| {
+                approval.deposit.start_utxo == start_utxo
+                    && approval.status == ApprovalStatus::Pending
+            }) {
+                Some(approval) => {
+                    approval.status = status;
+
+                    Ok(())
+                }
+                None => Err(BridgeError::PolicyViolation(format!(
+                    "Deposit {} is not waiting for approval",
+                    start_utxo
+                ))),
+            }
+        })
+        .await
+    }
+
+    async fn list_deposit_approvals(
+        &self,
+        status: Option<ApprovalStatus>,
+    ) -> Result<Vec<DepositApproval>, BridgeError> {
+        self.read(&[SIGNING_POLICY], |tables| {
+            Ok(tables
+                .deposit_approvals
+                .iter()
+                .filter(|approval| status.is_none_or(|status| approval.status == status))
+                .cloned()
+                .collect())
+        })
+        .await
+    }
+
+    async fn is_withdrawal_idx_signed(&self, idx: usize) -> Result<bool, BridgeError> {
+        self.read(&[VERIFIER, WITHDRAWAL_LEDGER], |tables| {
+            Ok(tables.withdrawal_sigs.contains_key(&idx)
+                || tables.musig2_withdrawals.contains_key(&idx)
+                || tables.withdrawal_ledger.contains_key(&idx))
+        })
+        .await
+    }
+
+    async fn get_outstanding_withdrawal_count(&self) -> Result<usize, BridgeError> {
+        self.read(&[CHAIN_WATCHER, VERIFIER, WITHDRAWAL_LEDGER], |tables| {
+            Ok(tables
+                .withdrawal_sigs
+                .values()
+                .map(|(txid, _)| OutPoint::new(*txid, 0))
+                .chain(
+                    tables
+                        .musig2_withdrawals
+                        .values()
+                        .map(|txid| OutPoint::new(*txid, 0)),
+                )
+                .chain(
+                    tables
+                        .withdrawal_ledger
+                        .values()
+                        .map(|entry| entry.bridge_utxo),
+                )
+                .collect::<HashSet<_>>()
+                .into_iter()
+                .filter(|outpoint| {
+                    !tables
+                        .bridge_utxos
+                        .iter()
+                        .any(|utxo| utxo.outpoint == *outpoint && utxo.spending_txid.is_some())
+                })
+                .count())
+        })
+        .await
+    }
+
+    async fn add_withdrawal_ledger_entry(
+        &self,
+        entry: &WithdrawalLedgerEntry,
+    ) -> Result<(), BridgeError> {
+        self.write(&[WITHDRAWAL_LEDGER], |tables| {
+            if !tables.withdrawal_ledger.contains_key(&entry.idx)
+                && !tables
+                    .withdrawal_ledger
+                    .values()
+                    .any(|e| e.bridge_utxo == entry.bridge_utxo)
+            {
+                tables.withdrawal_ledger.insert(entry.idx, entry.clone());
+            }
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get_withdrawal_ledger_entries(
+        &self,
+        idx: usize,
+        bridge_utxo: OutPoint,
+    ) -> Result<Vec<WithdrawalLedgerEntry>, BridgeError> {
+        self.read(&[WITHDRAWAL_LEDGER], |tables| {
+            Ok(tables
+                .withdrawal_ledger
+                .values()
+                .filter(|e| e.idx == idx || e.bridge_utxo == bridge_utxo)
+                .cloned()
+                .collect())
+        })
+        .await
+    }
+
+    async fn get_withdrawal_ledger(&self) -> Result<Vec<WithdrawalLedgerEntry>, BridgeError> {
+        self.read(&[WITHDRAWAL_LEDGER], |tables| {
+            Ok(tables.withdrawal_ledger.values().cloned().collect())
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{BridgeConfig, DatabaseBackend};
+    use crate::database::common::{Database, DepositStatus};
+    use crate::database::migrations::{Role, MIGRATIONS};
+    use crate::{mock::common, EVMAddress};
+    use bitcoin::hashes::Hash;
+    use bitcoin::{Address, BlockHash, OutPoint, Txid, XOnlyPublicKey};
+    use clementine_circuits::constants::BRIDGE_AMOUNT_SATS;
+    use secp256k1::Secp256k1;
+
+    async fn create_test_database(database_name: &str) -> (BridgeConfig, Database) {
+        let mut config = common::get_test_config("test_config.toml").unwrap();
+        config.db_backend = DatabaseBackend::Memory;
+        let config = Database::create_database(config, database_name)
+            .await
+            .unwrap();
+
+        let db = Database::new(config.clone()).await.unwrap();
+        db.migrate(&[Role::Operator, Role::Verifier]).await.unwrap();
+
+        (config, db)
+    }
+
+    #[tokio::test]
+    async fn databases_by_name() {
+        let mut config = common::get_test_config("test_config.toml").unwrap();
+        config.db_backend = DatabaseBackend::Memory;
+        let config = Database::create_database(config, "memory_databases_by_name")
+            .await
+            .unwrap();
+
+        let db = Database::new(config.clone()).await.unwrap();
+        assert_eq!(
+            db.get_pending_migrations(&[Role::Operator, Role::Verifier])
+                .await
+                .unwrap()
+                .len(),
+            MIGRATIONS.len()
+        );
+        db.migrate(&[Role::Operator]).await.unwrap();
+        assert!(db.get_signing_policy().await.is_err());
+
+        // Opening a database again doesn't lose its tables.
+        db.close().await;
+        let db = Database::new(config.clone()).await.unwrap();
+        assert_eq!(
+            db.get_pending_migrations(&[Role::Operator, Role::Verifier])
+                .await
+                .unwrap()
+                .len(),
+            3
+        );
+
+        let other = Database::create_database(config.clone(), "memory_databases_by_name_2")
+            .await
+            .unwrap();
+        let other = Database::new(other).await.unwrap();
+        assert_eq!(
+            other
+                .get_pending_migrations(&[Role::Operator])
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+
+        Database::drop_database(config.clone(), "memory_databases_by_name")
+            .await
+            .unwrap();
+        let db = Database::new(config).await.unwrap();
+        assert_eq!(
+            db.get_pending_migrations(&[Role::Operator])
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_calls() {
+        let (config, db) = create_test_database("memory_failed_calls").await;
+
+        let secp = Secp256k1::new();
+        let xonly_public_key = XOnlyPublicKey::from_slice(&[0x78u8; 32]).unwrap();
+        let address = Address::p2tr(&secp, xonly_public_key, None, config.network);
+        let evm_address = EVMAddress([0x45u8; 20]);
+        let start_utxo = OutPoint::new(Txid::from_byte_array([0x45u8; 32]), 0);
+        let move_txid = Txid::from_byte_array([0x46u8; 32]);
+
+        db.insert_move_txid(
+            start_utxo,
+            address.as_unchecked().clone(),
+            evm_address,
+            move_txid,
+            0,
+        )
+        .await
+        .unwrap();
+        assert_eq!(db.get_next_deposit_index().await.unwrap(), 1);
+
+        // Failed calls don't change anything.
+        assert!(db
+            .insert_move_txid(
+                start_utxo,
+                address.as_unchecked().clone(),
+                evm_address,
+                move_txid,
+                0,
+            )
+            .await
+            .is_err());
+        assert_eq!(db.get_next_deposit_index().await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn deposit_lifecycle() {
+        let (config, db) = create_test_database("memory_deposit_lifecycle").await;
+
+        let secp = Secp256k1::new();
+        let xonly_public_key = XOnlyPublicKey::from_slice(&[0x78u8; 32]).unwrap();
+        let address = Address::p2tr(&secp, xonly_public_key, None, config.network);
+        let evm_address = EVMAddress([0x45u8; 20]);
+
+        let start_utxos = [
+            OutPoint::new(Txid::from_byte_array([0x46u8; 32]), 0),
+            OutPoint::new(Txid::from_byte_array([0x45u8; 32]), 0),
+        ];
+        for start_utxo in start_utxos {
+            db.start_deposit(
+                start_utxo,
+                address.as_unchecked().clone(),
+                evm_address,
+                BRIDGE_AMOUNT_SATS,
+            )
+            .await
+            .unwrap();
+        }
+
+        // Deposits are listed in the order they are requested.
+        let deposits = db
+            .list_deposit_records(Some(evm_address), None, 0, 10)
+            .await
+            .unwrap();
+        assert_eq!(
+            deposits.iter().map(|d| d.start_utxo).collect::<Vec<_>>(),
+            start_utxos
+        );
+        assert_eq!(db.get_unfinished_deposits(0).await.unwrap().len(), 2);
+        assert!(db.get_unfinished_deposits(3600).await.unwrap().is_empty());
+
+        let block_hash = BlockHash::from_byte_array([0x45u8; 32]);
+        db.set_deposit_block_hash(start_utxos[0], block_hash)
+            .await
+            .unwrap();
+        db.set_deposit_confirmed(start_utxos[0], block_hash)
+            .await
+            .unwrap();
+        assert_eq!(
+            db.rollback_deposits_in_block(block_hash).await.unwrap(),
+            vec![start_utxos[0]]
+        );
+        let deposit = db.get_deposit_record(start_utxos[0]).await.unwrap();
+        assert_eq!(deposit.status, DepositStatus::MoveBroadcast);
+        assert_eq!(deposit.deposit_block_hash, None);
+        assert!(deposit.reorged);
+    }
+}
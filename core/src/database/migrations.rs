@@ -0,0 +1,258 @@
+//! # Migrations
+//!
+//! Database schema is built by the ordered migrations in `core/migrations`,
+//! which are embedded in the binaries. Applied migrations are recorded in the
+//! `schema_migrations` table with their checksums, so that a migration that is
+//! changed after it is applied is noticed.
+//!
+//! Every migration belongs to the operator, the verifier or both of them, so
+//! that their databases only have the tables they use. Every migration has a
+//! version for each storage backend, which build the same schema. In-memory
+//! databases don't have a schema and only record the applied migrations.
+//!
+//! PostgreSQL databases that are created with `scripts/schema.sql`, before
+//! migrations, already have some of the tables of the first migrations.
+//! Those migrations are replaced by upgrades in `core/migrations/postgres/baseline`,
+//! which alter the existing tables and create the rest, and are recorded as
+//! applied.
+
+use crate::config::DatabaseBackend;
+use crate::errors::BridgeError;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Owner of a database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    Operator,
+    Verifier,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    /// Roles that need this migration. Every role needs it if this is empty.
+    pub roles: &'static [Role],
+    pub postgres_sql: &'static str,
+    pub sqlite_sql: &'static str,
+}
+
+impl Migration {
+    /// Returns the migration for given backend.
+    pub fn sql(&self, backend: DatabaseBackend) -> &'static str {
+        match backend {
+            DatabaseBackend::Postgres => self.postgres_sql,
+            DatabaseBackend::Sqlite => self.sqlite_sql,
+            DatabaseBackend::Memory => "",
+        }
+    }
+
+    /// Returns hex encoded SHA256 hash of the migration for given backend.
+    pub fn checksum(&self, backend: DatabaseBackend) -> String {
+        hex::encode(Sha256::digest(self.sql(backend).as_bytes()))
+    }
+
+    pub fn is_needed_by(&self, roles: &[Role]) -> bool {
+        self.roles.is_empty() || self.roles.iter().any(|role| roles.contains(role))
+    }
+}
+
+macro_rules! migration {
+    ($version:expr, $name:literal, $roles:expr) => {
+        Migration {
+            version: $version,
+            name: $name,
+            roles: $roles,
+            postgres_sql: include_str!(concat!("../../migrations/postgres/", $name, ".sql")),
+            sqlite_sql: include_str!(concat!("../../migrations/sqlite/", $name, ".sql")),
+        }
+    };
+}
+
+/// Every migration, in the order they are applied.
+pub const MIGRATIONS: &[Migration] = &[
+    migration!(1, "0001_chain_watcher", &[]),
+    migration!(2, "0002_operator", &[Role::Operator]),
+    migration!(3, "0003_verifier", &[Role::Verifier]),
+    migration!(4, "0004_signing_policy", &[Role::Verifier]),
+    migration!(5, "0005_withdrawal_ledger", &[Role::Verifier]),
+];
+
+/// Tables that `scripts/schema.sql` created, before migrations.
+const BASELINE_SCHEMA_TABLES: &[&str] = &[
+    "new_deposit_requests",
+    "deposit_move_txs",
+    "withdrawal_sigs",
+];
+
+/// Migrations that create the tables of `scripts/schema.sql`, with the
+/// PostgreSQL upgrades that bring those tables to the migration instead.
+const BASELINE_UPGRADES: &[(i64, &str)] = &[
+    (
+        2,
+        include_str!("../../migrations/postgres/baseline/0002_operator.sql"),
+    ),
+    (
+        3,
+        include_str!("../../migrations/postgres/baseline/0003_verifier.sql"),
+    ),
+];
+
+/// Returns the migrations that a database without `schema_migrations` table
+/// should be upgraded to, with the upgrade of each. These are the migrations
+/// that replaced `scripts/schema.sql` if `tables` are created with it, or
+/// none if the database is empty. Fails if only some of the tables of
+/// `scripts/schema.sql` exist.
+pub fn legacy_baseline(
+    tables: &[String],
+) -> Result<Vec<(&'static Migration, &'static str)>, BridgeError> {
+    let existing = BASELINE_SCHEMA_TABLES
+        .iter()
+        .filter(|table| tables.iter().any(|t| t == *table))
+        .count();
+    if existing == 0 {
+        return Ok(vec![]);
+    }
+    if existing != BASELINE_SCHEMA_TABLES.len() {
+        return Err(BridgeError::MigrationError(format!(
+            "Database has some of the tables of scripts/schema.sql ({}) and no migrations",
+            BASELINE_SCHEMA_TABLES.join(", ")
+        )));
+    }
+
+    Ok(BASELINE_UPGRADES
+        .iter()
+        .map(|(version, upgrade)| {
+            let migration = MIGRATIONS
+                .iter()
+                .find(|m| m.version == *version)
+                .expect("baseline upgrades belong to a migration");
+
+            (migration, *upgrade)
+        })
+        .collect())
+}
+
+/// Returns migrations of `roles` that are not in `applied`, which is the
+/// versions and checksums of applied migrations. Fails if an applied
+/// migration is changed since.
+pub fn pending(
+    applied: &[(i64, String)],
+    roles: &[Role],
+    backend: DatabaseBackend,
+) -> Result<Vec<&'static Migration>, BridgeError> {
+    for (version, checksum) in applied {
+        match MIGRATIONS.iter().find(|m| m.version == *version) {
+            Some(migration) if migration.checksum(backend) != *checksum => {
+                return Err(BridgeError::MigrationError(format!(
+                    "Migration {} is changed after it is applied",
+                    migration.name
+                )));
+            }
+            Some(_) => {}
+            None => tracing::warn!(
+                "Database has migration {}, which is newer than this binary",
+                version
+            ),
+        }
+    }
+
+    Ok(MIGRATIONS
+        .iter()
+        .filter(|m| m.is_needed_by(roles))
+        .filter(|m| !applied.iter().any(|(version, _)| *version == m.version))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{legacy_baseline, pending, Role, BASELINE_SCHEMA_TABLES, MIGRATIONS};
+    use crate::config::DatabaseBackend;
+    use crate::errors::BridgeError;
+
+    #[test]
+    fn migration_order() {
+        for (i, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version, i as i64 + 1);
+            assert!(migration
+                .name
+                .starts_with(&format!("{:04}_", migration.version)));
+        }
+    }
+
+    #[test]
+    fn pending_migrations() {
+        let versions = |migrations: Vec<&super::Migration>| {
+            migrations.iter().map(|m| m.version).collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            versions(pending(&[], &[Role::Operator], DatabaseBackend::Postgres).unwrap()),
+            vec![1, 2]
+        );
+        assert_eq!(
+            versions(pending(&[], &[Role::Verifier], DatabaseBackend::Sqlite).unwrap()),
+            vec![1, 3, 4, 5]
+        );
+
+        let applied = vec![(1, MIGRATIONS[0].checksum(DatabaseBackend::Postgres))];
+        assert_eq!(
+            versions(
+                pending(
+                    &applied,
+                    &[Role::Operator, Role::Verifier],
+                    DatabaseBackend::Postgres
+                )
+                .unwrap()
+            ),
+            vec![2, 3, 4, 5]
+        );
+
+        // Unknown migrations are newer than the binary and are skipped.
+        let applied = vec![
+            (1, MIGRATIONS[0].checksum(DatabaseBackend::Postgres)),
+            (1000, String::new()),
+        ];
+        assert_eq!(
+            versions(pending(&applied, &[Role::Operator], DatabaseBackend::Postgres).unwrap()),
+            vec![2]
+        );
+
+        let applied = vec![(1, "changed".to_string())];
+        assert!(matches!(
+            pending(&applied, &[Role::Operator], DatabaseBackend::Postgres),
+            Err(BridgeError::MigrationError(_))
+        ));
+
+        // Checksums are of the backend's migrations.
+        let applied = vec![(1, MIGRATIONS[0].checksum(DatabaseBackend::Sqlite))];
+        assert!(pending(&applied, &[Role::Operator], DatabaseBackend::Postgres).is_err());
+        assert!(pending(&applied, &[Role::Operator], DatabaseBackend::Sqlite).is_ok());
+    }
+
+    #[test]
+    fn legacy_baseline_migrations() {
+        assert!(legacy_baseline(&[]).unwrap().is_empty());
+        assert!(legacy_baseline(&["blocks".to_string()]).unwrap().is_empty());
+
+        let tables = BASELINE_SCHEMA_TABLES
+            .iter()
+            .map(|table| table.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            legacy_baseline(&tables)
+                .unwrap()
+                .iter()
+                .map(|(m, _)| m.version)
+                .collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+
+        assert!(matches!(
+            legacy_baseline(&tables[1..]),
+            Err(BridgeError::MigrationError(_))
+        ));
+    }
+}
@@ -5,5 +5,10 @@
 //! directly.
 
 pub mod common;
+pub mod memory;
+pub mod migrations;
 pub mod operator;
+pub mod postgres;
+pub mod snapshot;
+pub mod sqlite;
 pub mod verifier;
@@ -1,5 +1,7 @@
 use super::common::Database;
+use super::migrations::Role;
 use crate::config::BridgeConfig;
+use crate::errors::BridgeError;
 use std::ops::{Deref, DerefMut};
 
 #[derive(Debug, Clone)]
@@ -8,10 +10,16 @@ pub struct OperatorDB {
 }
 
 impl OperatorDB {
-    pub async fn new(config: BridgeConfig) -> Self {
-        Self {
-            database: Database::new(config).await.unwrap(),
-        }
+    /// Connects to the database of the operator. Pending migrations are
+    /// applied if `db_auto_migrate` is set, otherwise they fail the
+    /// connection.
+    pub async fn new(config: BridgeConfig) -> Result<Self, BridgeError> {
+        let database = Database::new(config.clone()).await?;
+        database
+            .check_migrations(&[Role::Operator], config.db_auto_migrate)
+            .await?;
+
+        Ok(Self { database })
     }
 }
 
@@ -0,0 +1,1460 @@
+//! # PostgreSQL Storage
+//!
+//! [`Storage`] implementation that talks with a PostgreSQL server. It is
+//! expected that PostgreSQL is properly installed and configured.
+
+use super::common::{
+    decode_error, DepositRecord, DepositRecordRow, DepositRequest, DepositRequestRow,
+    DepositStatus, IndexedBlock, MoveTxRecord, SigningPolicyRow, SqlConnection,
+    WithdrawalLedgerEntry, WithdrawalLedgerRow, WithdrawalRecord, WithdrawalRecordRow,
+    WithdrawalStatus, DEPOSIT_RECORD_COLUMNS, WITHDRAWAL_RECORD_COLUMNS,
+};
+use super::migrations::{self, Migration, Role};
+use crate::config::{BridgeConfig, DatabaseBackend};
+use crate::errors::BridgeError;
+use crate::metrics;
+use crate::policy::{
+    AddressList, ApprovalStatus, DepositApproval, ListedAddress, PolicyAddress, SignedDeposit,
+    SigningPolicy,
+};
+use crate::traits::storage::{Storage, StorageTransaction};
+use crate::EVMAddress;
+use bitcoin::address::NetworkUnchecked;
+use bitcoin::consensus::encode::serialize_hex;
+use bitcoin::{Address, Amount, BlockHash, OutPoint, ScriptBuf, Transaction, Txid};
+use jsonrpsee::core::async_trait;
+use sqlx::{Connection, Executor, PgExecutor, Postgres};
+use std::collections::HashSet;
+use std::fs;
+use std::str::FromStr;
+
+/// Advisory lock that migrating processes hold, so that they don't apply the
+/// same migrations at the same time.
+const MIGRATION_LOCK_ID: i64 = 0x636c656d656e74;
+
+#[derive(Debug)]
+pub struct PostgresStorage {
+    connection: SqlConnection<Postgres>,
+}
+
+impl PostgresStorage {
+    /// Connects to the database in config. Returns error if database is not
+    /// available.
+    pub async fn new(config: &BridgeConfig) -> Result<Self, BridgeError> {
+        let url = "postgresql://".to_owned()
+            + config.db_host.as_str()
+            + ":"
+            + config.db_port.to_string().as_str()
+            + "?dbname="
+            + config.db_name.as_str()
+            + "&user="
+            + config.db_user.as_str()
+            + "&password="
+            + config.db_password.as_str();
+        tracing::debug!("Connecting database: {}", url);
+
+        match sqlx::PgPool::connect(url.as_str()).await {
+            Ok(c) => Ok(Self {
+                connection: SqlConnection::Pool(c),
+            }),
+            Err(e) => Err(BridgeError::DatabaseError(e)),
+        }
+    }
+
+    /// Drops the given database if it exists.
+    pub async fn drop_database(
+        config: &BridgeConfig,
+        database_name: &str,
+    ) -> Result<(), BridgeError> {
+        let url = "postgresql://".to_owned()
+            + config.db_user.as_str()
+            + ":"
+            + config.db_password.as_str()
+            + "@"
+            + config.db_host.as_str();
+        let conn = sqlx::PgPool::connect(url.as_str()).await?;
+
+        let query = format!("DROP DATABASE IF EXISTS {database_name}");
+        sqlx::query(&query).execute(&conn).await?;
+
+        conn.close().await;
+
+        Ok(())
+    }
+
+    /// Creates a new database with given name, after dropping it if it
+    /// exists.
+    pub async fn create_database(
+        config: &BridgeConfig,
+        database_name: &str,
+    ) -> Result<(), BridgeError> {
+        let url = "postgresql://".to_owned()
+            + config.db_user.as_str()
+            + ":"
+            + config.db_password.as_str()
+            + "@"
+            + config.db_host.as_str();
+        let conn = sqlx::PgPool::connect(url.as_str()).await?;
+
+        PostgresStorage::drop_database(config, database_name).await?;
+
+        let query = format!(
+            "CREATE DATABASE {} WITH OWNER {}",
+            database_name, config.db_user
+        );
+        sqlx::query(&query).execute(&conn).await?;
+
+        conn.close().await;
+
+        Ok(())
+    }
+}
+
+/// Returns the migrations that a database created with `scripts/schema.sql`
+/// is upgraded to, with their upgrades.
+async fn legacy_baseline<'c, E>(
+    executor: E,
+) -> Result<Vec<(&'static Migration, &'static str)>, BridgeError>
+where
+    E: PgExecutor<'c>,
+{
+    let tables: Vec<(String,)> = sqlx::query_as(
+        "SELECT tablename::text FROM pg_tables WHERE schemaname = current_schema();",
+    )
+    .fetch_all(executor)
+    .await?;
+
+    migrations::legacy_baseline(&tables.into_iter().map(|(table,)| table).collect::<Vec<_>>())
+}
+
+#[async_trait]
+impl Storage for PostgresStorage {
+    async fn close(&self) {
+        self.connection.close().await;
+    }
+
+    async fn run_sql_file(&self, sql_file: &str) -> Result<(), BridgeError> {
+        let contents = fs::read_to_string(sql_file).unwrap();
+
+        let mut connection = self.connection.acquire().await?;
+        (&mut *connection).execute(contents.as_str()).await?;
+
+        Ok(())
+    }
+
+    async fn get_pending_migrations(
+        &self,
+        roles: &[Role],
+    ) -> Result<Vec<&'static Migration>, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["get_pending_migrations"]);
+        let (exists,): (bool,) =
+            sqlx::query_as("SELECT to_regclass('schema_migrations') IS NOT NULL;")
+                .fetch_one(&mut *self.connection.acquire().await?)
+                .await?;
+
+        let applied: Vec<(i64, String)> = match exists {
+            true => {
+                sqlx::query_as("SELECT version, checksum FROM schema_migrations;")
+                    .fetch_all(&mut *self.connection.acquire().await?)
+                    .await?
+            }
+            false => vec![],
+        };
+
+        migrations::pending(&applied, roles, DatabaseBackend::Postgres)
+    }
+
+    async fn migrate(&self, roles: &[Role]) -> Result<Vec<&'static Migration>, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["migrate"]);
+        let mut connection = self.connection.acquire().await?;
+        let mut transaction = connection.begin().await?;
+
+        sqlx::query("SELECT pg_advisory_xact_lock($1);")
+            .bind(MIGRATION_LOCK_ID)
+            .execute(&mut *transaction)
+            .await?;
+
+        // Databases that are created with scripts/schema.sql don't have the
+        // migrations table but already have some tables of first migrations,
+        // which are upgraded instead of created.
+        let (has_migrations_table,): (bool,) =
+            sqlx::query_as("SELECT to_regclass('schema_migrations') IS NOT NULL;")
+                .fetch_one(&mut *transaction)
+                .await?;
+        let baseline = match has_migrations_table {
+            true => vec![],
+            false => legacy_baseline(&mut *transaction).await?,
+        };
+
+        sqlx::query("CREATE TABLE IF NOT EXISTS schema_migrations (version bigint primary key, name text not null, checksum text not null, applied_at timestamp not null default now());")
+            .execute(&mut *transaction)
+            .await?;
+        for (migration, upgrade) in baseline.iter() {
+            tracing::info!(
+                "Upgrading tables of scripts/schema.sql to migration {}",
+                migration.name
+            );
+
+            (&mut *transaction).execute(*upgrade).await?;
+            sqlx::query(
+                "INSERT INTO schema_migrations (version, name, checksum) VALUES ($1, $2, $3);",
+            )
+            .bind(migration.version)
+            .bind(migration.name)
+            .bind(migration.checksum(DatabaseBackend::Postgres))
+            .execute(&mut *transaction)
+            .await?;
+        }
+
+        let applied: Vec<(i64, String)> =
+            sqlx::query_as("SELECT version, checksum FROM schema_migrations;")
+                .fetch_all(&mut *transaction)
+                .await?;
+        let pending = migrations::pending(&applied, roles, DatabaseBackend::Postgres)?;
+
+        for migration in pending.iter() {
+            tracing::info!("Applying migration {}", migration.name);
+
+            // Migrations have more than one statement, which prepared
+            // statements can't have.
+            (&mut *transaction).execute(migration.postgres_sql).await?;
+            sqlx::query(
+                "INSERT INTO schema_migrations (version, name, checksum) VALUES ($1, $2, $3);",
+            )
+            .bind(migration.version)
+            .bind(migration.name)
+            .bind(migration.checksum(DatabaseBackend::Postgres))
+            .execute(&mut *transaction)
+            .await?;
+        }
+
+        transaction.commit().await?;
+
+        let mut applied = baseline
+            .into_iter()
+            .map(|(migration, _)| migration)
+            .chain(pending)
+            .collect::<Vec<_>>();
+        applied.sort_by_key(|m| m.version);
+
+        Ok(applied)
+    }
+
+    async fn begin_transaction(&self) -> Result<Box<dyn StorageTransaction>, BridgeError> {
+        Ok(Box::new(Self {
+            connection: self.connection.begin().await?,
+        }))
+    }
+
+    async fn add_new_deposit_request(
+        &self,
+        start_utxo: OutPoint,
+        recovery_taproot_address: Address<NetworkUnchecked>,
+        evm_address: EVMAddress,
+        amount: u64,
+    ) -> Result<(), BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["add_new_deposit_request"]);
+        let start_utxo = start_utxo.to_string();
+        let recovery_taproot_address = serde_json::to_string(&recovery_taproot_address)
+            .unwrap()
+            .trim_matches('"')
+            .to_owned();
+        let evm_address = serde_json::to_string(&evm_address)
+            .unwrap()
+            .trim_matches('"')
+            .to_owned();
+
+        sqlx::query("INSERT INTO new_deposit_requests (start_utxo, recovery_taproot_address, evm_address, amount) VALUES ($1, $2, $3, $4) ON CONFLICT (start_utxo) DO NOTHING;")
+            .bind(start_utxo)
+            .bind(recovery_taproot_address)
+            .bind(evm_address)
+            .bind(amount as i64)
+            .fetch_all(&mut *self.connection.acquire().await?)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_new_deposit_requests(
+        &self,
+        limit: usize,
+    ) -> Result<Vec<DepositRequest>, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["get_new_deposit_requests"]);
+        let qr: Vec<DepositRequestRow> = sqlx::query_as("SELECT start_utxo, recovery_taproot_address, evm_address, amount FROM new_deposit_requests ORDER BY id LIMIT $1;")
+            .bind(limit as i64)
+            .fetch_all(&mut *self.connection.acquire().await?)
+            .await?;
+
+        qr.into_iter().map(DepositRequest::try_from).collect()
+    }
+
+    async fn get_new_deposit_request_count(&self) -> Result<usize, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["get_new_deposit_request_count"]);
+        let qr: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM new_deposit_requests;")
+            .fetch_one(&mut *self.connection.acquire().await?)
+            .await?;
+
+        Ok(qr.0 as usize)
+    }
+
+    async fn remove_new_deposit_request(&self, start_utxo: OutPoint) -> Result<(), BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["remove_new_deposit_request"]);
+        sqlx::query("DELETE FROM new_deposit_requests WHERE start_utxo = $1;")
+            .bind(start_utxo.to_string())
+            .fetch_all(&mut *self.connection.acquire().await?)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_deposit_tx(&self, idx: usize) -> Result<Txid, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["get_deposit_tx"]);
+        let qr: (String,) = sqlx::query_as("SELECT move_txid FROM deposit_move_txs WHERE id = $1;")
+            .bind(idx as i64)
+            .fetch_one(&mut *self.connection.acquire().await?)
+            .await?;
+
+        match Txid::from_str(qr.0.as_str()) {
+            Ok(c) => Ok(c),
+            Err(e) => Err(BridgeError::DatabaseError(sqlx::Error::Decode(Box::new(e)))),
+        }
+    }
+
+    async fn get_bridge_utxo(&self, idx: usize) -> Result<OutPoint, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["get_bridge_utxo"]);
+        let qr: (String, i32) =
+            sqlx::query_as("SELECT move_txid, move_vout FROM deposit_move_txs WHERE id = $1;")
+                .bind(idx as i64)
+                .fetch_one(&mut *self.connection.acquire().await?)
+                .await?;
+
+        Ok(OutPoint {
+            txid: Txid::from_str(&qr.0).map_err(decode_error)?,
+            vout: qr.1 as u32,
+        })
+    }
+
+    async fn get_next_deposit_index(&self) -> Result<usize, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["get_next_deposit_index"]);
+        let qr: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM deposit_move_txs;")
+            .fetch_one(&mut *self.connection.acquire().await?)
+            .await?;
+
+        Ok(qr.0 as usize)
+    }
+
+    async fn insert_move_txid(
+        &self,
+        start_utxo: OutPoint,
+        recovery_taproot_address: Address<NetworkUnchecked>,
+        evm_address: EVMAddress,
+        move_txid: Txid,
+        move_vout: u32,
+    ) -> Result<(), BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["insert_move_txid"]);
+        sqlx::query("INSERT INTO deposit_move_txs (start_utxo, recovery_taproot_address, evm_address, move_txid, move_vout) VALUES ($1, $2, $3, $4, $5);")
+            .bind(start_utxo.to_string())
+            .bind(serde_json::to_string(&recovery_taproot_address).unwrap().trim_matches('"'))
+            .bind(serde_json::to_string(&evm_address).unwrap().trim_matches('"'))
+            .bind(move_txid.to_string())
+            .bind(move_vout as i32)
+            .fetch_all(&mut *self.connection.acquire().await?)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_move_txid(
+        &self,
+        start_utxo: OutPoint,
+        recovery_taproot_address: Address<NetworkUnchecked>,
+        evm_address: EVMAddress,
+    ) -> Result<Txid, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["get_move_txid"]);
+        let qr: (String,) = sqlx::query_as("SELECT (move_txid) FROM deposit_move_txs WHERE start_utxo = $1 AND recovery_taproot_address = $2 AND evm_address = $3;")
+            .bind(start_utxo.to_string())
+            .bind(serde_json::to_string(&recovery_taproot_address).unwrap().trim_matches('"'))
+            .bind(serde_json::to_string(&evm_address).unwrap().trim_matches('"'))
+            .fetch_one(&mut *self.connection.acquire().await?)
+            .await?;
+
+        let move_txid = Txid::from_str(&qr.0).unwrap();
+        Ok(move_txid)
+    }
+
+    async fn get_move_txs(&self) -> Result<Vec<MoveTxRecord>, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["get_move_txs"]);
+        let qr: Vec<(i32, String, String, String, String, i32)> = sqlx::query_as("SELECT id, start_utxo, recovery_taproot_address, evm_address, move_txid, move_vout FROM deposit_move_txs ORDER BY id;")
+            .fetch_all(&mut *self.connection.acquire().await?)
+            .await?;
+
+        qr.into_iter()
+            .map(|row| {
+                Ok(MoveTxRecord {
+                    idx: row.0 as usize,
+                    start_utxo: OutPoint::from_str(&row.1).map_err(decode_error)?,
+                    recovery_taproot_address: Address::from_str(&row.2).map_err(decode_error)?,
+                    evm_address: serde_json::from_str(&format!("\"{}\"", row.3))
+                        .map_err(decode_error)?,
+                    move_txid: Txid::from_str(&row.4).map_err(decode_error)?,
+                    move_vout: row.5 as u32,
+                })
+            })
+            .collect()
+    }
+
+    async fn save_withdrawal_sig(
+        &self,
+        idx: usize,
+        bridge_fund_txid: Txid,
+        sig: secp256k1::schnorr::Signature,
+    ) -> Result<(), BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["save_withdrawal_sig"]);
+        sqlx::query(
+            "INSERT INTO withdrawal_sigs (idx, bridge_fund_txid, sig) VALUES ($1, $2, $3);",
+        )
+        .bind(idx as i64)
+        .bind(bridge_fund_txid.to_string())
+        .bind(sig.to_string())
+        .fetch_all(&mut *self.connection.acquire().await?)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_withdrawal_sig_by_idx(
+        &self,
+        idx: usize,
+    ) -> Result<(Txid, secp256k1::schnorr::Signature), BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["get_withdrawal_sig_by_idx"]);
+        let qr: (String, String) =
+            sqlx::query_as("SELECT bridge_fund_txid, sig FROM withdrawal_sigs WHERE idx = $1;")
+                .bind(idx as i64)
+                .fetch_one(&mut *self.connection.acquire().await?)
+                .await?;
+
+        let bridge_fund_txid = Txid::from_str(&qr.0).unwrap();
+        let sig = secp256k1::schnorr::Signature::from_str(&qr.1).unwrap();
+        Ok((bridge_fund_txid, sig))
+    }
+
+    async fn get_withdrawal_sigs(
+        &self,
+    ) -> Result<Vec<(usize, Txid, secp256k1::schnorr::Signature)>, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["get_withdrawal_sigs"]);
+        let qr: Vec<(i32, String, String)> =
+            sqlx::query_as("SELECT idx, bridge_fund_txid, sig FROM withdrawal_sigs ORDER BY idx;")
+                .fetch_all(&mut *self.connection.acquire().await?)
+                .await?;
+
+        qr.into_iter()
+            .map(|(idx, bridge_fund_txid, sig)| {
+                Ok((
+                    idx as usize,
+                    Txid::from_str(&bridge_fund_txid).map_err(decode_error)?,
+                    secp256k1::schnorr::Signature::from_str(&sig).map_err(decode_error)?,
+                ))
+            })
+            .collect()
+    }
+
+    async fn save_musig2_withdrawal(
+        &self,
+        idx: usize,
+        bridge_fund_txid: Txid,
+    ) -> Result<(), BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["save_musig2_withdrawal"]);
+        sqlx::query(
+            "INSERT INTO musig2_withdrawals (idx, bridge_fund_txid) VALUES ($1, $2) ON CONFLICT (idx) DO NOTHING;",
+        )
+        .bind(idx as i64)
+        .bind(bridge_fund_txid.to_string())
+        .fetch_all(&mut *self.connection.acquire().await?)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_musig2_withdrawal_txid(&self, idx: usize) -> Result<Txid, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["get_musig2_withdrawal_txid"]);
+        let qr: (String,) =
+            sqlx::query_as("SELECT bridge_fund_txid FROM musig2_withdrawals WHERE idx = $1;")
+                .bind(idx as i64)
+                .fetch_one(&mut *self.connection.acquire().await?)
+                .await?;
+
+        let bridge_fund_txid = Txid::from_str(&qr.0).unwrap();
+        Ok(bridge_fund_txid)
+    }
+
+    async fn get_musig2_withdrawals(&self) -> Result<Vec<(usize, Txid)>, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["get_musig2_withdrawals"]);
+        let qr: Vec<(i32, String)> =
+            sqlx::query_as("SELECT idx, bridge_fund_txid FROM musig2_withdrawals ORDER BY idx;")
+                .fetch_all(&mut *self.connection.acquire().await?)
+                .await?;
+
+        qr.into_iter()
+            .map(|(idx, bridge_fund_txid)| {
+                Ok((
+                    idx as usize,
+                    Txid::from_str(&bridge_fund_txid).map_err(decode_error)?,
+                ))
+            })
+            .collect()
+    }
+
+    async fn start_deposit(
+        &self,
+        start_utxo: OutPoint,
+        recovery_taproot_address: Address<NetworkUnchecked>,
+        evm_address: EVMAddress,
+        amount: u64,
+    ) -> Result<(), BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["start_deposit"]);
+        sqlx::query("INSERT INTO deposit_lifecycle (start_utxo, recovery_taproot_address, evm_address, amount, status) VALUES ($1, $2, $3, $4, $5) ON CONFLICT (start_utxo) DO NOTHING;")
+            .bind(start_utxo.to_string())
+            .bind(serde_json::to_string(&recovery_taproot_address).unwrap().trim_matches('"'))
+            .bind(serde_json::to_string(&evm_address).unwrap().trim_matches('"'))
+            .bind(amount as i64)
+            .bind(DepositStatus::Requested.as_str())
+            .fetch_all(&mut *self.connection.acquire().await?)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn set_deposit_presigned(
+        &self,
+        start_utxo: OutPoint,
+        move_tx: &Transaction,
+    ) -> Result<(), BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["set_deposit_presigned"]);
+        sqlx::query("UPDATE deposit_lifecycle SET status = $2, move_tx = $3, move_txid = $4, updated_at = now() WHERE start_utxo = $1;")
+            .bind(start_utxo.to_string())
+            .bind(DepositStatus::PresignsCollected.as_str())
+            .bind(serialize_hex(move_tx))
+            .bind(move_tx.compute_txid().to_string())
+            .fetch_all(&mut *self.connection.acquire().await?)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn set_deposits_presigned(
+        &self,
+        start_utxos: &[OutPoint],
+        move_tx: &Transaction,
+    ) -> Result<(), BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["set_deposits_presigned"]);
+        let mut connection = self.connection.acquire().await?;
+        let mut transaction = connection.begin().await?;
+
+        for start_utxo in start_utxos {
+            sqlx::query("UPDATE deposit_lifecycle SET status = $2, move_tx = $3, move_txid = $4, updated_at = now() WHERE start_utxo = $1;")
+                .bind(start_utxo.to_string())
+                .bind(DepositStatus::PresignsCollected.as_str())
+                .bind(serialize_hex(move_tx))
+                .bind(move_tx.compute_txid().to_string())
+                .execute(&mut *transaction)
+                .await?;
+            sqlx::query("DELETE FROM new_deposit_requests WHERE start_utxo = $1;")
+                .bind(start_utxo.to_string())
+                .execute(&mut *transaction)
+                .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    async fn set_deposit_status(
+        &self,
+        start_utxo: OutPoint,
+        status: DepositStatus,
+    ) -> Result<(), BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["set_deposit_status"]);
+        sqlx::query(
+            "UPDATE deposit_lifecycle SET status = $2, updated_at = now() WHERE start_utxo = $1;",
+        )
+        .bind(start_utxo.to_string())
+        .bind(status.as_str())
+        .fetch_all(&mut *self.connection.acquire().await?)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_deposit_record(&self, start_utxo: OutPoint) -> Result<DepositRecord, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["get_deposit_record"]);
+        let qr: DepositRecordRow = sqlx::query_as(&format!(
+            "SELECT {} FROM deposit_lifecycle WHERE start_utxo = $1;",
+            DEPOSIT_RECORD_COLUMNS
+        ))
+        .bind(start_utxo.to_string())
+        .fetch_one(&mut *self.connection.acquire().await?)
+        .await?;
+
+        DepositRecord::try_from(qr)
+    }
+
+    async fn restore_deposit_record(&self, record: &DepositRecord) -> Result<(), BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["restore_deposit_record"]);
+        let recovery_taproot_address = serde_json::to_string(&record.recovery_taproot_address)
+            .unwrap()
+            .trim_matches('"')
+            .to_owned();
+        let evm_address = serde_json::to_string(&record.evm_address)
+            .unwrap()
+            .trim_matches('"')
+            .to_owned();
+
+        sqlx::query("INSERT INTO deposit_lifecycle (start_utxo, recovery_taproot_address, evm_address, amount, status, move_tx, move_txid, deposit_block_hash, move_block_hash, reorged) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10);")
+            .bind(record.start_utxo.to_string())
+            .bind(recovery_taproot_address)
+            .bind(evm_address)
+            .bind(record.amount as i64)
+            .bind(record.status.as_str())
+            .bind(record.move_tx.as_ref().map(serialize_hex))
+            .bind(record.move_tx.as_ref().map(|move_tx| move_tx.compute_txid().to_string()))
+            .bind(record.deposit_block_hash.map(|block_hash| block_hash.to_string()))
+            .bind(record.move_block_hash.map(|block_hash| block_hash.to_string()))
+            .bind(record.reorged)
+            .execute(&mut *self.connection.acquire().await?)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_moved_deposits(&self) -> Result<Vec<DepositRecord>, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["get_moved_deposits"]);
+        let qr: Vec<DepositRecordRow> = sqlx::query_as(&format!(
+            "SELECT {} FROM deposit_lifecycle WHERE status IN ($1, $2) ORDER BY created_at, start_utxo;",
+            DEPOSIT_RECORD_COLUMNS
+        ))
+        .bind(DepositStatus::MoveConfirmed.as_str())
+        .bind(DepositStatus::Minted.as_str())
+        .fetch_all(&mut *self.connection.acquire().await?)
+        .await?;
+
+        qr.into_iter().map(DepositRecord::try_from).collect()
+    }
+
+    async fn list_deposit_records(
+        &self,
+        evm_address: Option<EVMAddress>,
+        status: Option<DepositStatus>,
+        offset: u64,
+        limit: u64,
+    ) -> Result<Vec<DepositRecord>, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["list_deposit_records"]);
+        let qr: Vec<DepositRecordRow> = sqlx::query_as(&format!("SELECT {} FROM deposit_lifecycle WHERE ($1::text IS NULL OR evm_address = $1) AND ($2::text IS NULL OR status = $2) ORDER BY created_at, start_utxo OFFSET $3 LIMIT $4;", DEPOSIT_RECORD_COLUMNS))
+            .bind(evm_address.map(|evm_address| serde_json::to_string(&evm_address).unwrap().trim_matches('"').to_string()))
+            .bind(status.map(|status| status.as_str()))
+            .bind(offset as i64)
+            .bind(limit as i64)
+            .fetch_all(&mut *self.connection.acquire().await?)
+            .await?;
+
+        qr.into_iter().map(DepositRecord::try_from).collect()
+    }
+
+    async fn get_deposit_records_by_move_txid(
+        &self,
+        move_txid: Txid,
+    ) -> Result<Vec<DepositRecord>, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["get_deposit_records_by_move_txid"]);
+        let qr: Vec<DepositRecordRow> = sqlx::query_as(&format!(
+            "SELECT {} FROM deposit_lifecycle WHERE move_txid = $1 ORDER BY created_at, start_utxo;",
+            DEPOSIT_RECORD_COLUMNS
+        ))
+        .bind(move_txid.to_string())
+        .fetch_all(&mut *self.connection.acquire().await?)
+        .await?;
+
+        qr.into_iter().map(DepositRecord::try_from).collect()
+    }
+
+    async fn get_unfinished_deposits(
+        &self,
+        idle_secs: u64,
+    ) -> Result<Vec<DepositRecord>, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["get_unfinished_deposits"]);
+        let qr: Vec<DepositRecordRow> = sqlx::query_as(&format!("SELECT {} FROM deposit_lifecycle WHERE status IN ($1, $2, $3) AND updated_at <= now() - make_interval(secs => $4) ORDER BY created_at;", DEPOSIT_RECORD_COLUMNS))
+            .bind(DepositStatus::Requested.as_str())
+            .bind(DepositStatus::PresignsCollected.as_str())
+            .bind(DepositStatus::MoveBroadcast.as_str())
+            .bind(idle_secs as f64)
+            .fetch_all(&mut *self.connection.acquire().await?)
+            .await?;
+
+        qr.into_iter().map(DepositRecord::try_from).collect()
+    }
+
+    async fn get_unminted_deposits(
+        &self,
+        idle_secs: u64,
+    ) -> Result<Vec<DepositRecord>, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["get_unminted_deposits"]);
+        let qr: Vec<DepositRecordRow> = sqlx::query_as(&format!("SELECT {} FROM deposit_lifecycle WHERE status = $1 AND updated_at <= now() - make_interval(secs => $2) ORDER BY created_at;", DEPOSIT_RECORD_COLUMNS))
+            .bind(DepositStatus::MoveConfirmed.as_str())
+            .bind(idle_secs as f64)
+            .fetch_all(&mut *self.connection.acquire().await?)
+            .await?;
+
+        qr.into_iter().map(DepositRecord::try_from).collect()
+    }
+
+    async fn set_deposit_block_hash(
+        &self,
+        start_utxo: OutPoint,
+        block_hash: BlockHash,
+    ) -> Result<(), BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["set_deposit_block_hash"]);
+        sqlx::query(
+            "UPDATE deposit_lifecycle SET deposit_block_hash = $2, updated_at = now() WHERE start_utxo = $1;",
+        )
+        .bind(start_utxo.to_string())
+        .bind(block_hash.to_string())
+        .fetch_all(&mut *self.connection.acquire().await?)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn set_deposit_confirmed(
+        &self,
+        start_utxo: OutPoint,
+        move_block_hash: BlockHash,
+    ) -> Result<(), BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["set_deposit_confirmed"]);
+        sqlx::query("UPDATE deposit_lifecycle SET status = $2, move_block_hash = $3, updated_at = now() WHERE start_utxo = $1;")
+            .bind(start_utxo.to_string())
+            .bind(DepositStatus::MoveConfirmed.as_str())
+            .bind(move_block_hash.to_string())
+            .fetch_all(&mut *self.connection.acquire().await?)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn rollback_deposits_in_block(
+        &self,
+        block_hash: BlockHash,
+    ) -> Result<Vec<OutPoint>, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["rollback_deposits_in_block"]);
+        let mut connection = self.connection.acquire().await?;
+        let mut transaction = connection.begin().await?;
+
+        let move_qr: Vec<(String,)> = sqlx::query_as("UPDATE deposit_lifecycle SET status = CASE WHEN status IN ($2, $4) THEN $3 ELSE status END, move_block_hash = NULL, reorged = true, updated_at = now() WHERE move_block_hash = $1 RETURNING start_utxo;")
+            .bind(block_hash.to_string())
+            .bind(DepositStatus::MoveConfirmed.as_str())
+            .bind(DepositStatus::MoveBroadcast.as_str())
+            .bind(DepositStatus::Minted.as_str())
+            .fetch_all(&mut *transaction)
+            .await?;
+        let deposit_qr: Vec<(String,)> = sqlx::query_as("UPDATE deposit_lifecycle SET deposit_block_hash = NULL, reorged = true, updated_at = now() WHERE deposit_block_hash = $1 RETURNING start_utxo;")
+            .bind(block_hash.to_string())
+            .fetch_all(&mut *transaction)
+            .await?;
+
+        transaction.commit().await?;
+
+        let mut start_utxos = move_qr
+            .into_iter()
+            .chain(deposit_qr)
+            .map(|(start_utxo,)| OutPoint::from_str(&start_utxo).map_err(decode_error))
+            .collect::<Result<Vec<_>, _>>()?;
+        start_utxos.sort();
+        start_utxos.dedup();
+
+        Ok(start_utxos)
+    }
+
+    async fn save_withdrawal_tx(
+        &self,
+        idx: usize,
+        bridge_fund_txid: Txid,
+        withdrawal_address: Address<NetworkUnchecked>,
+        withdrawal_tx: &Transaction,
+    ) -> Result<(), BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["save_withdrawal_tx"]);
+        sqlx::query("INSERT INTO withdrawal_txs (idx, bridge_fund_txid, withdrawal_address, withdrawal_tx, withdrawal_txid, status) VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT (idx) DO NOTHING;")
+            .bind(idx as i64)
+            .bind(bridge_fund_txid.to_string())
+            .bind(serde_json::to_string(&withdrawal_address).unwrap().trim_matches('"'))
+            .bind(serialize_hex(withdrawal_tx))
+            .bind(withdrawal_tx.compute_txid().to_string())
+            .bind(WithdrawalStatus::Broadcast.as_str())
+            .fetch_all(&mut *self.connection.acquire().await?)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_withdrawal_record(&self, idx: usize) -> Result<WithdrawalRecord, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["get_withdrawal_record"]);
+        let qr: WithdrawalRecordRow = sqlx::query_as(&format!(
+            "SELECT {} FROM withdrawal_txs WHERE idx = $1;",
+            WITHDRAWAL_RECORD_COLUMNS
+        ))
+        .bind(idx as i64)
+        .fetch_one(&mut *self.connection.acquire().await?)
+        .await?;
+
+        WithdrawalRecord::try_from(qr)
+    }
+
+    async fn restore_withdrawal_record(
+        &self,
+        record: &WithdrawalRecord,
+    ) -> Result<(), BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["restore_withdrawal_record"]);
+        sqlx::query("INSERT INTO withdrawal_txs (idx, bridge_fund_txid, withdrawal_address, withdrawal_tx, withdrawal_txid, status, block_hash, reorged) VALUES ($1, $2, $3, $4, $5, $6, $7, $8);")
+            .bind(record.idx as i64)
+            .bind(record.bridge_fund_txid.to_string())
+            .bind(serde_json::to_string(&record.withdrawal_address).unwrap().trim_matches('"'))
+            .bind(serialize_hex(&record.withdrawal_tx))
+            .bind(record.withdrawal_tx.compute_txid().to_string())
+            .bind(record.status.as_str())
+            .bind(record.block_hash.map(|block_hash| block_hash.to_string()))
+            .bind(record.reorged)
+            .execute(&mut *self.connection.acquire().await?)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_confirmed_withdrawals(&self) -> Result<Vec<WithdrawalRecord>, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["get_confirmed_withdrawals"]);
+        let qr: Vec<WithdrawalRecordRow> = sqlx::query_as(&format!(
+            "SELECT {} FROM withdrawal_txs WHERE status = $1 ORDER BY idx;",
+            WITHDRAWAL_RECORD_COLUMNS
+        ))
+        .bind(WithdrawalStatus::Confirmed.as_str())
+        .fetch_all(&mut *self.connection.acquire().await?)
+        .await?;
+
+        qr.into_iter().map(WithdrawalRecord::try_from).collect()
+    }
+
+    async fn list_withdrawal_records(
+        &self,
+        status: Option<WithdrawalStatus>,
+        offset: u64,
+        limit: u64,
+    ) -> Result<Vec<WithdrawalRecord>, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["list_withdrawal_records"]);
+        let qr: Vec<WithdrawalRecordRow> = sqlx::query_as(&format!("SELECT {} FROM withdrawal_txs WHERE ($1::text IS NULL OR status = $1) ORDER BY idx OFFSET $2 LIMIT $3;", WITHDRAWAL_RECORD_COLUMNS))
+            .bind(status.map(|status| status.as_str()))
+            .bind(offset as i64)
+            .bind(limit as i64)
+            .fetch_all(&mut *self.connection.acquire().await?)
+            .await?;
+
+        qr.into_iter().map(WithdrawalRecord::try_from).collect()
+    }
+
+    async fn get_pending_record_counts(&self) -> Result<(usize, usize), BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["get_pending_record_counts"]);
+        let (deposits, withdrawals): (i64, i64) = sqlx::query_as("SELECT (SELECT COUNT(*) FROM deposit_lifecycle WHERE status IN ($1, $2, $3)), (SELECT COUNT(*) FROM withdrawal_txs WHERE status = $4);")
+            .bind(DepositStatus::Requested.as_str())
+            .bind(DepositStatus::PresignsCollected.as_str())
+            .bind(DepositStatus::MoveBroadcast.as_str())
+            .bind(WithdrawalStatus::Broadcast.as_str())
+            .fetch_one(&mut *self.connection.acquire().await?)
+            .await?;
+
+        Ok((deposits as usize, withdrawals as usize))
+    }
+
+    async fn get_unconfirmed_withdrawals(
+        &self,
+        idle_secs: u64,
+    ) -> Result<Vec<WithdrawalRecord>, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["get_unconfirmed_withdrawals"]);
+        let qr: Vec<WithdrawalRecordRow> = sqlx::query_as(&format!("SELECT {} FROM withdrawal_txs WHERE status = $1 AND updated_at <= now() - make_interval(secs => $2) ORDER BY idx;", WITHDRAWAL_RECORD_COLUMNS))
+            .bind(WithdrawalStatus::Broadcast.as_str())
+            .bind(idle_secs as f64)
+            .fetch_all(&mut *self.connection.acquire().await?)
+            .await?;
+
+        qr.into_iter().map(WithdrawalRecord::try_from).collect()
+    }
+
+    async fn set_withdrawal_confirmed(
+        &self,
+        idx: usize,
+        block_hash: BlockHash,
+    ) -> Result<(), BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["set_withdrawal_confirmed"]);
+        sqlx::query("UPDATE withdrawal_txs SET status = $2, block_hash = $3, updated_at = now() WHERE idx = $1;")
+            .bind(idx as i64)
+            .bind(WithdrawalStatus::Confirmed.as_str())
+            .bind(block_hash.to_string())
+            .fetch_all(&mut *self.connection.acquire().await?)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn rollback_withdrawals_in_block(
+        &self,
+        block_hash: BlockHash,
+    ) -> Result<Vec<usize>, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["rollback_withdrawals_in_block"]);
+        let qr: Vec<(i32,)> = sqlx::query_as("UPDATE withdrawal_txs SET status = $2, block_hash = NULL, reorged = true, updated_at = now() WHERE block_hash = $1 RETURNING idx;")
+            .bind(block_hash.to_string())
+            .bind(WithdrawalStatus::Broadcast.as_str())
+            .fetch_all(&mut *self.connection.acquire().await?)
+            .await?;
+
+        Ok(qr.into_iter().map(|(idx,)| idx as usize).collect())
+    }
+
+    async fn get_record_block_hashes(&self) -> Result<HashSet<BlockHash>, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["get_record_block_hashes"]);
+        let qr: Vec<(String,)> = sqlx::query_as("SELECT deposit_block_hash FROM deposit_lifecycle WHERE deposit_block_hash IS NOT NULL UNION SELECT move_block_hash FROM deposit_lifecycle WHERE move_block_hash IS NOT NULL UNION SELECT block_hash FROM withdrawal_txs WHERE block_hash IS NOT NULL;")
+            .fetch_all(&mut *self.connection.acquire().await?)
+            .await?;
+
+        qr.into_iter()
+            .map(|(block_hash,)| BlockHash::from_str(&block_hash).map_err(decode_error))
+            .collect()
+    }
+
+    async fn get_last_indexed_block(&self) -> Result<Option<(u64, BlockHash)>, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["get_last_indexed_block"]);
+        let qr: Option<(i64, String)> =
+            sqlx::query_as("SELECT height, hash FROM blocks ORDER BY height DESC LIMIT 1;")
+                .fetch_optional(&mut *self.connection.acquire().await?)
+                .await?;
+
+        match qr {
+            Some((height, hash)) => Ok(Some((
+                height as u64,
+                BlockHash::from_str(&hash).map_err(decode_error)?,
+            ))),
+            None => Ok(None),
+        }
+    }
+
+    async fn connect_block(&self, block: &IndexedBlock) -> Result<(), BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["connect_block"]);
+        let mut connection = self.connection.acquire().await?;
+        let mut transaction = connection.begin().await?;
+
+        sqlx::query("INSERT INTO blocks (height, hash, prev_hash) VALUES ($1, $2, $3);")
+            .bind(block.height as i64)
+            .bind(block.hash.to_string())
+            .bind(block.prev_hash.to_string())
+            .execute(&mut *transaction)
+            .await?;
+
+        for (outpoint, script_pubkey, amount) in block.deposit_utxos.iter() {
+            sqlx::query("INSERT INTO deposit_utxos (outpoint, script_pubkey, amount, block_hash) VALUES ($1, $2, $3, $4);")
+                .bind(outpoint.to_string())
+                .bind(script_pubkey.to_hex_string())
+                .bind(amount.to_sat() as i64)
+                .bind(block.hash.to_string())
+                .execute(&mut *transaction)
+                .await?;
+        }
+
+        for (outpoint, amount) in block.bridge_utxos.iter() {
+            sqlx::query(
+                "INSERT INTO bridge_utxos (outpoint, amount, block_hash) VALUES ($1, $2, $3);",
+            )
+            .bind(outpoint.to_string())
+            .bind(amount.to_sat() as i64)
+            .bind(block.hash.to_string())
+            .execute(&mut *transaction)
+            .await?;
+        }
+
+        for (outpoint, spending_txid) in block.spent_bridge_utxos.iter() {
+            sqlx::query("UPDATE bridge_utxos SET spending_txid = $2, spending_block_hash = $3 WHERE outpoint = $1;")
+                .bind(outpoint.to_string())
+                .bind(spending_txid.to_string())
+                .bind(block.hash.to_string())
+                .execute(&mut *transaction)
+                .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    async fn disconnect_block(&self, block_hash: BlockHash) -> Result<(), BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["disconnect_block"]);
+        let mut connection = self.connection.acquire().await?;
+        let mut transaction = connection.begin().await?;
+
+        sqlx::query("UPDATE bridge_utxos SET spending_txid = NULL, spending_block_hash = NULL WHERE spending_block_hash = $1;")
+            .bind(block_hash.to_string())
+            .execute(&mut *transaction)
+            .await?;
+        // Deposit and bridge UTXOs created in this block are deleted with it.
+        sqlx::query("DELETE FROM blocks WHERE hash = $1;")
+            .bind(block_hash.to_string())
+            .execute(&mut *transaction)
+            .await?;
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    async fn add_watched_deposit_script(
+        &self,
+        script_pubkey: &ScriptBuf,
+    ) -> Result<(), BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["add_watched_deposit_script"]);
+        sqlx::query("INSERT INTO watched_deposit_scripts (script_pubkey) VALUES ($1) ON CONFLICT (script_pubkey) DO NOTHING;")
+            .bind(script_pubkey.to_hex_string())
+            .fetch_all(&mut *self.connection.acquire().await?)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_watched_deposit_scripts(&self) -> Result<HashSet<ScriptBuf>, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["get_watched_deposit_scripts"]);
+        let qr: Vec<(String,)> =
+            sqlx::query_as("SELECT script_pubkey FROM watched_deposit_scripts;")
+                .fetch_all(&mut *self.connection.acquire().await?)
+                .await?;
+
+        qr.into_iter()
+            .map(|(script_pubkey,)| ScriptBuf::from_hex(&script_pubkey).map_err(decode_error))
+            .collect()
+    }
+
+    async fn get_unspent_bridge_utxos(&self) -> Result<Vec<(OutPoint, Amount)>, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["get_unspent_bridge_utxos"]);
+        let qr: Vec<(String, i64)> = sqlx::query_as(
+            "SELECT outpoint, amount FROM bridge_utxos WHERE spending_txid IS NULL ORDER BY created_at;",
+        )
+        .fetch_all(&mut *self.connection.acquire().await?)
+        .await?;
+
+        qr.into_iter()
+            .map(|(outpoint, amount)| {
+                Ok((
+                    OutPoint::from_str(&outpoint).map_err(decode_error)?,
+                    Amount::from_sat(amount as u64),
+                ))
+            })
+            .collect()
+    }
+
+    async fn get_bridge_balance(&self) -> Result<(Amount, usize), BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["get_bridge_balance"]);
+        let qr: (i64, i64) = sqlx::query_as(
+            "SELECT COALESCE(SUM(amount), 0)::bigint, COUNT(*) FROM bridge_utxos WHERE spending_txid IS NULL;",
+        )
+        .fetch_one(&mut *self.connection.acquire().await?)
+        .await?;
+
+        Ok((Amount::from_sat(qr.0 as u64), qr.1 as usize))
+    }
+
+    async fn is_withdrawal_signed(&self, bridge_utxo: OutPoint) -> Result<bool, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["is_withdrawal_signed"]);
+        let qr: (bool,) = sqlx::query_as("SELECT ($2 = 0 AND (EXISTS (SELECT 1 FROM withdrawal_sigs WHERE bridge_fund_txid = $1) OR EXISTS (SELECT 1 FROM musig2_withdrawals WHERE bridge_fund_txid = $1))) OR EXISTS (SELECT 1 FROM withdrawal_ledger WHERE bridge_utxo = $3);")
+            .bind(bridge_utxo.txid.to_string())
+            .bind(bridge_utxo.vout as i64)
+            .bind(bridge_utxo.to_string())
+            .fetch_one(&mut *self.connection.acquire().await?)
+            .await?;
+
+        Ok(qr.0)
+    }
+
+    async fn get_signing_policy(&self) -> Result<SigningPolicy, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["get_signing_policy"]);
+        let qr: Option<SigningPolicyRow> = sqlx::query_as("SELECT daily_limit, evm_address_daily_limit, max_outstanding_withdrawals, approval_threshold FROM signing_policy;")
+            .fetch_optional(&mut *self.connection.acquire().await?)
+            .await?;
+
+        Ok(qr.map(SigningPolicy::from).unwrap_or_default())
+    }
+
+    async fn set_signing_policy(&self, policy: &SigningPolicy) -> Result<(), BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["set_signing_policy"]);
+        sqlx::query("INSERT INTO signing_policy (daily_limit, evm_address_daily_limit, max_outstanding_withdrawals, approval_threshold) VALUES ($1, $2, $3, $4) ON CONFLICT (id) DO UPDATE SET daily_limit = $1, evm_address_daily_limit = $2, max_outstanding_withdrawals = $3, approval_threshold = $4, updated_at = now();")
+            .bind(policy.daily_limit.map(|limit| limit as i64))
+            .bind(policy.evm_address_daily_limit.map(|limit| limit as i64))
+            .bind(policy.max_outstanding_withdrawals.map(|max| max as i32))
+            .bind(policy.approval_threshold.map(|threshold| threshold as i64))
+            .fetch_all(&mut *self.connection.acquire().await?)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_policy_addresses(&self) -> Result<Vec<ListedAddress>, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["get_policy_addresses"]);
+        let qr: Vec<(String, String, String)> =
+            sqlx::query_as("SELECT list, kind, address FROM policy_addresses ORDER BY created_at;")
+                .fetch_all(&mut *self.connection.acquire().await?)
+                .await?;
+
+        qr.into_iter()
+            .map(|(list, kind, address)| {
+                Ok(ListedAddress {
+                    list: AddressList::from_str(&list)?,
+                    address: PolicyAddress::from_parts(&kind, &address)?,
+                })
+            })
+            .collect()
+    }
+
+    async fn add_policy_address(&self, listed: &ListedAddress) -> Result<(), BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["add_policy_address"]);
+        sqlx::query("INSERT INTO policy_addresses (list, kind, address) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING;")
+            .bind(listed.list.as_str())
+            .bind(listed.address.kind())
+            .bind(listed.address.address())
+            .fetch_all(&mut *self.connection.acquire().await?)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn remove_policy_address(&self, listed: &ListedAddress) -> Result<bool, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["remove_policy_address"]);
+        let res = sqlx::query(
+            "DELETE FROM policy_addresses WHERE list = $1 AND kind = $2 AND address = $3;",
+        )
+        .bind(listed.list.as_str())
+        .bind(listed.address.kind())
+        .bind(listed.address.address())
+        .execute(&mut *self.connection.acquire().await?)
+        .await?;
+
+        Ok(res.rows_affected() > 0)
+    }
+
+    async fn add_signed_deposits(&self, deposits: &[SignedDeposit]) -> Result<(), BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["add_signed_deposits"]);
+        let mut connection = self.connection.acquire().await?;
+        let mut transaction = connection.begin().await?;
+
+        for deposit in deposits {
+            let evm_address = serde_json::to_string(&deposit.evm_address)
+                .unwrap()
+                .trim_matches('"')
+                .to_owned();
+
+            sqlx::query("INSERT INTO signed_deposits (start_utxo, evm_address, amount) VALUES ($1, $2, $3) ON CONFLICT (start_utxo) DO NOTHING;")
+                .bind(deposit.start_utxo.to_string())
+                .bind(evm_address)
+                .bind(deposit.amount as i64)
+                .execute(&mut *transaction)
+                .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    async fn get_signed_deposits(
+        &self,
+        window_secs: u64,
+    ) -> Result<Vec<SignedDeposit>, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["get_signed_deposits"]);
+        let qr: Vec<(String, String, i64)> = sqlx::query_as("SELECT start_utxo, evm_address, amount FROM signed_deposits WHERE created_at > now() - make_interval(secs => $1);")
+            .bind(window_secs as f64)
+            .fetch_all(&mut *self.connection.acquire().await?)
+            .await?;
+
+        qr.into_iter()
+            .map(|(start_utxo, evm_address, amount)| {
+                Ok(SignedDeposit {
+                    start_utxo: OutPoint::from_str(&start_utxo).map_err(decode_error)?,
+                    evm_address: serde_json::from_str(&format!("\"{}\"", evm_address))
+                        .map_err(decode_error)?,
+                    amount: amount as u64,
+                })
+            })
+            .collect()
+    }
+
+    async fn add_deposit_approval(&self, deposit: &DepositRequest) -> Result<(), BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["add_deposit_approval"]);
+        let recovery_taproot_address = serde_json::to_string(&deposit.recovery_taproot_address)
+            .unwrap()
+            .trim_matches('"')
+            .to_owned();
+        let evm_address = serde_json::to_string(&deposit.evm_address)
+            .unwrap()
+            .trim_matches('"')
+            .to_owned();
+
+        sqlx::query("INSERT INTO deposit_approvals (start_utxo, recovery_taproot_address, evm_address, amount, status) VALUES ($1, $2, $3, $4, $5) ON CONFLICT (start_utxo) DO NOTHING;")
+            .bind(deposit.start_utxo.to_string())
+            .bind(recovery_taproot_address)
+            .bind(evm_address)
+            .bind(deposit.amount as i64)
+            .bind(ApprovalStatus::Pending.as_str())
+            .fetch_all(&mut *self.connection.acquire().await?)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_deposit_approval_status(
+        &self,
+        start_utxo: OutPoint,
+    ) -> Result<Option<ApprovalStatus>, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["get_deposit_approval_status"]);
+        let qr: Option<(String,)> =
+            sqlx::query_as("SELECT status FROM deposit_approvals WHERE start_utxo = $1;")
+                .bind(start_utxo.to_string())
+                .fetch_optional(&mut *self.connection.acquire().await?)
+                .await?;
+
+        qr.map(|(status,)| ApprovalStatus::from_str(&status))
+            .transpose()
+    }
+
+    async fn set_deposit_approval_status(
+        &self,
+        start_utxo: OutPoint,
+        status: ApprovalStatus,
+    ) -> Result<(), BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["set_deposit_approval_status"]);
+        let res = sqlx::query("UPDATE deposit_approvals SET status = $2, updated_at = now() WHERE start_utxo = $1 AND status = $3;")
+            .bind(start_utxo.to_string())
+            .bind(status.as_str())
+            .bind(ApprovalStatus::Pending.as_str())
+            .execute(&mut *self.connection.acquire().await?)
+            .await?;
+
+        if res.rows_affected() == 0 {
+            return Err(BridgeError::PolicyViolation(format!(
+                "Deposit {} is not waiting for approval",
+                start_utxo
+            )));
+        }
+
+        Ok(())
+    }
+
+    async fn list_deposit_approvals(
+        &self,
+        status: Option<ApprovalStatus>,
+    ) -> Result<Vec<DepositApproval>, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["list_deposit_approvals"]);
+        let qr: Vec<(String, String, String, i64, String)> = sqlx::query_as("SELECT start_utxo, recovery_taproot_address, evm_address, amount, status FROM deposit_approvals WHERE ($1::text IS NULL OR status = $1) ORDER BY created_at;")
+            .bind(status.map(|status| status.as_str()))
+            .fetch_all(&mut *self.connection.acquire().await?)
+            .await?;
+
+        qr.into_iter()
+            .map(|row| {
+                Ok(DepositApproval {
+                    status: ApprovalStatus::from_str(&row.4)?,
+                    deposit: DepositRequest::try_from((row.0, row.1, row.2, row.3))?,
+                })
+            })
+            .collect()
+    }
+
+    async fn is_withdrawal_idx_signed(&self, idx: usize) -> Result<bool, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["is_withdrawal_idx_signed"]);
+        let qr: (bool,) = sqlx::query_as("SELECT EXISTS (SELECT 1 FROM withdrawal_sigs WHERE idx = $1) OR EXISTS (SELECT 1 FROM musig2_withdrawals WHERE idx = $1) OR EXISTS (SELECT 1 FROM withdrawal_ledger WHERE idx = $1);")
+            .bind(idx as i32)
+            .fetch_one(&mut *self.connection.acquire().await?)
+            .await?;
+
+        Ok(qr.0)
+    }
+
+    async fn get_outstanding_withdrawal_count(&self) -> Result<usize, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["get_outstanding_withdrawal_count"]);
+        let qr: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM (SELECT bridge_fund_txid || ':0' AS bridge_utxo FROM withdrawal_sigs UNION SELECT bridge_fund_txid || ':0' FROM musig2_withdrawals UNION SELECT bridge_utxo FROM withdrawal_ledger) AS signed WHERE NOT EXISTS (SELECT 1 FROM bridge_utxos WHERE outpoint = signed.bridge_utxo AND spending_txid IS NOT NULL);")
+            .fetch_one(&mut *self.connection.acquire().await?)
+            .await?;
+
+        Ok(qr.0 as usize)
+    }
+
+    async fn add_withdrawal_ledger_entry(
+        &self,
+        entry: &WithdrawalLedgerEntry,
+    ) -> Result<(), BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["add_withdrawal_ledger_entry"]);
+        sqlx::query("INSERT INTO withdrawal_ledger (idx, bridge_utxo, withdrawal_address) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING;")
+            .bind(entry.idx as i64)
+            .bind(entry.bridge_utxo.to_string())
+            .bind(serde_json::to_string(&entry.withdrawal_address).unwrap().trim_matches('"'))
+            .fetch_all(&mut *self.connection.acquire().await?)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_withdrawal_ledger_entries(
+        &self,
+        idx: usize,
+        bridge_utxo: OutPoint,
+    ) -> Result<Vec<WithdrawalLedgerEntry>, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["get_withdrawal_ledger_entries"]);
+        let qr: Vec<WithdrawalLedgerRow> = sqlx::query_as("SELECT idx, bridge_utxo, withdrawal_address FROM withdrawal_ledger WHERE idx = $1 OR bridge_utxo = $2 ORDER BY idx;")
+            .bind(idx as i64)
+            .bind(bridge_utxo.to_string())
+            .fetch_all(&mut *self.connection.acquire().await?)
+            .await?;
+
+        qr.into_iter()
+            .map(WithdrawalLedgerEntry::try_from)
+            .collect()
+    }
+
+    async fn get_withdrawal_ledger(&self) -> Result<Vec<WithdrawalLedgerEntry>, BridgeError> {
+        let _timer = metrics::DB_QUERY_DURATION.start_timer(&["get_withdrawal_ledger"]);
+        let qr: Vec<WithdrawalLedgerRow> = sqlx::query_as(
+            "SELECT idx, bridge_utxo, withdrawal_address FROM withdrawal_ledger ORDER BY idx;",
+        )
+        .fetch_all(&mut *self.connection.acquire().await?)
+        .await?;
+
+        qr.into_iter()
+            .map(WithdrawalLedgerEntry::try_from)
+            .collect()
+    }
+}
+
+#[async_trait]
+impl StorageTransaction for PostgresStorage {
+    async fn commit(self: Box<Self>) -> Result<(), BridgeError> {
+        self.connection.commit().await
+    }
+
+    async fn rollback(self: Box<Self>) -> Result<(), BridgeError> {
+        self.connection.rollback().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PostgresStorage;
+    use crate::config::DatabaseBackend;
+    use crate::database::common::Database;
+    use crate::database::migrations::{Migration, Role};
+    use crate::mock::common;
+    use crate::EVMAddress;
+    use bitcoin::hashes::Hash;
+    use bitcoin::{Address, OutPoint, Txid};
+    use clementine_circuits::constants::BRIDGE_AMOUNT_SATS;
+    use secp256k1::schnorr::Signature;
+    use secp256k1::Secp256k1;
+    use sqlx::Executor;
+
+    #[tokio::test]
+    async fn migrate_legacy_schema() {
+        let config = common::get_test_config("test_config.toml").unwrap();
+        // Tests can be run without a PostgreSQL server.
+        if !matches!(config.db_backend, DatabaseBackend::Postgres) {
+            return;
+        }
+        let config = Database::create_database(config, "postgres_legacy_schema")
+            .await
+            .unwrap();
+
+        // Build the database the way it was built before migrations, with
+        // records of that time.
+        let storage = PostgresStorage::new(&config).await.unwrap();
+        let mut connection = storage.connection.acquire().await.unwrap();
+        (&mut *connection)
+            .execute(include_str!("../../tests/data/baseline_schema.sql"))
+            .await
+            .unwrap();
+        let start_utxo = OutPoint::new(Txid::from_byte_array([0x45u8; 32]), 0);
+        let address = Address::p2tr(
+            &Secp256k1::new(),
+            *crate::utils::UNSPENDABLE_XONLY_PUBKEY,
+            None,
+            config.network,
+        );
+        let evm_address = "45".repeat(20);
+        for _ in 0..2 {
+            sqlx::query("INSERT INTO new_deposit_requests (start_utxo, recovery_taproot_address, evm_address) VALUES ($1, $2, $3);")
+                .bind(start_utxo.to_string())
+                .bind(address.to_string())
+                .bind(&evm_address)
+                .execute(&mut *connection)
+                .await
+                .unwrap();
+        }
+        let move_txid = Txid::from_byte_array([0x46u8; 32]);
+        sqlx::query("INSERT INTO deposit_move_txs (start_utxo, recovery_taproot_address, evm_address, move_txid) VALUES ($1, $2, $3, $4);")
+            .bind(start_utxo.to_string())
+            .bind(address.to_string())
+            .bind(&evm_address)
+            .bind(move_txid.to_string())
+            .execute(&mut *connection)
+            .await
+            .unwrap();
+        let signature = Signature::from_slice(&[0x45u8; 64]).unwrap();
+        sqlx::query(
+            "INSERT INTO withdrawal_sigs (idx, bridge_fund_txid, sig) VALUES ($1, $2, $3);",
+        )
+        .bind(0)
+        .bind(move_txid.to_string())
+        .bind(signature.to_string())
+        .execute(&mut *connection)
+        .await
+        .unwrap();
+        drop(connection);
+        storage.connection.close().await;
+
+        let db = Database::new(config).await.unwrap();
+        let versions =
+            |migrations: Vec<&Migration>| migrations.iter().map(|m| m.version).collect::<Vec<_>>();
+        assert_eq!(
+            versions(db.migrate(&[Role::Operator]).await.unwrap()),
+            vec![1, 2, 3]
+        );
+        assert_eq!(
+            versions(db.migrate(&[Role::Verifier]).await.unwrap()),
+            vec![4, 5]
+        );
+        assert!(db
+            .get_pending_migrations(&[Role::Operator, Role::Verifier])
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Records of the old schema are kept, in the new schema.
+        let requests = db.get_new_deposit_requests(10).await.unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].amount, BRIDGE_AMOUNT_SATS);
+        assert_eq!(db.get_next_deposit_index().await.unwrap(), 1);
+        assert_eq!(
+            db.get_bridge_utxo(0).await.unwrap(),
+            OutPoint::new(move_txid, 0)
+        );
+        assert_eq!(
+            db.get_withdrawal_sig_by_idx(0).await.unwrap(),
+            (move_txid, signature)
+        );
+        // Requests are unique, as new ones.
+        db.add_new_deposit_request(
+            start_utxo,
+            address.as_unchecked().clone(),
+            EVMAddress([0x45u8; 20]),
+            BRIDGE_AMOUNT_SATS,
+        )
+        .await
+        .unwrap();
+        assert_eq!(db.get_new_deposit_request_count().await.unwrap(), 1);
+
+        db.close().await;
+    }
+}
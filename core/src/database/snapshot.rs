@@ -0,0 +1,688 @@
+//! # Snapshots
+//!
+//! Database of an operator or a verifier can be exported to a snapshot and
+//! imported into a fresh database, to move a node to new hardware or to
+//! recover it from a backup. Snapshots have everything that can't be found on
+//! Bitcoin again: queued deposit requests, move txs, deposit and withdrawal
+//! lifecycles, withdrawal signatures, the withdrawal ledger and the signing
+//! policy. Blocks and UTXOs are indexed again by the chain watcher after an
+//! import.
+//!
+//! Snapshots are signed with the key of the node. Imports check the signature
+//! and the consistency of a snapshot before anything is written, and check
+//! that the database has the same state as the snapshot after it.
+
+use super::common::{Database, DepositRecord, DepositRequest, DepositStatus, MoveTxRecord};
+use super::common::{WithdrawalLedgerEntry, WithdrawalRecord, WithdrawalStatus};
+use super::migrations::Role;
+use crate::actor::Actor;
+use crate::errors::BridgeError;
+use crate::policy::{
+    ApprovalStatus, DepositApproval, ListedAddress, SignedDeposit, SigningPolicy,
+    DAILY_LIMIT_WINDOW_SECS,
+};
+use crate::utils::SECP;
+use bitcoin::{OutPoint, ScriptBuf, Txid};
+use secp256k1::{schnorr, Message, XOnlyPublicKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Format version of the snapshots that are created by this binary. Older
+/// versions can still be imported.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+const SNAPSHOT_TAG: &[u8] = b"clementine/db-snapshot";
+
+/// Number of records that are read at once from paginated tables.
+const PAGE_SIZE: u64 = 1000;
+
+/// Encoding of a snapshot file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SnapshotFormat {
+    Json,
+    Cbor,
+}
+
+/// State of an operator or a verifier. Tables that belong to the other role
+/// are empty.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SnapshotData {
+    /// Deposit requests in the batch queue, oldest first.
+    pub deposit_requests: Vec<DepositRequest>,
+    /// Move txs, in the order of deposit indexes.
+    pub move_txs: Vec<MoveTxRecord>,
+    /// Deposits, in the order they are requested.
+    pub deposits: Vec<DepositRecord>,
+    /// Withdrawals, in the order of their indexes.
+    pub withdrawals: Vec<WithdrawalRecord>,
+    pub withdrawal_sigs: Vec<(usize, Txid, schnorr::Signature)>,
+    pub musig2_withdrawals: Vec<(usize, Txid)>,
+    pub watched_deposit_scripts: Vec<ScriptBuf>,
+    pub signing_policy: Option<SigningPolicy>,
+    pub policy_addresses: Vec<ListedAddress>,
+    /// Deposits that are signed in the daily limit window. They count towards
+    /// the limits for a day after they are imported.
+    pub signed_deposits: Vec<SignedDeposit>,
+    pub deposit_approvals: Vec<DepositApproval>,
+    #[serde(default)]
+    pub withdrawal_ledger: Vec<WithdrawalLedgerEntry>,
+}
+
+impl SnapshotData {
+    /// Checks if there is nothing but the default signing policy.
+    pub fn is_empty(&self) -> bool {
+        self.table_rows().iter().all(|(_, rows)| rows.is_empty())
+            && self
+                .signing_policy
+                .as_ref()
+                .is_none_or(|policy| *policy == SigningPolicy::default())
+    }
+
+    /// Returns rows of every table as sorted JSON strings, so that tables can
+    /// be compared regardless of the order that the database returns rows
+    /// with equal timestamps.
+    fn table_rows(&self) -> Vec<(&'static str, Vec<String>)> {
+        fn rows<T: Serialize>(table: &[T]) -> Vec<String> {
+            let mut rows = table
+                .iter()
+                .map(|row| serde_json::to_string(row).expect("rows are serializable"))
+                .collect::<Vec<_>>();
+            rows.sort();
+
+            rows
+        }
+
+        vec![
+            ("deposit_requests", rows(&self.deposit_requests)),
+            ("move_txs", rows(&self.move_txs)),
+            ("deposits", rows(&self.deposits)),
+            ("withdrawals", rows(&self.withdrawals)),
+            ("withdrawal_sigs", rows(&self.withdrawal_sigs)),
+            ("musig2_withdrawals", rows(&self.musig2_withdrawals)),
+            (
+                "watched_deposit_scripts",
+                rows(&self.watched_deposit_scripts),
+            ),
+            ("policy_addresses", rows(&self.policy_addresses)),
+            ("signed_deposits", rows(&self.signed_deposits)),
+            ("deposit_approvals", rows(&self.deposit_approvals)),
+            ("withdrawal_ledger", rows(&self.withdrawal_ledger)),
+        ]
+    }
+}
+
+/// Signed part of a snapshot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotContents {
+    pub version: u32,
+    pub role: Role,
+    /// Unix time that the snapshot is created at, in seconds.
+    pub created_at: u64,
+    pub data: SnapshotData,
+}
+
+impl SnapshotContents {
+    /// Returns the digest that is signed for the snapshot. It doesn't depend
+    /// on the format of the snapshot file.
+    pub fn digest(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(SNAPSHOT_TAG);
+        hasher.update(serde_json::to_vec(self).expect("snapshot is serializable"));
+
+        hasher.finalize().into()
+    }
+
+    /// Checks that the snapshot can be imported by this binary and that its
+    /// tables are consistent with each other.
+    pub fn check(&self) -> Result<(), BridgeError> {
+        if self.version > SNAPSHOT_VERSION {
+            return Err(invalid(format!(
+                "Snapshot version {} is newer than this binary",
+                self.version
+            )));
+        }
+
+        let data = &self.data;
+        let foreign_tables = match self.role {
+            Role::Operator => vec![
+                ("withdrawal_sigs", data.withdrawal_sigs.is_empty()),
+                ("musig2_withdrawals", data.musig2_withdrawals.is_empty()),
+                ("signing_policy", data.signing_policy.is_none()),
+                ("policy_addresses", data.policy_addresses.is_empty()),
+                ("signed_deposits", data.signed_deposits.is_empty()),
+                ("deposit_approvals", data.deposit_approvals.is_empty()),
+                ("withdrawal_ledger", data.withdrawal_ledger.is_empty()),
+            ],
+            Role::Verifier => vec![
+                ("deposit_requests", data.deposit_requests.is_empty()),
+                ("move_txs", data.move_txs.is_empty()),
+                ("deposits", data.deposits.is_empty()),
+                ("withdrawals", data.withdrawals.is_empty()),
+            ],
+        };
+        if let Some((table, _)) = foreign_tables.iter().find(|(_, is_empty)| !is_empty) {
+            return Err(invalid(format!(
+                "Snapshot of a {:?} can't have {}",
+                self.role, table
+            )));
+        }
+
+        check_unique("deposit_requests", &data.deposit_requests, |r| r.start_utxo)?;
+        check_unique("deposits", &data.deposits, |d| d.start_utxo)?;
+        check_unique("move_txs", &data.move_txs, |m| (m.move_txid, m.move_vout))?;
+        check_unique("withdrawals", &data.withdrawals, |w| w.idx)?;
+        check_unique("withdrawal_sigs", &data.withdrawal_sigs, |s| s.0)?;
+        check_unique("musig2_withdrawals", &data.musig2_withdrawals, |m| m.0)?;
+        check_unique(
+            "watched_deposit_scripts",
+            &data.watched_deposit_scripts,
+            |s| s.clone(),
+        )?;
+        check_unique("policy_addresses", &data.policy_addresses, |a| {
+            (a.list, a.address.clone())
+        })?;
+        check_unique("signed_deposits", &data.signed_deposits, |s| s.start_utxo)?;
+        check_unique("deposit_approvals", &data.deposit_approvals, |a| {
+            a.deposit.start_utxo
+        })?;
+        check_unique("withdrawal_ledger", &data.withdrawal_ledger, |e| e.idx)?;
+        check_unique("withdrawal_ledger", &data.withdrawal_ledger, |e| {
+            e.bridge_utxo
+        })?;
+
+        for (i, move_tx) in data.move_txs.iter().enumerate() {
+            if move_tx.idx != i {
+                return Err(invalid(format!(
+                    "Move tx of deposit {} is at index {}",
+                    move_tx.idx, i
+                )));
+            }
+        }
+
+        let move_txids: HashMap<OutPoint, Txid> = data
+            .move_txs
+            .iter()
+            .map(|move_tx| (move_tx.start_utxo, move_tx.move_txid))
+            .collect();
+        for deposit in data.deposits.iter() {
+            let has_move_tx = deposit.status >= DepositStatus::PresignsCollected;
+            let is_confirmed = deposit.status >= DepositStatus::MoveConfirmed;
+
+            if has_move_tx != deposit.move_tx.is_some()
+                || is_confirmed != deposit.move_block_hash.is_some()
+            {
+                return Err(invalid(format!(
+                    "Deposit {} doesn't match its status {:?}",
+                    deposit.start_utxo, deposit.status
+                )));
+            }
+
+            if let (Some(move_tx), Some(move_txid)) =
+                (&deposit.move_tx, move_txids.get(&deposit.start_utxo))
+            {
+                if move_tx.compute_txid() != *move_txid {
+                    return Err(invalid(format!(
+                        "Deposit {} has a different move tx than its deposit index",
+                        deposit.start_utxo
+                    )));
+                }
+            }
+        }
+
+        for withdrawal in data.withdrawals.iter() {
+            if (withdrawal.status == WithdrawalStatus::Confirmed) != withdrawal.block_hash.is_some()
+            {
+                return Err(invalid(format!(
+                    "Withdrawal {} doesn't match its status {:?}",
+                    withdrawal.idx, withdrawal.status
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A snapshot with the signature of the node that created it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub contents: SnapshotContents,
+    pub signer: XOnlyPublicKey,
+    pub signature: schnorr::Signature,
+}
+
+impl Snapshot {
+    pub fn encode(&self, format: SnapshotFormat) -> Result<Vec<u8>, BridgeError> {
+        match format {
+            SnapshotFormat::Json => {
+                serde_json::to_vec_pretty(self).map_err(|e| invalid(e.to_string()))
+            }
+            SnapshotFormat::Cbor => {
+                let mut bytes = vec![];
+                ciborium::into_writer(self, &mut bytes).map_err(|e| invalid(e.to_string()))?;
+
+                Ok(bytes)
+            }
+        }
+    }
+
+    /// Decodes a snapshot file in any of the formats. JSON snapshots start
+    /// with an object, while CBOR snapshots start with a map header.
+    pub fn decode(bytes: &[u8]) -> Result<Self, BridgeError> {
+        match bytes.first() {
+            Some(b'{') => serde_json::from_slice(bytes).map_err(|e| invalid(e.to_string())),
+            _ => ciborium::from_reader(bytes).map_err(|e| invalid(e.to_string())),
+        }
+    }
+
+    /// Checks that the snapshot is signed by `xonly_public_key`.
+    pub fn verify(&self, xonly_public_key: &XOnlyPublicKey) -> Result<(), BridgeError> {
+        if self.signer != *xonly_public_key {
+            return Err(invalid(format!(
+                "Snapshot is signed by {}, not {}",
+                self.signer, xonly_public_key
+            )));
+        }
+
+        SECP.verify_schnorr(
+            &self.signature,
+            &Message::from_digest(self.contents.digest()),
+            &self.signer,
+        )
+        .map_err(|_| invalid("Invalid signature".to_string()))
+    }
+}
+
+fn invalid(message: String) -> BridgeError {
+    BridgeError::InvalidSnapshot(message)
+}
+
+fn check_unique<T, K: Eq + Hash>(
+    table: &str,
+    rows: &[T],
+    key: impl Fn(&T) -> K,
+) -> Result<(), BridgeError> {
+    let mut keys = HashSet::new();
+
+    match rows.iter().all(|row| keys.insert(key(row))) {
+        true => Ok(()),
+        false => Err(invalid(format!("{} has duplicate rows", table))),
+    }
+}
+
+/// Reads the tables of `role` from the database.
+pub async fn read_data(db: &Database, role: Role) -> Result<SnapshotData, BridgeError> {
+    let mut data = SnapshotData {
+        watched_deposit_scripts: db
+            .get_watched_deposit_scripts()
+            .await?
+            .into_iter()
+            .collect(),
+        ..Default::default()
+    };
+    data.watched_deposit_scripts.sort();
+
+    match role {
+        Role::Operator => {
+            let count = db.get_new_deposit_request_count().await?;
+            data.deposit_requests = db.get_new_deposit_requests(count).await?;
+            data.move_txs = db.get_move_txs().await?;
+
+            loop {
+                let page = db
+                    .list_deposit_records(None, None, data.deposits.len() as u64, PAGE_SIZE)
+                    .await?;
+                let is_last = (page.len() as u64) < PAGE_SIZE;
+                data.deposits.extend(page);
+
+                if is_last {
+                    break;
+                }
+            }
+
+            loop {
+                let page = db
+                    .list_withdrawal_records(None, data.withdrawals.len() as u64, PAGE_SIZE)
+                    .await?;
+                let is_last = (page.len() as u64) < PAGE_SIZE;
+                data.withdrawals.extend(page);
+
+                if is_last {
+                    break;
+                }
+            }
+        }
+        Role::Verifier => {
+            data.withdrawal_sigs = db.get_withdrawal_sigs().await?;
+            data.musig2_withdrawals = db.get_musig2_withdrawals().await?;
+            data.signing_policy = Some(db.get_signing_policy().await?);
+            data.policy_addresses = db.get_policy_addresses().await?;
+            data.signed_deposits = db.get_signed_deposits(DAILY_LIMIT_WINDOW_SECS).await?;
+            data.deposit_approvals = db.list_deposit_approvals(None).await?;
+            data.withdrawal_ledger = db.get_withdrawal_ledger().await?;
+        }
+    }
+
+    Ok(data)
+}
+
+/// Exports the database of `role` to a snapshot, signed by `signer`.
+pub async fn export(db: &Database, role: Role, signer: &Actor) -> Result<Snapshot, BridgeError> {
+    let contents = SnapshotContents {
+        version: SNAPSHOT_VERSION,
+        role,
+        created_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time is after unix epoch")
+            .as_secs(),
+        data: read_data(db, role).await?,
+    };
+    contents.check()?;
+
+    Ok(Snapshot {
+        signature: signer.sign_digest(contents.digest())?,
+        signer: signer.xonly_public_key,
+        contents,
+    })
+}
+
+/// Imports a snapshot that is signed by `xonly_public_key` into a database
+/// that has no state. Database is migrated first, if it needs to be.
+///
+/// Import is not atomic: a database that fails to import a snapshot should
+/// be dropped before trying again.
+pub async fn import(
+    db: &Database,
+    snapshot: &Snapshot,
+    xonly_public_key: &XOnlyPublicKey,
+) -> Result<(), BridgeError> {
+    snapshot.verify(xonly_public_key)?;
+    snapshot.contents.check()?;
+
+    let role = snapshot.contents.role;
+    db.migrate(&[role]).await?;
+    if !read_data(db, role).await?.is_empty() {
+        return Err(invalid(
+            "Snapshots can only be imported into an empty database".to_string(),
+        ));
+    }
+
+    let data = &snapshot.contents.data;
+    for script_pubkey in data.watched_deposit_scripts.iter() {
+        db.add_watched_deposit_script(script_pubkey).await?;
+    }
+
+    for request in data.deposit_requests.iter() {
+        db.add_new_deposit_request(
+            request.start_utxo,
+            request.recovery_taproot_address.clone(),
+            request.evm_address,
+            request.amount,
+        )
+        .await?;
+    }
+    for move_tx in data.move_txs.iter() {
+        db.insert_move_txid(
+            move_tx.start_utxo,
+            move_tx.recovery_taproot_address.clone(),
+            move_tx.evm_address,
+            move_tx.move_txid,
+            move_tx.move_vout,
+        )
+        .await?;
+    }
+    for deposit in data.deposits.iter() {
+        db.restore_deposit_record(deposit).await?;
+    }
+    for withdrawal in data.withdrawals.iter() {
+        db.restore_withdrawal_record(withdrawal).await?;
+    }
+
+    for (idx, bridge_fund_txid, sig) in data.withdrawal_sigs.iter() {
+        db.save_withdrawal_sig(*idx, *bridge_fund_txid, *sig)
+            .await?;
+    }
+    for (idx, bridge_fund_txid) in data.musig2_withdrawals.iter() {
+        db.save_musig2_withdrawal(*idx, *bridge_fund_txid).await?;
+    }
+    if let Some(policy) = &data.signing_policy {
+        db.set_signing_policy(policy).await?;
+    }
+    for listed in data.policy_addresses.iter() {
+        db.add_policy_address(listed).await?;
+    }
+    if !data.signed_deposits.is_empty() {
+        db.add_signed_deposits(&data.signed_deposits).await?;
+    }
+    for approval in data.deposit_approvals.iter() {
+        db.add_deposit_approval(&approval.deposit).await?;
+        if approval.status != ApprovalStatus::Pending {
+            db.set_deposit_approval_status(approval.deposit.start_utxo, approval.status)
+                .await?;
+        }
+    }
+    for entry in data.withdrawal_ledger.iter() {
+        db.add_withdrawal_ledger_entry(entry).await?;
+    }
+
+    let imported = read_data(db, role).await?;
+    for ((table, expected), (_, rows)) in data.table_rows().iter().zip(imported.table_rows()) {
+        if *expected != rows {
+            return Err(invalid(format!(
+                "Imported {} don't match the snapshot",
+                table
+            )));
+        }
+    }
+    if imported.signing_policy != data.signing_policy {
+        return Err(invalid(
+            "Imported signing policy doesn't match the snapshot".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{export, import, read_data, Snapshot, SnapshotFormat};
+    use crate::actor::Actor;
+    use crate::config::{BridgeConfig, DatabaseBackend};
+    use crate::database::common::{Database, DepositStatus, WithdrawalLedgerEntry};
+    use crate::database::migrations::Role;
+    use crate::errors::BridgeError;
+    use crate::{mock::common, EVMAddress};
+    use bitcoin::hashes::Hash;
+    use bitcoin::{Address, BlockHash, OutPoint, ScriptBuf, Txid};
+    use clementine_circuits::constants::BRIDGE_AMOUNT_SATS;
+    use secp256k1::SecretKey;
+
+    async fn create_test_database(database_name: &str, role: Role) -> (BridgeConfig, Database) {
+        let mut config = common::get_test_config("test_config.toml").unwrap();
+        config.db_backend = DatabaseBackend::Memory;
+        let config = Database::create_database(config, database_name)
+            .await
+            .unwrap();
+
+        let db = Database::new(config.clone()).await.unwrap();
+        db.migrate(&[role]).await.unwrap();
+
+        (config, db)
+    }
+
+    #[tokio::test]
+    async fn export_import_operator() {
+        let (config, db) = create_test_database("snapshot_operator", Role::Operator).await;
+        let signer = Actor::new(config.secret_key.unwrap(), config.network);
+
+        let address = Address::p2tr(
+            &crate::utils::SECP,
+            *crate::utils::UNSPENDABLE_XONLY_PUBKEY,
+            None,
+            config.network,
+        );
+        let evm_address = EVMAddress([0x45; 20]);
+        let start_utxos = [
+            OutPoint::new(Txid::from_byte_array([0x45; 32]), 0),
+            OutPoint::new(Txid::from_byte_array([0x46; 32]), 1),
+        ];
+        for start_utxo in start_utxos {
+            db.start_deposit(
+                start_utxo,
+                address.as_unchecked().clone(),
+                evm_address,
+                BRIDGE_AMOUNT_SATS,
+            )
+            .await
+            .unwrap();
+        }
+        db.add_new_deposit_request(
+            start_utxos[1],
+            address.as_unchecked().clone(),
+            evm_address,
+            BRIDGE_AMOUNT_SATS,
+        )
+        .await
+        .unwrap();
+        db.set_deposit_block_hash(start_utxos[0], BlockHash::from_byte_array([1; 32]))
+            .await
+            .unwrap();
+        db.add_watched_deposit_script(&ScriptBuf::from_bytes(vec![0x51]))
+            .await
+            .unwrap();
+
+        let snapshot = export(&db, Role::Operator, &signer).await.unwrap();
+        assert_eq!(snapshot.contents.data.deposits.len(), 2);
+
+        for format in [SnapshotFormat::Json, SnapshotFormat::Cbor] {
+            let snapshot = Snapshot::decode(&snapshot.encode(format).unwrap()).unwrap();
+            let name = format!("snapshot_operator_{:?}", format).to_lowercase();
+            let (_, imported_db) = create_test_database(&name, Role::Operator).await;
+
+            import(&imported_db, &snapshot, &signer.xonly_public_key)
+                .await
+                .unwrap();
+            let deposit = imported_db
+                .get_deposit_record(start_utxos[0])
+                .await
+                .unwrap();
+            assert_eq!(deposit.status, DepositStatus::Requested);
+            assert_eq!(
+                deposit.deposit_block_hash,
+                Some(BlockHash::from_byte_array([1; 32]))
+            );
+            assert_eq!(
+                imported_db.get_new_deposit_request_count().await.unwrap(),
+                1
+            );
+
+            // Snapshots are only imported into empty databases.
+            assert!(matches!(
+                import(&imported_db, &snapshot, &signer.xonly_public_key).await,
+                Err(BridgeError::InvalidSnapshot(_))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn export_import_verifier() {
+        let (config, db) = create_test_database("snapshot_verifier", Role::Verifier).await;
+        let signer = Actor::new(config.secret_key.unwrap(), config.network);
+
+        let bridge_fund_txid = Txid::from_byte_array([0x45; 32]);
+        db.save_withdrawal_sig(0, bridge_fund_txid, signer.sign_digest([0x45; 32]).unwrap())
+            .await
+            .unwrap();
+        db.save_musig2_withdrawal(1, bridge_fund_txid)
+            .await
+            .unwrap();
+        let entry = WithdrawalLedgerEntry {
+            idx: 0,
+            bridge_utxo: OutPoint::new(bridge_fund_txid, 0),
+            withdrawal_address: Address::p2tr(
+                &crate::utils::SECP,
+                *crate::utils::UNSPENDABLE_XONLY_PUBKEY,
+                None,
+                config.network,
+            )
+            .as_unchecked()
+            .clone(),
+        };
+        db.add_withdrawal_ledger_entry(&entry).await.unwrap();
+
+        let snapshot = export(&db, Role::Verifier, &signer).await.unwrap();
+        let (_, imported_db) = create_test_database("snapshot_verifier_2", Role::Verifier).await;
+        import(&imported_db, &snapshot, &signer.xonly_public_key)
+            .await
+            .unwrap();
+
+        assert!(imported_db.is_withdrawal_idx_signed(0).await.unwrap());
+        assert_eq!(
+            imported_db.get_musig2_withdrawal_txid(1).await.unwrap(),
+            bridge_fund_txid
+        );
+        assert_eq!(
+            imported_db.get_withdrawal_ledger().await.unwrap(),
+            vec![entry]
+        );
+        assert_eq!(
+            read_data(&imported_db, Role::Verifier)
+                .await
+                .unwrap()
+                .withdrawal_sigs,
+            snapshot.contents.data.withdrawal_sigs
+        );
+    }
+
+    #[tokio::test]
+    async fn invalid_snapshots() {
+        let (config, db) = create_test_database("snapshot_invalid", Role::Verifier).await;
+        let signer = Actor::new(config.secret_key.unwrap(), config.network);
+        let other_signer = Actor::new(SecretKey::from_slice(&[0x45; 32]).unwrap(), config.network);
+
+        db.save_musig2_withdrawal(0, Txid::from_byte_array([0x45; 32]))
+            .await
+            .unwrap();
+        let snapshot = export(&db, Role::Verifier, &signer).await.unwrap();
+        let (_, imported_db) = create_test_database("snapshot_invalid_2", Role::Verifier).await;
+
+        // Snapshot of another node.
+        assert!(matches!(
+            import(&imported_db, &snapshot, &other_signer.xonly_public_key).await,
+            Err(BridgeError::InvalidSnapshot(_))
+        ));
+
+        // Modified snapshot.
+        let mut modified = snapshot.clone();
+        modified.contents.data.musig2_withdrawals[0].0 = 1;
+        assert!(matches!(
+            import(&imported_db, &modified, &signer.xonly_public_key).await,
+            Err(BridgeError::InvalidSnapshot(_))
+        ));
+
+        // Inconsistent snapshot, even if it is signed.
+        let mut inconsistent = snapshot.clone();
+        inconsistent
+            .contents
+            .data
+            .musig2_withdrawals
+            .push(inconsistent.contents.data.musig2_withdrawals[0]);
+        inconsistent.signature = signer.sign_digest(inconsistent.contents.digest()).unwrap();
+        assert!(matches!(
+            import(&imported_db, &inconsistent, &signer.xonly_public_key).await,
+            Err(BridgeError::InvalidSnapshot(_))
+        ));
+
+        assert!(read_data(&imported_db, Role::Verifier)
+            .await
+            .unwrap()
+            .is_empty());
+        import(&imported_db, &snapshot, &signer.xonly_public_key)
+            .await
+            .unwrap();
+    }
+}
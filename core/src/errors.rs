@@ -128,6 +128,77 @@ pub enum BridgeError {
     /// There was an error while creating a server.
     #[error("ServerError")]
     ServerError(std::io::Error),
+    /// MuSig2 key aggregation, nonce or signature operation failed
+    #[error("MuSig2Error: {0}")]
+    MuSig2Error(String),
+    /// MuSig2 request is sent to an N-of-N signer or vice versa
+    #[error("MuSig2ModeMismatch")]
+    MuSig2ModeMismatch,
+    /// Partial signature is requested before a nonce is generated for it
+    #[error("MuSig2NonceNotFound")]
+    MuSig2NonceNotFound,
+    /// Returned when a deposit status read from database is unknown
+    #[error("InvalidDepositStatus: {0}")]
+    InvalidDepositStatus(String),
+    /// Returned when a withdrawal status read from database is unknown
+    #[error("InvalidWithdrawalStatus: {0}")]
+    InvalidWithdrawalStatus(String),
+    /// Returned when a fee can't be calculated or paid
+    #[error("FeeError: {0}")]
+    FeeError(String),
+    /// Returned when a deposit amount is not one of the deposit denominations
+    #[error("InvalidDepositAmount: {0}")]
+    InvalidDepositAmount(u64),
+    /// Returned when a bridge UTXO doesn't lock a deposit denomination to the
+    /// bridge address
+    #[error("InvalidBridgeUTXO")]
+    InvalidBridgeUTXO,
+    /// Returned when a batch request is empty or has duplicate entries
+    #[error("InvalidBatch: {0}")]
+    InvalidBatch(String),
+    /// Returned when a withdrawal needs to be checked on rollup but no rollup
+    /// client is configured
+    #[error("RollupClientNotConfigured")]
+    RollupClientNotConfigured,
+    /// Returned when a withdrawal with given index, address and amount is not
+    /// made or not finalized on rollup
+    #[error("WithdrawalNotFinalized: {0}")]
+    WithdrawalNotFinalized(usize),
+    /// Returned when a request to a verifier is not authenticated by the
+    /// operator or is a replay
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+    /// Returned when TLS certificates or keys can't be loaded
+    #[error("TlsError: {0}")]
+    TlsError(String),
+    /// Returned when a proof of reserves report doesn't match the chain
+    #[error("InvalidReservesReport: {0}")]
+    InvalidReservesReport(String),
+    /// Returned when the verifier with given index fails a request of the
+    /// operator, after retries
+    #[error("VerifierError: Verifier {0} failed: {1}")]
+    VerifierError(usize, jsonrpsee::core::client::Error),
+    /// Returned when a deposit or withdrawal is refused by verifier's signing
+    /// policy
+    #[error("PolicyViolation: {0}")]
+    PolicyViolation(String),
+    /// Returned when deposits with given UTXOs wait for manual approval
+    #[error("ApprovalRequired: {0:?}")]
+    ApprovalRequired(Vec<bitcoin::OutPoint>),
+    /// Returned when a signer or keystore can't be loaded or fails to sign
+    #[error("SignerError: {0}")]
+    SignerError(String),
+    /// Returned when database migrations are pending or can't be applied
+    #[error("MigrationError: {0}")]
+    MigrationError(String),
+    /// Returned when a database snapshot can't be read, isn't signed by the
+    /// expected key or doesn't match the database
+    #[error("InvalidSnapshot: {0}")]
+    InvalidSnapshot(String),
+    /// Returned when a withdrawal index, bridge UTXO or withdrawal address is
+    /// already bound to another withdrawal in the verifier's ledger
+    #[error("WithdrawalConflict: {0}")]
+    WithdrawalConflict(String),
 }
 
 impl Into<ErrorObject<'static>> for BridgeError {
@@ -3,6 +3,7 @@
 //! This module provides helpful functions for Bitcoin RPC.
 
 use crate::errors::BridgeError;
+use crate::metrics;
 use crate::transaction_builder::TransactionBuilder;
 use crate::EVMAddress;
 use bitcoin::address::NetworkUnchecked;
@@ -16,7 +17,6 @@ use bitcoin::Work;
 use bitcoin_mock_rpc::RpcApiWrapper;
 use bitcoincore_rpc::json::AddressType;
 use bitcoincore_rpc::Auth;
-use clementine_circuits::constants::BRIDGE_AMOUNT_SATS;
 use crypto_bigint::Encoding;
 use crypto_bigint::U256;
 
@@ -49,21 +49,75 @@ where
         }
     }
 
+    /// Calls Bitcoin RPC with `f` and measures the call in metrics, labeled
+    /// with `method`.
+    fn call<T>(
+        &self,
+        method: &str,
+        f: impl FnOnce(&R) -> bitcoincore_rpc::Result<T>,
+    ) -> bitcoincore_rpc::Result<T> {
+        let timer = metrics::BITCOIN_RPC_DURATION.start_timer(&[method]);
+        let res = f(&self.client);
+        drop(timer);
+
+        if res.is_err() {
+            metrics::BITCOIN_RPC_ERRORS.inc(&[method]);
+        }
+
+        res
+    }
+
     pub fn confirmation_blocks(&self, txid: &bitcoin::Txid) -> Result<u32, BridgeError> {
-        let raw_transaction_results = self.client.get_raw_transaction_info(txid, None)?;
+        let raw_transaction_results = self.call("get_raw_transaction_info", |client| {
+            client.get_raw_transaction_info(txid, None)
+        })?;
 
         raw_transaction_results
             .confirmations
             .ok_or(BridgeError::NoConfirmationData)
     }
 
+    /// Returns confirmation count of a transaction and the hash of the block
+    /// it is confirmed in.
+    pub fn confirmation_info(
+        &self,
+        txid: &bitcoin::Txid,
+    ) -> Result<(u32, bitcoin::BlockHash), BridgeError> {
+        let raw_transaction_results = self.call("get_raw_transaction_info", |client| {
+            client.get_raw_transaction_info(txid, None)
+        })?;
+
+        match (
+            raw_transaction_results.confirmations,
+            raw_transaction_results.blockhash,
+        ) {
+            (Some(confirmations), Some(block_hash)) => Ok((confirmations, block_hash)),
+            _ => Err(BridgeError::NoConfirmationData),
+        }
+    }
+
+    /// Checks if given block is in the best chain.
+    pub fn is_block_in_best_chain(
+        &self,
+        block_hash: &bitcoin::BlockHash,
+    ) -> Result<bool, BridgeError> {
+        let header_info = self.call("get_block_header_info", |client| {
+            client.get_block_header_info(block_hash)
+        })?;
+
+        // Blocks that are not in the best chain have -1 confirmations.
+        Ok(header_info.confirmations >= 0)
+    }
+
     pub fn check_utxo_address_and_amount(
         &self,
         outpoint: &OutPoint,
         address: &ScriptBuf,
         amount_sats: u64,
     ) -> Result<bool, BridgeError> {
-        let tx = self.client.get_raw_transaction(&outpoint.txid, None)?;
+        let tx = self.call("get_raw_transaction", |client| {
+            client.get_raw_transaction(&outpoint.txid, None)
+        })?;
 
         let current_output = tx.output[outpoint.vout as usize].clone();
 
@@ -76,38 +130,60 @@ where
     }
 
     pub fn is_utxo_spent(&self, outpoint: &OutPoint) -> Result<bool, BridgeError> {
-        let res = self
-            .client
-            .get_tx_out(&outpoint.txid, outpoint.vout, Some(true))?;
+        let res = self.call("get_tx_out", |client| {
+            client.get_tx_out(&outpoint.txid, outpoint.vout, Some(true))
+        })?;
 
         Ok(res.is_none())
     }
 
+    /// Returns the output at given outpoint if it is unspent in the best
+    /// chain. Spends in mempool are ignored.
+    pub fn get_unspent_txout(&self, outpoint: &OutPoint) -> Result<Option<TxOut>, BridgeError> {
+        let res = self.call("get_tx_out", |client| {
+            client.get_tx_out(&outpoint.txid, outpoint.vout, Some(false))
+        })?;
+
+        Ok(res.map(|txout| TxOut {
+            value: txout.value,
+            script_pubkey: ScriptBuf::from(txout.script_pub_key.hex),
+        }))
+    }
+
     pub fn generate_dummy_block(&self) -> Result<Vec<bitcoin::BlockHash>, BridgeError> {
-        let address = self.client.get_new_address(None, None)?.assume_checked();
+        let address = self
+            .call("get_new_address", |client| {
+                client.get_new_address(None, None)
+            })?
+            .assume_checked();
 
         for _ in 0..10 {
-            let new_address = self.client.get_new_address(None, None)?.assume_checked();
+            let new_address = self
+                .call("get_new_address", |client| {
+                    client.get_new_address(None, None)
+                })?
+                .assume_checked();
             let amount = bitcoin::Amount::from_sat(1000); // TODO: Specify the amount to send
-            self.client.send_to_address(
-                &new_address,
-                amount,
-                None,
-                None,
-                None,
-                None,
-                None,
-                None,
-            )?;
+            self.call("send_to_address", |client| {
+                client.send_to_address(&new_address, amount, None, None, None, None, None, None)
+            })?;
         }
 
-        Ok(self.client.generate_to_address(1, &address)?)
+        Ok(self.call("generate_to_address", |client| {
+            client.generate_to_address(1, &address)
+        })?)
     }
 
     pub fn mine_blocks(&self, block_num: u64) -> Result<(), BridgeError> {
-        let new_address = self.client.get_new_address(None, None)?.assume_checked();
+        let new_address = self
+            .call("get_new_address", |client| {
+                client.get_new_address(None, None)
+            })?
+            .assume_checked();
 
-        self.client.generate_to_address(block_num, &new_address)?;
+        self.call("generate_to_address", |client| {
+            client.generate_to_address(block_num, &new_address)
+        })?;
 
         Ok(())
     }
@@ -117,18 +193,22 @@ where
         address: &Address,
         amount_sats: u64,
     ) -> Result<OutPoint, BridgeError> {
-        let txid = self.client.send_to_address(
-            address,
-            Amount::from_sat(amount_sats),
-            None,
-            None,
-            None,
-            None,
-            None,
-            None,
-        )?;
+        let txid = self.call("send_to_address", |client| {
+            client.send_to_address(
+                address,
+                Amount::from_sat(amount_sats),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+        })?;
 
-        let tx_result = self.client.get_transaction(&txid, None)?;
+        let tx_result = self.call("get_transaction", |client| {
+            client.get_transaction(&txid, None)
+        })?;
         let vout = tx_result.details[0].vout;
 
         Ok(OutPoint { txid, vout })
@@ -136,7 +216,7 @@ where
 
     pub fn get_work_at_block(&self, blockheight: u64) -> Result<Work, BridgeError> {
         let block_hash = self.get_block_hash(blockheight)?;
-        let block = self.client.get_block(&block_hash)?;
+        let block = self.call("get_block", |client| client.get_block(&block_hash))?;
         let work = block.header.work();
 
         Ok(work)
@@ -146,7 +226,9 @@ where
         &self,
         blockheight: u64,
     ) -> Result<bitcoin::BlockHash, bitcoincore_rpc::Error> {
-        let block_hash = self.client.get_block_hash(blockheight)?;
+        let block_hash = self.call("get_block_hash", |client| {
+            client.get_block_hash(blockheight)
+        })?;
 
         Ok(block_hash)
     }
@@ -155,7 +237,9 @@ where
         &self,
         block_hash: &bitcoin::BlockHash,
     ) -> Result<bitcoin::block::Header, bitcoincore_rpc::Error> {
-        let block_header = self.client.get_block_header(block_hash)?;
+        let block_header = self.call("get_block_header", |client| {
+            client.get_block_header(block_hash)
+        })?;
 
         Ok(block_header)
     }
@@ -180,7 +264,7 @@ where
     }
 
     pub fn get_total_work_as_u256(&self) -> Result<U256, BridgeError> {
-        let chain_info = self.client.get_blockchain_info()?;
+        let chain_info = self.call("get_blockchain_info", |client| client.get_blockchain_info())?;
         let total_work_bytes = chain_info.chain_work;
         let total_work: U256 = U256::from_be_bytes(total_work_bytes.try_into()?);
 
@@ -188,7 +272,7 @@ where
     }
 
     pub fn get_total_work(&self) -> Result<Work, BridgeError> {
-        let chain_info = self.client.get_blockchain_info()?;
+        let chain_info = self.call("get_blockchain_info", |client| client.get_blockchain_info())?;
         let total_work_bytes = chain_info.chain_work;
         let total_work: Work = Work::from_be_bytes(total_work_bytes.try_into()?);
 
@@ -196,7 +280,7 @@ where
     }
 
     pub fn get_block_height(&self) -> Result<u64, BridgeError> {
-        let chain_info = self.client.get_blockchain_info()?;
+        let chain_info = self.call("get_blockchain_info", |client| client.get_blockchain_info())?;
         let block_height = chain_info.blocks;
 
         Ok(block_height)
@@ -208,22 +292,24 @@ where
         options: Option<&bitcoincore_rpc::json::FundRawTransactionOptions>,
         is_witness: Option<bool>,
     ) -> Result<bitcoincore_rpc::json::FundRawTransactionResult, bitcoincore_rpc::Error> {
-        self.client.fund_raw_transaction(tx, options, is_witness)
+        self.call("fund_raw_transaction", |client| {
+            client.fund_raw_transaction(tx, options, is_witness)
+        })
     }
 
     // Following methods are just wrappers around the bitcoincore_rpc::Client methods
     pub fn get_blockchain_info(
         &self,
     ) -> Result<bitcoincore_rpc::json::GetBlockchainInfoResult, bitcoincore_rpc::Error> {
-        self.client.get_blockchain_info()
+        self.call("get_blockchain_info", |client| client.get_blockchain_info())
     }
 
     pub fn get_block_count(&self) -> Result<u64, bitcoincore_rpc::Error> {
-        self.client.get_block_count()
+        self.call("get_block_count", |client| client.get_block_count())
     }
 
     pub fn get_best_block_hash(&self) -> Result<bitcoin::BlockHash, bitcoincore_rpc::Error> {
-        self.client.get_best_block_hash()
+        self.call("get_best_block_hash", |client| client.get_best_block_hash())
     }
 
     pub fn get_raw_transaction(
@@ -231,7 +317,9 @@ where
         txid: &bitcoin::Txid,
         block_hash: Option<&bitcoin::BlockHash>,
     ) -> Result<bitcoin::Transaction, bitcoincore_rpc::Error> {
-        self.client.get_raw_transaction(txid, block_hash)
+        self.call("get_raw_transaction", |client| {
+            client.get_raw_transaction(txid, block_hash)
+        })
     }
 
     pub fn get_transaction(
@@ -239,28 +327,78 @@ where
         txid: &bitcoin::Txid,
         include_watchonly: Option<bool>,
     ) -> Result<bitcoincore_rpc::json::GetTransactionResult, bitcoincore_rpc::Error> {
-        self.client.get_transaction(txid, include_watchonly)
+        self.call("get_transaction", |client| {
+            client.get_transaction(txid, include_watchonly)
+        })
     }
 
     pub fn send_raw_transaction(
         &self,
         tx: &Transaction,
     ) -> Result<bitcoin::Txid, bitcoincore_rpc::Error> {
-        self.client.send_raw_transaction(tx)
+        self.call("send_raw_transaction", |client| {
+            client.send_raw_transaction(tx)
+        })
     }
 
     pub fn get_block(
         &self,
         block_hash: &bitcoin::BlockHash,
     ) -> Result<bitcoin::Block, bitcoincore_rpc::Error> {
-        self.client.get_block(block_hash)
+        self.call("get_block", |client| client.get_block(block_hash))
+    }
+
+    pub fn estimate_smart_fee(
+        &self,
+        conf_target: u16,
+    ) -> Result<bitcoincore_rpc::json::EstimateSmartFeeResult, bitcoincore_rpc::Error> {
+        self.call("estimate_smart_fee", |client| {
+            client.estimate_smart_fee(conf_target, None)
+        })
+    }
+
+    pub fn get_mempool_entry(
+        &self,
+        txid: &bitcoin::Txid,
+    ) -> Result<bitcoincore_rpc::json::GetMempoolEntryResult, bitcoincore_rpc::Error> {
+        self.call("get_mempool_entry", |client| client.get_mempool_entry(txid))
     }
+
+    pub fn list_unspent(
+        &self,
+        min_conf: Option<usize>,
+    ) -> Result<Vec<bitcoincore_rpc::json::ListUnspentResultEntry>, bitcoincore_rpc::Error> {
+        self.call("list_unspent", |client| {
+            client.list_unspent(min_conf, None, None, None, None)
+        })
+    }
+
+    pub fn get_raw_change_address(&self) -> Result<Address, BridgeError> {
+        let address = self.call("get_raw_change_address", |client| {
+            client.get_raw_change_address(None)
+        })?;
+
+        Ok(address.assume_checked())
+    }
+
+    pub fn sign_raw_transaction_with_wallet(
+        &self,
+        tx: &Transaction,
+        utxos: Option<&[bitcoincore_rpc::json::SignRawTransactionInput]>,
+    ) -> Result<bitcoincore_rpc::json::SignRawTransactionResult, bitcoincore_rpc::Error> {
+        self.call("sign_raw_transaction_with_wallet", |client| {
+            client.sign_raw_transaction_with_wallet(tx, utxos, None)
+        })
+    }
+
     pub fn get_raw_transaction_info(
         &self,
         txid: &bitcoin::Txid,
         block_hash: Option<&bitcoin::BlockHash>,
     ) -> Result<bitcoincore_rpc::json::GetRawTransactionResult, bitcoincore_rpc::Error> {
-        self.client.get_raw_transaction_info(txid, block_hash)
+        self.call("get_raw_transaction_info", |client| {
+            client.get_raw_transaction_info(txid, block_hash)
+        })
     }
 
     pub fn check_deposit_utxo(
@@ -280,7 +418,7 @@ where
         let (deposit_address, _) = tx_builder.generate_deposit_address(
             recovery_taproot_address,
             evm_address,
-            BRIDGE_AMOUNT_SATS,
+            amount_sats,
             user_takes_after,
         )?;
 
@@ -299,22 +437,63 @@ where
         Ok(())
     }
 
+    /// Returns the bridge output created by a move tx, after checking that it
+    /// locks one of `deposit_denominations` to the bridge address.
+    pub fn get_bridge_txout(
+        &self,
+        tx_builder: &TransactionBuilder,
+        bridge_utxo: &OutPoint,
+        deposit_denominations: &[u64],
+    ) -> Result<TxOut, BridgeError> {
+        Ok(self
+            .get_bridge_deposit(tx_builder, bridge_utxo, deposit_denominations)?
+            .0)
+    }
+
+    /// Returns the bridge output created by a move tx and the deposit amount
+    /// that it is created for, which is one of `deposit_denominations`.
+    pub fn get_bridge_deposit(
+        &self,
+        tx_builder: &TransactionBuilder,
+        bridge_utxo: &OutPoint,
+        deposit_denominations: &[u64],
+    ) -> Result<(TxOut, u64), BridgeError> {
+        let move_tx = self.call("get_raw_transaction", |client| {
+            client.get_raw_transaction(&bridge_utxo.txid, None)
+        })?;
+        let bridge_txout = move_tx
+            .output
+            .get(bridge_utxo.vout as usize)
+            .ok_or(BridgeError::InvalidBridgeUTXO)?;
+
+        // Every deposit of a move tx has an input.
+        let amount = tx_builder.bridge_txout_amount(
+            bridge_txout,
+            move_tx.input.len(),
+            deposit_denominations,
+        )?;
+
+        Ok((bridge_txout.clone(), amount))
+    }
+
     /// Generates bitcoins to specified address.
     pub fn generate_to_address(
         &self,
         block_num: u64,
         address: &Address,
     ) -> Result<(), BridgeError> {
-        self.client.generate_to_address(block_num, address)?;
+        self.call("generate_to_address", |client| {
+            client.generate_to_address(block_num, address)
+        })?;
 
         Ok(())
     }
 
     /// Requests a new Bitcoin address via an RPC call.
     pub fn get_new_address(&self) -> Result<Address, BridgeError> {
-        let address = self
-            .client
-            .get_new_address(None, Some(AddressType::Bech32m));
+        let address = self.call("get_new_address", |client| {
+            client.get_new_address(None, Some(AddressType::Bech32m))
+        });
 
         Ok(address?.assume_checked())
     }
@@ -0,0 +1,372 @@
+//! # Fee Manager
+//!
+//! Move and withdrawal txs are signed by every verifier, so their fees can't
+//! be changed after signing. They pay minimum relay fee for their virtual
+//! size and carry an anyone-can-spend output instead. When they are stuck,
+//! fee manager spends that output in a child tx that pays for both of them
+//! (CPFP), at a fee rate estimated by Bitcoin. Child txs signal RBF, so a
+//! child can be replaced by one that pays more if fee rates keep rising.
+
+use crate::config::BridgeConfig;
+use crate::errors::BridgeError;
+use crate::extended_rpc::ExtendedRpc;
+use crate::script_builder;
+use crate::transaction_builder::TransactionBuilder;
+use bitcoin::{Amount, FeeRate, OutPoint, Transaction, TxOut, Txid, Witness};
+use bitcoin_mock_rpc::RpcApiWrapper;
+use bitcoincore_rpc::json::{ListUnspentResultEntry, SignRawTransactionInput};
+
+/// Fee rate of txs that are signed by verifiers.
+pub const PRESIGNED_TX_FEE_RATE: FeeRate = FeeRate::BROADCAST_MIN;
+
+/// Confirmation target in blocks, if not configured.
+pub const DEFAULT_FEE_RATE_CONF_TARGET: u16 = 6;
+/// Fee rate in sat/vB to use if Bitcoin can't estimate one and it is not
+/// configured.
+pub const DEFAULT_FALLBACK_FEE_RATE: u32 = 2;
+/// Maximum fee rate in sat/vB for fee bumping, if not configured.
+pub const DEFAULT_MAX_FEE_RATE: u32 = 100;
+
+/// Returns the fee of a tx with given virtual size, at given fee rate.
+pub fn fee_for_vsize(fee_rate: FeeRate, vsize: u64) -> Result<Amount, BridgeError> {
+    fee_rate.fee_vb(vsize).ok_or(BridgeError::FeeError(format!(
+        "Fee overflow for {} vB at {}",
+        vsize, fee_rate
+    )))
+}
+
+/// Returns the fee rate of a tx or a package, from its fee and virtual
+/// size.
+pub fn fee_rate_of(fee: Amount, vsize: u64) -> FeeRate {
+    if vsize == 0 {
+        return FeeRate::ZERO;
+    }
+
+    // 1 sat/vB is 250 sat/kwu.
+    FeeRate::from_sat_per_kwu(fee.to_sat().saturating_mul(250) / vsize)
+}
+
+/// Returns the position of the anyone-can-spend output of a tx.
+pub fn anyone_can_spend_vout(tx: &Transaction) -> Option<u32> {
+    let anyone_can_spend_txout = script_builder::anyone_can_spend_txout();
+
+    tx.output
+        .iter()
+        .position(|txout| txout.script_pubkey == anyone_can_spend_txout.script_pubkey)
+        .map(|vout| vout as u32)
+}
+
+/// Fee that a CPFP child should pay, so that parent and child together pay
+/// `fee_rate`. Child always pays at least minimum relay fee for itself.
+pub fn cpfp_child_fee(
+    fee_rate: FeeRate,
+    parent_vsize: u64,
+    parent_fee: Amount,
+    child_vsize: u64,
+) -> Result<Amount, BridgeError> {
+    let package_fee = fee_for_vsize(fee_rate, parent_vsize + child_vsize)?;
+    let min_child_fee = fee_for_vsize(PRESIGNED_TX_FEE_RATE, child_vsize)?;
+
+    Ok(package_fee
+        .checked_sub(parent_fee)
+        .unwrap_or(Amount::ZERO)
+        .max(min_child_fee))
+}
+
+#[derive(Debug, Clone)]
+pub struct FeeManager<R>
+where
+    R: RpcApiWrapper,
+{
+    rpc: ExtendedRpc<R>,
+    conf_target: u16,
+    fallback_fee_rate: FeeRate,
+    max_fee_rate: FeeRate,
+}
+
+impl<R> FeeManager<R>
+where
+    R: RpcApiWrapper,
+{
+    pub fn new(config: &BridgeConfig, rpc: ExtendedRpc<R>) -> Self {
+        let fallback_fee_rate = config
+            .fallback_fee_rate
+            .unwrap_or(DEFAULT_FALLBACK_FEE_RATE);
+        let max_fee_rate = config.max_fee_rate.unwrap_or(DEFAULT_MAX_FEE_RATE);
+
+        Self {
+            rpc,
+            conf_target: config
+                .fee_rate_conf_target
+                .unwrap_or(DEFAULT_FEE_RATE_CONF_TARGET),
+            fallback_fee_rate: FeeRate::from_sat_per_vb_u32(fallback_fee_rate),
+            max_fee_rate: FeeRate::from_sat_per_vb_u32(max_fee_rate),
+        }
+    }
+
+    /// Estimates the fee rate for a tx to be confirmed in the configured
+    /// number of blocks. Fallback fee rate is used if Bitcoin has no
+    /// estimation, which is the case for fresh nodes and regtest. Result is
+    /// never below minimum relay fee rate or above maximum fee rate.
+    pub fn estimate_fee_rate(&self) -> FeeRate {
+        let fee_rate = match self.rpc.estimate_smart_fee(self.conf_target) {
+            // Estimation is in BTC/kvB.
+            Ok(estimation) => match estimation.fee_rate {
+                Some(fee_rate) => FeeRate::from_sat_per_kwu(fee_rate.to_sat() / 4),
+                None => {
+                    tracing::debug!(
+                        "No fee rate estimation, using fallback: {:?}",
+                        estimation.errors
+                    );
+                    self.fallback_fee_rate
+                }
+            },
+            Err(e) => {
+                tracing::warn!("Failed to estimate fee rate, using fallback: {}", e);
+                self.fallback_fee_rate
+            }
+        };
+
+        fee_rate.min(self.max_fee_rate).max(PRESIGNED_TX_FEE_RATE)
+    }
+
+    /// Bumps the fee of an unconfirmed tx, by broadcasting a child tx that
+    /// spends its anyone-can-spend output. Nothing is done if the tx is
+    /// confirmed, not in mempool or already pays the estimated fee rate
+    /// together with its children.
+    ///
+    /// Returns the txid of the child tx, if one is broadcasted.
+    pub fn bump_fee(&self, parent_tx: &Transaction) -> Result<Option<Txid>, BridgeError> {
+        let parent_txid = parent_tx.compute_txid();
+
+        let mempool_entry = match self.rpc.get_mempool_entry(&parent_txid) {
+            Ok(entry) => entry,
+            Err(_) => return Ok(None),
+        };
+
+        let fee_rate = self.estimate_fee_rate();
+        let package_fee_rate =
+            fee_rate_of(mempool_entry.fees.descendant, mempool_entry.descendant_size);
+        if package_fee_rate >= fee_rate {
+            return Ok(None);
+        }
+
+        let child_tx = self.create_cpfp_tx(
+            parent_tx,
+            mempool_entry.vsize,
+            mempool_entry.fees.base,
+            fee_rate,
+        )?;
+        let child_txid = self.rpc.send_raw_transaction(&child_tx)?;
+
+        tracing::info!(
+            "Bumped fee of {} to {} with child tx {}",
+            parent_txid,
+            fee_rate,
+            child_txid
+        );
+
+        Ok(Some(child_txid))
+    }
+
+    /// Creates a signed child tx that spends the anyone-can-spend output of
+    /// the parent tx and a wallet UTXO, so that both txs pay `fee_rate`.
+    /// Everything except the fee is sent back to the wallet.
+    pub fn create_cpfp_tx(
+        &self,
+        parent_tx: &Transaction,
+        parent_vsize: u64,
+        parent_fee: Amount,
+        fee_rate: FeeRate,
+    ) -> Result<Transaction, BridgeError> {
+        let anyone_can_spend_utxo = OutPoint {
+            txid: parent_tx.compute_txid(),
+            vout: anyone_can_spend_vout(parent_tx).ok_or(BridgeError::FeeError(
+                "Tx has no anyone-can-spend output".to_string(),
+            ))?,
+        };
+        let anyone_can_spend_txout = parent_tx.output[anyone_can_spend_utxo.vout as usize].clone();
+
+        let change_address = self.rpc.get_raw_change_address()?;
+        let mut child_tx = TransactionBuilder::create_btc_tx(
+            TransactionBuilder::create_tx_ins(vec![anyone_can_spend_utxo, OutPoint::null()]),
+            vec![TxOut {
+                value: Amount::ZERO,
+                script_pubkey: change_address.script_pubkey(),
+            }],
+        );
+
+        let child_fee = cpfp_child_fee(
+            fee_rate,
+            parent_vsize,
+            parent_fee,
+            cpfp_child_vsize(&child_tx),
+        )?;
+
+        let funding_utxo = self.select_funding_utxo(child_fee)?;
+        child_tx.input[1].previous_output = OutPoint {
+            txid: funding_utxo.txid,
+            vout: funding_utxo.vout,
+        };
+        child_tx.output[0].value = anyone_can_spend_txout.value + funding_utxo.amount - child_fee;
+
+        let signed = self.rpc.sign_raw_transaction_with_wallet(
+            &child_tx,
+            Some(&[SignRawTransactionInput {
+                txid: anyone_can_spend_utxo.txid,
+                vout: anyone_can_spend_utxo.vout,
+                script_pub_key: anyone_can_spend_txout.script_pubkey,
+                redeem_script: None,
+                amount: Some(anyone_can_spend_txout.value),
+            }]),
+        )?;
+        let mut child_tx = signed
+            .transaction()
+            .map_err(|e| BridgeError::FeeError(e.to_string()))?;
+
+        // Wallet can't sign the anyone-can-spend input, it only needs the
+        // witness script.
+        let mut witness = Witness::new();
+        witness.push(script_builder::anyone_can_spend_script());
+        child_tx.input[0].witness = witness;
+
+        Ok(child_tx)
+    }
+
+    /// Selects the smallest confirmed wallet UTXO that can pay given fee and
+    /// still leave a non-dust change.
+    fn select_funding_utxo(&self, fee: Amount) -> Result<ListUnspentResultEntry, BridgeError> {
+        self.rpc
+            .list_unspent(Some(1))?
+            .into_iter()
+            .filter(|utxo| {
+                utxo.spendable
+                    && utxo.safe
+                    && (utxo.script_pub_key.is_p2wpkh() || utxo.script_pub_key.is_p2tr())
+                    && utxo.amount >= fee + utxo.script_pub_key.minimal_non_dust()
+            })
+            .min_by_key(|utxo| utxo.amount)
+            .ok_or(BridgeError::FeeError(format!(
+                "No wallet UTXO to pay {} for fee bumping",
+                fee
+            )))
+    }
+}
+
+/// Virtual size of a CPFP child tx, before it is signed. Funding input is
+/// assumed to be P2WPKH, which has a bigger witness than a taproot key spend.
+fn cpfp_child_vsize(child_tx: &Transaction) -> u64 {
+    let mut dummy_tx = child_tx.clone();
+
+    let mut anyone_can_spend_witness = Witness::new();
+    anyone_can_spend_witness.push(script_builder::anyone_can_spend_script());
+    dummy_tx.input[0].witness = anyone_can_spend_witness;
+
+    let mut funding_witness = Witness::new();
+    funding_witness.push([0u8; 72]);
+    funding_witness.push([0u8; 33]);
+    dummy_tx.input[1].witness = funding_witness;
+
+    dummy_tx.vsize() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction_builder::TransactionBuilder;
+    use bitcoin::{Address, Network, ScriptBuf};
+    use clementine_circuits::constants::BRIDGE_AMOUNT_SATS;
+    use secp256k1::XOnlyPublicKey;
+    use std::str::FromStr;
+
+    #[test]
+    fn fee_rate_conversions() {
+        let fee_rate = FeeRate::from_sat_per_vb_u32(3);
+
+        assert_eq!(fee_for_vsize(fee_rate, 200).unwrap(), Amount::from_sat(600));
+        assert_eq!(fee_rate_of(Amount::from_sat(600), 200), fee_rate);
+        assert_eq!(fee_rate_of(Amount::from_sat(600), 0), FeeRate::ZERO);
+    }
+
+    #[test]
+    fn cpfp_child_pays_for_parent() {
+        let fee_rate = FeeRate::from_sat_per_vb_u32(10);
+
+        // Package of 300 vB should pay 3000 sats.
+        let child_fee = cpfp_child_fee(fee_rate, 200, Amount::from_sat(200), 100).unwrap();
+        assert_eq!(child_fee, Amount::from_sat(2800));
+
+        // Parent already pays enough, child pays for itself.
+        let child_fee = cpfp_child_fee(fee_rate, 200, Amount::from_sat(5000), 100).unwrap();
+        assert_eq!(
+            child_fee,
+            fee_for_vsize(PRESIGNED_TX_FEE_RATE, 100).unwrap()
+        );
+    }
+
+    #[test]
+    fn presigned_tx_fees() {
+        let verifier_pks: Vec<XOnlyPublicKey> = [
+            "9bef8d556d80e43ae7e0becb3a7e6838b95defe45896ed6075bb9035d06c9964",
+            "e37d58a1aae4ba059fd2503712d998470d3a2522f7e2335f544ef384d2199e02",
+            "688466442a134ee312299bafb37058e385c98dd6005eaaf0f538f533efe5f91f",
+        ]
+        .iter()
+        .map(|pk| XOnlyPublicKey::from_str(pk).unwrap())
+        .collect();
+
+        let n_of_n = TransactionBuilder::new(verifier_pks.clone(), Network::Regtest);
+        let musig2 = TransactionBuilder::new_musig2(verifier_pks, Network::Regtest).unwrap();
+
+        // Move tx pays at least 1 sat/vB for its size, which grows with the
+        // number of signatures.
+        let n_of_n_fee = n_of_n.move_tx_fee(1).unwrap();
+        let musig2_fee = musig2.move_tx_fee(1).unwrap();
+        assert!(musig2_fee < n_of_n_fee);
+
+        let move_tx = n_of_n
+            .create_move_tx(
+                OutPoint::null(),
+                &crate::EVMAddress([0u8; 20]),
+                Address::p2tr(
+                    &crate::utils::SECP,
+                    *crate::utils::UNSPENDABLE_XONLY_PUBKEY,
+                    None,
+                    Network::Regtest,
+                )
+                .as_unchecked(),
+                200,
+                BRIDGE_AMOUNT_SATS,
+            )
+            .unwrap();
+        assert_eq!(
+            move_tx.tx.output[0],
+            n_of_n.bridge_txout(BRIDGE_AMOUNT_SATS, 1).unwrap()
+        );
+        assert_eq!(anyone_can_spend_vout(&move_tx.tx), Some(1));
+        assert!(n_of_n_fee.to_sat() > move_tx.tx.vsize() as u64);
+
+        // Withdrawal fee depends on the size of withdrawal address.
+        let p2tr = Address::from_script(
+            &ScriptBuf::new_p2tr(
+                &crate::utils::SECP,
+                *crate::utils::UNSPENDABLE_XONLY_PUBKEY,
+                None,
+            ),
+            Network::Regtest,
+        )
+        .unwrap();
+        let p2wpkh = Address::from_str("bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080")
+            .unwrap()
+            .assume_checked();
+
+        let bridge_txout = n_of_n.bridge_txout(BRIDGE_AMOUNT_SATS, 1).unwrap();
+        let p2tr_tx = n_of_n
+            .create_withdraw_tx(OutPoint::null(), bridge_txout.clone(), &p2tr)
+            .unwrap();
+        let p2wpkh_tx = n_of_n
+            .create_withdraw_tx(OutPoint::null(), bridge_txout, &p2wpkh)
+            .unwrap();
+        assert!(p2wpkh_tx.tx.output[0].value > p2tr_tx.tx.output[0].value);
+    }
+}
@@ -8,22 +8,34 @@ use clementine_circuits::{HashType, PreimageType};
 use serde::{Deserialize, Serialize};
 
 pub mod actor;
+pub mod chain_watcher;
 pub mod cli;
 pub mod config;
 pub mod database;
 pub mod env_writer;
 pub mod errors;
 pub mod extended_rpc;
+pub mod fee;
+pub mod lifecycle;
 pub mod merkle;
+pub mod metrics;
 pub mod mock;
+pub mod musig2;
 pub mod operator;
+pub mod policy;
+pub mod proof_of_reserves;
+pub mod rollup;
+pub mod rpc_auth;
 pub mod script_builder;
 pub mod servers;
+pub mod signer;
+pub mod tls;
 pub mod traits;
 pub mod transaction_builder;
 pub mod user;
 pub mod utils;
 pub mod verifier;
+pub mod verifier_pool;
 
 pub type ConnectorUTXOTree = Vec<Vec<OutPoint>>;
 pub type HashTree = Vec<Vec<HashType>>;
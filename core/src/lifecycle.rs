@@ -0,0 +1,228 @@
+//! # Lifecycle
+//!
+//! Events that are streamed to subscribers of a deposit or withdrawal. They
+//! are derived from operator's lifecycle records and the confirmation count of
+//! the related tx.
+
+use crate::database::common::{DepositRecord, DepositStatus, WithdrawalRecord, WithdrawalStatus};
+use crate::errors::BridgeError;
+use bitcoin::Txid;
+use serde::{Deserialize, Serialize};
+
+/// State of a deposit or withdrawal, as seen by a subscriber.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum LifecycleEvent {
+    /// Deposit request is accepted but presigns are not collected yet.
+    Requested,
+    /// Move tx is signed by every verifier but not broadcasted yet.
+    Presigned { txid: Txid },
+    /// Tx is broadcasted but not included in a block yet.
+    Broadcast { txid: Txid },
+    /// Tx is included in a block but doesn't have enough confirmations yet.
+    Confirmed { txid: Txid, confirmations: u32 },
+    /// Tx has enough confirmations.
+    Finalized { txid: Txid },
+    /// Deposit is minted on the rollup.
+    Minted { txid: Txid },
+    /// Lifecycle can't be followed anymore.
+    Failed { reason: String },
+}
+
+impl LifecycleEvent {
+    fn broadcast(txid: Txid, confirmations: u32) -> Self {
+        if confirmations > 0 {
+            LifecycleEvent::Confirmed {
+                txid,
+                confirmations,
+            }
+        } else {
+            LifecycleEvent::Broadcast { txid }
+        }
+    }
+}
+
+/// Returns the event for the current state of a deposit. `confirmations` is
+/// the confirmation count of the move tx and is only used while it is
+/// broadcasted.
+pub fn deposit_event(
+    deposit: &DepositRecord,
+    confirmations: u32,
+) -> Result<LifecycleEvent, BridgeError> {
+    if deposit.status == DepositStatus::Requested {
+        return Ok(LifecycleEvent::Requested);
+    }
+
+    let txid = match &deposit.move_tx {
+        Some(move_tx) => move_tx.compute_txid(),
+        None => {
+            return Err(BridgeError::InvalidDepositStatus(format!(
+                "Deposit is {} but has no move tx",
+                deposit.status.as_str()
+            )))
+        }
+    };
+
+    Ok(match deposit.status {
+        DepositStatus::Requested => LifecycleEvent::Requested,
+        DepositStatus::PresignsCollected => LifecycleEvent::Presigned { txid },
+        DepositStatus::MoveBroadcast => LifecycleEvent::broadcast(txid, confirmations),
+        DepositStatus::MoveConfirmed => LifecycleEvent::Finalized { txid },
+        DepositStatus::Minted => LifecycleEvent::Minted { txid },
+    })
+}
+
+/// Returns the event for the current state of a withdrawal. `confirmations`
+/// is the confirmation count of the withdrawal tx and is only used while it is
+/// broadcasted.
+pub fn withdrawal_event(withdrawal: &WithdrawalRecord, confirmations: u32) -> LifecycleEvent {
+    let txid = withdrawal.withdrawal_tx.compute_txid();
+
+    match withdrawal.status {
+        WithdrawalStatus::Broadcast => LifecycleEvent::broadcast(txid, confirmations),
+        WithdrawalStatus::Confirmed => LifecycleEvent::Finalized { txid },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{deposit_event, withdrawal_event, LifecycleEvent};
+    use crate::database::common::{
+        DepositRecord, DepositStatus, WithdrawalRecord, WithdrawalStatus,
+    };
+    use crate::EVMAddress;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::transaction::Version;
+    use bitcoin::{Address, Network, OutPoint, Transaction};
+
+    fn empty_tx() -> Transaction {
+        Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![],
+            output: vec![],
+        }
+    }
+
+    fn address() -> Address<bitcoin::address::NetworkUnchecked> {
+        Address::p2tr(
+            &crate::utils::SECP,
+            *crate::utils::UNSPENDABLE_XONLY_PUBKEY,
+            None,
+            Network::Regtest,
+        )
+        .as_unchecked()
+        .clone()
+    }
+
+    #[test]
+    fn deposit_events() {
+        let move_tx = empty_tx();
+        let txid = move_tx.compute_txid();
+        let mut deposit = DepositRecord {
+            start_utxo: OutPoint::null(),
+            recovery_taproot_address: address(),
+            evm_address: EVMAddress([0u8; 20]),
+            amount: 100_000_000,
+            status: DepositStatus::Requested,
+            move_tx: None,
+            deposit_block_hash: None,
+            move_block_hash: None,
+            reorged: false,
+        };
+        assert_eq!(
+            deposit_event(&deposit, 0).unwrap(),
+            LifecycleEvent::Requested
+        );
+
+        // Every later state needs a move tx.
+        deposit.status = DepositStatus::PresignsCollected;
+        assert!(deposit_event(&deposit, 0).is_err());
+
+        deposit.move_tx = Some(move_tx);
+        assert_eq!(
+            deposit_event(&deposit, 0).unwrap(),
+            LifecycleEvent::Presigned { txid }
+        );
+
+        deposit.status = DepositStatus::MoveBroadcast;
+        assert_eq!(
+            deposit_event(&deposit, 0).unwrap(),
+            LifecycleEvent::Broadcast { txid }
+        );
+        assert_eq!(
+            deposit_event(&deposit, 2).unwrap(),
+            LifecycleEvent::Confirmed {
+                txid,
+                confirmations: 2
+            }
+        );
+
+        deposit.status = DepositStatus::MoveConfirmed;
+        assert_eq!(
+            deposit_event(&deposit, 6).unwrap(),
+            LifecycleEvent::Finalized { txid }
+        );
+
+        deposit.status = DepositStatus::Minted;
+        assert_eq!(
+            deposit_event(&deposit, 6).unwrap(),
+            LifecycleEvent::Minted { txid }
+        );
+    }
+
+    #[test]
+    fn withdrawal_events() {
+        let withdrawal_tx = empty_tx();
+        let txid = withdrawal_tx.compute_txid();
+        let mut withdrawal = WithdrawalRecord {
+            idx: 0,
+            bridge_fund_txid: txid,
+            withdrawal_address: address(),
+            withdrawal_tx,
+            status: WithdrawalStatus::Broadcast,
+            block_hash: None,
+            reorged: false,
+        };
+
+        assert_eq!(
+            withdrawal_event(&withdrawal, 0),
+            LifecycleEvent::Broadcast { txid }
+        );
+        assert_eq!(
+            withdrawal_event(&withdrawal, 1),
+            LifecycleEvent::Confirmed {
+                txid,
+                confirmations: 1
+            }
+        );
+
+        withdrawal.status = WithdrawalStatus::Confirmed;
+        assert_eq!(
+            withdrawal_event(&withdrawal, 6),
+            LifecycleEvent::Finalized { txid }
+        );
+    }
+
+    #[test]
+    fn event_format() {
+        let txid = empty_tx().compute_txid();
+
+        assert_eq!(
+            serde_json::to_value(LifecycleEvent::Confirmed {
+                txid,
+                confirmations: 3
+            })
+            .unwrap(),
+            serde_json::json!({
+                "state": "confirmed",
+                "txid": txid.to_string(),
+                "confirmations": 3
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(LifecycleEvent::Requested).unwrap(),
+            serde_json::json!({ "state": "requested" })
+        );
+    }
+}
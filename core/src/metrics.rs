@@ -0,0 +1,665 @@
+//! # Metrics
+//!
+//! Prometheus metrics of operator and verifier servers. Metrics are kept in
+//! process wide statics and served in Prometheus text format by
+//! [`start_server`], at `/metrics`.
+//!
+//! RPC servers are measured with [`RpcMetricsLayer`] and operator's verifier
+//! clients with [`VerifierMetricsLayer`]. Database queries and Bitcoin RPC
+//! calls are measured where they are made.
+
+use crate::errors::BridgeError;
+use hyper::service::service_fn;
+use hyper::{Body, Method, Request, Response, StatusCode};
+use jsonrpsee::http_client::transport::Error as TransportError;
+use jsonrpsee::server::middleware::rpc::RpcServiceT;
+use jsonrpsee::server::{MethodResponse, ServerHandle};
+use jsonrpsee::types::Request as RpcRequest;
+use lazy_static::lazy_static;
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::fmt::Write;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tokio::net::TcpListener;
+use tower::{Layer, Service};
+
+/// Upper bounds of latency histogram buckets, in seconds.
+pub const LATENCY_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+/// Error code of [`BridgeError`]s in JSON-RPC responses.
+const BRIDGE_ERROR_CODE: i32 = -30000;
+
+lazy_static! {
+    pub static ref DEPOSITS_RECEIVED: Counter = Counter::new(
+        "clementine_deposits_received_total",
+        "Deposit requests received by the operator",
+        &[]
+    );
+    pub static ref RPC_REQUEST_DURATION: Histogram = Histogram::new(
+        "clementine_rpc_request_duration_seconds",
+        "Time spent on handling JSON-RPC requests",
+        &["method"],
+        LATENCY_BUCKETS
+    );
+    pub static ref RPC_FAILURES: Counter = Counter::new(
+        "clementine_rpc_failures_total",
+        "JSON-RPC requests that returned an error, by error",
+        &["method", "error"]
+    );
+    pub static ref VERIFIER_REQUEST_DURATION: Histogram = Histogram::new(
+        "clementine_verifier_request_duration_seconds",
+        "Time spent on operator's requests to verifiers",
+        &["verifier", "method"],
+        LATENCY_BUCKETS
+    );
+    pub static ref VERIFIER_REQUEST_ERRORS: Counter = Counter::new(
+        "clementine_verifier_request_errors_total",
+        "Operator's requests to verifiers that got no response",
+        &["verifier", "method"]
+    );
+    pub static ref VERIFIER_UP: Gauge = Gauge::new(
+        "clementine_verifier_up",
+        "Whether the verifier passed operator's last health check",
+        &["verifier"]
+    );
+    pub static ref DB_QUERY_DURATION: Histogram = Histogram::new(
+        "clementine_db_query_duration_seconds",
+        "Time spent on database queries",
+        &["query"],
+        LATENCY_BUCKETS
+    );
+    pub static ref BITCOIN_RPC_DURATION: Histogram = Histogram::new(
+        "clementine_bitcoin_rpc_duration_seconds",
+        "Time spent on Bitcoin RPC calls",
+        &["method"],
+        LATENCY_BUCKETS
+    );
+    pub static ref BITCOIN_RPC_ERRORS: Counter = Counter::new(
+        "clementine_bitcoin_rpc_errors_total",
+        "Bitcoin RPC calls that returned an error",
+        &["method"]
+    );
+    pub static ref PENDING_DEPOSITS: Gauge = Gauge::new(
+        "clementine_pending_deposits",
+        "Deposits that don't have their move tx confirmed yet",
+        &[]
+    );
+    pub static ref PENDING_WITHDRAWALS: Gauge = Gauge::new(
+        "clementine_pending_withdrawals",
+        "Withdrawals that don't have their withdrawal tx confirmed yet",
+        &[]
+    );
+}
+
+/// Renders every metric in Prometheus text format.
+pub fn render() -> String {
+    let metrics: [&dyn Metric; 11] = [
+        &*DEPOSITS_RECEIVED,
+        &*RPC_REQUEST_DURATION,
+        &*RPC_FAILURES,
+        &*VERIFIER_REQUEST_DURATION,
+        &*VERIFIER_REQUEST_ERRORS,
+        &*VERIFIER_UP,
+        &*DB_QUERY_DURATION,
+        &*BITCOIN_RPC_DURATION,
+        &*BITCOIN_RPC_ERRORS,
+        &*PENDING_DEPOSITS,
+        &*PENDING_WITHDRAWALS,
+    ];
+
+    let mut out = String::new();
+    for metric in metrics {
+        metric.render(&mut out);
+    }
+
+    out
+}
+
+trait Metric: Sync {
+    fn render(&self, out: &mut String);
+}
+
+fn render_header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// Formats labels as `{name="value",...}`, or nothing if there are none.
+fn render_labels(names: &[&str], values: &[String], extra: Option<(&str, &str)>) -> String {
+    let labels = names
+        .iter()
+        .zip(values.iter().map(String::as_str))
+        .chain(extra.as_ref().map(|(name, value)| (name, *value)))
+        .map(|(name, value)| {
+            format!(
+                "{}=\"{}\"",
+                name,
+                value
+                    .replace('\\', "\\\\")
+                    .replace('"', "\\\"")
+                    .replace('\n', "\\n")
+            )
+        })
+        .collect::<Vec<_>>();
+
+    if labels.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", labels.join(","))
+    }
+}
+
+fn label_values(values: &[&str]) -> Vec<String> {
+    values.iter().map(|value| value.to_string()).collect()
+}
+
+/// A value that only goes up, for every combination of label values.
+#[derive(Debug)]
+pub struct Counter {
+    name: &'static str,
+    help: &'static str,
+    label_names: &'static [&'static str],
+    values: Mutex<BTreeMap<Vec<String>, u64>>,
+}
+
+impl Counter {
+    pub fn new(
+        name: &'static str,
+        help: &'static str,
+        label_names: &'static [&'static str],
+    ) -> Self {
+        Self {
+            name,
+            help,
+            label_names,
+            values: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn inc(&self, label_values: &[&str]) {
+        debug_assert_eq!(label_values.len(), self.label_names.len());
+
+        *self
+            .values
+            .lock()
+            .unwrap()
+            .entry(self::label_values(label_values))
+            .or_default() += 1;
+    }
+
+    pub fn get(&self, label_values: &[&str]) -> u64 {
+        self.values
+            .lock()
+            .unwrap()
+            .get(&self::label_values(label_values))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+impl Metric for Counter {
+    fn render(&self, out: &mut String) {
+        render_header(out, self.name, self.help, "counter");
+        for (values, value) in self.values.lock().unwrap().iter() {
+            let labels = render_labels(self.label_names, values, None);
+            let _ = writeln!(out, "{}{} {}", self.name, labels, value);
+        }
+    }
+}
+
+/// A value that can go up and down, for every combination of label values.
+#[derive(Debug)]
+pub struct Gauge {
+    name: &'static str,
+    help: &'static str,
+    label_names: &'static [&'static str],
+    values: Mutex<BTreeMap<Vec<String>, f64>>,
+}
+
+impl Gauge {
+    pub fn new(
+        name: &'static str,
+        help: &'static str,
+        label_names: &'static [&'static str],
+    ) -> Self {
+        Self {
+            name,
+            help,
+            label_names,
+            values: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn set(&self, label_values: &[&str], value: f64) {
+        debug_assert_eq!(label_values.len(), self.label_names.len());
+
+        self.values
+            .lock()
+            .unwrap()
+            .insert(self::label_values(label_values), value);
+    }
+
+    pub fn get(&self, label_values: &[&str]) -> f64 {
+        self.values
+            .lock()
+            .unwrap()
+            .get(&self::label_values(label_values))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+impl Metric for Gauge {
+    fn render(&self, out: &mut String) {
+        render_header(out, self.name, self.help, "gauge");
+        for (values, value) in self.values.lock().unwrap().iter() {
+            let labels = render_labels(self.label_names, values, None);
+            let _ = writeln!(out, "{}{} {}", self.name, labels, value);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct HistogramValue {
+    /// Number of observations in each bucket, not cumulative.
+    bucket_counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+/// Distribution of observed values in buckets, for every combination of
+/// label values.
+#[derive(Debug)]
+pub struct Histogram {
+    name: &'static str,
+    help: &'static str,
+    label_names: &'static [&'static str],
+    buckets: &'static [f64],
+    values: Mutex<BTreeMap<Vec<String>, HistogramValue>>,
+}
+
+impl Histogram {
+    pub fn new(
+        name: &'static str,
+        help: &'static str,
+        label_names: &'static [&'static str],
+        buckets: &'static [f64],
+    ) -> Self {
+        Self {
+            name,
+            help,
+            label_names,
+            buckets,
+            values: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn observe(&self, label_values: &[&str], value: f64) {
+        debug_assert_eq!(label_values.len(), self.label_names.len());
+
+        let mut values = self.values.lock().unwrap();
+        let histogram = values
+            .entry(self::label_values(label_values))
+            .or_insert_with(|| HistogramValue {
+                bucket_counts: vec![0; self.buckets.len()],
+                sum: 0.0,
+                count: 0,
+            });
+
+        if let Some(i) = self.buckets.iter().position(|bound| value <= *bound) {
+            histogram.bucket_counts[i] += 1;
+        }
+        histogram.sum += value;
+        histogram.count += 1;
+    }
+
+    /// Returns a timer that observes the time until it is dropped, in
+    /// seconds.
+    pub fn start_timer(&self, label_values: &[&str]) -> HistogramTimer<'_> {
+        HistogramTimer {
+            histogram: self,
+            label_values: self::label_values(label_values),
+            start: Instant::now(),
+        }
+    }
+
+    /// Returns the number of observations.
+    pub fn count(&self, label_values: &[&str]) -> u64 {
+        self.values
+            .lock()
+            .unwrap()
+            .get(&self::label_values(label_values))
+            .map(|histogram| histogram.count)
+            .unwrap_or_default()
+    }
+}
+
+impl Metric for Histogram {
+    fn render(&self, out: &mut String) {
+        render_header(out, self.name, self.help, "histogram");
+        for (values, histogram) in self.values.lock().unwrap().iter() {
+            let mut cumulative = 0;
+            for (bound, count) in self.buckets.iter().zip(histogram.bucket_counts.iter()) {
+                cumulative += count;
+                let labels =
+                    render_labels(self.label_names, values, Some(("le", &bound.to_string())));
+                let _ = writeln!(out, "{}_bucket{} {}", self.name, labels, cumulative);
+            }
+            let labels = render_labels(self.label_names, values, Some(("le", "+Inf")));
+            let _ = writeln!(out, "{}_bucket{} {}", self.name, labels, histogram.count);
+
+            let labels = render_labels(self.label_names, values, None);
+            let _ = writeln!(out, "{}_sum{} {}", self.name, labels, histogram.sum);
+            let _ = writeln!(out, "{}_count{} {}", self.name, labels, histogram.count);
+        }
+    }
+}
+
+/// Observes the time from its creation until it is dropped.
+#[derive(Debug)]
+pub struct HistogramTimer<'a> {
+    histogram: &'a Histogram,
+    label_values: Vec<String>,
+    start: Instant,
+}
+
+impl Drop for HistogramTimer<'_> {
+    fn drop(&mut self) {
+        let label_values = self
+            .label_values
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+
+        self.histogram
+            .observe(&label_values, self.start.elapsed().as_secs_f64());
+    }
+}
+
+/// Returns the label of a failed JSON-RPC response: Name of the
+/// [`BridgeError`] variant, or the error code for other errors.
+fn error_label(response: &MethodResponse) -> String {
+    let code = response.as_error_code().unwrap_or_default();
+    if code != BRIDGE_ERROR_CODE {
+        return format!("code_{}", code);
+    }
+
+    // Message of a bridge error is its debug format, which starts with the
+    // variant name.
+    serde_json::from_str::<serde_json::Value>(response.as_result())
+        .ok()
+        .and_then(|response| {
+            response["error"]["message"].as_str().map(|message| {
+                message
+                    .chars()
+                    .take_while(|c| c.is_ascii_alphanumeric())
+                    .collect::<String>()
+            })
+        })
+        .filter(|variant| !variant.is_empty())
+        .unwrap_or_else(|| format!("code_{}", code))
+}
+
+/// Measures every JSON-RPC call of a server, in
+/// [`struct@RPC_REQUEST_DURATION`] and [`struct@RPC_FAILURES`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RpcMetricsLayer;
+
+impl<S> Layer<S> for RpcMetricsLayer {
+    type Service = RpcMetrics<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        RpcMetrics { service }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RpcMetrics<S> {
+    service: S,
+}
+
+impl<'a, S> RpcServiceT<'a> for RpcMetrics<S>
+where
+    S: RpcServiceT<'a> + Send + Sync,
+    S::Future: 'a,
+{
+    type Future = Pin<Box<dyn Future<Output = MethodResponse> + Send + 'a>>;
+
+    fn call(&self, request: RpcRequest<'a>) -> Self::Future {
+        let method = request.method_name().to_string();
+        let response = self.service.call(request);
+
+        Box::pin(async move {
+            let timer = RPC_REQUEST_DURATION.start_timer(&[&method]);
+            let response = response.await;
+            drop(timer);
+
+            if response.is_error() {
+                RPC_FAILURES.inc(&[&method, &error_label(&response)]);
+            }
+
+            response
+        })
+    }
+}
+
+/// Measures every request of a verifier client, in
+/// [`struct@VERIFIER_REQUEST_DURATION`] and
+/// [`struct@VERIFIER_REQUEST_ERRORS`]. Requests that fail on the transport or
+/// get an unsuccessful HTTP status are counted as errors.
+#[derive(Debug, Clone)]
+pub struct VerifierMetricsLayer {
+    verifier: String,
+}
+
+impl VerifierMetricsLayer {
+    /// Creates a layer that labels requests with given verifier endpoint.
+    pub fn new(verifier: &str) -> Self {
+        Self {
+            verifier: verifier.to_string(),
+        }
+    }
+}
+
+impl<S> Layer<S> for VerifierMetricsLayer {
+    type Service = VerifierMetricsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        VerifierMetricsService {
+            inner,
+            verifier: self.verifier.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VerifierMetricsService<S> {
+    inner: S,
+    verifier: String,
+}
+
+impl<S> Service<Request<Body>> for VerifierMetricsService<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>, Error = TransportError>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send,
+{
+    type Response = Response<Body>;
+    type Error = TransportError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        // Use the service that is polled ready and leave a clone in its place.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let verifier = self.verifier.clone();
+
+        Box::pin(async move {
+            let (parts, body) = request.into_parts();
+            let body = hyper::body::to_bytes(body)
+                .await
+                .map_err(|e| TransportError::Http(e.into()))?;
+            let method = serde_json::from_slice::<serde_json::Value>(&body)
+                .ok()
+                .and_then(|request| request["method"].as_str().map(str::to_string))
+                .unwrap_or_else(|| "batch".to_string());
+
+            let timer = VERIFIER_REQUEST_DURATION.start_timer(&[&verifier, &method]);
+            let response = inner
+                .call(Request::from_parts(parts, Body::from(body)))
+                .await;
+            drop(timer);
+
+            match &response {
+                Ok(response) if response.status().is_success() => {}
+                _ => VERIFIER_REQUEST_ERRORS.inc(&[&verifier, &method]),
+            }
+
+            response
+        })
+    }
+}
+
+async fn handle_request(request: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let response = match (request.method(), request.uri().path()) {
+        (&Method::GET, "/metrics") => Response::builder()
+            .header("content-type", "text/plain; version=0.0.4")
+            .body(Body::from(render())),
+        _ => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty()),
+    };
+
+    Ok(response.expect("response is valid"))
+}
+
+/// Starts an HTTP server that serves metrics at `/metrics`. Metrics server
+/// runs until the given RPC server is stopped.
+pub async fn start_server(addr: &str, rpc_server: ServerHandle) -> Result<SocketAddr, BridgeError> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .map_err(BridgeError::ServerError)?;
+    let local_addr = listener.local_addr().map_err(BridgeError::ServerError)?;
+
+    tokio::spawn(async move {
+        let stopped = rpc_server.stopped();
+        tokio::pin!(stopped);
+
+        loop {
+            let stream = tokio::select! {
+                res = listener.accept() => match res {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        tracing::error!("Failed to accept metrics connection: {}", e);
+                        continue;
+                    }
+                },
+                _ = &mut stopped => break,
+            };
+
+            tokio::spawn(async move {
+                if let Err(e) = hyper::server::conn::Http::new()
+                    .serve_connection(stream, service_fn(handle_request))
+                    .await
+                {
+                    tracing::debug!("Metrics connection closed with error: {}", e);
+                }
+            });
+        }
+    });
+
+    tracing::info!("Metrics server started with address: {}", local_addr);
+
+    Ok(local_addr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Counter, Histogram, Metric};
+
+    #[test]
+    fn render_counter() {
+        let counter = Counter::new("test_total", "Test counter", &["method", "error"]);
+        counter.inc(&["new_deposit", "UTXOSpent"]);
+        counter.inc(&["new_deposit", "UTXOSpent"]);
+        counter.inc(&["new_deposit", "with \"quotes\""]);
+        assert_eq!(counter.get(&["new_deposit", "UTXOSpent"]), 2);
+
+        let mut out = String::new();
+        counter.render(&mut out);
+        assert_eq!(
+            out,
+            "# HELP test_total Test counter\n\
+             # TYPE test_total counter\n\
+             test_total{method=\"new_deposit\",error=\"UTXOSpent\"} 2\n\
+             test_total{method=\"new_deposit\",error=\"with \\\"quotes\\\"\"} 1\n"
+        );
+    }
+
+    #[test]
+    fn render_histogram() {
+        let histogram = Histogram::new("test_seconds", "Test histogram", &[], &[0.1, 1.0]);
+        histogram.observe(&[], 0.05);
+        histogram.observe(&[], 0.5);
+        histogram.observe(&[], 5.0);
+        assert_eq!(histogram.count(&[]), 3);
+
+        let mut out = String::new();
+        histogram.render(&mut out);
+        assert_eq!(
+            out,
+            "# HELP test_seconds Test histogram\n\
+             # TYPE test_seconds histogram\n\
+             test_seconds_bucket{le=\"0.1\"} 1\n\
+             test_seconds_bucket{le=\"1\"} 2\n\
+             test_seconds_bucket{le=\"+Inf\"} 3\n\
+             test_seconds_sum 5.55\n\
+             test_seconds_count 3\n"
+        );
+
+        drop(histogram.start_timer(&[]));
+        assert_eq!(histogram.count(&[]), 4);
+    }
+
+    #[tokio::test]
+    async fn metrics_server() {
+        super::DEPOSITS_RECEIVED.inc(&[]);
+
+        let (_stop_handle, rpc_server) = jsonrpsee::server::stop_channel();
+        let addr = super::start_server("127.0.0.1:0", rpc_server.clone())
+            .await
+            .unwrap();
+        let client = hyper::Client::new();
+
+        let response = client
+            .get(format!("http://{}/metrics", addr).parse().unwrap())
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains("# TYPE clementine_deposits_received_total counter"));
+        assert!(body.contains("# TYPE clementine_pending_deposits gauge"));
+
+        let response = client
+            .get(format!("http://{}/other", addr).parse().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), hyper::StatusCode::NOT_FOUND);
+
+        rpc_server.stop().unwrap();
+    }
+}
@@ -34,6 +34,8 @@ pub fn get_test_config(configuration_file: &str) -> Result<BridgeConfig, BridgeE
     // Overwrite user's environment to test's hard coded data if environment
     // file is specified.
     if let Some(env_config) = env_config {
+        config.db_backend = env_config.db_backend;
+        config.db_dir = env_config.db_dir;
         config.db_host = env_config.db_host;
         config.db_port = env_config.db_port;
         config.db_user = env_config.db_user;
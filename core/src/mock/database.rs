@@ -19,7 +19,10 @@ macro_rules! create_test_config {
 
         let database = Database::new(config.clone()).await.unwrap();
         database
-            .run_sql_file("../scripts/schema.sql")
+            .migrate(&[
+                $crate::database::migrations::Role::Operator,
+                $crate::database::migrations::Role::Verifier,
+            ])
             .await
             .unwrap();
 
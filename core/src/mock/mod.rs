@@ -6,4 +6,5 @@
 pub mod common;
 pub mod database;
 pub mod env;
+pub mod rollup;
 pub mod rpc;
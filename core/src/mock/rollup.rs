@@ -0,0 +1,109 @@
+//! # Rollup Mock
+//!
+//! In-process rollup for tests. Deposits and withdrawals are added by tests
+//! and can be read directly, as a [`RollupClient`], or through a JSON-RPC
+//! server.
+
+use crate::errors::BridgeError;
+use crate::rollup::{RollupDeposit, RollupWithdrawal};
+use crate::traits::rollup::RollupClient;
+use crate::traits::rpc::RollupRpcServer;
+use crate::EVMAddress;
+use bitcoin::address::NetworkUnchecked;
+use bitcoin::{Address, OutPoint};
+use jsonrpsee::core::async_trait;
+use jsonrpsee::server::{Server, ServerHandle};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, Default)]
+pub struct MockRollup {
+    withdrawals: Arc<Mutex<HashMap<usize, RollupWithdrawal>>>,
+    deposits: Arc<Mutex<HashMap<OutPoint, RollupDeposit>>>,
+}
+
+impl MockRollup {
+    /// Adds a withdrawal event, replacing the one with the same index.
+    pub fn add_withdrawal(&self, withdrawal: RollupWithdrawal) {
+        self.withdrawals
+            .lock()
+            .unwrap()
+            .insert(withdrawal.idx, withdrawal);
+    }
+
+    /// Marks the withdrawal with given index as finalized.
+    pub fn finalize_withdrawal(&self, idx: usize) {
+        if let Some(withdrawal) = self.withdrawals.lock().unwrap().get_mut(&idx) {
+            withdrawal.finalized = true;
+        }
+    }
+
+    /// Adds a mint for a deposit, replacing the one for the same bridge UTXO.
+    pub fn add_deposit(&self, deposit: RollupDeposit) {
+        self.deposits
+            .lock()
+            .unwrap()
+            .insert(deposit.move_utxo, deposit);
+    }
+
+    /// Starts a JSON-RPC server for this rollup on a random local port.
+    /// Returns its URL and handle; server stops when handle is dropped.
+    pub async fn start_server(&self) -> Result<(String, ServerHandle), BridgeError> {
+        let server = Server::builder()
+            .build("127.0.0.1:0")
+            .await
+            .map_err(BridgeError::ServerError)?;
+        let addr = server.local_addr().map_err(BridgeError::ServerError)?;
+        let handle = server.start(self.clone().into_rpc());
+
+        Ok((format!("http://{}/", addr), handle))
+    }
+}
+
+#[async_trait]
+impl RollupClient for MockRollup {
+    async fn is_withdrawal_finalized(
+        &self,
+        idx: usize,
+        withdrawal_address: &Address<NetworkUnchecked>,
+        amount: u64,
+    ) -> Result<bool, BridgeError> {
+        Ok(match self.withdrawals.lock().unwrap().get(&idx) {
+            Some(withdrawal) => {
+                withdrawal.finalized
+                    && withdrawal.withdrawal_address == *withdrawal_address
+                    && withdrawal.amount == amount
+            }
+            None => false,
+        })
+    }
+
+    async fn is_deposit_minted(
+        &self,
+        move_utxo: OutPoint,
+        evm_address: &EVMAddress,
+        amount: u64,
+    ) -> Result<bool, BridgeError> {
+        Ok(match self.deposits.lock().unwrap().get(&move_utxo) {
+            Some(deposit) => deposit.evm_address == *evm_address && deposit.amount == amount,
+            None => false,
+        })
+    }
+}
+
+#[async_trait]
+impl RollupRpcServer for MockRollup {
+    async fn get_withdrawal_rpc(
+        &self,
+        idx: usize,
+    ) -> Result<Option<RollupWithdrawal>, BridgeError> {
+        Ok(self.withdrawals.lock().unwrap().get(&idx).cloned())
+    }
+
+    async fn get_deposit_rpc(
+        &self,
+        move_utxo: OutPoint,
+    ) -> Result<Option<RollupDeposit>, BridgeError> {
+        Ok(self.deposits.lock().unwrap().get(&move_utxo).cloned())
+    }
+}
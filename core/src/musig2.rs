@@ -0,0 +1,524 @@
+//! # MuSig2
+//!
+//! This module implements the parts of [BIP-327](https://github.com/bitcoin/bips/blob/master/bip-0327.mediawiki)
+//! that the bridge needs: key aggregation, nonce generation and aggregation,
+//! partial signing, partial signature verification and partial signature
+//! aggregation. Tweaking is not supported, because aggregated keys are only
+//! used inside tapscripts.
+//!
+//! Verifier public keys are x-only keys. They are lifted to the public keys
+//! with even Y coordinates before aggregation, so a signer whose public key
+//! has an odd Y coordinate signs with the negated secret key.
+
+use crate::errors::BridgeError;
+use crate::utils::SECP;
+use crypto_bigint::{Encoding, U256};
+use secp256k1::rand::{thread_rng, RngCore};
+use secp256k1::{schnorr, Parity, PublicKey, Scalar, SecretKey, XOnlyPublicKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Order of the secp256k1 curve.
+const CURVE_ORDER: U256 =
+    U256::from_be_hex("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141");
+
+/// Public nonce of a signer: two compressed points.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MuSigPubNonce(#[serde(with = "hex::serde")] pub [u8; 66]);
+
+/// Aggregated nonce of all signers: two compressed points.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MuSigAggNonce(#[serde(with = "hex::serde")] pub [u8; 66]);
+
+/// Partial signature of a signer: a scalar.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MuSigPartialSignature(#[serde(with = "hex::serde")] pub [u8; 32]);
+
+/// Secret nonce of a signer. It is consumed by [`partial_sign`] so that it
+/// can't be used twice.
+pub struct MuSigSecNonce {
+    k1: SecretKey,
+    k2: SecretKey,
+    public_key: PublicKey,
+}
+
+impl core::fmt::Debug for MuSigSecNonce {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("MuSigSecNonce")
+            .field("public_key", &self.public_key)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Key aggregation context of a fixed, ordered signer set.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyAggContext {
+    public_keys: Vec<PublicKey>,
+    coefficients: Vec<Scalar>,
+    aggregated_public_key: PublicKey,
+}
+
+impl KeyAggContext {
+    /// Aggregates given public keys. Order of the keys matters and every
+    /// signer must use the same order.
+    pub fn new(public_keys: Vec<PublicKey>) -> Result<Self, BridgeError> {
+        if public_keys.is_empty() {
+            return Err(BridgeError::MuSig2Error("No public keys given".to_string()));
+        }
+
+        let mut engine = tagged_hash_engine("KeyAgg list");
+        public_keys
+            .iter()
+            .for_each(|pk| engine.update(pk.serialize()));
+        let list_hash: [u8; 32] = engine.finalize().into();
+
+        let second_key = public_keys.iter().find(|pk| **pk != public_keys[0]);
+
+        let coefficients = public_keys
+            .iter()
+            .map(|pk| {
+                if Some(pk) == second_key {
+                    Scalar::ONE
+                } else {
+                    let mut engine = tagged_hash_engine("KeyAgg coefficient");
+                    engine.update(list_hash);
+                    engine.update(pk.serialize());
+                    scalar_from_hash(engine.finalize().into())
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let weighted_keys = public_keys
+            .iter()
+            .zip(coefficients.iter())
+            .map(|(pk, coefficient)| point_mul(pk, coefficient))
+            .collect::<Result<Vec<_>, BridgeError>>()?;
+        let aggregated_public_key =
+            PublicKey::combine_keys(&weighted_keys.iter().collect::<Vec<_>>())?;
+
+        Ok(Self {
+            public_keys,
+            coefficients,
+            aggregated_public_key,
+        })
+    }
+
+    /// Aggregates given x-only public keys, using their even Y lifts.
+    pub fn from_xonly(public_keys: &[XOnlyPublicKey]) -> Result<Self, BridgeError> {
+        Self::new(
+            public_keys
+                .iter()
+                .map(|pk| pk.public_key(Parity::Even))
+                .collect(),
+        )
+    }
+
+    pub fn aggregated_public_key(&self) -> PublicKey {
+        self.aggregated_public_key
+    }
+
+    pub fn aggregated_xonly_public_key(&self) -> XOnlyPublicKey {
+        self.aggregated_public_key.x_only_public_key().0
+    }
+
+    pub fn public_keys(&self) -> &[PublicKey] {
+        &self.public_keys
+    }
+
+    fn coefficient(&self, public_key: &PublicKey) -> Result<Scalar, BridgeError> {
+        self.public_keys
+            .iter()
+            .position(|pk| pk == public_key)
+            .map(|i| self.coefficients[i])
+            .ok_or(BridgeError::PublicKeyNotFound)
+    }
+
+    /// Returns `g` of BIP-327 as a boolean: `true` means `g = -1`.
+    fn is_aggregated_key_odd(&self) -> bool {
+        self.aggregated_public_key.x_only_public_key().1 == Parity::Odd
+    }
+}
+
+/// Values that every signer derives from the aggregated nonce and the
+/// message.
+struct SessionValues {
+    /// Nonce coefficient.
+    b: Scalar,
+    /// Final nonce.
+    r: PublicKey,
+    /// Challenge.
+    e: Scalar,
+}
+
+impl SessionValues {
+    fn new(
+        key_agg_ctx: &KeyAggContext,
+        agg_nonce: &MuSigAggNonce,
+        message: &[u8; 32],
+    ) -> Result<Self, BridgeError> {
+        let (r1, r2) = parse_nonce(&agg_nonce.0)?;
+
+        let mut engine = tagged_hash_engine("MuSig/noncecoef");
+        engine.update(agg_nonce.0);
+        engine.update(key_agg_ctx.aggregated_xonly_public_key().serialize());
+        engine.update(message);
+        let b = scalar_from_hash(engine.finalize().into());
+
+        let r = r1.combine(&point_mul(&r2, &b)?)?;
+
+        let mut engine = tagged_hash_engine("BIP0340/challenge");
+        engine.update(r.x_only_public_key().0.serialize());
+        engine.update(key_agg_ctx.aggregated_xonly_public_key().serialize());
+        engine.update(message);
+        let e = scalar_from_hash(engine.finalize().into());
+
+        Ok(Self { b, r, e })
+    }
+}
+
+/// Generates a nonce pair for `secret_key`. The message and aggregated public
+/// key are optional, but strengthen nonce generation against bad randomness.
+pub fn nonce_gen(
+    secret_key: &SecretKey,
+    aggregated_public_key: Option<&XOnlyPublicKey>,
+    message: Option<&[u8; 32]>,
+) -> Result<(MuSigSecNonce, MuSigPubNonce), BridgeError> {
+    let public_key = even_secret_key(secret_key).public_key(&SECP);
+
+    let mut rand_bytes = [0u8; 32];
+    thread_rng().fill_bytes(&mut rand_bytes);
+    let mut engine = tagged_hash_engine("MuSig/aux");
+    engine.update(rand_bytes);
+    let aux: [u8; 32] = engine.finalize().into();
+    let rand: Vec<u8> = secret_key
+        .secret_bytes()
+        .iter()
+        .zip(aux.iter())
+        .map(|(a, b)| a ^ b)
+        .collect();
+
+    let nonce_hash = |i: u8| {
+        let mut engine = tagged_hash_engine("MuSig/nonce");
+        engine.update(&rand);
+        engine.update([33u8]);
+        engine.update(public_key.serialize());
+        match aggregated_public_key {
+            Some(pk) => {
+                engine.update([32u8]);
+                engine.update(pk.serialize());
+            }
+            None => engine.update([0u8]),
+        }
+        match message {
+            Some(m) => {
+                engine.update([1u8]);
+                engine.update(32u64.to_be_bytes());
+                engine.update(m);
+            }
+            None => engine.update([0u8]),
+        }
+        engine.update(0u32.to_be_bytes());
+        engine.update([i]);
+        scalar_from_hash(engine.finalize().into())
+    };
+
+    let k1 = SecretKey::from_slice(&nonce_hash(0).to_be_bytes())?;
+    let k2 = SecretKey::from_slice(&nonce_hash(1).to_be_bytes())?;
+
+    let mut pub_nonce = [0u8; 66];
+    pub_nonce[..33].copy_from_slice(&k1.public_key(&SECP).serialize());
+    pub_nonce[33..].copy_from_slice(&k2.public_key(&SECP).serialize());
+
+    Ok((
+        MuSigSecNonce { k1, k2, public_key },
+        MuSigPubNonce(pub_nonce),
+    ))
+}
+
+/// Aggregates public nonces of every signer.
+pub fn aggregate_nonces(pub_nonces: &[MuSigPubNonce]) -> Result<MuSigAggNonce, BridgeError> {
+    let parsed = pub_nonces
+        .iter()
+        .map(|nonce| parse_nonce(&nonce.0))
+        .collect::<Result<Vec<_>, BridgeError>>()?;
+
+    let r1 = PublicKey::combine_keys(&parsed.iter().map(|(r1, _)| r1).collect::<Vec<_>>())?;
+    let r2 = PublicKey::combine_keys(&parsed.iter().map(|(_, r2)| r2).collect::<Vec<_>>())?;
+
+    let mut agg_nonce = [0u8; 66];
+    agg_nonce[..33].copy_from_slice(&r1.serialize());
+    agg_nonce[33..].copy_from_slice(&r2.serialize());
+
+    Ok(MuSigAggNonce(agg_nonce))
+}
+
+/// Creates a partial signature for `message`. Secret nonce is consumed.
+pub fn partial_sign(
+    key_agg_ctx: &KeyAggContext,
+    sec_nonce: MuSigSecNonce,
+    secret_key: &SecretKey,
+    agg_nonce: &MuSigAggNonce,
+    message: &[u8; 32],
+) -> Result<MuSigPartialSignature, BridgeError> {
+    let secret_key = even_secret_key(secret_key);
+    if secret_key.public_key(&SECP) != sec_nonce.public_key {
+        return Err(BridgeError::MuSig2Error(
+            "Secret nonce does not belong to the secret key".to_string(),
+        ));
+    }
+
+    let session = SessionValues::new(key_agg_ctx, agg_nonce, message)?;
+    let coefficient = key_agg_ctx.coefficient(&sec_nonce.public_key)?;
+
+    let (mut k1, mut k2) = (sec_nonce.k1, sec_nonce.k2);
+    if session.r.x_only_public_key().1 == Parity::Odd {
+        k1 = k1.negate();
+        k2 = k2.negate();
+    }
+
+    let mut d = secret_key;
+    if key_agg_ctx.is_aggregated_key_odd() {
+        d = d.negate();
+    }
+
+    // s = k1 + b * k2 + e * a * d
+    let s = k2
+        .mul_tweak(&session.b)?
+        .add_tweak(&Scalar::from(k1))?
+        .add_tweak(&Scalar::from(
+            d.mul_tweak(&coefficient)?.mul_tweak(&session.e)?,
+        ))?;
+
+    Ok(MuSigPartialSignature(s.secret_bytes()))
+}
+
+/// Verifies a partial signature of the signer with `public_key`. This lets
+/// the aggregator find out which signer sent an invalid partial signature.
+pub fn partial_sig_verify(
+    key_agg_ctx: &KeyAggContext,
+    pub_nonce: &MuSigPubNonce,
+    public_key: &PublicKey,
+    agg_nonce: &MuSigAggNonce,
+    message: &[u8; 32],
+    partial_signature: &MuSigPartialSignature,
+) -> Result<bool, BridgeError> {
+    let session = SessionValues::new(key_agg_ctx, agg_nonce, message)?;
+    let coefficient = key_agg_ctx.coefficient(public_key)?;
+    let (r1, r2) = parse_nonce(&pub_nonce.0)?;
+
+    let s = match SecretKey::from_slice(&partial_signature.0) {
+        Ok(s) => s,
+        Err(_) => return Ok(false),
+    };
+
+    let mut re = r1.combine(&point_mul(&r2, &session.b)?)?;
+    if session.r.x_only_public_key().1 == Parity::Odd {
+        re = re.negate(&SECP);
+    }
+
+    let mut p = point_mul(&point_mul(public_key, &coefficient)?, &session.e)?;
+    if key_agg_ctx.is_aggregated_key_odd() {
+        p = p.negate(&SECP);
+    }
+
+    Ok(s.public_key(&SECP) == re.combine(&p)?)
+}
+
+/// Aggregates partial signatures of every signer into a BIP-340 signature.
+pub fn aggregate_partial_signatures(
+    key_agg_ctx: &KeyAggContext,
+    agg_nonce: &MuSigAggNonce,
+    message: &[u8; 32],
+    partial_signatures: &[MuSigPartialSignature],
+) -> Result<schnorr::Signature, BridgeError> {
+    let session = SessionValues::new(key_agg_ctx, agg_nonce, message)?;
+
+    let (first, rest) = partial_signatures
+        .split_first()
+        .ok_or(BridgeError::MuSig2Error(
+            "No partial signatures given".to_string(),
+        ))?;
+    let s = rest.iter().try_fold(
+        SecretKey::from_slice(&first.0)?,
+        |acc, partial_signature| {
+            acc.add_tweak(&Scalar::from_be_bytes(partial_signature.0).map_err(|_| {
+                BridgeError::MuSig2Error("Partial signature is out of range".to_string())
+            })?)
+            .map_err(BridgeError::from)
+        },
+    )?;
+
+    let mut signature = [0u8; 64];
+    signature[..32].copy_from_slice(&session.r.x_only_public_key().0.serialize());
+    signature[32..].copy_from_slice(&s.secret_bytes());
+
+    Ok(schnorr::Signature::from_slice(&signature)?)
+}
+
+/// Returns the secret key whose public key has an even Y coordinate.
+fn even_secret_key(secret_key: &SecretKey) -> SecretKey {
+    match secret_key.x_only_public_key(&SECP).1 {
+        Parity::Even => *secret_key,
+        Parity::Odd => secret_key.negate(),
+    }
+}
+
+fn parse_nonce(nonce: &[u8; 66]) -> Result<(PublicKey, PublicKey), BridgeError> {
+    Ok((
+        PublicKey::from_slice(&nonce[..33])?,
+        PublicKey::from_slice(&nonce[33..])?,
+    ))
+}
+
+fn point_mul(point: &PublicKey, scalar: &Scalar) -> Result<PublicKey, BridgeError> {
+    Ok(point.mul_tweak(&SECP, scalar)?)
+}
+
+/// Interprets a hash as an integer modulo curve order.
+fn scalar_from_hash(hash: [u8; 32]) -> Scalar {
+    let value = U256::from_be_bytes(hash);
+    let reduced = if value >= CURVE_ORDER {
+        value.wrapping_sub(&CURVE_ORDER)
+    } else {
+        value
+    };
+
+    Scalar::from_be_bytes(reduced.to_be_bytes()).expect("reduced below curve order")
+}
+
+fn tagged_hash_engine(tag: &str) -> Sha256 {
+    let tag_hash = Sha256::digest(tag.as_bytes());
+
+    let mut engine = Sha256::new();
+    engine.update(tag_hash);
+    engine.update(tag_hash);
+
+    engine
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    /// Key aggregation test vector from BIP-327.
+    #[test]
+    fn key_aggregation_vector() {
+        let public_keys = [
+            "02F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9",
+            "03DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659",
+            "023590A94E768F8E1815C2F24B4D80A8E3149316C3518CE7B7AD338368D038CA66",
+        ]
+        .iter()
+        .map(|pk| PublicKey::from_str(pk).unwrap())
+        .collect::<Vec<_>>();
+
+        let key_agg_ctx = KeyAggContext::new(public_keys).unwrap();
+
+        assert_eq!(
+            key_agg_ctx.aggregated_xonly_public_key().to_string(),
+            "90539eede565f5d054f32cc0c220126889ed1e5d193baf15aef344fe59d4610c"
+        );
+    }
+
+    #[test]
+    fn sign_and_aggregate() {
+        let secret_keys = (0..4)
+            .map(|_| SecretKey::new(&mut thread_rng()))
+            .collect::<Vec<_>>();
+        let xonly_public_keys = secret_keys
+            .iter()
+            .map(|sk| sk.x_only_public_key(&SECP).0)
+            .collect::<Vec<_>>();
+        let key_agg_ctx = KeyAggContext::from_xonly(&xonly_public_keys).unwrap();
+        let message = [0x45u8; 32];
+
+        let nonces = secret_keys
+            .iter()
+            .map(|sk| {
+                nonce_gen(
+                    sk,
+                    Some(&key_agg_ctx.aggregated_xonly_public_key()),
+                    Some(&message),
+                )
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+        let agg_nonce = aggregate_nonces(
+            &nonces
+                .iter()
+                .map(|(_, pub_nonce)| *pub_nonce)
+                .collect::<Vec<_>>(),
+        )
+        .unwrap();
+
+        let partial_signatures = secret_keys
+            .iter()
+            .zip(nonces)
+            .map(|(sk, (sec_nonce, pub_nonce))| {
+                let partial_signature =
+                    partial_sign(&key_agg_ctx, sec_nonce, sk, &agg_nonce, &message).unwrap();
+
+                assert!(partial_sig_verify(
+                    &key_agg_ctx,
+                    &pub_nonce,
+                    &sk.x_only_public_key(&SECP).0.public_key(Parity::Even),
+                    &agg_nonce,
+                    &message,
+                    &partial_signature,
+                )
+                .unwrap());
+
+                partial_signature
+            })
+            .collect::<Vec<_>>();
+
+        let signature =
+            aggregate_partial_signatures(&key_agg_ctx, &agg_nonce, &message, &partial_signatures)
+                .unwrap();
+
+        SECP.verify_schnorr(
+            &signature,
+            &secp256k1::Message::from_digest(message),
+            &key_agg_ctx.aggregated_xonly_public_key(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn invalid_partial_signature() {
+        let secret_keys = (0..2)
+            .map(|_| SecretKey::new(&mut thread_rng()))
+            .collect::<Vec<_>>();
+        let xonly_public_keys = secret_keys
+            .iter()
+            .map(|sk| sk.x_only_public_key(&SECP).0)
+            .collect::<Vec<_>>();
+        let key_agg_ctx = KeyAggContext::from_xonly(&xonly_public_keys).unwrap();
+        let message = [0x45u8; 32];
+
+        let (sec_nonce, pub_nonce) = nonce_gen(&secret_keys[0], None, None).unwrap();
+        let (_, other_pub_nonce) = nonce_gen(&secret_keys[1], None, None).unwrap();
+        let agg_nonce = aggregate_nonces(&[pub_nonce, other_pub_nonce]).unwrap();
+
+        let partial_signature = partial_sign(
+            &key_agg_ctx,
+            sec_nonce,
+            &secret_keys[0],
+            &agg_nonce,
+            &message,
+        )
+        .unwrap();
+
+        // Signature of the first signer is not valid for the second one.
+        assert!(!partial_sig_verify(
+            &key_agg_ctx,
+            &pub_nonce,
+            &xonly_public_keys[1].public_key(Parity::Even),
+            &agg_nonce,
+            &message,
+            &partial_signature,
+        )
+        .unwrap());
+    }
+}
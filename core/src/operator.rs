@@ -1,21 +1,55 @@
 use crate::actor::Actor;
+use crate::chain_watcher::{self, ChainEvent, TipChange};
 use crate::config::BridgeConfig;
+use crate::database::common::{
+    DepositRecord, DepositRequest, DepositStatus, WithdrawalLedgerEntry, WithdrawalRecord,
+    WithdrawalStatus,
+};
 use crate::database::operator::OperatorDB;
 use crate::errors::BridgeError;
 use crate::extended_rpc::ExtendedRpc;
+use crate::fee::FeeManager;
+use crate::lifecycle::{self, LifecycleEvent};
+use crate::metrics;
+use crate::musig2::{
+    self, KeyAggContext, MuSigAggNonce, MuSigPartialSignature, MuSigPubNonce, MuSigSecNonce,
+};
+use crate::rollup;
+use crate::rpc_auth::VerifierClient;
+use crate::traits::rollup::RollupClient;
 use crate::traits::rpc::{OperatorRpcServer, VerifierRpcClient};
-use crate::transaction_builder::TransactionBuilder;
+use crate::transaction_builder::{CreateTxOutputs, TransactionBuilder};
 use crate::utils::handle_taproot_witness_new;
-use crate::{script_builder, EVMAddress};
+use crate::verifier_pool::{VerifierPool, VerifierStatus};
+use crate::EVMAddress;
 use bitcoin::address::{NetworkChecked, NetworkUnchecked};
+use bitcoin::hashes::Hash;
 use bitcoin::secp256k1::schnorr;
-use bitcoin::{Address, Amount, OutPoint, TxOut, Txid};
+use bitcoin::{Address, BlockHash, OutPoint, TapSighash, Transaction, Txid};
 use bitcoin_mock_rpc::RpcApiWrapper;
-use clementine_circuits::constants::BRIDGE_AMOUNT_SATS;
-use futures::stream::FuturesOrdered;
-use futures::TryStreamExt;
-use jsonrpsee::core::async_trait;
+use jsonrpsee::core::{async_trait, SubscriptionResult};
+use jsonrpsee::{PendingSubscriptionSink, SubscriptionMessage};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+/// Interval for operator to resume unfinished deposits and withdrawals, in
+/// seconds. Records that are updated more recently than that are assumed to
+/// be in progress.
+pub const DEPOSIT_RESUME_INTERVAL_SECS: u64 = 60;
+
+/// Interval for operator to check the deposit batch queue, in seconds.
+pub const DEPOSIT_BATCH_POLL_INTERVAL_SECS: u64 = 5;
+
+/// Maximum number of deposits that are moved in a single tx.
+pub const MAX_DEPOSIT_BATCH_SIZE: usize = 100;
+
+/// Maximum number of records that are returned by a single query.
+pub const MAX_QUERY_LIMIT: u64 = 100;
+
+/// Interval for operator to check the lifecycle of a subscribed deposit or
+/// withdrawal, in seconds.
+pub const SUBSCRIPTION_POLL_INTERVAL_SECS: u64 = 1;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DepositPresigns {
@@ -23,6 +57,49 @@ pub struct DepositPresigns {
     pub operator_claim_sign: Vec<schnorr::Signature>,
 }
 
+/// Total amount that is locked in unspent bridge UTXOs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BridgeBalance {
+    /// Locked amount in sats.
+    pub amount: u64,
+    pub utxo_count: usize,
+}
+
+/// Operator's state in a MuSig2 signing round for every input of a tx.
+struct MuSig2BatchRound {
+    sighashes: Vec<TapSighash>,
+    sec_nonces: Vec<MuSigSecNonce>,
+    /// Nonces of every signer for each input, operator last.
+    pub_nonces: Vec<Vec<MuSigPubNonce>>,
+    agg_nonces: Vec<MuSigAggNonce>,
+}
+
+/// A withdrawal that is paid in a batch withdrawal tx, from given bridge
+/// UTXO.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchWithdrawal {
+    pub idx: usize,
+    pub bridge_utxo: OutPoint,
+    pub withdrawal_address: Address<NetworkUnchecked>,
+}
+
+impl From<&BatchWithdrawal> for WithdrawalLedgerEntry {
+    fn from(withdrawal: &BatchWithdrawal) -> Self {
+        WithdrawalLedgerEntry {
+            idx: withdrawal.idx,
+            bridge_utxo: withdrawal.bridge_utxo,
+            withdrawal_address: withdrawal.withdrawal_address.clone(),
+        }
+    }
+}
+
+/// A deposit or withdrawal that a client is subscribed to.
+#[derive(Debug, Clone, Copy)]
+enum Subscription {
+    Deposit(OutPoint),
+    Withdrawal(usize),
+}
+
 #[derive(Debug, Clone)]
 pub struct Operator<R>
 where
@@ -32,10 +109,21 @@ where
     db: OperatorDB,
     signer: Actor,
     transaction_builder: TransactionBuilder,
-    verifier_connector: Vec<jsonrpsee::http_client::HttpClient>,
+    verifier_connector: VerifierPool,
+    fee_manager: FeeManager<R>,
     confirmation_treshold: u32,
-    min_relay_fee: u64,
     user_takes_after: u32,
+    /// Deposit amounts that are accepted by the bridge.
+    deposit_denominations: Vec<u64>,
+    /// Number of queued deposits that triggers a batch move tx.
+    deposit_batch_size: Option<usize>,
+    /// Maximum time between batch move txs, in seconds.
+    deposit_batch_interval_secs: Option<u64>,
+    /// Set if verifiers sign with a MuSig2 aggregated key.
+    key_agg_ctx: Option<KeyAggContext>,
+    /// Rollup that confirmed deposits are minted on. Mints are not checked
+    /// if it is not set.
+    rollup_client: Option<Arc<dyn RollupClient>>,
 }
 
 impl<R> Operator<R>
@@ -46,60 +134,106 @@ where
     pub async fn new(
         config: BridgeConfig,
         rpc: ExtendedRpc<R>,
-        verifiers: Vec<jsonrpsee::http_client::HttpClient>,
+        verifiers: Vec<VerifierClient>,
     ) -> Result<Self, BridgeError> {
         let num_verifiers = config.verifiers_public_keys.len();
 
-        let signer = Actor::new(config.secret_key, config.network);
+        let signer = Actor::from_config(&config)?;
         if signer.xonly_public_key != config.verifiers_public_keys[num_verifiers - 1] {
             return Err(BridgeError::InvalidOperatorKey);
         }
 
-        let transaction_builder =
-            TransactionBuilder::new(config.verifiers_public_keys.clone(), config.network);
+        let transaction_builder = TransactionBuilder::from_config(&config)?;
+
+        let key_agg_ctx = if config.musig2 {
+            Some(KeyAggContext::from_xonly(&config.verifiers_public_keys)?)
+        } else {
+            None
+        };
+
+        let db = OperatorDB::new(config.clone()).await?;
+
+        let fee_manager = FeeManager::new(&config, rpc.clone());
+
+        let rollup_client = rollup::new_rollup_client(&config)?;
 
-        let db = OperatorDB::new(config.clone()).await;
+        let verifier_connector = VerifierPool::new(verifiers, &config);
 
         Ok(Self {
             rpc,
             db,
             signer,
             transaction_builder,
-            verifier_connector: verifiers,
+            verifier_connector,
+            fee_manager,
             confirmation_treshold: config.confirmation_treshold,
-            min_relay_fee: config.min_relay_fee,
             user_takes_after: config.user_takes_after,
+            deposit_batch_size: config.deposit_batch_size,
+            deposit_batch_interval_secs: config.deposit_batch_interval_secs,
+            deposit_denominations: config.deposit_denominations,
+            key_agg_ctx,
+            rollup_client,
         })
     }
 
+    /// Returns operator's verifier clients. Clones of it share their health
+    /// status with the operator.
+    pub fn verifier_pool(&self) -> &VerifierPool {
+        &self.verifier_connector
+    }
+
     /// Public endpoint for every depositor to call.
     ///
     /// It will get signatures from all verifiers:
     ///
-    /// 1. Check if the deposit UTXO is valid and finalized (6 blocks confirmation)
-    /// 2. Check if the UTXO is not already spent
-    /// 3. Get signatures from all verifiers 1 move signature, ~150 operator takes signatures
-    /// 4. Create a move transaction and return the output UTXO
+    /// 1. Check if the deposit amount is one of the deposit denominations
+    /// 2. Check if the deposit UTXO is valid and finalized (6 blocks confirmation)
+    /// 3. Check if the UTXO is not already spent
+    /// 4. Get signatures from all verifiers 1 move signature, ~150 operator takes signatures
+    /// 5. Create a move transaction and return the output UTXO
+    ///
+    /// If deposit batching is enabled, valid deposits are queued after step 3
+    /// and nothing is returned until they are moved by
+    /// [`Operator::process_deposit_batch`].
     pub async fn new_deposit(
         &self,
         start_utxo: OutPoint,
         recovery_taproot_address: &Address<NetworkUnchecked>,
         evm_address: &EVMAddress,
-    ) -> Result<Txid, BridgeError> {
+        amount: u64,
+    ) -> Result<Option<Txid>, BridgeError> {
         tracing::info!(
-            "New deposit request for UTXO: {:?}, EVM address: {:?} and recovery taproot address of: {:?}",
+            "New deposit request for UTXO: {:?}, EVM address: {:?}, amount: {} and recovery taproot address of: {:?}",
             start_utxo,
             evm_address,
+            amount,
             recovery_taproot_address
         );
 
+        if !self.deposit_denominations.contains(&amount) {
+            return Err(BridgeError::InvalidDepositAmount(amount));
+        }
+
         // If deposit request already been made, return it's TXID.
         if let Ok(move_txid) = self
             .db
             .get_move_txid(start_utxo, recovery_taproot_address.clone(), *evm_address)
             .await
         {
-            return Ok(move_txid);
+            return Ok(Some(move_txid));
+        }
+
+        // If move tx is already signed, there is no need to ask verifiers
+        // again.
+        if let Ok(DepositRecord {
+            move_tx: Some(move_tx),
+            ..
+        }) = self.db.get_deposit_record(start_utxo).await
+        {
+            return self
+                .broadcast_move_tx(start_utxo, recovery_taproot_address, evm_address, &move_tx)
+                .await
+                .map(Some);
         }
 
         self.rpc.check_deposit_utxo(
@@ -107,11 +241,49 @@ where
             &start_utxo,
             recovery_taproot_address,
             evm_address,
-            BRIDGE_AMOUNT_SATS,
+            amount,
             self.user_takes_after,
             self.confirmation_treshold,
         )?;
 
+        self.db
+            .start_deposit(
+                start_utxo,
+                recovery_taproot_address.clone(),
+                *evm_address,
+                amount,
+            )
+            .await?;
+        let (_, deposit_block_hash) = self.rpc.confirmation_info(&start_utxo.txid)?;
+        self.db
+            .set_deposit_block_hash(start_utxo, deposit_block_hash)
+            .await?;
+
+        // Chain watcher reports the deposit UTXO if it is mined again, after
+        // its block leaves the best chain.
+        let (deposit_address, _) = self.transaction_builder.generate_deposit_address(
+            recovery_taproot_address,
+            evm_address,
+            amount,
+            self.user_takes_after,
+        )?;
+        self.db
+            .add_watched_deposit_script(&deposit_address.script_pubkey())
+            .await?;
+
+        if self.deposit_batching() {
+            self.db
+                .add_new_deposit_request(
+                    start_utxo,
+                    recovery_taproot_address.clone(),
+                    *evm_address,
+                    amount,
+                )
+                .await?;
+
+            return Ok(None);
+        }
+
         // Create a move transaction, then return the output UTXO. Save the UTXO
         // as a pending deposit.
         let mut move_tx = self.transaction_builder.create_move_tx(
@@ -119,26 +291,56 @@ where
             evm_address,
             recovery_taproot_address,
             self.user_takes_after,
+            amount,
         )?;
 
-        let presigns_from_all_verifiers: Vec<_> = self
-            .verifier_connector
-            .iter()
-            .map(|verifier| async {
-                // Attempt to get the deposit presigns.
-                verifier
-                    .new_deposit_rpc(
+        let move_signatures = match &self.key_agg_ctx {
+            Some(key_agg_ctx) => {
+                vec![
+                    self.musig2_deposit_signature(
+                        key_agg_ctx,
+                        &mut move_tx,
                         start_utxo,
-                        recovery_taproot_address.clone(),
-                        0, // Since we don't have bitvm yet, deposit index is useless.
-                        *evm_address,
-                        self.signer.address.as_unchecked().clone(),
+                        recovery_taproot_address,
+                        evm_address,
+                        amount,
                     )
-                    .await
-            })
-            .collect::<FuturesOrdered<_>>()
-            .try_collect()
-            .await?;
+                    .await?,
+                ]
+            }
+            None => {
+                let presigns_from_all_verifiers: Vec<_> = self
+                    .verifier_connector
+                    .request(|verifier| async {
+                        // Attempt to get the deposit presigns.
+                        verifier
+                            .new_deposit_rpc(
+                                start_utxo,
+                                recovery_taproot_address.clone(),
+                                0, // Since we don't have bitvm yet, deposit index is useless.
+                                *evm_address,
+                                amount,
+                                self.signer.address.as_unchecked().clone(),
+                            )
+                            .await
+                    })
+                    .await?;
+
+                // Add collected signatures to the move_tx.
+                let mut move_signatures = presigns_from_all_verifiers
+                    .iter()
+                    .map(|presign| presign.move_sign)
+                    .collect::<Vec<_>>();
+
+                let sig = self
+                    .signer
+                    .sign_taproot_script_spend_tx_new(&mut move_tx, 0, 0)?;
+                move_signatures.push(sig);
+                move_signatures.reverse();
+
+                move_signatures
+            }
+        };
 
         tracing::info!(
             "presigns_from_all_verifiers done for TXID: {:?}",
@@ -146,18 +348,6 @@ where
         );
         tracing::debug!("move_tx details: {:?}", move_tx);
 
-        // Add collected signatures to the move_tx.
-        let mut move_signatures = presigns_from_all_verifiers
-            .iter()
-            .map(|presign| presign.move_sign)
-            .collect::<Vec<_>>();
-
-        let sig = self
-            .signer
-            .sign_taproot_script_spend_tx_new(&mut move_tx, 0, 0)?;
-        move_signatures.push(sig);
-        move_signatures.reverse();
-
         let mut witness_elements: Vec<&[u8]> = Vec::new();
         for sig in move_signatures.iter() {
             witness_elements.push(sig.as_ref());
@@ -165,22 +355,637 @@ where
 
         handle_taproot_witness_new(&mut move_tx, &witness_elements, 0, 0)?;
 
-        let transaction = self.db.begin_transaction().await?;
+        self.db
+            .set_deposit_presigned(start_utxo, &move_tx.tx)
+            .await?;
+
+        self.broadcast_move_tx(
+            start_utxo,
+            recovery_taproot_address,
+            evm_address,
+            &move_tx.tx,
+        )
+        .await
+        .map(Some)
+    }
+
+    fn deposit_batching(&self) -> bool {
+        self.deposit_batch_size.is_some() || self.deposit_batch_interval_secs.is_some()
+    }
+
+    /// Moves queued deposits in a single tx, with signatures for every input
+    /// collected from each verifier in a single round. Deposits that are not
+    /// valid anymore are dropped from the queue; they are queued again when
+    /// they are resumed, if they become valid.
+    ///
+    /// Returns the move txid, or nothing if the queue is empty.
+    pub async fn process_deposit_batch(&self) -> Result<Option<Txid>, BridgeError> {
+        let mut deposits = Vec::new();
+        for deposit in self
+            .db
+            .get_new_deposit_requests(MAX_DEPOSIT_BATCH_SIZE)
+            .await?
+        {
+            if let Err(e) = self.rpc.check_deposit_utxo(
+                &self.transaction_builder,
+                &deposit.start_utxo,
+                &deposit.recovery_taproot_address,
+                &deposit.evm_address,
+                deposit.amount,
+                self.user_takes_after,
+                self.confirmation_treshold,
+            ) {
+                tracing::warn!(
+                    "Queued deposit {:?} is not valid anymore: {}",
+                    deposit.start_utxo,
+                    e
+                );
+                self.db
+                    .remove_new_deposit_request(deposit.start_utxo)
+                    .await?;
+                continue;
+            }
+
+            deposits.push(deposit);
+        }
+        if deposits.is_empty() {
+            return Ok(None);
+        }
+
+        let mut move_tx = self.transaction_builder.create_batch_move_tx(
+            &deposits
+                .iter()
+                .map(|deposit| {
+                    (
+                        deposit.start_utxo,
+                        deposit.evm_address,
+                        deposit.recovery_taproot_address.clone(),
+                        deposit.amount,
+                    )
+                })
+                .collect::<Vec<_>>(),
+            self.user_takes_after,
+        )?;
+        tracing::info!(
+            "Moving {} deposits with batch move tx {:?}",
+            deposits.len(),
+            move_tx.tx.compute_txid()
+        );
 
-        self.rpc.send_raw_transaction(&move_tx.tx)?;
+        let witnesses = match &self.key_agg_ctx {
+            Some(key_agg_ctx) => self
+                .musig2_batch_deposit_signatures(key_agg_ctx, &mut move_tx, &deposits)
+                .await?
+                .into_iter()
+                .map(|sig| vec![sig])
+                .collect(),
+            None => {
+                let verifier_sigs: Vec<Vec<schnorr::Signature>> = self
+                    .verifier_connector
+                    .request(|verifier| async {
+                        verifier.new_deposit_batch_rpc(deposits.clone()).await
+                    })
+                    .await?;
+
+                self.n_of_n_batch_witnesses(&mut move_tx, verifier_sigs)?
+            }
+        };
+        Operator::<R>::set_batch_witnesses(&mut move_tx, &witnesses)?;
 
+        let start_utxos: Vec<OutPoint> =
+            deposits.iter().map(|deposit| deposit.start_utxo).collect();
         self.db
-            .insert_move_txid(
-                start_utxo,
-                recovery_taproot_address.clone(),
-                *evm_address,
-                move_tx.tx.compute_txid(),
+            .set_deposits_presigned(&start_utxos, &move_tx.tx)
+            .await?;
+
+        for deposit in deposits.iter() {
+            self.broadcast_move_tx(
+                deposit.start_utxo,
+                &deposit.recovery_taproot_address,
+                &deposit.evm_address,
+                &move_tx.tx,
             )
             .await?;
+        }
+
+        Ok(Some(move_tx.tx.compute_txid()))
+    }
+
+    /// Moves queued deposits forever, whenever `deposit_batch_size` deposits
+    /// are queued or `deposit_batch_interval_secs` seconds passed since the
+    /// last batch.
+    pub async fn run_deposit_batcher(self) {
+        let mut last_batch = std::time::Instant::now();
+
+        loop {
+            tokio::time::sleep(std::time::Duration::from_secs(
+                DEPOSIT_BATCH_POLL_INTERVAL_SECS,
+            ))
+            .await;
+
+            let interval_passed = match self.deposit_batch_interval_secs {
+                Some(interval_secs) => last_batch.elapsed().as_secs() >= interval_secs,
+                None => false,
+            };
+            let batch_full = match self.deposit_batch_size {
+                Some(batch_size) => match self.db.get_new_deposit_request_count().await {
+                    Ok(count) => count >= batch_size,
+                    Err(e) => {
+                        tracing::error!("Failed to read deposit batch queue: {}", e);
+                        false
+                    }
+                },
+                None => false,
+            };
+            if !interval_passed && !batch_full {
+                continue;
+            }
+
+            if let Err(e) = self.process_deposit_batch().await {
+                tracing::error!("Failed to move deposit batch: {}", e);
+            }
+            last_batch = std::time::Instant::now();
+        }
+    }
+
+    /// Broadcasts a signed move tx and marks the deposit as broadcasted. A
+    /// move tx that is already in mempool or in chain is not an error, so
+    /// this can be called again for a deposit that was interrupted.
+    async fn broadcast_move_tx(
+        &self,
+        start_utxo: OutPoint,
+        recovery_taproot_address: &Address<NetworkUnchecked>,
+        evm_address: &EVMAddress,
+        move_tx: &Transaction,
+    ) -> Result<Txid, BridgeError> {
+        let move_txid = move_tx.compute_txid();
+
+        if let Err(e) = self.rpc.send_raw_transaction(move_tx) {
+            if self.rpc.get_raw_transaction(&move_txid, None).is_err() {
+                return Err(e.into());
+            }
+            tracing::debug!("Move tx {:?} is already broadcasted", move_txid);
+        }
+
+        let move_vout = TransactionBuilder::move_vout(start_utxo, move_tx)?;
+
+        let transaction = self.db.begin_transaction().await?;
+
+        if transaction
+            .get_move_txid(start_utxo, recovery_taproot_address.clone(), *evm_address)
+            .await
+            .is_err()
+        {
+            transaction
+                .insert_move_txid(
+                    start_utxo,
+                    recovery_taproot_address.clone(),
+                    *evm_address,
+                    move_txid,
+                    move_vout,
+                )
+                .await?;
+        }
+        transaction
+            .set_deposit_status(start_utxo, DepositStatus::MoveBroadcast)
+            .await?;
 
         transaction.commit().await?;
 
-        Ok(move_tx.tx.compute_txid())
+        Ok(move_txid)
+    }
+
+    /// Takes every unfinished deposit one step further: Requested deposits
+    /// are processed again, signed move txs are broadcasted and broadcasted
+    /// move txs are checked for confirmation or rebroadcasted if they are
+    /// dropped.
+    ///
+    /// Only deposits that were idle for `idle_secs` seconds are touched, so
+    /// that deposits that are being processed by a request are left alone.
+    pub async fn resume_deposits(&self, idle_secs: u64) -> Result<(), BridgeError> {
+        for deposit in self.db.get_unfinished_deposits(idle_secs).await? {
+            tracing::info!(
+                "Resuming deposit {:?} at status {:?}",
+                deposit.start_utxo,
+                deposit.status
+            );
+
+            let res = match (deposit.status, &deposit.move_tx) {
+                (DepositStatus::MoveBroadcast, Some(move_tx)) => {
+                    self.check_move_tx_confirmation(deposit.start_utxo, move_tx)
+                        .await
+                }
+                (DepositStatus::PresignsCollected, Some(move_tx)) => self
+                    .broadcast_move_tx(
+                        deposit.start_utxo,
+                        &deposit.recovery_taproot_address,
+                        &deposit.evm_address,
+                        move_tx,
+                    )
+                    .await
+                    .map(|_| ()),
+                _ => self
+                    .new_deposit(
+                        deposit.start_utxo,
+                        &deposit.recovery_taproot_address,
+                        &deposit.evm_address,
+                        deposit.amount,
+                    )
+                    .await
+                    .map(|_| ()),
+            };
+
+            if let Err(e) = res {
+                tracing::error!("Failed to resume deposit {:?}: {}", deposit.start_utxo, e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Marks a broadcasted deposit as confirmed if its move tx has enough
+    /// confirmations. Bumps the fee of the move tx if it is still in mempool
+    /// and rebroadcasts it if it is not known by Bitcoin anymore.
+    async fn check_move_tx_confirmation(
+        &self,
+        start_utxo: OutPoint,
+        move_tx: &Transaction,
+    ) -> Result<(), BridgeError> {
+        let move_txid = move_tx.compute_txid();
+
+        match self.rpc.confirmation_info(&move_txid) {
+            Ok((confirmations, block_hash)) if confirmations >= self.confirmation_treshold => {
+                self.db.set_deposit_confirmed(start_utxo, block_hash).await
+            }
+            // Not confirmed enough.
+            Ok(_) => Ok(()),
+            Err(BridgeError::NoConfirmationData) => self.fee_manager.bump_fee(move_tx).map(|_| ()),
+            Err(_) => {
+                tracing::warn!("Move tx {:?} is dropped, rebroadcasting", move_txid);
+                self.rpc.send_raw_transaction(move_tx)?;
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Checks every confirmed deposit on the rollup and marks the ones that
+    /// are minted to their EVM address. Does nothing if there is no rollup
+    /// client.
+    ///
+    /// Only deposits that were idle for `idle_secs` seconds are touched.
+    pub async fn check_deposit_mints(&self, idle_secs: u64) -> Result<(), BridgeError> {
+        let rollup_client = match &self.rollup_client {
+            Some(rollup_client) => rollup_client,
+            None => return Ok(()),
+        };
+
+        for deposit in self.db.get_unminted_deposits(idle_secs).await? {
+            let res = match &deposit.move_tx {
+                Some(move_tx) => {
+                    self.check_deposit_mint(rollup_client, &deposit, move_tx)
+                        .await
+                }
+                None => Err(BridgeError::InvalidDepositStatus(
+                    "Confirmed deposit has no move tx".to_string(),
+                )),
+            };
+
+            if let Err(e) = res {
+                tracing::error!(
+                    "Failed to check mint of deposit {:?}: {}",
+                    deposit.start_utxo,
+                    e
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Marks a deposit as minted if its bridge UTXO is credited to the
+    /// committed EVM address on the rollup.
+    async fn check_deposit_mint(
+        &self,
+        rollup_client: &Arc<dyn RollupClient>,
+        deposit: &DepositRecord,
+        move_tx: &Transaction,
+    ) -> Result<(), BridgeError> {
+        let move_utxo = OutPoint {
+            txid: move_tx.compute_txid(),
+            vout: TransactionBuilder::move_vout(deposit.start_utxo, move_tx)?,
+        };
+
+        if !rollup_client
+            .is_deposit_minted(move_utxo, &deposit.evm_address, deposit.amount)
+            .await?
+        {
+            tracing::debug!("Deposit {:?} is not minted yet", deposit.start_utxo);
+            return Ok(());
+        }
+
+        tracing::info!(
+            "Deposit {:?} is minted to {:?}",
+            deposit.start_utxo,
+            deposit.evm_address
+        );
+        self.db
+            .set_deposit_status(deposit.start_utxo, DepositStatus::Minted)
+            .await
+    }
+
+    /// Marks broadcasted withdrawals as confirmed if they have enough
+    /// confirmations. Withdrawal txs that are stuck in mempool get their fees
+    /// bumped and the ones that are not known by Bitcoin anymore are
+    /// rebroadcasted.
+    ///
+    /// Only withdrawals that were idle for `idle_secs` seconds are touched.
+    pub async fn resume_withdrawals(&self, idle_secs: u64) -> Result<(), BridgeError> {
+        for withdrawal in self.db.get_unconfirmed_withdrawals(idle_secs).await? {
+            let withdrawal_txid = withdrawal.withdrawal_tx.compute_txid();
+
+            let res = match self.rpc.confirmation_info(&withdrawal_txid) {
+                Ok((confirmations, block_hash)) if confirmations >= self.confirmation_treshold => {
+                    self.db
+                        .set_withdrawal_confirmed(withdrawal.idx, block_hash)
+                        .await
+                }
+                Ok(_) => Ok(()),
+                Err(BridgeError::NoConfirmationData) => self
+                    .fee_manager
+                    .bump_fee(&withdrawal.withdrawal_tx)
+                    .map(|_| ()),
+                Err(_) => {
+                    tracing::warn!(
+                        "Withdrawal tx {:?} is dropped, rebroadcasting",
+                        withdrawal_txid
+                    );
+                    self.rpc
+                        .send_raw_transaction(&withdrawal.withdrawal_tx)
+                        .map(|_| ())
+                        .map_err(BridgeError::from)
+                }
+            };
+
+            if let Err(e) = res {
+                tracing::error!("Failed to resume withdrawal {}: {}", withdrawal.idx, e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Rolls back deposits and withdrawals that depend on a block which left
+    /// the best chain. They will be confirmed again, or rebroadcasted if they
+    /// are dropped, when they are resumed.
+    pub async fn handle_disconnected_block(
+        &self,
+        block_hash: BlockHash,
+    ) -> Result<(), BridgeError> {
+        for start_utxo in self.db.rollback_deposits_in_block(block_hash).await? {
+            tracing::warn!(
+                "Deposit {:?} is rolled back, because block {} left the best chain",
+                start_utxo,
+                block_hash
+            );
+        }
+
+        for idx in self.db.rollback_withdrawals_in_block(block_hash).await? {
+            tracing::warn!(
+                "Withdrawal {} is rolled back, because block {} left the best chain",
+                idx,
+                block_hash
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Checks every block that a deposit or withdrawal depends on and rolls
+    /// back the ones that are not in the best chain anymore.
+    pub async fn check_reorgs(&self) -> Result<(), BridgeError> {
+        for block_hash in self.db.get_record_block_hashes().await? {
+            if !self.rpc.is_block_in_best_chain(&block_hash)? {
+                self.handle_disconnected_block(block_hash).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Binds deposits to the block that their deposit UTXO is received in.
+    /// A deposit loses its block when the block leaves the best chain, and
+    /// gets the new one here when the deposit UTXO is mined again. Runs until
+    /// chain watcher is gone.
+    pub async fn watch_deposits(self, mut chain_events: broadcast::Receiver<ChainEvent>) {
+        loop {
+            match chain_events.recv().await {
+                Ok(ChainEvent::DepositReceived {
+                    outpoint,
+                    block_hash,
+                    ..
+                }) => {
+                    if let Err(e) = self.handle_received_deposit(outpoint, block_hash).await {
+                        tracing::error!("Failed to handle received deposit {}: {}", outpoint, e);
+                    }
+                }
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(count)) => {
+                    tracing::warn!("Missed {} chain events", count)
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            }
+        }
+    }
+
+    async fn handle_received_deposit(
+        &self,
+        start_utxo: OutPoint,
+        block_hash: BlockHash,
+    ) -> Result<(), BridgeError> {
+        // A watched deposit address can receive other UTXOs, which are not
+        // deposit requests.
+        let deposit = match self.db.get_deposit_record(start_utxo).await {
+            Ok(deposit) => deposit,
+            Err(_) => return Ok(()),
+        };
+        if deposit.deposit_block_hash == Some(block_hash) {
+            return Ok(());
+        }
+
+        tracing::info!(
+            "Deposit {:?} is received in block {}",
+            start_utxo,
+            block_hash
+        );
+        self.db.set_deposit_block_hash(start_utxo, block_hash).await
+    }
+
+    /// Updates the metrics of pending deposits and withdrawals.
+    async fn update_pending_metrics(&self) -> Result<(), BridgeError> {
+        let (deposits, withdrawals) = self.db.get_pending_record_counts().await?;
+        metrics::PENDING_DEPOSITS.set(&[], deposits as f64);
+        metrics::PENDING_WITHDRAWALS.set(&[], withdrawals as f64);
+
+        Ok(())
+    }
+
+    /// Resumes unfinished deposits and withdrawals forever, once in every
+    /// `interval_secs` seconds or when chain tip changes. Records that depend
+    /// on a block which left the best chain are rolled back and confirmed
+    /// deposits are checked for their mints on the rollup. Deposits that were
+    /// left from a previous run are resumed immediately. Metrics of pending
+    /// deposits and withdrawals are updated on every round.
+    pub async fn run_monitor(
+        self,
+        interval_secs: u64,
+        mut chain_events: broadcast::Receiver<ChainEvent>,
+    ) {
+        let mut idle_secs = 0;
+        let mut chain_watcher_alive = true;
+
+        // Blocks might have left the best chain while operator was down.
+        if let Err(e) = self.check_reorgs().await {
+            tracing::error!("Failed to check reorgs: {}", e);
+        }
+
+        loop {
+            if let Err(e) = self.resume_deposits(idle_secs).await {
+                tracing::error!("Failed to resume deposits: {}", e);
+            }
+            if let Err(e) = self.resume_withdrawals(idle_secs).await {
+                tracing::error!("Failed to resume withdrawals: {}", e);
+            }
+            if let Err(e) = self.check_deposit_mints(idle_secs).await {
+                tracing::error!("Failed to check deposit mints: {}", e);
+            }
+            if let Err(e) = self.update_pending_metrics().await {
+                tracing::error!("Failed to update pending metrics: {}", e);
+            }
+            idle_secs = interval_secs;
+
+            let sleep = tokio::time::sleep(std::time::Duration::from_secs(interval_secs));
+            if !chain_watcher_alive {
+                sleep.await;
+                continue;
+            }
+
+            let res = tokio::select! {
+                _ = sleep => Ok(()),
+                tip_change = chain_watcher::wait_for_tip_change(&mut chain_events) => {
+                    match tip_change {
+                        Some(TipChange::Connected(_)) => Ok(()),
+                        Some(TipChange::Disconnected(block_hash)) => {
+                            self.handle_disconnected_block(block_hash).await
+                        }
+                        Some(TipChange::Unknown) => self.check_reorgs().await,
+                        None => {
+                            chain_watcher_alive = false;
+                            Ok(())
+                        }
+                    }
+                }
+            };
+            if let Err(e) = res {
+                tracing::error!("Failed to handle chain tip change: {}", e);
+            }
+        }
+    }
+
+    /// Returns the confirmation count of a tx, which is zero if it is not in
+    /// a block.
+    fn confirmations(&self, txid: &Txid) -> u32 {
+        self.rpc
+            .confirmation_info(txid)
+            .map(|(confirmations, _)| confirmations)
+            .unwrap_or(0)
+    }
+
+    /// Returns the event for the current state of a subscribed deposit or
+    /// withdrawal.
+    async fn lifecycle_event(
+        &self,
+        subscription: Subscription,
+    ) -> Result<LifecycleEvent, BridgeError> {
+        match subscription {
+            Subscription::Deposit(start_utxo) => {
+                let deposit = self.db.get_deposit_record(start_utxo).await?;
+                let confirmations = match (&deposit.status, &deposit.move_tx) {
+                    (DepositStatus::MoveBroadcast, Some(move_tx)) => {
+                        self.confirmations(&move_tx.compute_txid())
+                    }
+                    _ => 0,
+                };
+
+                lifecycle::deposit_event(&deposit, confirmations)
+            }
+            Subscription::Withdrawal(idx) => {
+                let withdrawal = self.db.get_withdrawal_record(idx).await?;
+                let confirmations = match withdrawal.status {
+                    WithdrawalStatus::Broadcast => {
+                        self.confirmations(&withdrawal.withdrawal_tx.compute_txid())
+                    }
+                    WithdrawalStatus::Confirmed => 0,
+                };
+
+                Ok(lifecycle::withdrawal_event(&withdrawal, confirmations))
+            }
+        }
+    }
+
+    /// Checks if nothing will change after given event.
+    fn is_last_event(&self, subscription: Subscription, event: &LifecycleEvent) -> bool {
+        match (subscription, event) {
+            (_, LifecycleEvent::Failed { .. }) => true,
+            (Subscription::Deposit(_), LifecycleEvent::Minted { .. }) => true,
+            (Subscription::Deposit(_), LifecycleEvent::Finalized { .. }) => {
+                self.rollup_client.is_none()
+            }
+            (Subscription::Withdrawal(_), LifecycleEvent::Finalized { .. }) => true,
+            _ => false,
+        }
+    }
+
+    /// Sends the current state of a deposit or withdrawal to the subscriber
+    /// and then every change to it, until its last event or until the
+    /// subscriber leaves. Subscriptions to unknown deposits and withdrawals
+    /// are rejected. Failing polls are logged and retried.
+    async fn stream_lifecycle(
+        &self,
+        pending: PendingSubscriptionSink,
+        subscription: Subscription,
+    ) -> SubscriptionResult {
+        let mut event = match self.lifecycle_event(subscription).await {
+            Ok(event) => event,
+            Err(e) => {
+                pending.reject(e).await;
+                return Ok(());
+            }
+        };
+        let sink = pending.accept().await?;
+        let mut last_event = None;
+
+        loop {
+            if last_event.as_ref() != Some(&event) {
+                sink.send(SubscriptionMessage::from_json(&event)?).await?;
+                if self.is_last_event(subscription, &event) {
+                    return Ok(());
+                }
+                last_event = Some(event.clone());
+            }
+
+            tokio::select! {
+                _ = sink.closed() => return Ok(()),
+                _ = tokio::time::sleep(std::time::Duration::from_secs(SUBSCRIPTION_POLL_INTERVAL_SECS)) => {}
+            }
+
+            // Errors of a poll don't change the lifecycle, so the subscriber
+            // keeps the last event until a poll succeeds.
+            match self.lifecycle_event(subscription).await {
+                Ok(new_event) => event = new_event,
+                Err(e) => tracing::warn!("Failed to poll lifecycle of {:?}: {}", subscription, e),
+            }
+        }
     }
 
     pub async fn new_withdrawal_direct(
@@ -188,25 +993,26 @@ where
         idx: usize,
         withdrawal_address: Address<NetworkChecked>,
     ) -> Result<Txid, BridgeError> {
-        let deposit_tx_info = self.db.get_deposit_tx(idx).await?;
+        let deposit_utxo = self.db.get_bridge_utxo(idx).await?;
+        // Verifiers' direct withdrawal endpoint only knows the first output of
+        // a move tx, so outputs of batch move txs are spent in a batch.
+        if deposit_utxo.vout != 0 {
+            return self
+                .new_withdrawal_batch(vec![(idx, withdrawal_address)])
+                .await;
+        }
+
+        let deposit_tx_info = deposit_utxo.txid;
         tracing::debug!(
             "Operator is signing withdrawal tx with txid: {:?}",
             deposit_tx_info
         );
 
-        let (bridge_address, _) = self.transaction_builder.generate_bridge_address()?;
-
-        let dust_value = script_builder::anyone_can_spend_txout().value;
-
-        let deposit_txout = TxOut {
-            value: Amount::from_sat(BRIDGE_AMOUNT_SATS - self.min_relay_fee) - dust_value,
-            script_pubkey: bridge_address.script_pubkey(),
-        };
-
-        let deposit_utxo = OutPoint {
-            txid: deposit_tx_info,
-            vout: 0,
-        };
+        let deposit_txout = self.rpc.get_bridge_txout(
+            &self.transaction_builder,
+            &deposit_utxo,
+            &self.deposit_denominations,
+        )?;
 
         let mut withdrawal_tx = self.transaction_builder.create_withdraw_tx(
             deposit_utxo,
@@ -214,37 +1020,481 @@ where
             &withdrawal_address,
         )?;
 
-        let mut verifier_sigs: Vec<_> = self
-            .verifier_connector
+        let verifier_sigs = match &self.key_agg_ctx {
+            Some(key_agg_ctx) => {
+                vec![
+                    self.musig2_withdrawal_signature(
+                        key_agg_ctx,
+                        &mut withdrawal_tx,
+                        idx,
+                        deposit_tx_info,
+                        &withdrawal_address,
+                    )
+                    .await?,
+                ]
+            }
+            None => {
+                let mut verifier_sigs: Vec<_> = self
+                    .verifier_connector
+                    .request(|verifier| async {
+                        verifier
+                            .new_withdrawal_direct_rpc(
+                                idx,
+                                deposit_tx_info,
+                                withdrawal_address.as_unchecked().clone(),
+                            )
+                            .await
+                    })
+                    .await?;
+
+                let sig = self
+                    .signer
+                    .sign_taproot_script_spend_tx_new(&mut withdrawal_tx, 0, 0)?;
+                verifier_sigs.push(sig);
+                verifier_sigs.reverse();
+
+                verifier_sigs
+            }
+        };
+
+        let witness_elements: Vec<&[u8]> = verifier_sigs
             .iter()
-            .map(|verifier| async {
+            .map(|sig| sig.as_ref() as &[u8])
+            .collect();
+
+        handle_taproot_witness_new(&mut withdrawal_tx, &witness_elements, 0, 0)?;
+
+        let withdrawal_txid = self.rpc.send_raw_transaction(&withdrawal_tx.tx)?;
+
+        self.db
+            .save_withdrawal_tx(
+                idx,
+                deposit_tx_info,
+                withdrawal_address.as_unchecked().clone(),
+                &withdrawal_tx.tx,
+            )
+            .await?;
+
+        Ok(withdrawal_txid)
+    }
+
+    /// Pays every given withdrawal in a single tx, which spends the bridge
+    /// funds of each withdrawal. Signatures for all inputs are collected from
+    /// every verifier in a single round.
+    pub async fn new_withdrawal_batch(
+        &self,
+        withdrawals: Vec<(usize, Address<NetworkChecked>)>,
+    ) -> Result<Txid, BridgeError> {
+        let mut batch = Vec::new();
+        let mut tx_withdrawals = Vec::new();
+        for (idx, withdrawal_address) in withdrawals {
+            let bridge_utxo = self.db.get_bridge_utxo(idx).await?;
+            let bridge_txout = self.rpc.get_bridge_txout(
+                &self.transaction_builder,
+                &bridge_utxo,
+                &self.deposit_denominations,
+            )?;
+
+            batch.push(BatchWithdrawal {
+                idx,
+                bridge_utxo,
+                withdrawal_address: withdrawal_address.as_unchecked().clone(),
+            });
+            tx_withdrawals.push((bridge_utxo, bridge_txout, withdrawal_address));
+        }
+
+        let mut withdrawal_tx = self
+            .transaction_builder
+            .create_batch_withdraw_tx(&tx_withdrawals)?;
+        tracing::debug!(
+            "Operator is signing batch withdrawal tx with txid: {:?}",
+            withdrawal_tx.tx.compute_txid()
+        );
+
+        let witnesses = match &self.key_agg_ctx {
+            Some(key_agg_ctx) => self
+                .musig2_batch_withdrawal_signatures(key_agg_ctx, &mut withdrawal_tx, &batch)
+                .await?
+                .into_iter()
+                .map(|sig| vec![sig])
+                .collect(),
+            None => {
+                let verifier_sigs: Vec<Vec<schnorr::Signature>> = self
+                    .verifier_connector
+                    .request(|verifier| async {
+                        verifier.new_withdrawal_batch_rpc(batch.clone()).await
+                    })
+                    .await?;
+
+                self.n_of_n_batch_witnesses(&mut withdrawal_tx, verifier_sigs)?
+            }
+        };
+        Operator::<R>::set_batch_witnesses(&mut withdrawal_tx, &witnesses)?;
+
+        let withdrawal_txid = self.rpc.send_raw_transaction(&withdrawal_tx.tx)?;
+
+        for withdrawal in batch {
+            self.db
+                .save_withdrawal_tx(
+                    withdrawal.idx,
+                    withdrawal.bridge_utxo.txid,
+                    withdrawal.withdrawal_address,
+                    &withdrawal_tx.tx,
+                )
+                .await?;
+        }
+
+        Ok(withdrawal_txid)
+    }
+
+    /// Collects MuSig2 nonces and partial signatures of every verifier for the
+    /// move tx and combines them with operator's into a single signature.
+    async fn musig2_deposit_signature(
+        &self,
+        key_agg_ctx: &KeyAggContext,
+        move_tx: &mut CreateTxOutputs,
+        start_utxo: OutPoint,
+        recovery_taproot_address: &Address<NetworkUnchecked>,
+        evm_address: &EVMAddress,
+        amount: u64,
+    ) -> Result<schnorr::Signature, BridgeError> {
+        let pub_nonces: Vec<MuSigPubNonce> = self
+            .verifier_connector
+            .request(|verifier| async {
+                verifier
+                    .new_deposit_nonce_rpc(
+                        start_utxo,
+                        recovery_taproot_address.clone(),
+                        *evm_address,
+                        amount,
+                    )
+                    .await
+            })
+            .await?;
+
+        let sighash = self.signer.sighash_taproot_script_spend(move_tx, 0, 0)?;
+        let (sec_nonce, pub_nonce) = self.signer.musig2_nonce(key_agg_ctx, sighash)?;
+
+        let mut all_pub_nonces = pub_nonces;
+        all_pub_nonces.push(pub_nonce);
+        let agg_nonce = musig2::aggregate_nonces(&all_pub_nonces)?;
+
+        let mut partial_sigs: Vec<MuSigPartialSignature> = self
+            .verifier_connector
+            .request(|verifier| async {
+                verifier
+                    .new_deposit_partial_sig_rpc(
+                        start_utxo,
+                        recovery_taproot_address.clone(),
+                        *evm_address,
+                        amount,
+                        agg_nonce,
+                    )
+                    .await
+            })
+            .await?;
+        partial_sigs.push(self.signer.musig2_partial_sign(
+            key_agg_ctx,
+            sec_nonce,
+            &agg_nonce,
+            sighash,
+        )?);
+
+        Operator::<R>::aggregate_musig2_signature(
+            key_agg_ctx,
+            &all_pub_nonces,
+            &agg_nonce,
+            sighash.as_byte_array(),
+            &partial_sigs,
+        )
+    }
+
+    /// Collects MuSig2 nonces and partial signatures of every verifier for the
+    /// withdrawal tx and combines them with operator's into a single
+    /// signature.
+    async fn musig2_withdrawal_signature(
+        &self,
+        key_agg_ctx: &KeyAggContext,
+        withdrawal_tx: &mut CreateTxOutputs,
+        idx: usize,
+        bridge_fund_txid: Txid,
+        withdrawal_address: &Address<NetworkChecked>,
+    ) -> Result<schnorr::Signature, BridgeError> {
+        let pub_nonces: Vec<MuSigPubNonce> = self
+            .verifier_connector
+            .request(|verifier| async {
                 verifier
-                    .new_withdrawal_direct_rpc(
+                    .new_withdrawal_nonce_rpc(
                         idx,
-                        deposit_tx_info,
+                        bridge_fund_txid,
                         withdrawal_address.as_unchecked().clone(),
                     )
                     .await
             })
-            .collect::<FuturesOrdered<_>>()
-            .try_collect()
             .await?;
 
-        let sig = self
+        let sighash = self
             .signer
-            .sign_taproot_script_spend_tx_new(&mut withdrawal_tx, 0, 0)?;
-        verifier_sigs.push(sig);
-        verifier_sigs.reverse();
+            .sighash_taproot_script_spend(withdrawal_tx, 0, 0)?;
+        let (sec_nonce, pub_nonce) = self.signer.musig2_nonce(key_agg_ctx, sighash)?;
 
-        let witness_elements: Vec<&[u8]> = verifier_sigs
+        let mut all_pub_nonces = pub_nonces;
+        all_pub_nonces.push(pub_nonce);
+        let agg_nonce = musig2::aggregate_nonces(&all_pub_nonces)?;
+
+        let mut partial_sigs: Vec<MuSigPartialSignature> = self
+            .verifier_connector
+            .request(|verifier| async {
+                verifier
+                    .new_withdrawal_partial_sig_rpc(
+                        idx,
+                        bridge_fund_txid,
+                        withdrawal_address.as_unchecked().clone(),
+                        agg_nonce,
+                    )
+                    .await
+            })
+            .await?;
+        partial_sigs.push(self.signer.musig2_partial_sign(
+            key_agg_ctx,
+            sec_nonce,
+            &agg_nonce,
+            sighash,
+        )?);
+
+        Operator::<R>::aggregate_musig2_signature(
+            key_agg_ctx,
+            &all_pub_nonces,
+            &agg_nonce,
+            sighash.as_byte_array(),
+            &partial_sigs,
+        )
+    }
+
+    /// Collects MuSig2 nonces and partial signatures of every verifier for
+    /// every input of the batch move tx and combines them with operator's
+    /// into a signature per input.
+    async fn musig2_batch_deposit_signatures(
+        &self,
+        key_agg_ctx: &KeyAggContext,
+        move_tx: &mut CreateTxOutputs,
+        deposits: &[DepositRequest],
+    ) -> Result<Vec<schnorr::Signature>, BridgeError> {
+        let verifier_pub_nonces: Vec<Vec<MuSigPubNonce>> = self
+            .verifier_connector
+            .request(|verifier| async {
+                verifier
+                    .new_deposit_batch_nonce_rpc(deposits.to_vec())
+                    .await
+            })
+            .await?;
+
+        let round = self.musig2_batch_round(key_agg_ctx, move_tx, verifier_pub_nonces)?;
+
+        let verifier_partial_sigs: Vec<Vec<MuSigPartialSignature>> = self
+            .verifier_connector
+            .request(|verifier| async {
+                verifier
+                    .new_deposit_batch_partial_sig_rpc(deposits.to_vec(), round.agg_nonces.clone())
+                    .await
+            })
+            .await?;
+
+        self.musig2_batch_signatures(key_agg_ctx, round, verifier_partial_sigs)
+    }
+
+    /// Collects MuSig2 nonces and partial signatures of every verifier for
+    /// every input of the batch withdrawal tx and combines them with
+    /// operator's into a signature per input.
+    async fn musig2_batch_withdrawal_signatures(
+        &self,
+        key_agg_ctx: &KeyAggContext,
+        withdrawal_tx: &mut CreateTxOutputs,
+        batch: &[BatchWithdrawal],
+    ) -> Result<Vec<schnorr::Signature>, BridgeError> {
+        let verifier_pub_nonces: Vec<Vec<MuSigPubNonce>> = self
+            .verifier_connector
+            .request(|verifier| async {
+                verifier
+                    .new_withdrawal_batch_nonce_rpc(batch.to_vec())
+                    .await
+            })
+            .await?;
+
+        let round = self.musig2_batch_round(key_agg_ctx, withdrawal_tx, verifier_pub_nonces)?;
+
+        let verifier_partial_sigs: Vec<Vec<MuSigPartialSignature>> = self
+            .verifier_connector
+            .request(|verifier| async {
+                verifier
+                    .new_withdrawal_batch_partial_sig_rpc(batch.to_vec(), round.agg_nonces.clone())
+                    .await
+            })
+            .await?;
+
+        self.musig2_batch_signatures(key_agg_ctx, round, verifier_partial_sigs)
+    }
+
+    /// Generates operator's nonces for every input of the tx and aggregates
+    /// them with verifiers' nonces, which are given per verifier.
+    fn musig2_batch_round(
+        &self,
+        key_agg_ctx: &KeyAggContext,
+        tx: &mut CreateTxOutputs,
+        verifier_pub_nonces: Vec<Vec<MuSigPubNonce>>,
+    ) -> Result<MuSig2BatchRound, BridgeError> {
+        let num_inputs = tx.tx.input.len();
+        if verifier_pub_nonces
             .iter()
-            .map(|sig| sig.as_ref() as &[u8])
-            .collect();
+            .any(|nonces| nonces.len() != num_inputs)
+        {
+            return Err(BridgeError::InvalidBatch(
+                "Verifier didn't give a nonce for every input".to_string(),
+            ));
+        }
 
-        handle_taproot_witness_new(&mut withdrawal_tx, &witness_elements, 0, 0)?;
+        let mut round = MuSig2BatchRound {
+            sighashes: Vec::new(),
+            sec_nonces: Vec::new(),
+            pub_nonces: Vec::new(),
+            agg_nonces: Vec::new(),
+        };
+        for i in 0..num_inputs {
+            let sighash = self.signer.sighash_taproot_script_spend(tx, i, 0)?;
+            let (sec_nonce, pub_nonce) = self.signer.musig2_nonce(key_agg_ctx, sighash)?;
+
+            let mut pub_nonces: Vec<MuSigPubNonce> =
+                verifier_pub_nonces.iter().map(|nonces| nonces[i]).collect();
+            pub_nonces.push(pub_nonce);
+
+            round
+                .agg_nonces
+                .push(musig2::aggregate_nonces(&pub_nonces)?);
+            round.sighashes.push(sighash);
+            round.sec_nonces.push(sec_nonce);
+            round.pub_nonces.push(pub_nonces);
+        }
 
-        let withdrawal_txid = self.rpc.send_raw_transaction(&withdrawal_tx.tx)?;
-        Ok(withdrawal_txid)
+        Ok(round)
+    }
+
+    /// Adds operator's partial signatures to verifiers' ones, which are given
+    /// per verifier, and aggregates them into a signature per input.
+    fn musig2_batch_signatures(
+        &self,
+        key_agg_ctx: &KeyAggContext,
+        round: MuSig2BatchRound,
+        verifier_partial_sigs: Vec<Vec<MuSigPartialSignature>>,
+    ) -> Result<Vec<schnorr::Signature>, BridgeError> {
+        if verifier_partial_sigs
+            .iter()
+            .any(|partial_sigs| partial_sigs.len() != round.sighashes.len())
+        {
+            return Err(BridgeError::InvalidBatch(
+                "Verifier didn't partially sign every input".to_string(),
+            ));
+        }
+
+        round
+            .sec_nonces
+            .into_iter()
+            .enumerate()
+            .map(|(i, sec_nonce)| {
+                let mut partial_sigs: Vec<MuSigPartialSignature> = verifier_partial_sigs
+                    .iter()
+                    .map(|partial_sigs| partial_sigs[i])
+                    .collect();
+                partial_sigs.push(self.signer.musig2_partial_sign(
+                    key_agg_ctx,
+                    sec_nonce,
+                    &round.agg_nonces[i],
+                    round.sighashes[i],
+                )?);
+
+                Operator::<R>::aggregate_musig2_signature(
+                    key_agg_ctx,
+                    &round.pub_nonces[i],
+                    &round.agg_nonces[i],
+                    round.sighashes[i].as_byte_array(),
+                    &partial_sigs,
+                )
+            })
+            .collect()
+    }
+
+    /// Adds operator's signature to verifiers' ones, which are given per
+    /// verifier, for every input of the tx. Returns the signatures of each
+    /// input in witness order.
+    fn n_of_n_batch_witnesses(
+        &self,
+        tx: &mut CreateTxOutputs,
+        verifier_sigs: Vec<Vec<schnorr::Signature>>,
+    ) -> Result<Vec<Vec<schnorr::Signature>>, BridgeError> {
+        let num_inputs = tx.tx.input.len();
+        if verifier_sigs.iter().any(|sigs| sigs.len() != num_inputs) {
+            return Err(BridgeError::InvalidBatch(
+                "Verifier didn't sign every input".to_string(),
+            ));
+        }
+
+        (0..num_inputs)
+            .map(|i| {
+                let mut sigs: Vec<_> = verifier_sigs.iter().map(|sigs| sigs[i]).collect();
+                sigs.push(self.signer.sign_taproot_script_spend_tx_new(tx, i, 0)?);
+                sigs.reverse();
+
+                Ok(sigs)
+            })
+            .collect()
+    }
+
+    /// Sets the witness of every input of the tx from its signatures.
+    fn set_batch_witnesses(
+        tx: &mut CreateTxOutputs,
+        witnesses: &[Vec<schnorr::Signature>],
+    ) -> Result<(), BridgeError> {
+        for (i, sigs) in witnesses.iter().enumerate() {
+            let witness_elements: Vec<&[u8]> =
+                sigs.iter().map(|sig| sig.as_ref() as &[u8]).collect();
+
+            handle_taproot_witness_new(tx, &witness_elements, i, 0)?;
+        }
+
+        Ok(())
+    }
+
+    /// Verifies every partial signature and aggregates them. Nonces and
+    /// partial signatures must be in the same order with the verifiers'
+    /// public keys, operator last.
+    fn aggregate_musig2_signature(
+        key_agg_ctx: &KeyAggContext,
+        pub_nonces: &[MuSigPubNonce],
+        agg_nonce: &MuSigAggNonce,
+        message: &[u8; 32],
+        partial_sigs: &[MuSigPartialSignature],
+    ) -> Result<schnorr::Signature, BridgeError> {
+        for (i, ((pub_nonce, public_key), partial_sig)) in pub_nonces
+            .iter()
+            .zip(key_agg_ctx.public_keys())
+            .zip(partial_sigs)
+            .enumerate()
+        {
+            if !musig2::partial_sig_verify(
+                key_agg_ctx,
+                pub_nonce,
+                public_key,
+                agg_nonce,
+                message,
+                partial_sig,
+            )? {
+                return Err(BridgeError::MuSig2Error(format!(
+                    "Invalid partial signature from verifier {}",
+                    i
+                )));
+            }
+        }
+
+        musig2::aggregate_partial_signatures(key_agg_ctx, agg_nonce, message, partial_sigs)
     }
 }
 
@@ -258,8 +1508,12 @@ where
         start_utxo: OutPoint,
         recovery_taproot_address: Address<NetworkUnchecked>,
         evm_address: EVMAddress,
-    ) -> Result<Txid, BridgeError> {
-        self.new_deposit(start_utxo, &recovery_taproot_address, &evm_address)
+        amount: u64,
+    ) -> Result<Option<Txid>, BridgeError> {
+        // Deposits that are resumed by the monitor are not counted again.
+        metrics::DEPOSITS_RECEIVED.inc(&[]);
+
+        self.new_deposit(start_utxo, &recovery_taproot_address, &evm_address, amount)
             .await
     }
 
@@ -271,6 +1525,88 @@ where
         self.new_withdrawal_direct(idx, withdrawal_address.assume_checked())
             .await
     }
+
+    async fn new_withdrawal_batch_rpc(
+        &self,
+        withdrawals: Vec<(usize, Address<NetworkUnchecked>)>,
+    ) -> Result<Txid, BridgeError> {
+        self.new_withdrawal_batch(
+            withdrawals
+                .into_iter()
+                .map(|(idx, withdrawal_address)| (idx, withdrawal_address.assume_checked()))
+                .collect(),
+        )
+        .await
+    }
+
+    async fn deposit_status_rpc(&self, start_utxo: OutPoint) -> Result<DepositStatus, BridgeError> {
+        Ok(self.db.get_deposit_record(start_utxo).await?.status)
+    }
+
+    async fn list_deposits_rpc(
+        &self,
+        evm_address: Option<EVMAddress>,
+        status: Option<DepositStatus>,
+        offset: u64,
+        limit: u64,
+    ) -> Result<Vec<DepositRecord>, BridgeError> {
+        self.db
+            .list_deposit_records(evm_address, status, offset, limit.min(MAX_QUERY_LIMIT))
+            .await
+    }
+
+    async fn get_deposit_rpc(&self, start_utxo: OutPoint) -> Result<DepositRecord, BridgeError> {
+        self.db.get_deposit_record(start_utxo).await
+    }
+
+    async fn get_deposits_by_move_txid_rpc(
+        &self,
+        move_txid: Txid,
+    ) -> Result<Vec<DepositRecord>, BridgeError> {
+        self.db.get_deposit_records_by_move_txid(move_txid).await
+    }
+
+    async fn list_withdrawals_rpc(
+        &self,
+        status: Option<WithdrawalStatus>,
+        offset: u64,
+        limit: u64,
+    ) -> Result<Vec<WithdrawalRecord>, BridgeError> {
+        self.db
+            .list_withdrawal_records(status, offset, limit.min(MAX_QUERY_LIMIT))
+            .await
+    }
+
+    async fn bridge_balance_rpc(&self) -> Result<BridgeBalance, BridgeError> {
+        let (amount, utxo_count) = self.db.get_bridge_balance().await?;
+
+        Ok(BridgeBalance {
+            amount: amount.to_sat(),
+            utxo_count,
+        })
+    }
+
+    async fn verifier_status_rpc(&self) -> Result<Vec<VerifierStatus>, BridgeError> {
+        Ok(self.verifier_connector.status())
+    }
+
+    async fn subscribe_deposit(
+        &self,
+        pending: PendingSubscriptionSink,
+        start_utxo: OutPoint,
+    ) -> SubscriptionResult {
+        self.stream_lifecycle(pending, Subscription::Deposit(start_utxo))
+            .await
+    }
+
+    async fn subscribe_withdrawal(
+        &self,
+        pending: PendingSubscriptionSink,
+        idx: usize,
+    ) -> SubscriptionResult {
+        self.stream_lifecycle(pending, Subscription::Withdrawal(idx))
+            .await
+    }
 }
 
 #[cfg(feature = "poc")]
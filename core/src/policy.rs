@@ -0,0 +1,515 @@
+//! # Signing Policy
+//!
+//! Verifier checks deposits and withdrawals against its own signing policy
+//! before it signs them, on top of the checks of the bridge protocol. Policy
+//! is kept in verifier's database and is managed with the admin RPC:
+//!
+//! - Deposits are refused if their EVM or recovery address is in the deny
+//!   list, or if the allow list of that kind isn't empty and doesn't have it.
+//! - Deposits that are signed in the last 24 hours can't exceed the daily
+//!   limit, in total and per EVM address.
+//! - Deposits above the approval threshold are queued and refused until an
+//!   admin approves them.
+//! - New withdrawals are refused while the number of signed withdrawals,
+//!   whose bridge funds are not spent yet, is at the maximum.
+
+use crate::database::common::{Database, DepositRequest};
+use crate::errors::BridgeError;
+use crate::EVMAddress;
+use bitcoin::address::NetworkUnchecked;
+use bitcoin::{Address, OutPoint};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+/// Time window of daily limits, in seconds.
+pub const DAILY_LIMIT_WINDOW_SECS: u64 = 24 * 60 * 60;
+
+/// Limits of the signing policy. Nothing is limited by default.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SigningPolicy {
+    /// Maximum total amount of deposits signed in 24 hours, in sats.
+    pub daily_limit: Option<u64>,
+    /// Maximum total amount of deposits to a single EVM address signed in 24
+    /// hours, in sats.
+    pub evm_address_daily_limit: Option<u64>,
+    /// Maximum number of signed withdrawals whose bridge funds are not spent
+    /// yet.
+    pub max_outstanding_withdrawals: Option<usize>,
+    /// Deposits above this amount in sats need approval of an admin.
+    pub approval_threshold: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AddressList {
+    Allow,
+    Deny,
+}
+
+impl AddressList {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AddressList::Allow => "allow",
+            AddressList::Deny => "deny",
+        }
+    }
+}
+
+impl FromStr for AddressList {
+    type Err = BridgeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "allow" => Ok(AddressList::Allow),
+            "deny" => Ok(AddressList::Deny),
+            _ => Err(BridgeError::PolicyViolation(format!(
+                "Unknown address list {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// An address that can be listed in the policy.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "address", rename_all = "snake_case")]
+pub enum PolicyAddress {
+    EvmAddress(EVMAddress),
+    RecoveryAddress(Address<NetworkUnchecked>),
+}
+
+impl PolicyAddress {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            PolicyAddress::EvmAddress(_) => "evm_address",
+            PolicyAddress::RecoveryAddress(_) => "recovery_address",
+        }
+    }
+
+    /// Returns the address in the format it is stored in the database.
+    pub fn address(&self) -> String {
+        let address = match self {
+            PolicyAddress::EvmAddress(evm_address) => serde_json::to_string(evm_address),
+            PolicyAddress::RecoveryAddress(address) => serde_json::to_string(address),
+        };
+
+        address.unwrap().trim_matches('"').to_owned()
+    }
+
+    /// Parses an address from its kind and stored format.
+    pub fn from_parts(kind: &str, address: &str) -> Result<Self, BridgeError> {
+        let address = serde_json::json!({ "kind": kind, "address": address });
+
+        serde_json::from_value(address)
+            .map_err(|e| BridgeError::PolicyViolation(format!("Invalid policy address: {}", e)))
+    }
+}
+
+/// An address and the list it is in.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListedAddress {
+    pub list: AddressList,
+    pub address: PolicyAddress,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+impl ApprovalStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApprovalStatus::Pending => "pending",
+            ApprovalStatus::Approved => "approved",
+            ApprovalStatus::Rejected => "rejected",
+        }
+    }
+}
+
+impl FromStr for ApprovalStatus {
+    type Err = BridgeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(ApprovalStatus::Pending),
+            "approved" => Ok(ApprovalStatus::Approved),
+            "rejected" => Ok(ApprovalStatus::Rejected),
+            _ => Err(BridgeError::PolicyViolation(format!(
+                "Unknown approval status {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// A deposit in the manual approval queue.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DepositApproval {
+    pub deposit: DepositRequest,
+    pub status: ApprovalStatus,
+}
+
+/// Current usage of the policy limits.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolicyUsage {
+    /// Total amount of deposits signed in the last 24 hours, in sats.
+    pub signed_deposits: u64,
+    /// Number of signed withdrawals whose bridge funds are not spent yet.
+    pub outstanding_withdrawals: usize,
+}
+
+/// A deposit that is signed by the verifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedDeposit {
+    pub start_utxo: OutPoint,
+    pub evm_address: EVMAddress,
+    pub amount: u64,
+}
+
+impl From<&DepositRequest> for SignedDeposit {
+    fn from(deposit: &DepositRequest) -> Self {
+        SignedDeposit {
+            start_utxo: deposit.start_utxo,
+            evm_address: deposit.evm_address,
+            amount: deposit.amount,
+        }
+    }
+}
+
+/// Checks deposits against the policy, before they are signed. Deposits above
+/// the approval threshold are added to the approval queue, if they are not
+/// there yet.
+pub async fn check_deposits(db: &Database, deposits: &[DepositRequest]) -> Result<(), BridgeError> {
+    let policy = db.get_signing_policy().await?;
+    let addresses = db.get_policy_addresses().await?;
+
+    for deposit in deposits {
+        check_addresses(&addresses, deposit)?;
+    }
+
+    if let Some(threshold) = policy.approval_threshold {
+        let mut pending = vec![];
+        for deposit in deposits.iter().filter(|deposit| deposit.amount > threshold) {
+            match db.get_deposit_approval_status(deposit.start_utxo).await? {
+                Some(ApprovalStatus::Approved) => {}
+                Some(ApprovalStatus::Rejected) => {
+                    return Err(BridgeError::PolicyViolation(format!(
+                        "Deposit {} is rejected",
+                        deposit.start_utxo
+                    )))
+                }
+                Some(ApprovalStatus::Pending) => pending.push(deposit.start_utxo),
+                None => {
+                    tracing::info!(
+                        "Deposit {} of {} sats is waiting for approval",
+                        deposit.start_utxo,
+                        deposit.amount
+                    );
+                    db.add_deposit_approval(deposit).await?;
+                    pending.push(deposit.start_utxo);
+                }
+            }
+        }
+
+        if !pending.is_empty() {
+            return Err(BridgeError::ApprovalRequired(pending));
+        }
+    }
+
+    let signed_deposits = db.get_signed_deposits(DAILY_LIMIT_WINDOW_SECS).await?;
+
+    check_daily_limits(&policy, &signed_deposits, deposits)
+}
+
+/// Checks withdrawals with given indexes against the policy, before they are
+/// signed.
+pub async fn check_withdrawals(db: &Database, idxs: &[usize]) -> Result<(), BridgeError> {
+    let max_outstanding_withdrawals = match db.get_signing_policy().await? {
+        SigningPolicy {
+            max_outstanding_withdrawals: Some(max_outstanding_withdrawals),
+            ..
+        } => max_outstanding_withdrawals,
+        _ => return Ok(()),
+    };
+
+    // Withdrawals that are signed before are already outstanding.
+    let mut new_withdrawals = 0;
+    for idx in idxs {
+        if !db.is_withdrawal_idx_signed(*idx).await? {
+            new_withdrawals += 1;
+        }
+    }
+    if new_withdrawals == 0 {
+        return Ok(());
+    }
+
+    let outstanding_withdrawals = db.get_outstanding_withdrawal_count().await?;
+    if outstanding_withdrawals + new_withdrawals > max_outstanding_withdrawals {
+        return Err(BridgeError::PolicyViolation(format!(
+            "{} withdrawals are outstanding, maximum is {}",
+            outstanding_withdrawals, max_outstanding_withdrawals
+        )));
+    }
+
+    Ok(())
+}
+
+/// Returns the current usage of the policy limits.
+pub async fn usage(db: &Database) -> Result<PolicyUsage, BridgeError> {
+    let signed_deposits = db.get_signed_deposits(DAILY_LIMIT_WINDOW_SECS).await?;
+
+    Ok(PolicyUsage {
+        signed_deposits: signed_deposits.iter().map(|deposit| deposit.amount).sum(),
+        outstanding_withdrawals: db.get_outstanding_withdrawal_count().await?,
+    })
+}
+
+/// Checks the addresses of a deposit against allow and deny lists.
+fn check_addresses(
+    addresses: &[ListedAddress],
+    deposit: &DepositRequest,
+) -> Result<(), BridgeError> {
+    let deposit_addresses = [
+        PolicyAddress::EvmAddress(deposit.evm_address),
+        PolicyAddress::RecoveryAddress(deposit.recovery_taproot_address.clone()),
+    ];
+
+    for address in deposit_addresses.iter() {
+        let listed = |list| {
+            addresses
+                .iter()
+                .filter(|listed| listed.list == list && listed.address.kind() == address.kind())
+                .collect::<Vec<_>>()
+        };
+
+        if listed(AddressList::Deny)
+            .iter()
+            .any(|listed| listed.address == *address)
+        {
+            return Err(BridgeError::PolicyViolation(format!(
+                "{} {} is denied",
+                address.kind(),
+                address.address()
+            )));
+        }
+
+        let allowed = listed(AddressList::Allow);
+        if !allowed.is_empty() && !allowed.iter().any(|listed| listed.address == *address) {
+            return Err(BridgeError::PolicyViolation(format!(
+                "{} {} is not allowed",
+                address.kind(),
+                address.address()
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks if signing the deposits would exceed daily limits. Deposits that
+/// are already signed are not counted twice, so they can be signed again.
+fn check_daily_limits(
+    policy: &SigningPolicy,
+    signed_deposits: &[SignedDeposit],
+    deposits: &[DepositRequest],
+) -> Result<(), BridgeError> {
+    let signed_utxos = signed_deposits
+        .iter()
+        .map(|deposit| deposit.start_utxo)
+        .collect::<HashSet<_>>();
+    let new_deposits = deposits
+        .iter()
+        .filter(|deposit| !signed_utxos.contains(&deposit.start_utxo))
+        .collect::<Vec<_>>();
+    if new_deposits.is_empty() {
+        return Ok(());
+    }
+
+    if let Some(daily_limit) = policy.daily_limit {
+        let total = signed_deposits
+            .iter()
+            .map(|deposit| deposit.amount)
+            .chain(new_deposits.iter().map(|deposit| deposit.amount))
+            .sum::<u64>();
+
+        if total > daily_limit {
+            return Err(BridgeError::PolicyViolation(format!(
+                "Daily limit of {} sats is exceeded",
+                daily_limit
+            )));
+        }
+    }
+
+    if let Some(evm_address_daily_limit) = policy.evm_address_daily_limit {
+        let mut totals: HashMap<EVMAddress, u64> = HashMap::new();
+        for (evm_address, amount) in signed_deposits
+            .iter()
+            .map(|deposit| (deposit.evm_address, deposit.amount))
+            .chain(
+                new_deposits
+                    .iter()
+                    .map(|deposit| (deposit.evm_address, deposit.amount)),
+            )
+        {
+            *totals.entry(evm_address).or_default() += amount;
+        }
+
+        for deposit in new_deposits {
+            if totals[&deposit.evm_address] > evm_address_daily_limit {
+                return Err(BridgeError::PolicyViolation(format!(
+                    "Daily limit of {} sats is exceeded for EVM address {}",
+                    evm_address_daily_limit,
+                    hex::encode(deposit.evm_address.0)
+                )));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        check_addresses, check_daily_limits, AddressList, ListedAddress, PolicyAddress,
+        SignedDeposit, SigningPolicy,
+    };
+    use crate::database::common::DepositRequest;
+    use crate::errors::BridgeError;
+    use crate::EVMAddress;
+    use bitcoin::hashes::Hash;
+    use bitcoin::{Address, Network, OutPoint, Txid};
+
+    fn deposit(vout: u32, evm_address: EVMAddress, amount: u64) -> DepositRequest {
+        DepositRequest {
+            start_utxo: OutPoint {
+                txid: Txid::all_zeros(),
+                vout,
+            },
+            recovery_taproot_address: Address::p2tr(
+                &crate::utils::SECP,
+                *crate::utils::UNSPENDABLE_XONLY_PUBKEY,
+                None,
+                Network::Regtest,
+            )
+            .as_unchecked()
+            .clone(),
+            evm_address,
+            amount,
+        }
+    }
+
+    #[test]
+    fn address_lists() {
+        let deposit = deposit(0, EVMAddress([0x45; 20]), 100);
+        let other = PolicyAddress::EvmAddress(EVMAddress([0x46; 20]));
+        let listed = |list, address: &PolicyAddress| ListedAddress {
+            list,
+            address: address.clone(),
+        };
+
+        check_addresses(&[], &deposit).unwrap();
+        check_addresses(&[listed(AddressList::Deny, &other)], &deposit).unwrap();
+
+        // An allow list only restricts addresses of its own kind.
+        let recovery_address =
+            PolicyAddress::RecoveryAddress(deposit.recovery_taproot_address.clone());
+        check_addresses(&[listed(AddressList::Allow, &recovery_address)], &deposit).unwrap();
+        assert!(matches!(
+            check_addresses(&[listed(AddressList::Allow, &other)], &deposit),
+            Err(BridgeError::PolicyViolation(_))
+        ));
+
+        let evm_address = PolicyAddress::EvmAddress(deposit.evm_address);
+        assert!(matches!(
+            check_addresses(
+                &[
+                    listed(AddressList::Allow, &evm_address),
+                    listed(AddressList::Deny, &recovery_address)
+                ],
+                &deposit
+            ),
+            Err(BridgeError::PolicyViolation(_))
+        ));
+    }
+
+    #[test]
+    fn policy_address_format() {
+        let address = PolicyAddress::EvmAddress(EVMAddress([0x45; 20]));
+        assert_eq!(address.address(), "45".repeat(20));
+        assert_eq!(
+            PolicyAddress::from_parts(address.kind(), &address.address()).unwrap(),
+            address
+        );
+        assert!(PolicyAddress::from_parts("evm_address", "0x45").is_err());
+    }
+
+    #[test]
+    fn daily_limits() {
+        let policy = SigningPolicy {
+            daily_limit: Some(300),
+            evm_address_daily_limit: Some(200),
+            ..SigningPolicy::default()
+        };
+        let signed_deposits = [SignedDeposit {
+            start_utxo: deposit(0, EVMAddress([0x45; 20]), 100).start_utxo,
+            evm_address: EVMAddress([0x45; 20]),
+            amount: 100,
+        }];
+
+        check_daily_limits(
+            &policy,
+            &signed_deposits,
+            &[deposit(1, EVMAddress([0x45; 20]), 100)],
+        )
+        .unwrap();
+        check_daily_limits(
+            &policy,
+            &signed_deposits,
+            &[
+                deposit(1, EVMAddress([0x45; 20]), 100),
+                deposit(2, EVMAddress([0x46; 20]), 100),
+            ],
+        )
+        .unwrap();
+
+        // Per EVM address.
+        assert!(check_daily_limits(
+            &policy,
+            &signed_deposits,
+            &[
+                deposit(1, EVMAddress([0x45; 20]), 100),
+                deposit(2, EVMAddress([0x45; 20]), 100),
+            ],
+        )
+        .is_err());
+
+        // In total.
+        assert!(check_daily_limits(
+            &policy,
+            &signed_deposits,
+            &[
+                deposit(1, EVMAddress([0x46; 20]), 200),
+                deposit(2, EVMAddress([0x47; 20]), 100),
+            ],
+        )
+        .is_err());
+
+        // Signed deposits can be signed again.
+        let policy = SigningPolicy {
+            daily_limit: Some(0),
+            ..SigningPolicy::default()
+        };
+        check_daily_limits(
+            &policy,
+            &signed_deposits,
+            &[deposit(0, EVMAddress([0x45; 20]), 100)],
+        )
+        .unwrap();
+    }
+}
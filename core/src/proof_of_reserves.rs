@@ -0,0 +1,356 @@
+//! # Proof of Reserves
+//!
+//! Operator lists the bridge UTXOs that are created by move txs of confirmed
+//! deposits and checks each one against Bitcoin. Total of the unspent ones
+//! should be equal to the deposited amount minus the amount that is paid by
+//! confirmed withdrawals.
+//!
+//! Report is signed by the operator and can be co-signed by verifiers. A
+//! verifier only signs after checking every listed UTXO with its own node, so
+//! its signature attests to the UTXOs and their total; deposited and withdrawn
+//! amounts come from operator's database.
+
+use crate::actor::Actor;
+use crate::config::BridgeConfig;
+use crate::database::common::Database;
+use crate::errors::BridgeError;
+use crate::extended_rpc::ExtendedRpc;
+use crate::rpc_auth::{self, VerifierClient};
+use crate::traits::rpc::VerifierRpcClient;
+use crate::transaction_builder::TransactionBuilder;
+use crate::utils::SECP;
+use bitcoin::address::NetworkUnchecked;
+use bitcoin::{Address, BlockHash, OutPoint};
+use bitcoin_mock_rpc::RpcApiWrapper;
+use secp256k1::{schnorr, Message, XOnlyPublicKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+
+const REPORT_TAG: &[u8] = b"clementine/proof-of-reserves";
+
+/// An unspent bridge UTXO.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReserveUtxo {
+    pub outpoint: OutPoint,
+    /// Locked amount in sats.
+    pub amount: u64,
+}
+
+/// Reserves of the bridge at a block. Amounts are in sats.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReservesReport {
+    pub bridge_address: Address<NetworkUnchecked>,
+    /// Best block when the report is created.
+    pub block_height: u64,
+    pub block_hash: BlockHash,
+    /// Unspent bridge UTXOs that are created by move txs.
+    pub utxos: Vec<ReserveUtxo>,
+    /// Total amount of `utxos`.
+    pub total: u64,
+    /// Total amount of bridge outputs of confirmed deposits.
+    pub deposited: u64,
+    /// Total amount of bridge outputs that are spent by confirmed withdrawals.
+    pub withdrawn: u64,
+    /// Bridge outputs of deposits that are spent, but not by a confirmed
+    /// withdrawal. Outputs that are spent by a withdrawal tx without enough
+    /// confirmations are listed here too, until the withdrawal is confirmed.
+    pub unaccounted: Vec<OutPoint>,
+}
+
+impl ReservesReport {
+    /// Checks if reserves cover every deposit that is not withdrawn.
+    pub fn is_balanced(&self) -> bool {
+        self.deposited.checked_sub(self.withdrawn) == Some(self.total)
+    }
+
+    /// Returns the digest that is signed for the report.
+    pub fn digest(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(REPORT_TAG);
+        hasher.update(serde_json::to_vec(self).expect("report is serializable"));
+
+        hasher.finalize().into()
+    }
+}
+
+/// A report with the signatures of operator and verifiers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReservesAttestation {
+    pub report: ReservesReport,
+    /// Signers and their signatures of the report digest, operator first.
+    pub signatures: Vec<(XOnlyPublicKey, schnorr::Signature)>,
+}
+
+impl ReservesAttestation {
+    /// Checks every signature of the attestation.
+    pub fn verify(&self) -> Result<(), BridgeError> {
+        let message = Message::from_digest(self.report.digest());
+
+        for (xonly_pk, signature) in self.signatures.iter() {
+            SECP.verify_schnorr(signature, &message, xonly_pk)
+                .map_err(|_| {
+                    BridgeError::InvalidReservesReport(format!("Invalid signature of {}", xonly_pk))
+                })?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Creates a report from the deposits and withdrawals in operator's database.
+pub async fn create_report<R>(
+    rpc: &ExtendedRpc<R>,
+    db: &Database,
+    transaction_builder: &TransactionBuilder,
+) -> Result<ReservesReport, BridgeError>
+where
+    R: RpcApiWrapper,
+{
+    let (bridge_address, _) = transaction_builder.generate_bridge_address()?;
+    let bridge_script = bridge_address.script_pubkey();
+    let block_height = rpc.get_block_count()?;
+    let block_hash = rpc.get_best_block_hash()?;
+
+    let withdrawn_utxos = db
+        .get_confirmed_withdrawals()
+        .await?
+        .iter()
+        .flat_map(|withdrawal| {
+            withdrawal
+                .withdrawal_tx
+                .input
+                .iter()
+                .map(|input| input.previous_output)
+                .collect::<Vec<_>>()
+        })
+        .collect::<HashSet<_>>();
+
+    let mut report = ReservesReport {
+        bridge_address: bridge_address.as_unchecked().clone(),
+        block_height,
+        block_hash,
+        utxos: vec![],
+        total: 0,
+        deposited: 0,
+        withdrawn: 0,
+        unaccounted: vec![],
+    };
+
+    for deposit in db.get_moved_deposits().await? {
+        let move_tx = deposit
+            .move_tx
+            .as_ref()
+            .ok_or(BridgeError::InvalidDepositStatus(
+                "Confirmed deposit has no move tx".to_string(),
+            ))?;
+        let vout = TransactionBuilder::move_vout(deposit.start_utxo, move_tx)?;
+        let outpoint = OutPoint {
+            txid: move_tx.compute_txid(),
+            vout,
+        };
+        let amount = move_tx.output[vout as usize].value.to_sat();
+        report.deposited += amount;
+
+        match rpc.get_unspent_txout(&outpoint)? {
+            Some(txout) if txout.script_pubkey == bridge_script => {
+                report.total += txout.value.to_sat();
+                report.utxos.push(ReserveUtxo {
+                    outpoint,
+                    amount: txout.value.to_sat(),
+                });
+            }
+            None if withdrawn_utxos.contains(&outpoint) => report.withdrawn += amount,
+            _ => {
+                tracing::warn!("Bridge UTXO {} is spent without a withdrawal", outpoint);
+                report.unaccounted.push(outpoint);
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Checks the report against Bitcoin. Every UTXO must be unspent, locked to
+/// the bridge address and sum up to the reported total.
+pub fn check_report<R>(
+    rpc: &ExtendedRpc<R>,
+    transaction_builder: &TransactionBuilder,
+    report: &ReservesReport,
+) -> Result<(), BridgeError>
+where
+    R: RpcApiWrapper,
+{
+    let (bridge_address, _) = transaction_builder.generate_bridge_address()?;
+    if report.bridge_address != *bridge_address.as_unchecked() {
+        return Err(BridgeError::InvalidReservesReport(
+            "Bridge address mismatch".to_string(),
+        ));
+    }
+
+    if !rpc.is_block_in_best_chain(&report.block_hash)? {
+        return Err(BridgeError::InvalidReservesReport(format!(
+            "Block {} is not in the best chain",
+            report.block_hash
+        )));
+    }
+
+    let mut outpoints = HashSet::new();
+    let mut total = 0;
+    for utxo in report.utxos.iter() {
+        if !outpoints.insert(utxo.outpoint) {
+            return Err(BridgeError::InvalidReservesReport(format!(
+                "UTXO {} is listed twice",
+                utxo.outpoint
+            )));
+        }
+
+        match rpc.get_unspent_txout(&utxo.outpoint)? {
+            Some(txout)
+                if txout.script_pubkey == bridge_address.script_pubkey()
+                    && txout.value.to_sat() == utxo.amount =>
+            {
+                total += utxo.amount
+            }
+            _ => {
+                return Err(BridgeError::InvalidReservesReport(format!(
+                    "UTXO {} is not an unspent bridge UTXO of {} sats",
+                    utxo.outpoint, utxo.amount
+                )))
+            }
+        }
+    }
+
+    if total != report.total {
+        return Err(BridgeError::InvalidReservesReport(format!(
+            "Total is {} sats, not {} sats",
+            total, report.total
+        )));
+    }
+
+    Ok(())
+}
+
+/// Signs the report with operator's key and collects co-signatures from given
+/// verifiers. Every co-signature is checked before it is added.
+pub async fn attest(
+    report: ReservesReport,
+    signer: &Actor,
+    verifiers: &[(XOnlyPublicKey, VerifierClient)],
+) -> Result<ReservesAttestation, BridgeError> {
+    let digest = report.digest();
+    let mut signatures = vec![(signer.xonly_public_key, signer.sign_digest(digest)?)];
+
+    for (xonly_pk, verifier) in verifiers.iter() {
+        let signature = verifier.sign_reserves_report_rpc(report.clone()).await?;
+
+        SECP.verify_schnorr(&signature, &Message::from_digest(digest), xonly_pk)
+            .map_err(|_| {
+                BridgeError::InvalidReservesReport(format!("Invalid co-signature of {}", xonly_pk))
+            })?;
+        signatures.push((*xonly_pk, signature));
+    }
+
+    Ok(ReservesAttestation { report, signatures })
+}
+
+/// Creates a report with operator's configuration and signs it. Report is
+/// co-signed by every verifier if verifier endpoints are configured.
+pub async fn create_attestation<R>(
+    config: BridgeConfig,
+    rpc: ExtendedRpc<R>,
+) -> Result<ReservesAttestation, BridgeError>
+where
+    R: RpcApiWrapper,
+{
+    let signer = Actor::from_config(&config)?;
+    let transaction_builder = TransactionBuilder::from_config(&config)?;
+    let db = Database::new(config.clone()).await?;
+
+    // Verifier keys are in the same order with their endpoints.
+    let verifiers = config
+        .verifier_endpoints
+        .clone()
+        .unwrap_or_default()
+        .iter()
+        .zip(config.verifiers_public_keys.iter())
+        .map(|(endpoint, xonly_pk)| {
+            Ok((
+                *xonly_pk,
+                rpc_auth::verifier_client(endpoint, signer.clone(), &config)?,
+            ))
+        })
+        .collect::<Result<Vec<_>, BridgeError>>()?;
+
+    let report = create_report(&rpc, &db, &transaction_builder).await?;
+
+    attest(report, &signer, &verifiers).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{attest, ReserveUtxo, ReservesReport};
+    use crate::actor::Actor;
+    use bitcoin::hashes::Hash;
+    use bitcoin::{Address, BlockHash, Network, OutPoint, Txid};
+    use secp256k1::SecretKey;
+
+    fn report() -> ReservesReport {
+        let utxo = ReserveUtxo {
+            outpoint: OutPoint {
+                txid: Txid::from_byte_array([0x45; 32]),
+                vout: 0,
+            },
+            amount: 99_000_000,
+        };
+
+        ReservesReport {
+            bridge_address: Address::p2tr(
+                &crate::utils::SECP,
+                *crate::utils::UNSPENDABLE_XONLY_PUBKEY,
+                None,
+                Network::Regtest,
+            )
+            .as_unchecked()
+            .clone(),
+            block_height: 100,
+            block_hash: BlockHash::from_byte_array([1; 32]),
+            utxos: vec![utxo],
+            total: 99_000_000,
+            deposited: 198_000_000,
+            withdrawn: 99_000_000,
+            unaccounted: vec![],
+        }
+    }
+
+    #[test]
+    fn balanced_report() {
+        let mut report = report();
+        assert!(report.is_balanced());
+
+        report.withdrawn = 0;
+        assert!(!report.is_balanced());
+
+        report.withdrawn = 200_000_000;
+        assert!(!report.is_balanced());
+    }
+
+    #[tokio::test]
+    async fn signed_attestation() {
+        let signer = Actor::new(SecretKey::from_slice(&[1u8; 32]).unwrap(), Network::Regtest);
+
+        let attestation = attest(report(), &signer, &[]).await.unwrap();
+        assert_eq!(attestation.signatures.len(), 1);
+        assert_eq!(attestation.signatures[0].0, signer.xonly_public_key);
+        attestation.verify().unwrap();
+
+        // Attestation should survive a round trip through JSON.
+        let json = serde_json::to_string(&attestation).unwrap();
+        let attestation: super::ReservesAttestation = serde_json::from_str(&json).unwrap();
+        attestation.verify().unwrap();
+
+        let mut tampered = attestation.clone();
+        tampered.report.total += 1;
+        assert!(tampered.verify().is_err());
+    }
+}
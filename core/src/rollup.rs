@@ -0,0 +1,192 @@
+//! # Rollup
+//!
+//! JSON-RPC implementation of [`RollupClient`], that reads deposit and
+//! withdrawal events from a rollup node.
+
+use crate::config::BridgeConfig;
+use crate::errors::BridgeError;
+use crate::traits::rollup::RollupClient;
+use crate::traits::rpc::RollupRpcClient;
+use crate::EVMAddress;
+use bitcoin::address::NetworkUnchecked;
+use bitcoin::{Address, OutPoint};
+use jsonrpsee::core::async_trait;
+use jsonrpsee::http_client::{HttpClient, HttpClientBuilder};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// A withdrawal event on the rollup.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RollupWithdrawal {
+    pub idx: usize,
+    pub withdrawal_address: Address<NetworkUnchecked>,
+    /// Withdrawn amount in sats, one of the deposit denominations.
+    pub amount: u64,
+    /// Set if the block that the withdrawal is made in is finalized.
+    pub finalized: bool,
+}
+
+/// A mint on the rollup for a deposit that is moved to the bridge.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RollupDeposit {
+    pub move_utxo: OutPoint,
+    pub evm_address: EVMAddress,
+    /// Minted amount in sats, one of the deposit denominations.
+    pub amount: u64,
+}
+
+/// Creates a client for the rollup in config, if there is one.
+pub fn new_rollup_client(
+    config: &BridgeConfig,
+) -> Result<Option<Arc<dyn RollupClient>>, BridgeError> {
+    Ok(match &config.rollup_rpc_url {
+        Some(url) => Some(Arc::new(JsonRpcRollupClient::new(url)?)),
+        None => None,
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct JsonRpcRollupClient {
+    client: HttpClient,
+}
+
+impl JsonRpcRollupClient {
+    pub fn new(url: &str) -> Result<Self, BridgeError> {
+        Ok(Self {
+            client: HttpClientBuilder::default().build(url)?,
+        })
+    }
+}
+
+#[async_trait]
+impl RollupClient for JsonRpcRollupClient {
+    async fn is_withdrawal_finalized(
+        &self,
+        idx: usize,
+        withdrawal_address: &Address<NetworkUnchecked>,
+        amount: u64,
+    ) -> Result<bool, BridgeError> {
+        let withdrawal = match self.client.get_withdrawal_rpc(idx).await? {
+            Some(withdrawal) => withdrawal,
+            None => return Ok(false),
+        };
+
+        Ok(withdrawal.finalized
+            && withdrawal.withdrawal_address == *withdrawal_address
+            && withdrawal.amount == amount)
+    }
+
+    async fn is_deposit_minted(
+        &self,
+        move_utxo: OutPoint,
+        evm_address: &EVMAddress,
+        amount: u64,
+    ) -> Result<bool, BridgeError> {
+        let deposit = match self.client.get_deposit_rpc(move_utxo).await? {
+            Some(deposit) => deposit,
+            None => return Ok(false),
+        };
+
+        if deposit.evm_address != *evm_address || deposit.amount != amount {
+            tracing::error!(
+                "Deposit moved to {:?} is minted with wrong parameters: {:?}",
+                move_utxo,
+                deposit
+            );
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{JsonRpcRollupClient, RollupDeposit, RollupWithdrawal};
+    use crate::mock::rollup::MockRollup;
+    use crate::traits::rollup::RollupClient;
+    use crate::EVMAddress;
+    use bitcoin::{Address, Network, OutPoint};
+
+    #[tokio::test]
+    async fn json_rpc_rollup_client() {
+        let rollup = MockRollup::default();
+        let (url, _handle) = rollup.start_server().await.unwrap();
+        let client = JsonRpcRollupClient::new(&url).unwrap();
+
+        let withdrawal_address = Address::p2tr(
+            &crate::utils::SECP,
+            *crate::utils::UNSPENDABLE_XONLY_PUBKEY,
+            None,
+            Network::Regtest,
+        );
+        let withdrawal_address = withdrawal_address.as_unchecked();
+
+        assert!(!client
+            .is_withdrawal_finalized(0, withdrawal_address, 100_000_000)
+            .await
+            .unwrap());
+
+        rollup.add_withdrawal(RollupWithdrawal {
+            idx: 0,
+            withdrawal_address: withdrawal_address.clone(),
+            amount: 100_000_000,
+            finalized: false,
+        });
+        assert!(!client
+            .is_withdrawal_finalized(0, withdrawal_address, 100_000_000)
+            .await
+            .unwrap());
+
+        rollup.finalize_withdrawal(0);
+        assert!(client
+            .is_withdrawal_finalized(0, withdrawal_address, 100_000_000)
+            .await
+            .unwrap());
+
+        // Every field of the withdrawal must match.
+        assert!(!client
+            .is_withdrawal_finalized(0, withdrawal_address, 10_000_000)
+            .await
+            .unwrap());
+        assert!(!client
+            .is_withdrawal_finalized(1, withdrawal_address, 100_000_000)
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn json_rpc_rollup_client_deposit() {
+        let rollup = MockRollup::default();
+        let (url, _handle) = rollup.start_server().await.unwrap();
+        let client = JsonRpcRollupClient::new(&url).unwrap();
+
+        let move_utxo = OutPoint::null();
+        let evm_address = EVMAddress([0x45; 20]);
+
+        assert!(!client
+            .is_deposit_minted(move_utxo, &evm_address, 100_000_000)
+            .await
+            .unwrap());
+
+        rollup.add_deposit(RollupDeposit {
+            move_utxo,
+            evm_address,
+            amount: 100_000_000,
+        });
+        assert!(client
+            .is_deposit_minted(move_utxo, &evm_address, 100_000_000)
+            .await
+            .unwrap());
+
+        // Mint must be for the committed EVM address and amount.
+        assert!(!client
+            .is_deposit_minted(move_utxo, &EVMAddress([0x46; 20]), 100_000_000)
+            .await
+            .unwrap());
+        assert!(!client
+            .is_deposit_minted(move_utxo, &evm_address, 10_000_000)
+            .await
+            .unwrap());
+    }
+}
@@ -0,0 +1,452 @@
+//! # RPC Authentication
+//!
+//! Verifiers only sign for the operator. Every request that operator sends to
+//! a verifier carries a Schnorr signature of operator's key over the request
+//! body, a timestamp and a random nonce, in HTTP headers. Verifiers reject
+//! requests that are not signed by the operator, that are too old or that
+//! they have already seen.
+//!
+//! Signing and checking are done by HTTP middlewares: [`OperatorAuthLayer`]
+//! for the operator's verifier clients and [`VerifierAuthLayer`] for the
+//! verifier servers.
+
+use crate::actor::Actor;
+use crate::config::BridgeConfig;
+use crate::errors::BridgeError;
+use crate::metrics::{VerifierMetricsLayer, VerifierMetricsService};
+use crate::tls::{TlsTransport, TlsTransportLayer};
+use crate::utils::SECP;
+use crate::verifier_pool::DEFAULT_VERIFIER_TIMEOUT_SECS;
+use hyper::header::{HeaderMap, HeaderValue};
+use hyper::{Body, Request, Response, StatusCode};
+use jsonrpsee::core::http_helpers::HttpError;
+use jsonrpsee::http_client::transport::Error as TransportError;
+use jsonrpsee::http_client::{HttpClient, HttpClientBuilder};
+use secp256k1::rand::{thread_rng, RngCore};
+use secp256k1::{schnorr, Message, XOnlyPublicKey};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tower::{Layer, Service};
+
+/// Maximum difference between a request's timestamp and verifier's clock, in
+/// seconds. Nonces are remembered for twice as long, so a request can't be
+/// replayed while its timestamp is accepted.
+pub const AUTH_WINDOW_SECS: u64 = 30;
+
+const AUTH_TAG: &[u8] = b"clementine/operator-auth";
+const TIMESTAMP_HEADER: &str = "x-operator-timestamp";
+const NONCE_HEADER: &str = "x-operator-nonce";
+const SIGNATURE_HEADER: &str = "x-operator-signature";
+
+/// Client that operator uses to send authenticated requests to a verifier.
+pub type VerifierClient = HttpClient<VerifierMetricsService<OperatorAuthService<TlsTransport>>>;
+
+/// Creates a client for the verifier at `url`, that signs every request with
+/// `signer` and connects with the TLS settings in config. Requests are
+/// measured in metrics, labeled with `url`, and time out after the configured
+/// verifier timeout.
+pub fn verifier_client(
+    url: &str,
+    signer: Actor,
+    config: &BridgeConfig,
+) -> Result<VerifierClient, BridgeError> {
+    let timeout_secs = config
+        .verifier_timeout_secs
+        .unwrap_or(DEFAULT_VERIFIER_TIMEOUT_SECS);
+
+    Ok(HttpClientBuilder::default()
+        .request_timeout(Duration::from_secs(timeout_secs))
+        .set_http_middleware(
+            tower::ServiceBuilder::new()
+                .layer(VerifierMetricsLayer::new(url))
+                .layer(OperatorAuthLayer::new(signer))
+                .layer(TlsTransportLayer::new(config)?),
+        )
+        .build(url)?)
+}
+
+/// Operator's authentication of a request body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OperatorAuth {
+    /// UNIX time that the request is signed at, in seconds.
+    pub timestamp: u64,
+    pub nonce: [u8; 32],
+    pub signature: schnorr::Signature,
+}
+
+impl OperatorAuth {
+    /// Signs `body` with a fresh nonce and current time.
+    pub fn sign(signer: &Actor, body: &[u8]) -> Result<Self, BridgeError> {
+        let mut nonce = [0u8; 32];
+        thread_rng().fill_bytes(&mut nonce);
+
+        Self::sign_with(signer, body, unix_time(), nonce)
+    }
+
+    fn sign_with(
+        signer: &Actor,
+        body: &[u8],
+        timestamp: u64,
+        nonce: [u8; 32],
+    ) -> Result<Self, BridgeError> {
+        Ok(Self {
+            timestamp,
+            nonce,
+            signature: signer.sign_digest(auth_digest(timestamp, &nonce, body))?,
+        })
+    }
+
+    /// Checks if `body` is signed by the operator.
+    pub fn verify(
+        &self,
+        operator_xonly_pk: &XOnlyPublicKey,
+        body: &[u8],
+    ) -> Result<(), BridgeError> {
+        let message = Message::from_digest(auth_digest(self.timestamp, &self.nonce, body));
+
+        SECP.verify_schnorr(&self.signature, &message, operator_xonly_pk)
+            .map_err(|_| BridgeError::Unauthorized("Invalid signature".to_string()))
+    }
+
+    fn to_headers(self, headers: &mut HeaderMap) {
+        headers.insert(TIMESTAMP_HEADER, HeaderValue::from(self.timestamp));
+        headers.insert(
+            NONCE_HEADER,
+            HeaderValue::from_str(&hex::encode(self.nonce)).expect("hex is a valid header"),
+        );
+        headers.insert(
+            SIGNATURE_HEADER,
+            HeaderValue::from_str(&self.signature.to_string()).expect("hex is a valid header"),
+        );
+    }
+
+    fn from_headers(headers: &HeaderMap) -> Result<Self, BridgeError> {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .ok_or(BridgeError::Unauthorized(format!(
+                    "Missing {} header",
+                    name
+                )))
+        };
+        let invalid = |name: &str| BridgeError::Unauthorized(format!("Invalid {} header", name));
+
+        let timestamp = header(TIMESTAMP_HEADER)?
+            .parse()
+            .map_err(|_| invalid(TIMESTAMP_HEADER))?;
+        let mut nonce = [0u8; 32];
+        hex::decode_to_slice(header(NONCE_HEADER)?, &mut nonce)
+            .map_err(|_| invalid(NONCE_HEADER))?;
+        let signature = schnorr::Signature::from_str(header(SIGNATURE_HEADER)?)
+            .map_err(|_| invalid(SIGNATURE_HEADER))?;
+
+        Ok(Self {
+            timestamp,
+            nonce,
+            signature,
+        })
+    }
+}
+
+fn auth_digest(timestamp: u64, nonce: &[u8; 32], body: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(AUTH_TAG);
+    hasher.update(timestamp.to_be_bytes());
+    hasher.update(nonce);
+    hasher.update(body);
+
+    hasher.finalize().into()
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time is after UNIX epoch")
+        .as_secs()
+}
+
+/// Accepts requests that are signed by the operator, recently and only once.
+#[derive(Debug, Clone)]
+pub struct RequestAuthenticator {
+    operator_xonly_pk: XOnlyPublicKey,
+    /// Nonces of accepted requests and their timestamps.
+    seen_nonces: Arc<Mutex<HashMap<[u8; 32], u64>>>,
+}
+
+impl RequestAuthenticator {
+    pub fn new(operator_xonly_pk: XOnlyPublicKey) -> Self {
+        Self {
+            operator_xonly_pk,
+            seen_nonces: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Checks the authentication of a request body at UNIX time `now`.
+    pub fn authenticate(
+        &self,
+        auth: &OperatorAuth,
+        body: &[u8],
+        now: u64,
+    ) -> Result<(), BridgeError> {
+        if auth.timestamp.abs_diff(now) > AUTH_WINDOW_SECS {
+            return Err(BridgeError::Unauthorized(format!(
+                "Timestamp {} is out of the window",
+                auth.timestamp
+            )));
+        }
+
+        auth.verify(&self.operator_xonly_pk, body)?;
+
+        let mut seen_nonces = self.seen_nonces.lock().unwrap();
+        seen_nonces.retain(|_, timestamp| timestamp.abs_diff(now) <= 2 * AUTH_WINDOW_SECS);
+        if seen_nonces.insert(auth.nonce, auth.timestamp).is_some() {
+            return Err(BridgeError::Unauthorized("Replayed request".to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+/// Signs every request with operator's key.
+#[derive(Debug, Clone)]
+pub struct OperatorAuthLayer {
+    signer: Actor,
+}
+
+impl OperatorAuthLayer {
+    pub fn new(signer: Actor) -> Self {
+        Self { signer }
+    }
+}
+
+impl<S> Layer<S> for OperatorAuthLayer {
+    type Service = OperatorAuthService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        OperatorAuthService {
+            inner,
+            signer: self.signer.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OperatorAuthService<S> {
+    inner: S,
+    signer: Actor,
+}
+
+impl<S> Service<Request<Body>> for OperatorAuthService<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>, Error = TransportError>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send,
+{
+    type Response = Response<Body>;
+    type Error = TransportError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        // Use the service that is polled ready and leave a clone in its place.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let signer = self.signer.clone();
+
+        Box::pin(async move {
+            let (mut parts, body) = request.into_parts();
+            let body = hyper::body::to_bytes(body)
+                .await
+                .map_err(|e| TransportError::Http(e.into()))?;
+
+            // Transport errors can't carry a signer error, so it is logged.
+            OperatorAuth::sign(&signer, &body)
+                .map_err(|e| {
+                    tracing::error!("Can't sign request to verifier: {}", e);
+                    TransportError::Http(HttpError::Malformed)
+                })?
+                .to_headers(&mut parts.headers);
+
+            inner
+                .call(Request::from_parts(parts, Body::from(body)))
+                .await
+        })
+    }
+}
+
+/// Rejects every request that is not authenticated by
+/// [`RequestAuthenticator`], with `401 Unauthorized`.
+#[derive(Debug, Clone)]
+pub struct VerifierAuthLayer {
+    authenticator: RequestAuthenticator,
+}
+
+impl VerifierAuthLayer {
+    pub fn new(operator_xonly_pk: XOnlyPublicKey) -> Self {
+        Self {
+            authenticator: RequestAuthenticator::new(operator_xonly_pk),
+        }
+    }
+}
+
+impl<S> Layer<S> for VerifierAuthLayer {
+    type Service = VerifierAuthService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        VerifierAuthService {
+            inner,
+            authenticator: self.authenticator.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VerifierAuthService<S> {
+    inner: S,
+    authenticator: RequestAuthenticator,
+}
+
+impl<S> Service<Request<Body>> for VerifierAuthService<S>
+where
+    S: Service<
+            Request<Body>,
+            Response = Response<Body>,
+            Error = Box<dyn std::error::Error + Send + Sync>,
+        > + Clone
+        + Send
+        + 'static,
+    S::Future: Send,
+{
+    type Response = Response<Body>;
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        // Use the service that is polled ready and leave a clone in its place.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let authenticator = self.authenticator.clone();
+
+        Box::pin(async move {
+            let (parts, body) = request.into_parts();
+            let body = hyper::body::to_bytes(body).await?;
+
+            let res = OperatorAuth::from_headers(&parts.headers)
+                .and_then(|auth| authenticator.authenticate(&auth, &body, unix_time()));
+            if let Err(e) = res {
+                tracing::warn!("Rejected request: {}", e);
+
+                return Ok(Response::builder()
+                    .status(StatusCode::UNAUTHORIZED)
+                    .body(Body::from(e.to_string()))?);
+            }
+
+            inner
+                .call(Request::from_parts(parts, Body::from(body)))
+                .await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        unix_time, verifier_client, OperatorAuth, RequestAuthenticator, VerifierAuthLayer,
+        AUTH_WINDOW_SECS,
+    };
+    use crate::actor::Actor;
+    use crate::config::BridgeConfig;
+    use bitcoin::Network;
+    use jsonrpsee::core::client::ClientT;
+    use jsonrpsee::http_client::HttpClientBuilder;
+    use jsonrpsee::rpc_params;
+    use jsonrpsee::server::{RpcModule, Server};
+    use secp256k1::rand::thread_rng;
+    use secp256k1::SecretKey;
+
+    fn new_actor() -> Actor {
+        Actor::new(SecretKey::new(&mut thread_rng()), Network::Regtest)
+    }
+
+    #[test]
+    fn authenticate_request() {
+        let operator = new_actor();
+        let authenticator = RequestAuthenticator::new(operator.xonly_public_key);
+        let body = b"request body";
+        let now = unix_time();
+
+        let auth = OperatorAuth::sign_with(&operator, body, now, [0x45; 32]).unwrap();
+        authenticator.authenticate(&auth, body, now).unwrap();
+
+        // Same request can't be accepted twice.
+        assert!(authenticator.authenticate(&auth, body, now).is_err());
+
+        // Signature must cover the exact body and be made by the operator.
+        let auth = OperatorAuth::sign_with(&operator, body, now, [0x46; 32]).unwrap();
+        assert!(authenticator
+            .authenticate(&auth, b"another body", now)
+            .is_err());
+        let auth = OperatorAuth::sign_with(&new_actor(), body, now, [0x46; 32]).unwrap();
+        assert!(authenticator.authenticate(&auth, body, now).is_err());
+
+        // Timestamp must be in the window.
+        let old = now - AUTH_WINDOW_SECS - 1;
+        let auth = OperatorAuth::sign_with(&operator, body, old, [0x47; 32]).unwrap();
+        assert!(authenticator.authenticate(&auth, body, now).is_err());
+        let auth =
+            OperatorAuth::sign_with(&operator, body, now + AUTH_WINDOW_SECS, [0x47; 32]).unwrap();
+        authenticator.authenticate(&auth, body, now).unwrap();
+    }
+
+    #[tokio::test]
+    async fn authenticated_server() {
+        let operator = new_actor();
+
+        let server = Server::builder()
+            .set_http_middleware(
+                tower::ServiceBuilder::new()
+                    .layer(VerifierAuthLayer::new(operator.xonly_public_key)),
+            )
+            .build("127.0.0.1:0")
+            .await
+            .unwrap();
+        let url = format!("http://{}/", server.local_addr().unwrap());
+        let mut module = RpcModule::new(());
+        module
+            .register_method("ping", |_, _| "pong".to_string())
+            .unwrap();
+        let _handle = server.start(module);
+
+        let client = verifier_client(&url, operator, &BridgeConfig::default()).unwrap();
+        let response: String = client.request("ping", rpc_params![]).await.unwrap();
+        assert_eq!(response, "pong");
+
+        let unauthenticated = HttpClientBuilder::default().build(&url).unwrap();
+        assert!(unauthenticated
+            .request::<String, _>("ping", rpc_params![])
+            .await
+            .is_err());
+
+        let impostor = verifier_client(&url, new_actor(), &BridgeConfig::default()).unwrap();
+        assert!(impostor
+            .request::<String, _>("ping", rpc_params![])
+            .await
+            .is_err());
+    }
+}
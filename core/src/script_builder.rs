@@ -14,9 +14,14 @@ use bitcoin::{
 };
 use secp256k1::XOnlyPublicKey;
 
+/// Witness script of the anyone-can-spend output. It is the only witness
+/// element needed to spend that output.
+pub fn anyone_can_spend_script() -> ScriptBuf {
+    Builder::new().push_opcode(OP_PUSHNUM_1).into_script()
+}
+
 pub fn anyone_can_spend_txout() -> TxOut {
-    let script = Builder::new().push_opcode(OP_PUSHNUM_1).into_script();
-    let script_pubkey = script.to_p2wsh();
+    let script_pubkey = anyone_can_spend_script().to_p2wsh();
     let value = script_pubkey.minimal_non_dust();
 
     TxOut {
@@ -57,14 +62,38 @@ pub fn generate_script_n_of_n(verifiers_pks: &[XOnlyPublicKey]) -> ScriptBuf {
     create_n_of_n_builder(verifiers_pks).into_script()
 }
 
+/// Creates a builder that needs a single signature of the MuSig2 aggregated
+/// key, instead of one signature per verifier.
+pub fn create_musig2_builder(aggregated_pk: &XOnlyPublicKey) -> Builder {
+    Builder::new()
+        .push_x_only_key(aggregated_pk)
+        .push_opcode(OP_CHECKSIG)
+}
+
+pub fn generate_script_musig2(aggregated_pk: &XOnlyPublicKey) -> ScriptBuf {
+    create_musig2_builder(aggregated_pk).into_script()
+}
+
 pub fn create_deposit_script(
     verifiers_pks: &[XOnlyPublicKey],
     evm_address: &EVMAddress,
     amount: u64,
 ) -> ScriptBuf {
+    push_deposit_commitment(create_n_of_n_builder(verifiers_pks), evm_address, amount)
+}
+
+pub fn create_musig2_deposit_script(
+    aggregated_pk: &XOnlyPublicKey,
+    evm_address: &EVMAddress,
+    amount: u64,
+) -> ScriptBuf {
+    push_deposit_commitment(create_musig2_builder(aggregated_pk), evm_address, amount)
+}
+
+fn push_deposit_commitment(builder: Builder, evm_address: &EVMAddress, amount: u64) -> ScriptBuf {
     let citrea: [u8; 6] = "citrea".as_bytes().try_into().unwrap();
 
-    create_n_of_n_builder(verifiers_pks)
+    builder
         .push_opcode(OP_FALSE)
         .push_opcode(OP_IF)
         .push_slice(citrea)
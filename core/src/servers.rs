@@ -3,23 +3,34 @@
 //! Utilities for operator and verifier servers.
 
 use crate::{
+    actor::Actor,
+    chain_watcher::{ChainWatcher, CHAIN_WATCHER_POLL_INTERVAL_SECS},
     config::BridgeConfig,
     errors,
     extended_rpc::ExtendedRpc,
+    metrics::{self, RpcMetricsLayer},
     operator,
-    traits::{self, rpc::VerifierRpcServer},
+    rpc_auth::{self, VerifierAuthLayer, VerifierClient},
+    tls::{self, TlsTransport},
+    traits::{
+        self,
+        rpc::{VerifierAdminRpcServer, VerifierRpcServer},
+    },
     verifier::Verifier,
+    verifier_pool::DEFAULT_HEALTH_CHECK_INTERVAL_SECS,
 };
 use bitcoin_mock_rpc::RpcApiWrapper;
 use errors::BridgeError;
 use jsonrpsee::{
-    http_client::{HttpClient, HttpClientBuilder},
-    server::{Server, ServerHandle},
+    http_client::HttpClient,
+    server::{RpcServiceBuilder, Server, ServerHandle},
 };
-use operator::Operator;
+use operator::{Operator, DEPOSIT_RESUME_INTERVAL_SECS};
 use traits::rpc::OperatorRpcServer;
 
-/// Starts a server for a verifier.
+/// Starts a server for a verifier. Only requests that are signed by the
+/// operator, which is the last of the verifiers, are accepted. Server uses TLS
+/// if a certificate is configured.
 pub async fn create_verifier_server<R>(
     config: BridgeConfig,
     rpc: ExtendedRpc<R>,
@@ -27,28 +38,63 @@ pub async fn create_verifier_server<R>(
 where
     R: RpcApiWrapper,
 {
-    let server = match Server::builder()
-        .build(format!("{}:{}", config.host, config.port))
-        .await
-    {
-        Ok(s) => s,
-        Err(e) => return Err(BridgeError::ServerError(e)),
-    };
+    let operator_xonly_pk = *config
+        .verifiers_public_keys
+        .last()
+        .ok_or(BridgeError::InvalidOperatorKey)?;
+
+    let tls_config = tls::server_config(&config)?;
+    let server_builder = Server::builder()
+        .set_http_middleware(
+            tower::ServiceBuilder::new().layer(VerifierAuthLayer::new(operator_xonly_pk)),
+        )
+        .set_rpc_middleware(RpcServiceBuilder::new().layer(RpcMetricsLayer));
+    let server_addr = format!("{}:{}", config.host, config.port);
 
-    let verifier = Verifier::new(rpc, config).await?;
+    let verifier = Verifier::new(rpc.clone(), config.clone()).await?;
+    let verifier_admin = Verifier::new(rpc.clone(), config.clone()).await?;
 
-    let addr = match server.local_addr() {
-        Ok(a) => a,
-        Err(e) => return Err(BridgeError::ServerError(e)),
+    let chain_watcher = ChainWatcher::new(config.clone(), rpc.clone()).await?;
+    let bridge_spend_watcher = Verifier::new(rpc, config.clone()).await?;
+    tokio::spawn(bridge_spend_watcher.watch_bridge_spends(chain_watcher.subscribe()));
+    tokio::spawn(chain_watcher.run(CHAIN_WATCHER_POLL_INTERVAL_SECS));
+
+    let (addr, handle) = match tls_config {
+        Some(tls_config) => {
+            tls::start_server(
+                &server_addr,
+                tls_config,
+                server_builder.to_service_builder(),
+                VerifierRpcServer::into_rpc(verifier),
+            )
+            .await?
+        }
+        None => {
+            let server = match server_builder.build(server_addr).await {
+                Ok(s) => s,
+                Err(e) => return Err(BridgeError::ServerError(e)),
+            };
+
+            let addr = match server.local_addr() {
+                Ok(a) => a,
+                Err(e) => return Err(BridgeError::ServerError(e)),
+            };
+
+            (addr, server.start(VerifierRpcServer::into_rpc(verifier)))
+        }
     };
-    let handle = server.start(verifier.into_rpc());
 
     tracing::info!("Verifier server started with address: {}", addr);
 
+    start_metrics_server(&config, handle.clone()).await?;
+    start_admin_server(&config, verifier_admin, handle.clone()).await?;
+
     Ok((addr, handle))
 }
 
-/// Starts the server for the operator.
+/// Starts the server for the operator. Server uses TLS if a certificate is
+/// configured and verifiers are connected with the client certificate, if
+/// there is one.
 pub async fn create_operator_server<R>(
     config: BridgeConfig,
     rpc: ExtendedRpc<R>,
@@ -57,33 +103,122 @@ pub async fn create_operator_server<R>(
 where
     R: RpcApiWrapper,
 {
-    let verifiers: Vec<HttpClient> = verifier_endpoints
-        .clone()
+    let signer = Actor::from_config(&config)?;
+    let verifiers: Vec<VerifierClient> = verifier_endpoints
         .iter()
-        .map(|verifier| HttpClientBuilder::default().build(verifier))
-        .collect::<Result<Vec<HttpClient>, jsonrpsee::core::client::Error>>()?;
+        .map(|verifier| rpc_auth::verifier_client(verifier, signer.clone(), &config))
+        .collect::<Result<Vec<VerifierClient>, BridgeError>>()?;
 
-    let operator = Operator::new(config.clone(), rpc, verifiers).await?;
+    let operator = Operator::new(config.clone(), rpc.clone(), verifiers.clone()).await?;
 
-    let server = match Server::builder()
-        .build(format!("{}:{}", config.host, config.port))
-        .await
-    {
-        Ok(s) => s,
-        Err(e) => return Err(BridgeError::ServerError(e)),
-    };
+    // Check verifiers' health, in background. Operator reports the results.
+    let health_check_interval_secs = config
+        .verifier_health_check_interval_secs
+        .unwrap_or(DEFAULT_HEALTH_CHECK_INTERVAL_SECS);
+    tokio::spawn(
+        operator
+            .verifier_pool()
+            .clone()
+            .run_health_checks(health_check_interval_secs),
+    );
+
+    let chain_watcher = ChainWatcher::new(config.clone(), rpc.clone()).await?;
+
+    // Resume interrupted deposits and withdrawals and handle reorgs, in
+    // background.
+    let monitor = Operator::new(config.clone(), rpc.clone(), verifiers.clone()).await?;
+    tokio::spawn(monitor.run_monitor(DEPOSIT_RESUME_INTERVAL_SECS, chain_watcher.subscribe()));
+    let deposit_watcher = Operator::new(config.clone(), rpc.clone(), verifiers.clone()).await?;
+    tokio::spawn(deposit_watcher.watch_deposits(chain_watcher.subscribe()));
 
-    let addr = match server.local_addr() {
-        Ok(s) => s,
-        Err(e) => return Err(BridgeError::ServerError(e)),
+    // Move queued deposits in batches, in background.
+    if config.deposit_batch_size.is_some() || config.deposit_batch_interval_secs.is_some() {
+        let batcher = Operator::new(config.clone(), rpc, verifiers).await?;
+        tokio::spawn(batcher.run_deposit_batcher());
+    }
+    tokio::spawn(chain_watcher.run(CHAIN_WATCHER_POLL_INTERVAL_SECS));
+
+    let server_builder =
+        Server::builder().set_rpc_middleware(RpcServiceBuilder::new().layer(RpcMetricsLayer));
+    let server_addr = format!("{}:{}", config.host, config.port);
+    let (addr, handle) = match tls::server_config(&config)? {
+        Some(tls_config) => {
+            tls::start_server(
+                &server_addr,
+                tls_config,
+                server_builder.to_service_builder(),
+                operator.into_rpc(),
+            )
+            .await?
+        }
+        None => {
+            let server = match server_builder.build(server_addr).await {
+                Ok(s) => s,
+                Err(e) => return Err(BridgeError::ServerError(e)),
+            };
+
+            let addr = match server.local_addr() {
+                Ok(s) => s,
+                Err(e) => return Err(BridgeError::ServerError(e)),
+            };
+
+            (addr, server.start(operator.into_rpc()))
+        }
     };
-    let handle = server.start(operator.into_rpc());
 
     tracing::info!("Operator server started with address: {}", addr);
 
+    start_metrics_server(&config, handle.clone()).await?;
+
     Ok((addr, handle))
 }
 
+/// Starts the admin server of a verifier on localhost, if an admin port is
+/// configured. It is stopped with the RPC server.
+async fn start_admin_server<R>(
+    config: &BridgeConfig,
+    verifier: Verifier<R>,
+    rpc_server: ServerHandle,
+) -> Result<(), BridgeError>
+where
+    R: RpcApiWrapper,
+{
+    let admin_port = match config.admin_port {
+        Some(admin_port) => admin_port,
+        None => return Ok(()),
+    };
+
+    let server = Server::builder()
+        .set_rpc_middleware(RpcServiceBuilder::new().layer(RpcMetricsLayer))
+        .build(format!("127.0.0.1:{}", admin_port))
+        .await
+        .map_err(BridgeError::ServerError)?;
+    let addr = server.local_addr().map_err(BridgeError::ServerError)?;
+    let handle = server.start(VerifierAdminRpcServer::into_rpc(verifier));
+
+    tracing::info!("Verifier admin server started with address: {}", addr);
+
+    tokio::spawn(async move {
+        rpc_server.stopped().await;
+        let _ = handle.stop();
+    });
+
+    Ok(())
+}
+
+/// Starts the metrics server of an operator or a verifier, if a metrics port
+/// is configured. It is stopped with the RPC server.
+async fn start_metrics_server(
+    config: &BridgeConfig,
+    rpc_server: ServerHandle,
+) -> Result<(), BridgeError> {
+    if let Some(metrics_port) = config.metrics_port {
+        metrics::start_server(&format!("{}:{}", config.host, metrics_port), rpc_server).await?;
+    }
+
+    Ok(())
+}
+
 /// Starts operator and verifiers servers. This function's intended use is for
 /// tests.
 ///
@@ -100,13 +235,19 @@ pub async fn create_operator_and_verifiers<R>(
     config: BridgeConfig,
     rpc: ExtendedRpc<R>,
 ) -> (
-    HttpClient,
+    HttpClient<TlsTransport>,
     ServerHandle,
     Vec<(std::net::SocketAddr, ServerHandle)>,
 )
 where
     R: RpcApiWrapper,
 {
+    let scheme = if config.tls_cert_path.is_some() {
+        "https"
+    } else {
+        "http"
+    };
+
     let mut all_secret_keys = config.all_secret_keys.clone().unwrap_or_else(|| {
         panic!("All secret keys are required for testing");
     });
@@ -120,9 +261,13 @@ where
             create_verifier_server(
                 BridgeConfig {
                     verifiers_public_keys: config.verifiers_public_keys.clone(),
-                    secret_key: *sk,
+                    secret_key: Some(*sk),
                     port: 0, // Use the index to calculate the port
                     db_name: config.db_name.clone() + &i.to_string(),
+                    // Only the operator serves metrics and verifiers can't
+                    // share an admin port.
+                    metrics_port: None,
+                    admin_port: None,
                     ..config.clone()
                 },
                 rpc.clone(),
@@ -133,20 +278,25 @@ where
 
     let verifier_endpoints: Vec<String> = results
         .iter()
-        .map(|(socket_addr, _)| format!("http://{}:{}/", socket_addr.ip(), socket_addr.port()))
+        .map(|(socket_addr, _)| {
+            format!("{}://{}:{}/", scheme, socket_addr.ip(), socket_addr.port())
+        })
         .collect();
 
     let (operator_socket_addr, operator_handle) =
-        create_operator_server(config, rpc, verifier_endpoints)
+        create_operator_server(config.clone(), rpc, verifier_endpoints)
             .await
             .unwrap();
-    let operator_client = HttpClientBuilder::default()
-        .build(format!(
-            "http://{}:{}/",
+    let operator_client = tls::http_client(
+        &format!(
+            "{}://{}:{}/",
+            scheme,
             operator_socket_addr.ip(),
             operator_socket_addr.port()
-        ))
-        .unwrap();
+        ),
+        &config,
+    )
+    .unwrap();
     results.push((operator_socket_addr, operator_handle.clone()));
 
     (operator_client, operator_handle, results)
@@ -0,0 +1,696 @@
+//! # Signer
+//!
+//! [`Actor`](crate::actor::Actor) signs with a [`Signer`], which keeps the
+//! secret key of the operator or verifier. There are three backends:
+//!
+//! - [`InMemorySigner`] keeps the secret key in memory. It is loaded from the
+//!   `secret_key` in config, or from an encrypted [`Keystore`] file.
+//! - [`RemoteSigner`] sends digests to a signer process over a Unix socket,
+//!   so the secret key never enters the bridge process. [`serve`] runs the
+//!   other side of the protocol, which the `keys serve` subcommand hosts for a
+//!   keystore.
+//!
+//! ## Remote Signer Protocol
+//!
+//! Every request and response is a JSON object on a single line. Requests are
+//! tagged with `method`:
+//!
+//! ```json
+//! {"method":"xonly_public_key"}
+//! {"method":"sign_schnorr","digest":"<hex>","tweak":"<hex>"}
+//! {"method":"sign_ecdsa","digest":"<hex>"}
+//! ```
+//!
+//! Responses are `{"xonly_public_key":"<hex>"}`,
+//! `{"schnorr_signature":"<hex>"}`, `{"ecdsa_signature":"<hex>"}` or
+//! `{"error":"<message>"}`.
+//!
+//! MuSig2 needs the secret key next to its secret nonces, so only the
+//! in-memory signer supports it.
+
+use crate::config::BridgeConfig;
+use crate::errors::BridgeError;
+use crate::musig2::{
+    self, KeyAggContext, MuSigAggNonce, MuSigPartialSignature, MuSigPubNonce, MuSigSecNonce,
+};
+use crate::utils::SECP;
+use bitcoin::secp256k1::{ecdsa, schnorr, Keypair, Message, SecretKey, XOnlyPublicKey};
+use bitcoin::TapTweakHash;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use secp256k1::rand::{thread_rng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use tokio::runtime::{Handle, RuntimeFlavor};
+
+/// Environment variable that the keystore password is read from.
+pub const KEYSTORE_PASSWORD_ENV: &str = "CLEMENTINE_KEYSTORE_PASSWORD";
+/// Version of the keystore file format.
+pub const KEYSTORE_VERSION: u32 = 1;
+/// Logarithm of the scrypt cost parameter of new keystores.
+pub const DEFAULT_KEYSTORE_SCRYPT_LOG_N: u8 = 15;
+/// Timeout of a request to a remote signer.
+const REMOTE_SIGNER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Holds a secret key and signs with it.
+pub trait Signer: std::fmt::Debug + Send + Sync {
+    fn xonly_public_key(&self) -> XOnlyPublicKey;
+
+    /// Signs a 32 byte digest with the secret key, tweaked with `tweak` if
+    /// it is given.
+    fn sign_schnorr(
+        &self,
+        digest: [u8; 32],
+        tweak: Option<TapTweakHash>,
+    ) -> Result<schnorr::Signature, BridgeError>;
+
+    fn sign_ecdsa(&self, digest: [u8; 32]) -> Result<ecdsa::Signature, BridgeError>;
+
+    /// Generates a MuSig2 nonce pair for signing `message` with the
+    /// aggregated key.
+    fn musig2_nonce(
+        &self,
+        _key_agg_ctx: &KeyAggContext,
+        _message: &[u8; 32],
+    ) -> Result<(MuSigSecNonce, MuSigPubNonce), BridgeError> {
+        Err(BridgeError::SignerError(
+            "MuSig2 is not supported by this signer".to_string(),
+        ))
+    }
+
+    /// Creates a MuSig2 partial signature for `message`, using a secret
+    /// nonce generated by [`Signer::musig2_nonce`].
+    fn musig2_partial_sign(
+        &self,
+        _key_agg_ctx: &KeyAggContext,
+        _sec_nonce: MuSigSecNonce,
+        _agg_nonce: &MuSigAggNonce,
+        _message: &[u8; 32],
+    ) -> Result<MuSigPartialSignature, BridgeError> {
+        Err(BridgeError::SignerError(
+            "MuSig2 is not supported by this signer".to_string(),
+        ))
+    }
+}
+
+/// Creates the signer that is selected in config: a remote signer if
+/// `remote_signer_socket` is set, a keystore if `keystore_path` is set and
+/// `secret_key` otherwise. Keystore password is read from
+/// [`KEYSTORE_PASSWORD_ENV`].
+pub fn from_config(config: &BridgeConfig) -> Result<Arc<dyn Signer>, BridgeError> {
+    match (
+        &config.remote_signer_socket,
+        &config.keystore_path,
+        config.secret_key,
+    ) {
+        (Some(_), Some(_), _) | (Some(_), _, Some(_)) | (_, Some(_), Some(_)) => {
+            Err(BridgeError::ConfigError(
+                "Only one of secret_key, keystore_path and remote_signer_socket can be set"
+                    .to_string(),
+            ))
+        }
+        (Some(_), None, None) if config.musig2 => Err(BridgeError::ConfigError(
+            "musig2 can't be used with remote_signer_socket, remote signers don't support MuSig2"
+                .to_string(),
+        )),
+        (Some(socket_path), None, None) => Ok(Arc::new(RemoteSigner::connect(socket_path)?)),
+        (None, Some(keystore_path), None) => {
+            let password = std::env::var(KEYSTORE_PASSWORD_ENV).map_err(|_| {
+                BridgeError::ConfigError(format!(
+                    "{} must be set to open the keystore",
+                    KEYSTORE_PASSWORD_ENV
+                ))
+            })?;
+
+            Ok(Arc::new(InMemorySigner::from_keystore(
+                keystore_path,
+                &password,
+            )?))
+        }
+        (None, None, Some(secret_key)) => Ok(Arc::new(InMemorySigner::new(secret_key))),
+        (None, None, None) => Err(BridgeError::ConfigError(
+            "One of secret_key, keystore_path and remote_signer_socket must be set".to_string(),
+        )),
+    }
+}
+
+/// Signer that keeps the secret key in memory.
+#[derive(Clone)]
+pub struct InMemorySigner {
+    keypair: Keypair,
+}
+
+impl InMemorySigner {
+    pub fn new(secret_key: SecretKey) -> Self {
+        Self {
+            keypair: Keypair::from_secret_key(&SECP, &secret_key),
+        }
+    }
+
+    /// Decrypts the keystore at `path` with `password`.
+    pub fn from_keystore(path: &Path, password: &str) -> Result<Self, BridgeError> {
+        let secret_key = Keystore::read(path)?.decrypt(password)?;
+
+        Ok(Self::new(secret_key))
+    }
+}
+
+impl std::fmt::Debug for InMemorySigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InMemorySigner")
+            .field("xonly_public_key", &self.xonly_public_key())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Signer for InMemorySigner {
+    fn xonly_public_key(&self) -> XOnlyPublicKey {
+        self.keypair.x_only_public_key().0
+    }
+
+    fn sign_schnorr(
+        &self,
+        digest: [u8; 32],
+        tweak: Option<TapTweakHash>,
+    ) -> Result<schnorr::Signature, BridgeError> {
+        let keypair = match tweak {
+            Some(tweak) => self.keypair.add_xonly_tweak(&SECP, &tweak.to_scalar())?,
+            None => self.keypair,
+        };
+
+        Ok(SECP.sign_schnorr(&Message::from_digest(digest), &keypair))
+    }
+
+    fn sign_ecdsa(&self, digest: [u8; 32]) -> Result<ecdsa::Signature, BridgeError> {
+        Ok(SECP.sign_ecdsa(&Message::from_digest(digest), &self.keypair.secret_key()))
+    }
+
+    fn musig2_nonce(
+        &self,
+        key_agg_ctx: &KeyAggContext,
+        message: &[u8; 32],
+    ) -> Result<(MuSigSecNonce, MuSigPubNonce), BridgeError> {
+        musig2::nonce_gen(
+            &self.keypair.secret_key(),
+            Some(&key_agg_ctx.aggregated_xonly_public_key()),
+            Some(message),
+        )
+    }
+
+    fn musig2_partial_sign(
+        &self,
+        key_agg_ctx: &KeyAggContext,
+        sec_nonce: MuSigSecNonce,
+        agg_nonce: &MuSigAggNonce,
+        message: &[u8; 32],
+    ) -> Result<MuSigPartialSignature, BridgeError> {
+        musig2::partial_sign(
+            key_agg_ctx,
+            sec_nonce,
+            &self.keypair.secret_key(),
+            agg_nonce,
+            message,
+        )
+    }
+}
+
+/// Secret key file, encrypted with ChaCha20-Poly1305 under a key that is
+/// derived from a password with scrypt.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Keystore {
+    pub version: u32,
+    /// Public key of the encrypted secret key, so that keystores can be told
+    /// apart without the password.
+    pub xonly_public_key: XOnlyPublicKey,
+    pub scrypt_log_n: u8,
+    #[serde(with = "hex::serde")]
+    pub salt: [u8; 32],
+    #[serde(with = "hex::serde")]
+    pub nonce: [u8; 12],
+    #[serde(with = "hex::serde")]
+    pub ciphertext: Vec<u8>,
+}
+
+impl Keystore {
+    /// Encrypts `secret_key` with `password`.
+    pub fn encrypt(secret_key: &SecretKey, password: &str) -> Result<Self, BridgeError> {
+        Self::encrypt_with_log_n(secret_key, password, DEFAULT_KEYSTORE_SCRYPT_LOG_N)
+    }
+
+    fn encrypt_with_log_n(
+        secret_key: &SecretKey,
+        password: &str,
+        scrypt_log_n: u8,
+    ) -> Result<Self, BridgeError> {
+        let mut salt = [0u8; 32];
+        thread_rng().fill_bytes(&mut salt);
+        let mut nonce = [0u8; 12];
+        thread_rng().fill_bytes(&mut nonce);
+
+        let cipher = keystore_cipher(password, &salt, scrypt_log_n)?;
+        let ciphertext = cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                secret_key.secret_bytes().as_slice(),
+            )
+            .map_err(|_| BridgeError::SignerError("Can't encrypt the keystore".to_string()))?;
+
+        Ok(Self {
+            version: KEYSTORE_VERSION,
+            xonly_public_key: secret_key.x_only_public_key(&SECP).0,
+            scrypt_log_n,
+            salt,
+            nonce,
+            ciphertext,
+        })
+    }
+
+    /// Decrypts the secret key with `password`.
+    pub fn decrypt(&self, password: &str) -> Result<SecretKey, BridgeError> {
+        if self.version != KEYSTORE_VERSION {
+            return Err(BridgeError::SignerError(format!(
+                "Unsupported keystore version {}",
+                self.version
+            )));
+        }
+
+        let cipher = keystore_cipher(password, &self.salt, self.scrypt_log_n)?;
+        let secret_bytes = cipher
+            .decrypt(Nonce::from_slice(&self.nonce), self.ciphertext.as_slice())
+            .map_err(|_| BridgeError::SignerError("Wrong keystore password".to_string()))?;
+        let secret_key = SecretKey::from_slice(&secret_bytes)?;
+
+        if secret_key.x_only_public_key(&SECP).0 != self.xonly_public_key {
+            return Err(BridgeError::SignerError(
+                "Keystore public key doesn't match its secret key".to_string(),
+            ));
+        }
+
+        Ok(secret_key)
+    }
+
+    pub fn read(path: &Path) -> Result<Self, BridgeError> {
+        let contents = fs::read_to_string(path).map_err(|e| {
+            BridgeError::SignerError(format!("Can't read keystore {}: {}", path.display(), e))
+        })?;
+
+        serde_json::from_str(&contents).map_err(|e| {
+            BridgeError::SignerError(format!("Invalid keystore {}: {}", path.display(), e))
+        })
+    }
+
+    /// Writes the keystore to `path`, readable only by its owner.
+    pub fn write(&self, path: &Path) -> Result<(), BridgeError> {
+        use std::os::unix::fs::OpenOptionsExt;
+
+        let write = || -> std::io::Result<()> {
+            let mut file = fs::OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .mode(0o600)
+                .open(path)?;
+            file.write_all(serde_json::to_string_pretty(self)?.as_bytes())
+        };
+
+        write().map_err(|e| {
+            BridgeError::SignerError(format!("Can't write keystore {}: {}", path.display(), e))
+        })
+    }
+}
+
+fn keystore_cipher(
+    password: &str,
+    salt: &[u8],
+    scrypt_log_n: u8,
+) -> Result<ChaCha20Poly1305, BridgeError> {
+    let params = scrypt::Params::new(scrypt_log_n, 8, 1, 32)
+        .map_err(|e| BridgeError::SignerError(format!("Invalid scrypt parameters: {}", e)))?;
+
+    let mut key = [0u8; 32];
+    scrypt::scrypt(password.as_bytes(), salt, &params, &mut key)
+        .map_err(|e| BridgeError::SignerError(format!("Can't derive keystore key: {}", e)))?;
+
+    Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+}
+
+/// Request of the remote signer protocol.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum SignerRequest {
+    XonlyPublicKey,
+    SignSchnorr {
+        #[serde(with = "hex::serde")]
+        digest: [u8; 32],
+        tweak: Option<TapTweakHash>,
+    },
+    SignEcdsa {
+        #[serde(with = "hex::serde")]
+        digest: [u8; 32],
+    },
+}
+
+/// Response of the remote signer protocol.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignerResponse {
+    XonlyPublicKey(XOnlyPublicKey),
+    SchnorrSignature(schnorr::Signature),
+    EcdsaSignature(ecdsa::Signature),
+    Error(String),
+}
+
+/// Signer that sends requests to a signer process on a Unix socket. Every
+/// request uses a new connection and blocks until it is answered. Requests
+/// that are made on a Tokio worker thread move the other tasks of the thread
+/// to other workers first, so a slow signer doesn't stall them.
+#[derive(Debug, Clone)]
+pub struct RemoteSigner {
+    socket_path: PathBuf,
+    xonly_public_key: XOnlyPublicKey,
+}
+
+impl RemoteSigner {
+    /// Connects to the signer at `socket_path` and gets its public key.
+    pub fn connect(socket_path: &Path) -> Result<Self, BridgeError> {
+        match request(socket_path, &SignerRequest::XonlyPublicKey)? {
+            SignerResponse::XonlyPublicKey(xonly_public_key) => Ok(Self {
+                socket_path: socket_path.to_path_buf(),
+                xonly_public_key,
+            }),
+            response => Err(unexpected_response(response)),
+        }
+    }
+}
+
+impl Signer for RemoteSigner {
+    fn xonly_public_key(&self) -> XOnlyPublicKey {
+        self.xonly_public_key
+    }
+
+    fn sign_schnorr(
+        &self,
+        digest: [u8; 32],
+        tweak: Option<TapTweakHash>,
+    ) -> Result<schnorr::Signature, BridgeError> {
+        let signature = match request(
+            &self.socket_path,
+            &SignerRequest::SignSchnorr { digest, tweak },
+        )? {
+            SignerResponse::SchnorrSignature(signature) => signature,
+            response => return Err(unexpected_response(response)),
+        };
+
+        // Signatures are checked, so that a wrong key is noticed here and not
+        // when a transaction is rejected.
+        let xonly_public_key = match tweak {
+            Some(tweak) => {
+                self.xonly_public_key
+                    .add_tweak(&SECP, &tweak.to_scalar())?
+                    .0
+            }
+            None => self.xonly_public_key,
+        };
+        SECP.verify_schnorr(&signature, &Message::from_digest(digest), &xonly_public_key)
+            .map_err(|_| {
+                BridgeError::SignerError("Remote signer returned an invalid signature".to_string())
+            })?;
+
+        Ok(signature)
+    }
+
+    fn sign_ecdsa(&self, digest: [u8; 32]) -> Result<ecdsa::Signature, BridgeError> {
+        match request(&self.socket_path, &SignerRequest::SignEcdsa { digest })? {
+            SignerResponse::EcdsaSignature(signature) => Ok(signature),
+            response => Err(unexpected_response(response)),
+        }
+    }
+}
+
+/// Sends a request to the signer at `socket_path` and returns its response.
+fn request(socket_path: &Path, request: &SignerRequest) -> Result<SignerResponse, BridgeError> {
+    match Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(|| blocking_request(socket_path, request))
+        }
+        _ => blocking_request(socket_path, request),
+    }
+}
+
+fn blocking_request(
+    socket_path: &Path,
+    request: &SignerRequest,
+) -> Result<SignerResponse, BridgeError> {
+    let io_error = |e: std::io::Error| {
+        BridgeError::SignerError(format!(
+            "Remote signer {} failed: {}",
+            socket_path.display(),
+            e
+        ))
+    };
+
+    let mut stream = UnixStream::connect(socket_path).map_err(io_error)?;
+    stream
+        .set_read_timeout(Some(REMOTE_SIGNER_TIMEOUT))
+        .map_err(io_error)?;
+    stream
+        .set_write_timeout(Some(REMOTE_SIGNER_TIMEOUT))
+        .map_err(io_error)?;
+
+    let mut line = serde_json::to_string(request).expect("request should serialize");
+    line.push('\n');
+    stream.write_all(line.as_bytes()).map_err(io_error)?;
+
+    let mut line = String::new();
+    BufReader::new(stream)
+        .read_line(&mut line)
+        .map_err(io_error)?;
+
+    match serde_json::from_str(&line) {
+        Ok(SignerResponse::Error(e)) => Err(BridgeError::SignerError(e)),
+        Ok(response) => Ok(response),
+        Err(e) => Err(BridgeError::SignerError(format!(
+            "Invalid remote signer response: {}",
+            e
+        ))),
+    }
+}
+
+fn unexpected_response(response: SignerResponse) -> BridgeError {
+    BridgeError::SignerError(format!("Unexpected remote signer response {:?}", response))
+}
+
+/// Answers remote signer requests on `listener` with `signer`, until the
+/// listener fails.
+pub async fn serve(
+    listener: tokio::net::UnixListener,
+    signer: Arc<dyn Signer>,
+) -> Result<(), BridgeError> {
+    loop {
+        let (stream, _) = listener.accept().await.map_err(BridgeError::ServerError)?;
+        let signer = signer.clone();
+
+        tokio::spawn(async move {
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = tokio::io::BufReader::new(reader).lines();
+
+            while let Ok(Some(line)) = lines.next_line().await {
+                let response = match serde_json::from_str(&line) {
+                    Ok(request) => handle_request(signer.as_ref(), request),
+                    Err(e) => SignerResponse::Error(format!("Invalid request: {}", e)),
+                };
+
+                let mut line = serde_json::to_string(&response).expect("response should serialize");
+                line.push('\n');
+                if writer.write_all(line.as_bytes()).await.is_err() {
+                    break;
+                }
+            }
+        });
+    }
+}
+
+fn handle_request(signer: &dyn Signer, request: SignerRequest) -> SignerResponse {
+    let response = match request {
+        SignerRequest::XonlyPublicKey => {
+            Ok(SignerResponse::XonlyPublicKey(signer.xonly_public_key()))
+        }
+        SignerRequest::SignSchnorr { digest, tweak } => signer
+            .sign_schnorr(digest, tweak)
+            .map(SignerResponse::SchnorrSignature),
+        SignerRequest::SignEcdsa { digest } => signer
+            .sign_ecdsa(digest)
+            .map(SignerResponse::EcdsaSignature),
+    };
+
+    response.unwrap_or_else(|e| SignerResponse::Error(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        from_config, serve, InMemorySigner, Keystore, RemoteSigner, Signer, KEYSTORE_PASSWORD_ENV,
+    };
+    use crate::actor::Actor;
+    use crate::config::BridgeConfig;
+    use crate::errors::BridgeError;
+    use crate::musig2::KeyAggContext;
+    use crate::utils::SECP;
+    use bitcoin::secp256k1::{Message, SecretKey};
+    use bitcoin::{Network, TapTweakHash};
+    use secp256k1::rand::{thread_rng, RngCore};
+    use std::sync::Arc;
+
+    #[test]
+    fn keystore() {
+        let secret_key = SecretKey::new(&mut thread_rng());
+        let keystore = Keystore::encrypt_with_log_n(&secret_key, "password", 4).unwrap();
+        assert_eq!(
+            keystore.xonly_public_key,
+            secret_key.x_only_public_key(&SECP).0
+        );
+        assert_eq!(keystore.decrypt("password").unwrap(), secret_key);
+        assert!(matches!(
+            keystore.decrypt("wrong password"),
+            Err(BridgeError::SignerError(_))
+        ));
+
+        let path = std::env::temp_dir().join(format!("keystore-{}.json", thread_rng().next_u64()));
+        keystore.write(&path).unwrap();
+        let signer = InMemorySigner::from_keystore(&path, "password").unwrap();
+        assert_eq!(signer.xonly_public_key(), keystore.xonly_public_key);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn signer_from_config() {
+        let secret_key = SecretKey::new(&mut thread_rng());
+        let path = std::env::temp_dir().join(format!("keystore-{}.json", thread_rng().next_u64()));
+        Keystore::encrypt_with_log_n(&secret_key, "password", 4)
+            .unwrap()
+            .write(&path)
+            .unwrap();
+
+        let mut config = BridgeConfig {
+            secret_key: None,
+            keystore_path: Some(path.clone()),
+            ..BridgeConfig::default()
+        };
+        std::env::set_var(KEYSTORE_PASSWORD_ENV, "password");
+        let signer = from_config(&config).unwrap();
+        assert_eq!(
+            signer.xonly_public_key(),
+            secret_key.x_only_public_key(&SECP).0
+        );
+
+        // Only one source of the key can be set.
+        config.secret_key = Some(secret_key);
+        assert!(matches!(
+            from_config(&config),
+            Err(BridgeError::ConfigError(_))
+        ));
+        config.secret_key = None;
+        config.keystore_path = None;
+        assert!(matches!(
+            from_config(&config),
+            Err(BridgeError::ConfigError(_))
+        ));
+
+        // Remote signers can't sign with MuSig2.
+        config.remote_signer_socket = Some(path.clone());
+        config.musig2 = true;
+        assert!(matches!(
+            from_config(&config),
+            Err(BridgeError::ConfigError(_))
+        ));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn remote_signer() {
+        let secret_key = SecretKey::new(&mut thread_rng());
+        let socket_path =
+            std::env::temp_dir().join(format!("signer-{}.sock", thread_rng().next_u64()));
+        let listener = tokio::net::UnixListener::bind(&socket_path).unwrap();
+        tokio::spawn(serve(listener, Arc::new(InMemorySigner::new(secret_key))));
+
+        // Remote signer blocks, so it is not used on the runtime thread.
+        let (remote_signer, signature, tweaked_signature, musig2_nonce) =
+            tokio::task::spawn_blocking({
+                let socket_path = socket_path.clone();
+                move || {
+                    let remote_signer = RemoteSigner::connect(&socket_path).unwrap();
+                    let signature = remote_signer.sign_schnorr([0x45; 32], None);
+                    let tweak =
+                        TapTweakHash::from_key_and_tweak(remote_signer.xonly_public_key(), None);
+                    let tweaked_signature = remote_signer.sign_schnorr([0x45; 32], Some(tweak));
+                    let key_agg_ctx =
+                        KeyAggContext::from_xonly(&[remote_signer.xonly_public_key()]).unwrap();
+                    let musig2_nonce = remote_signer.musig2_nonce(&key_agg_ctx, &[0x45; 32]);
+
+                    (remote_signer, signature, tweaked_signature, musig2_nonce)
+                }
+            })
+            .await
+            .unwrap();
+
+        let xonly_public_key = secret_key.x_only_public_key(&SECP).0;
+        assert_eq!(remote_signer.xonly_public_key(), xonly_public_key);
+        SECP.verify_schnorr(
+            &signature.unwrap(),
+            &Message::from_digest([0x45; 32]),
+            &xonly_public_key,
+        )
+        .unwrap();
+        let actor = Actor::with_signer(Arc::new(remote_signer), Network::Regtest);
+        let tweaked_xonly_public_key = actor.address.script_pubkey().as_bytes()[2..].to_vec();
+        SECP.verify_schnorr(
+            &tweaked_signature.unwrap(),
+            &Message::from_digest([0x45; 32]),
+            &bitcoin::XOnlyPublicKey::from_slice(&tweaked_xonly_public_key).unwrap(),
+        )
+        .unwrap();
+        assert!(matches!(musig2_nonce, Err(BridgeError::SignerError(_))));
+
+        std::fs::remove_file(&socket_path).unwrap();
+    }
+
+    /// Signer process and the requests run on the only worker thread of the
+    /// runtime, so the signer can only answer if requests give that thread
+    /// away while they wait.
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn remote_signer_on_runtime_thread() {
+        let secret_key = SecretKey::new(&mut thread_rng());
+        let socket_path =
+            std::env::temp_dir().join(format!("signer-{}.sock", thread_rng().next_u64()));
+        let listener = tokio::net::UnixListener::bind(&socket_path).unwrap();
+        tokio::spawn(serve(listener, Arc::new(InMemorySigner::new(secret_key))));
+
+        let signature = tokio::spawn({
+            let socket_path = socket_path.clone();
+            async move {
+                RemoteSigner::connect(&socket_path)
+                    .unwrap()
+                    .sign_schnorr([0x45; 32], None)
+                    .unwrap()
+            }
+        })
+        .await
+        .unwrap();
+        SECP.verify_schnorr(
+            &signature,
+            &Message::from_digest([0x45; 32]),
+            &secret_key.x_only_public_key(&SECP).0,
+        )
+        .unwrap();
+
+        std::fs::remove_file(&socket_path).unwrap();
+    }
+}
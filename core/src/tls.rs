@@ -0,0 +1,396 @@
+//! # TLS
+//!
+//! Optional TLS for operator and verifier servers and the clients that
+//! connect to them. Certificates and keys are read from PEM files given in
+//! [`BridgeConfig`].
+//!
+//! A server that has a client CA configured also requires clients to present
+//! a certificate signed by it. This is how verifiers accept only the
+//! operator.
+
+use crate::config::BridgeConfig;
+use crate::errors::BridgeError;
+use hyper::client::HttpConnector;
+use hyper::{Body, Request, Response};
+use hyper_rustls::HttpsConnector;
+use jsonrpsee::http_client::transport::Error as TransportError;
+use jsonrpsee::http_client::{HttpClient, HttpClientBuilder};
+use jsonrpsee::server::{stop_channel, Methods, ServerHandle, TowerService, TowerServiceBuilder};
+use rustls::server::AllowAnyAuthenticatedClient;
+use rustls::{Certificate, ClientConfig, PrivateKey, RootCertStore, ServerConfig};
+use std::fs::File;
+use std::future::Future;
+use std::io::BufReader;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+use tower::{Layer, Service};
+
+/// Creates the TLS configuration of a server. Returns nothing if no
+/// certificate is configured, which means server doesn't use TLS.
+pub fn server_config(config: &BridgeConfig) -> Result<Option<Arc<ServerConfig>>, BridgeError> {
+    let (cert_path, key_path) = match (&config.tls_cert_path, &config.tls_key_path) {
+        (Some(cert_path), Some(key_path)) => (cert_path, key_path),
+        (None, None) => return Ok(None),
+        _ => {
+            return Err(BridgeError::TlsError(
+                "Both certificate and key are needed for TLS".to_string(),
+            ))
+        }
+    };
+
+    let builder = ServerConfig::builder().with_safe_defaults();
+    let builder = match &config.tls_client_ca_path {
+        Some(client_ca_path) => builder.with_client_cert_verifier(
+            AllowAnyAuthenticatedClient::new(read_root_certs(client_ca_path)?).boxed(),
+        ),
+        None => builder.with_no_client_auth(),
+    };
+    let server_config = builder
+        .with_single_cert(read_certs(cert_path)?, read_private_key(key_path)?)
+        .map_err(|e| BridgeError::TlsError(e.to_string()))?;
+
+    Ok(Some(Arc::new(server_config)))
+}
+
+/// Creates the TLS configuration of a client. Servers are trusted if their
+/// certificates are signed by the configured CA, or by a root of the system
+/// if there is none. Client certificate is presented if it is configured.
+pub fn client_config(config: &BridgeConfig) -> Result<ClientConfig, BridgeError> {
+    let roots = match &config.tls_ca_path {
+        Some(ca_path) => read_root_certs(ca_path)?,
+        None => native_root_certs()?,
+    };
+
+    let builder = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(roots);
+
+    match (&config.tls_client_cert_path, &config.tls_client_key_path) {
+        (Some(cert_path), Some(key_path)) => builder
+            .with_client_auth_cert(read_certs(cert_path)?, read_private_key(key_path)?)
+            .map_err(|e| BridgeError::TlsError(e.to_string())),
+        (None, None) => Ok(builder.with_no_client_auth()),
+        _ => Err(BridgeError::TlsError(
+            "Both client certificate and key are needed for TLS".to_string(),
+        )),
+    }
+}
+
+fn read_certs(path: &Path) -> Result<Vec<Certificate>, BridgeError> {
+    let file = File::open(path)
+        .map_err(|e| BridgeError::TlsError(format!("Can't open {:?}: {}", path, e)))?;
+    let certs = rustls_pemfile::certs(&mut BufReader::new(file))
+        .map_err(|e| BridgeError::TlsError(format!("Can't read {:?}: {}", path, e)))?;
+
+    if certs.is_empty() {
+        return Err(BridgeError::TlsError(format!(
+            "No certificate found in {:?}",
+            path
+        )));
+    }
+
+    Ok(certs.into_iter().map(Certificate).collect())
+}
+
+fn read_root_certs(path: &Path) -> Result<RootCertStore, BridgeError> {
+    let mut roots = RootCertStore::empty();
+    for cert in read_certs(path)? {
+        roots
+            .add(&cert)
+            .map_err(|e| BridgeError::TlsError(format!("Invalid CA in {:?}: {}", path, e)))?;
+    }
+
+    Ok(roots)
+}
+
+fn native_root_certs() -> Result<RootCertStore, BridgeError> {
+    let certs = rustls_native_certs::load_native_certs()
+        .map_err(|e| BridgeError::TlsError(format!("Can't load system roots: {}", e)))?;
+
+    let mut roots = RootCertStore::empty();
+    roots.add_parsable_certificates(&certs.into_iter().map(|cert| cert.0).collect::<Vec<_>>());
+
+    Ok(roots)
+}
+
+fn read_private_key(path: &Path) -> Result<PrivateKey, BridgeError> {
+    let file = File::open(path)
+        .map_err(|e| BridgeError::TlsError(format!("Can't open {:?}: {}", path, e)))?;
+    let items = rustls_pemfile::read_all(&mut BufReader::new(file))
+        .map_err(|e| BridgeError::TlsError(format!("Can't read {:?}: {}", path, e)))?;
+
+    items
+        .into_iter()
+        .find_map(|item| match item {
+            rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::ECKey(key)
+            | rustls_pemfile::Item::RSAKey(key) => Some(PrivateKey(key)),
+            _ => None,
+        })
+        .ok_or(BridgeError::TlsError(format!(
+            "No private key found in {:?}",
+            path
+        )))
+}
+
+/// Starts a JSON-RPC server at `addr` that only accepts TLS connections.
+pub async fn start_server<M, L>(
+    addr: &str,
+    tls_config: Arc<ServerConfig>,
+    service_builder: TowerServiceBuilder<M, L>,
+    methods: impl Into<Methods>,
+) -> Result<(SocketAddr, ServerHandle), BridgeError>
+where
+    M: Clone + Send + Sync + 'static,
+    L: Clone + Send + Sync + 'static,
+    TowerService<M, L>: hyper::service::Service<
+            Request<Body>,
+            Response = Response<Body>,
+            Error = Box<dyn std::error::Error + Send + Sync>,
+        > + Send
+        + 'static,
+    <TowerService<M, L> as hyper::service::Service<Request<Body>>>::Future: Send + 'static,
+{
+    let listener = TcpListener::bind(addr)
+        .await
+        .map_err(BridgeError::ServerError)?;
+    let local_addr = listener.local_addr().map_err(BridgeError::ServerError)?;
+
+    let acceptor = TlsAcceptor::from(tls_config);
+    let methods: Methods = methods.into();
+    let (stop_handle, server_handle) = stop_channel();
+
+    tokio::spawn(async move {
+        loop {
+            let stream = tokio::select! {
+                res = listener.accept() => match res {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        tracing::error!("Failed to accept connection: {}", e);
+                        continue;
+                    }
+                },
+                _ = stop_handle.clone().shutdown() => break,
+            };
+
+            let acceptor = acceptor.clone();
+            let service = service_builder
+                .clone()
+                .build(methods.clone(), stop_handle.clone());
+            let stop_handle = stop_handle.clone();
+
+            tokio::spawn(async move {
+                let stream = match acceptor.accept(stream).await {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        tracing::warn!("TLS handshake failed: {}", e);
+                        return;
+                    }
+                };
+
+                let connection = hyper::server::conn::Http::new()
+                    .serve_connection(stream, service)
+                    .with_upgrades();
+                tokio::pin!(connection);
+
+                tokio::select! {
+                    res = connection.as_mut() => {
+                        if let Err(e) = res {
+                            tracing::debug!("Connection closed with error: {}", e);
+                        }
+                    }
+                    _ = stop_handle.shutdown() => {
+                        connection.as_mut().graceful_shutdown();
+                        let _ = connection.await;
+                    }
+                }
+            });
+        }
+    });
+
+    Ok((local_addr, server_handle))
+}
+
+/// Creates a JSON-RPC client for `url`, that connects with the TLS settings
+/// in config if its scheme is `https`.
+pub fn http_client(
+    url: &str,
+    config: &BridgeConfig,
+) -> Result<HttpClient<TlsTransport>, BridgeError> {
+    Ok(HttpClientBuilder::default()
+        .set_http_middleware(tower::ServiceBuilder::new().layer(TlsTransportLayer::new(config)?))
+        .build(url)?)
+}
+
+/// Replaces the transport of a JSON-RPC client with one that uses the TLS
+/// settings in config, including the client certificate.
+#[derive(Debug, Clone)]
+pub struct TlsTransportLayer {
+    client: hyper::Client<HttpsConnector<HttpConnector>>,
+}
+
+impl TlsTransportLayer {
+    pub fn new(config: &BridgeConfig) -> Result<Self, BridgeError> {
+        let connector = hyper_rustls::HttpsConnectorBuilder::new()
+            .with_tls_config(client_config(config)?)
+            .https_or_http()
+            .enable_http1()
+            .build();
+
+        Ok(Self {
+            client: hyper::Client::builder().build(connector),
+        })
+    }
+}
+
+impl<S> Layer<S> for TlsTransportLayer {
+    type Service = TlsTransport;
+
+    fn layer(&self, _inner: S) -> Self::Service {
+        TlsTransport {
+            client: self.client.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TlsTransport {
+    client: hyper::Client<HttpsConnector<HttpConnector>>,
+}
+
+impl Service<Request<Body>> for TlsTransport {
+    type Response = Response<Body>;
+    type Error = TransportError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let response = self.client.request(request);
+
+        Box::pin(async move { response.await.map_err(|e| TransportError::Http(e.into())) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{http_client, server_config, start_server};
+    use crate::config::BridgeConfig;
+    use jsonrpsee::core::client::ClientT;
+    use jsonrpsee::rpc_params;
+    use jsonrpsee::server::{RpcModule, Server};
+    use std::path::PathBuf;
+
+    fn test_file(name: &str) -> Option<PathBuf> {
+        Some(
+            PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join("tests/data/tls")
+                .join(name),
+        )
+    }
+
+    /// Starts a TLS server that requires client certificates, with a single
+    /// method.
+    async fn start_test_server() -> (String, jsonrpsee::server::ServerHandle) {
+        let config = BridgeConfig {
+            tls_cert_path: test_file("server.pem"),
+            tls_key_path: test_file("server.key"),
+            tls_client_ca_path: test_file("ca.pem"),
+            ..Default::default()
+        };
+
+        let mut module = RpcModule::new(());
+        module
+            .register_method("ping", |_, _| "pong".to_string())
+            .unwrap();
+
+        let (addr, handle) = start_server(
+            "127.0.0.1:0",
+            server_config(&config).unwrap().unwrap(),
+            Server::builder().to_service_builder(),
+            module,
+        )
+        .await
+        .unwrap();
+
+        (format!("https://{}/", addr), handle)
+    }
+
+    #[tokio::test]
+    async fn mutual_tls() {
+        let (url, _handle) = start_test_server().await;
+
+        let config = BridgeConfig {
+            tls_ca_path: test_file("ca.pem"),
+            tls_client_cert_path: test_file("client.pem"),
+            tls_client_key_path: test_file("client.key"),
+            ..Default::default()
+        };
+        let client = http_client(&url, &config).unwrap();
+        let response: String = client.request("ping", rpc_params![]).await.unwrap();
+        assert_eq!(response, "pong");
+
+        // Clients without a certificate signed by the client CA are rejected.
+        let anonymous = http_client(
+            &url,
+            &BridgeConfig {
+                tls_ca_path: test_file("ca.pem"),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(anonymous
+            .request::<String, _>("ping", rpc_params![])
+            .await
+            .is_err());
+
+        let untrusted = http_client(
+            &url,
+            &BridgeConfig {
+                tls_ca_path: test_file("ca.pem"),
+                tls_client_cert_path: test_file("untrusted_client.pem"),
+                tls_client_key_path: test_file("untrusted_client.key"),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(untrusted
+            .request::<String, _>("ping", rpc_params![])
+            .await
+            .is_err());
+
+        // Server must be trusted by the client too.
+        let distrustful = http_client(
+            &url,
+            &BridgeConfig {
+                tls_ca_path: test_file("client.pem"),
+                tls_client_cert_path: test_file("client.pem"),
+                tls_client_key_path: test_file("client.key"),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(distrustful
+            .request::<String, _>("ping", rpc_params![])
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn server_config_needs_key() {
+        assert!(server_config(&BridgeConfig::default()).unwrap().is_none());
+
+        let config = BridgeConfig {
+            tls_cert_path: test_file("server.pem"),
+            ..Default::default()
+        };
+        assert!(server_config(&config).is_err());
+    }
+}
@@ -1 +1,3 @@
+pub mod rollup;
 pub mod rpc;
+pub mod storage;
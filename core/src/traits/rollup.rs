@@ -0,0 +1,32 @@
+//! # Rollup Client
+//!
+//! Verifiers sign a withdrawal only after it is made and finalized on the
+//! rollup and operator follows each deposit until it is minted on the rollup.
+//! [`RollupClient`] is how they get that evidence.
+
+use crate::errors::BridgeError;
+use crate::EVMAddress;
+use bitcoin::address::NetworkUnchecked;
+use bitcoin::{Address, OutPoint};
+use jsonrpsee::core::async_trait;
+
+#[async_trait]
+pub trait RollupClient: std::fmt::Debug + Send + Sync {
+    /// Checks if a withdrawal with given index, address and amount in sats
+    /// exists and is finalized on the rollup.
+    async fn is_withdrawal_finalized(
+        &self,
+        idx: usize,
+        withdrawal_address: &Address<NetworkUnchecked>,
+        amount: u64,
+    ) -> Result<bool, BridgeError>;
+
+    /// Checks if the deposit that is moved to given bridge UTXO is minted on
+    /// the rollup, to given EVM address with given amount in sats.
+    async fn is_deposit_minted(
+        &self,
+        move_utxo: OutPoint,
+        evm_address: &EVMAddress,
+        amount: u64,
+    ) -> Result<bool, BridgeError>;
+}
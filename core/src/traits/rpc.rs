@@ -1,6 +1,21 @@
-use crate::{errors::BridgeError, operator::DepositPresigns, EVMAddress};
+use crate::database::common::{
+    DepositRecord, DepositRequest, DepositStatus, WithdrawalRecord, WithdrawalStatus,
+};
+use crate::lifecycle::LifecycleEvent;
+use crate::musig2::{MuSigAggNonce, MuSigPartialSignature, MuSigPubNonce};
+use crate::operator::{BatchWithdrawal, BridgeBalance, DepositPresigns};
+use crate::policy::{
+    AddressList, ApprovalStatus, DepositApproval, ListedAddress, PolicyAddress, PolicyUsage,
+    SigningPolicy,
+};
+use crate::proof_of_reserves::ReservesReport;
+use crate::rollup::{RollupDeposit, RollupWithdrawal};
+use crate::verifier::VerifierHealth;
+use crate::verifier_pool::VerifierStatus;
+use crate::{errors::BridgeError, EVMAddress};
 use bitcoin::address::NetworkUnchecked;
 use bitcoin::{Address, OutPoint, Txid};
+use jsonrpsee::core::SubscriptionResult;
 use jsonrpsee::proc_macros::rpc;
 use secp256k1::schnorr;
 
@@ -13,6 +28,7 @@ pub trait VerifierRpc {
         recovery_taproot_address: Address<NetworkUnchecked>,
         deposit_index: u32,
         evm_address: EVMAddress,
+        amount: u64,
         operator_address: Address<NetworkUnchecked>,
     ) -> Result<DepositPresigns, BridgeError>;
     #[method(name = "new_withdrawal")]
@@ -22,17 +38,168 @@ pub trait VerifierRpc {
         bridge_fund_txid: Txid,
         withdrawal_address: Address<NetworkUnchecked>,
     ) -> Result<schnorr::Signature, BridgeError>;
+
+    /// Returns verifier's MuSig2 public nonce for the move tx of the deposit.
+    #[method(name = "new_deposit_nonce")]
+    async fn new_deposit_nonce_rpc(
+        &self,
+        start_utxo: OutPoint,
+        recovery_taproot_address: Address<NetworkUnchecked>,
+        evm_address: EVMAddress,
+        amount: u64,
+    ) -> Result<MuSigPubNonce, BridgeError>;
+    /// Returns verifier's MuSig2 partial signature for the move tx of the
+    /// deposit. A nonce must be requested first.
+    #[method(name = "new_deposit_partial_sig")]
+    async fn new_deposit_partial_sig_rpc(
+        &self,
+        start_utxo: OutPoint,
+        recovery_taproot_address: Address<NetworkUnchecked>,
+        evm_address: EVMAddress,
+        amount: u64,
+        agg_nonce: MuSigAggNonce,
+    ) -> Result<MuSigPartialSignature, BridgeError>;
+
+    /// Returns verifier's MuSig2 public nonce for the withdrawal tx.
+    #[method(name = "new_withdrawal_nonce")]
+    async fn new_withdrawal_nonce_rpc(
+        &self,
+        withdrawal_idx: usize,
+        bridge_fund_txid: Txid,
+        withdrawal_address: Address<NetworkUnchecked>,
+    ) -> Result<MuSigPubNonce, BridgeError>;
+    /// Returns verifier's MuSig2 partial signature for the withdrawal tx. A
+    /// nonce must be requested first.
+    #[method(name = "new_withdrawal_partial_sig")]
+    async fn new_withdrawal_partial_sig_rpc(
+        &self,
+        withdrawal_idx: usize,
+        bridge_fund_txid: Txid,
+        withdrawal_address: Address<NetworkUnchecked>,
+        agg_nonce: MuSigAggNonce,
+    ) -> Result<MuSigPartialSignature, BridgeError>;
+
+    /// Returns verifier's signatures for every input of the batch move tx, in
+    /// the same order with the deposits.
+    #[method(name = "new_deposit_batch")]
+    async fn new_deposit_batch_rpc(
+        &self,
+        deposits: Vec<DepositRequest>,
+    ) -> Result<Vec<schnorr::Signature>, BridgeError>;
+    /// Returns verifier's MuSig2 public nonces for every input of the batch
+    /// move tx.
+    #[method(name = "new_deposit_batch_nonce")]
+    async fn new_deposit_batch_nonce_rpc(
+        &self,
+        deposits: Vec<DepositRequest>,
+    ) -> Result<Vec<MuSigPubNonce>, BridgeError>;
+    /// Returns verifier's MuSig2 partial signatures for every input of the
+    /// batch move tx. Nonces must be requested first.
+    #[method(name = "new_deposit_batch_partial_sig")]
+    async fn new_deposit_batch_partial_sig_rpc(
+        &self,
+        deposits: Vec<DepositRequest>,
+        agg_nonces: Vec<MuSigAggNonce>,
+    ) -> Result<Vec<MuSigPartialSignature>, BridgeError>;
+
+    /// Returns verifier's signatures for every input of the batch withdrawal
+    /// tx, in the same order with the withdrawals.
+    #[method(name = "new_withdrawal_batch")]
+    async fn new_withdrawal_batch_rpc(
+        &self,
+        withdrawals: Vec<BatchWithdrawal>,
+    ) -> Result<Vec<schnorr::Signature>, BridgeError>;
+    /// Returns verifier's MuSig2 public nonces for every input of the batch
+    /// withdrawal tx.
+    #[method(name = "new_withdrawal_batch_nonce")]
+    async fn new_withdrawal_batch_nonce_rpc(
+        &self,
+        withdrawals: Vec<BatchWithdrawal>,
+    ) -> Result<Vec<MuSigPubNonce>, BridgeError>;
+    /// Returns verifier's MuSig2 partial signatures for every input of the
+    /// batch withdrawal tx. Nonces must be requested first.
+    #[method(name = "new_withdrawal_batch_partial_sig")]
+    async fn new_withdrawal_batch_partial_sig_rpc(
+        &self,
+        withdrawals: Vec<BatchWithdrawal>,
+        agg_nonces: Vec<MuSigAggNonce>,
+    ) -> Result<Vec<MuSigPartialSignature>, BridgeError>;
+
+    /// Returns verifier's signature of the proof of reserves report, after
+    /// checking every listed UTXO.
+    #[method(name = "sign_reserves_report")]
+    async fn sign_reserves_report_rpc(
+        &self,
+        report: ReservesReport,
+    ) -> Result<schnorr::Signature, BridgeError>;
+
+    /// Returns the health of the verifier.
+    #[method(name = "health")]
+    async fn health_rpc(&self) -> Result<VerifierHealth, BridgeError>;
+}
+
+/// Management of a verifier's signing policy. Served only on localhost, for
+/// the verifier's own admins.
+#[rpc(client, server, namespace = "admin")]
+pub trait VerifierAdminRpc {
+    #[method(name = "get_policy")]
+    async fn get_policy_rpc(&self) -> Result<SigningPolicy, BridgeError>;
+
+    /// Replaces the limits of the signing policy.
+    #[method(name = "set_policy")]
+    async fn set_policy_rpc(&self, policy: SigningPolicy) -> Result<(), BridgeError>;
+
+    /// Returns the current usage of the policy limits.
+    #[method(name = "policy_usage")]
+    async fn policy_usage_rpc(&self) -> Result<PolicyUsage, BridgeError>;
+
+    /// Returns every address in the allow and deny lists.
+    #[method(name = "list_addresses")]
+    async fn list_addresses_rpc(&self) -> Result<Vec<ListedAddress>, BridgeError>;
+
+    #[method(name = "add_address")]
+    async fn add_address_rpc(
+        &self,
+        list: AddressList,
+        address: PolicyAddress,
+    ) -> Result<(), BridgeError>;
+
+    /// Returns false if the address is not in the list.
+    #[method(name = "remove_address")]
+    async fn remove_address_rpc(
+        &self,
+        list: AddressList,
+        address: PolicyAddress,
+    ) -> Result<bool, BridgeError>;
+
+    /// Returns deposits in the approval queue with given status, or all of
+    /// them if status is not given.
+    #[method(name = "list_approvals")]
+    async fn list_approvals_rpc(
+        &self,
+        status: Option<ApprovalStatus>,
+    ) -> Result<Vec<DepositApproval>, BridgeError>;
+
+    /// Approves a pending deposit. It is signed when operator retries it.
+    #[method(name = "approve_deposit")]
+    async fn approve_deposit_rpc(&self, start_utxo: OutPoint) -> Result<(), BridgeError>;
+
+    #[method(name = "reject_deposit")]
+    async fn reject_deposit_rpc(&self, start_utxo: OutPoint) -> Result<(), BridgeError>;
 }
 
 #[rpc(client, server, namespace = "operator")]
 pub trait OperatorRpc {
+    /// Returns the move txid of the deposit, or nothing if the deposit is
+    /// queued to be moved in a batch.
     #[method(name = "new_deposit")]
     async fn new_deposit_rpc(
         &self,
         start_utxo: OutPoint,
         recovery_taproot_address: Address<NetworkUnchecked>,
         evm_address: EVMAddress,
-    ) -> Result<Txid, BridgeError>;
+        amount: u64,
+    ) -> Result<Option<Txid>, BridgeError>;
 
     #[method(name = "new_withdrawal")]
     async fn new_withdrawal_direct_rpc(
@@ -40,4 +207,90 @@ pub trait OperatorRpc {
         idx: usize,
         withdrawal_address: Address<NetworkUnchecked>,
     ) -> Result<Txid, BridgeError>;
+
+    /// Pays every given withdrawal in a single tx.
+    #[method(name = "new_withdrawal_batch")]
+    async fn new_withdrawal_batch_rpc(
+        &self,
+        withdrawals: Vec<(usize, Address<NetworkUnchecked>)>,
+    ) -> Result<Txid, BridgeError>;
+
+    /// Returns how far the deposit with given deposit UTXO has progressed,
+    /// up to being minted on the rollup.
+    #[method(name = "deposit_status")]
+    async fn deposit_status_rpc(&self, start_utxo: OutPoint) -> Result<DepositStatus, BridgeError>;
+
+    /// Returns deposits in the order they are requested, skipping the first
+    /// `offset` of them. Deposits are filtered by EVM address and status, if
+    /// those are given. At most [`crate::operator::MAX_QUERY_LIMIT`] deposits
+    /// are returned.
+    #[method(name = "list_deposits")]
+    async fn list_deposits_rpc(
+        &self,
+        evm_address: Option<EVMAddress>,
+        status: Option<DepositStatus>,
+        offset: u64,
+        limit: u64,
+    ) -> Result<Vec<DepositRecord>, BridgeError>;
+
+    /// Returns the deposit with given deposit UTXO.
+    #[method(name = "get_deposit")]
+    async fn get_deposit_rpc(&self, start_utxo: OutPoint) -> Result<DepositRecord, BridgeError>;
+
+    /// Returns every deposit that is moved with given move tx.
+    #[method(name = "get_deposits_by_move_txid")]
+    async fn get_deposits_by_move_txid_rpc(
+        &self,
+        move_txid: Txid,
+    ) -> Result<Vec<DepositRecord>, BridgeError>;
+
+    /// Returns withdrawals in the order of their indexes, skipping the first
+    /// `offset` of them. Withdrawals are filtered by status, if it is given.
+    /// At most [`crate::operator::MAX_QUERY_LIMIT`] withdrawals are returned.
+    #[method(name = "list_withdrawals")]
+    async fn list_withdrawals_rpc(
+        &self,
+        status: Option<WithdrawalStatus>,
+        offset: u64,
+        limit: u64,
+    ) -> Result<Vec<WithdrawalRecord>, BridgeError>;
+
+    /// Returns the amount that is locked in unspent bridge UTXOs.
+    #[method(name = "bridge_balance")]
+    async fn bridge_balance_rpc(&self) -> Result<BridgeBalance, BridgeError>;
+
+    /// Returns the status of every verifier, as of operator's last health
+    /// check.
+    #[method(name = "verifier_status")]
+    async fn verifier_status_rpc(&self) -> Result<Vec<VerifierStatus>, BridgeError>;
+
+    /// Streams the state of the deposit with given deposit UTXO and every
+    /// change to it, until it is minted. If operator doesn't check mints,
+    /// stream ends when the move tx is finalized. Needs a WebSocket
+    /// connection.
+    #[subscription(name = "subscribe_deposit", unsubscribe = "unsubscribe_deposit", item = LifecycleEvent)]
+    async fn subscribe_deposit(&self, start_utxo: OutPoint) -> SubscriptionResult;
+
+    /// Streams the state of the withdrawal with given index and every change
+    /// to it, until the withdrawal tx is finalized. Needs a WebSocket
+    /// connection.
+    #[subscription(name = "subscribe_withdrawal", unsubscribe = "unsubscribe_withdrawal", item = LifecycleEvent)]
+    async fn subscribe_withdrawal(&self, idx: usize) -> SubscriptionResult;
+}
+
+/// Interface of a rollup node, for reading deposit and withdrawal events.
+#[rpc(client, server, namespace = "rollup")]
+pub trait RollupRpc {
+    /// Returns the withdrawal with given index, if it is made on the rollup.
+    #[method(name = "get_withdrawal")]
+    async fn get_withdrawal_rpc(&self, idx: usize)
+        -> Result<Option<RollupWithdrawal>, BridgeError>;
+
+    /// Returns the mint of the deposit that is moved to given bridge UTXO, if
+    /// it is made on the rollup.
+    #[method(name = "get_deposit")]
+    async fn get_deposit_rpc(
+        &self,
+        move_utxo: OutPoint,
+    ) -> Result<Option<RollupDeposit>, BridgeError>;
 }
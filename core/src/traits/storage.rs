@@ -0,0 +1,401 @@
+//! # Storage
+//!
+//! Operator and verifier keep their state in a [`Storage`], which is selected
+//! with `db_backend` in the configuration. Every backend builds the same
+//! schema with its own migrations and gives the same results for the same
+//! calls.
+
+use crate::database::common::{
+    DepositRecord, DepositRequest, DepositStatus, IndexedBlock, MoveTxRecord,
+    WithdrawalLedgerEntry, WithdrawalRecord, WithdrawalStatus,
+};
+use crate::database::migrations::{Migration, Role};
+use crate::errors::BridgeError;
+use crate::policy::{ApprovalStatus, DepositApproval, ListedAddress, SignedDeposit, SigningPolicy};
+use crate::EVMAddress;
+use bitcoin::address::NetworkUnchecked;
+use bitcoin::{Address, Amount, BlockHash, OutPoint, ScriptBuf, Transaction, Txid};
+use jsonrpsee::core::async_trait;
+use std::collections::HashSet;
+
+/// A transaction that is started with [`Storage::begin_transaction`]. Its
+/// [`Storage`] calls run in the transaction and other calls don't see their
+/// changes until it is committed.
+#[async_trait]
+pub trait StorageTransaction: Storage {
+    /// Commits every operation done in the transaction.
+    async fn commit(self: Box<Self>) -> Result<(), BridgeError>;
+
+    /// Rolls back every operation done in the transaction. Dropping a
+    /// transaction without committing it does the same.
+    async fn rollback(self: Box<Self>) -> Result<(), BridgeError>;
+}
+
+#[async_trait]
+pub trait Storage: std::fmt::Debug + Send + Sync {
+    /// Closes database connection.
+    async fn close(&self);
+
+    /// Runs given SQL file to database. Database connection must be established
+    /// before calling this function.
+    async fn run_sql_file(&self, sql_file: &str) -> Result<(), BridgeError>;
+
+    /// Returns migrations of `roles` that are not applied yet.
+    async fn get_pending_migrations(
+        &self,
+        roles: &[Role],
+    ) -> Result<Vec<&'static Migration>, BridgeError>;
+
+    /// Applies pending migrations of `roles` in a single transaction and
+    /// returns them. Concurrent calls wait for each other.
+    async fn migrate(&self, roles: &[Role]) -> Result<Vec<&'static Migration>, BridgeError>;
+
+    /// Starts a database transaction. Transactions can't be nested.
+    ///
+    /// Only calls on the return value are done in the transaction. If it is
+    /// not committed, database will rollback every one of them.
+    async fn begin_transaction(&self) -> Result<Box<dyn StorageTransaction>, BridgeError>;
+
+    /// Adds a validated deposit request to the batch queue. Adding an already
+    /// queued deposit is a no-op.
+    async fn add_new_deposit_request(
+        &self,
+        start_utxo: OutPoint,
+        recovery_taproot_address: Address<NetworkUnchecked>,
+        evm_address: EVMAddress,
+        amount: u64,
+    ) -> Result<(), BridgeError>;
+
+    /// Returns at most `limit` queued deposit requests, oldest first.
+    async fn get_new_deposit_requests(
+        &self,
+        limit: usize,
+    ) -> Result<Vec<DepositRequest>, BridgeError>;
+
+    async fn get_new_deposit_request_count(&self) -> Result<usize, BridgeError>;
+
+    async fn remove_new_deposit_request(&self, start_utxo: OutPoint) -> Result<(), BridgeError>;
+
+    async fn get_deposit_tx(&self, idx: usize) -> Result<Txid, BridgeError>;
+
+    /// Returns the bridge UTXO of the deposit with given index.
+    async fn get_bridge_utxo(&self, idx: usize) -> Result<OutPoint, BridgeError>;
+
+    async fn get_next_deposit_index(&self) -> Result<usize, BridgeError>;
+
+    async fn insert_move_txid(
+        &self,
+        start_utxo: OutPoint,
+        recovery_taproot_address: Address<NetworkUnchecked>,
+        evm_address: EVMAddress,
+        move_txid: Txid,
+        move_vout: u32,
+    ) -> Result<(), BridgeError>;
+
+    async fn get_move_txid(
+        &self,
+        start_utxo: OutPoint,
+        recovery_taproot_address: Address<NetworkUnchecked>,
+        evm_address: EVMAddress,
+    ) -> Result<Txid, BridgeError>;
+
+    /// Returns every saved move tx, in the order of deposit indexes.
+    async fn get_move_txs(&self) -> Result<Vec<MoveTxRecord>, BridgeError>;
+
+    async fn save_withdrawal_sig(
+        &self,
+        idx: usize,
+        bridge_fund_txid: Txid,
+        sig: secp256k1::schnorr::Signature,
+    ) -> Result<(), BridgeError>;
+
+    async fn get_withdrawal_sig_by_idx(
+        &self,
+        idx: usize,
+    ) -> Result<(Txid, secp256k1::schnorr::Signature), BridgeError>;
+
+    /// Returns every saved withdrawal signature with its index and bridge
+    /// funds, in the order of indexes.
+    async fn get_withdrawal_sigs(
+        &self,
+    ) -> Result<Vec<(usize, Txid, secp256k1::schnorr::Signature)>, BridgeError>;
+
+    /// Binds a withdrawal index to the bridge funds it spends, before a MuSig2
+    /// partial signature is given for it. Binding an already bound index is a
+    /// no-op.
+    async fn save_musig2_withdrawal(
+        &self,
+        idx: usize,
+        bridge_fund_txid: Txid,
+    ) -> Result<(), BridgeError>;
+
+    async fn get_musig2_withdrawal_txid(&self, idx: usize) -> Result<Txid, BridgeError>;
+
+    /// Returns every MuSig2 withdrawal index with its bridge funds, in the
+    /// order of indexes.
+    async fn get_musig2_withdrawals(&self) -> Result<Vec<(usize, Txid)>, BridgeError>;
+
+    /// Records a new deposit request in the lifecycle table. Recording an
+    /// already known deposit is a no-op.
+    async fn start_deposit(
+        &self,
+        start_utxo: OutPoint,
+        recovery_taproot_address: Address<NetworkUnchecked>,
+        evm_address: EVMAddress,
+        amount: u64,
+    ) -> Result<(), BridgeError>;
+
+    /// Saves the fully signed move tx of a deposit, so that it can be
+    /// rebroadcasted without asking verifiers again.
+    async fn set_deposit_presigned(
+        &self,
+        start_utxo: OutPoint,
+        move_tx: &Transaction,
+    ) -> Result<(), BridgeError>;
+
+    /// Saves the fully signed move tx of a batch for every deposit in it and
+    /// removes them from the batch queue.
+    async fn set_deposits_presigned(
+        &self,
+        start_utxos: &[OutPoint],
+        move_tx: &Transaction,
+    ) -> Result<(), BridgeError>;
+
+    async fn set_deposit_status(
+        &self,
+        start_utxo: OutPoint,
+        status: DepositStatus,
+    ) -> Result<(), BridgeError>;
+
+    async fn get_deposit_record(&self, start_utxo: OutPoint) -> Result<DepositRecord, BridgeError>;
+
+    /// Inserts a deposit record with every field as it is, for importing
+    /// snapshots. Fails if the deposit is already known.
+    async fn restore_deposit_record(&self, record: &DepositRecord) -> Result<(), BridgeError>;
+
+    /// Returns deposits that have their move tx confirmed, including the
+    /// minted ones.
+    async fn get_moved_deposits(&self) -> Result<Vec<DepositRecord>, BridgeError>;
+
+    /// Returns deposits in the order they are requested, skipping the first
+    /// `offset` of them. Deposits are filtered by EVM address and status, if
+    /// those are given.
+    async fn list_deposit_records(
+        &self,
+        evm_address: Option<EVMAddress>,
+        status: Option<DepositStatus>,
+        offset: u64,
+        limit: u64,
+    ) -> Result<Vec<DepositRecord>, BridgeError>;
+
+    /// Returns every deposit that is moved with given move tx. Batch move txs
+    /// have more than one.
+    async fn get_deposit_records_by_move_txid(
+        &self,
+        move_txid: Txid,
+    ) -> Result<Vec<DepositRecord>, BridgeError>;
+
+    /// Returns deposits that are not confirmed yet and weren't updated in the
+    /// last `idle_secs` seconds.
+    async fn get_unfinished_deposits(
+        &self,
+        idle_secs: u64,
+    ) -> Result<Vec<DepositRecord>, BridgeError>;
+
+    /// Returns deposits that are confirmed but not minted yet and weren't
+    /// updated in the last `idle_secs` seconds.
+    async fn get_unminted_deposits(
+        &self,
+        idle_secs: u64,
+    ) -> Result<Vec<DepositRecord>, BridgeError>;
+
+    async fn set_deposit_block_hash(
+        &self,
+        start_utxo: OutPoint,
+        block_hash: BlockHash,
+    ) -> Result<(), BridgeError>;
+
+    /// Marks a deposit's move tx as confirmed in given block.
+    async fn set_deposit_confirmed(
+        &self,
+        start_utxo: OutPoint,
+        move_block_hash: BlockHash,
+    ) -> Result<(), BridgeError>;
+
+    /// Rolls back deposits that depend on given block, after it left the best
+    /// chain. Confirmed or minted move txs are marked as broadcasted again.
+    /// Every affected deposit is flagged as reorged.
+    ///
+    /// Returns start UTXOs of the affected deposits.
+    async fn rollback_deposits_in_block(
+        &self,
+        block_hash: BlockHash,
+    ) -> Result<Vec<OutPoint>, BridgeError>;
+
+    /// Saves a broadcasted withdrawal tx. Saving an already saved withdrawal
+    /// index is a no-op.
+    async fn save_withdrawal_tx(
+        &self,
+        idx: usize,
+        bridge_fund_txid: Txid,
+        withdrawal_address: Address<NetworkUnchecked>,
+        withdrawal_tx: &Transaction,
+    ) -> Result<(), BridgeError>;
+
+    async fn get_withdrawal_record(&self, idx: usize) -> Result<WithdrawalRecord, BridgeError>;
+
+    /// Inserts a withdrawal record with every field as it is, for importing
+    /// snapshots. Fails if the withdrawal index is already saved.
+    async fn restore_withdrawal_record(&self, record: &WithdrawalRecord)
+        -> Result<(), BridgeError>;
+
+    /// Returns withdrawals that have their withdrawal tx confirmed.
+    async fn get_confirmed_withdrawals(&self) -> Result<Vec<WithdrawalRecord>, BridgeError>;
+
+    /// Returns withdrawals in the order of their indexes, skipping the first
+    /// `offset` of them. Withdrawals are filtered by status, if it is given.
+    async fn list_withdrawal_records(
+        &self,
+        status: Option<WithdrawalStatus>,
+        offset: u64,
+        limit: u64,
+    ) -> Result<Vec<WithdrawalRecord>, BridgeError>;
+
+    /// Returns the number of deposits that don't have their move tx confirmed
+    /// and the number of withdrawals that don't have their withdrawal tx
+    /// confirmed.
+    async fn get_pending_record_counts(&self) -> Result<(usize, usize), BridgeError>;
+
+    /// Returns withdrawals that are not confirmed yet and weren't updated in
+    /// the last `idle_secs` seconds.
+    async fn get_unconfirmed_withdrawals(
+        &self,
+        idle_secs: u64,
+    ) -> Result<Vec<WithdrawalRecord>, BridgeError>;
+
+    /// Marks a withdrawal tx as confirmed in given block.
+    async fn set_withdrawal_confirmed(
+        &self,
+        idx: usize,
+        block_hash: BlockHash,
+    ) -> Result<(), BridgeError>;
+
+    /// Marks withdrawals that are confirmed in given block as broadcasted
+    /// again, after the block left the best chain. Every affected withdrawal
+    /// is flagged as reorged.
+    ///
+    /// Returns indexes of the affected withdrawals.
+    async fn rollback_withdrawals_in_block(
+        &self,
+        block_hash: BlockHash,
+    ) -> Result<Vec<usize>, BridgeError>;
+
+    /// Returns every block hash that a deposit or withdrawal record depends
+    /// on.
+    async fn get_record_block_hashes(&self) -> Result<HashSet<BlockHash>, BridgeError>;
+
+    /// Returns height and hash of the highest indexed block, if there is any.
+    async fn get_last_indexed_block(&self) -> Result<Option<(u64, BlockHash)>, BridgeError>;
+
+    /// Indexes a block and everything found in it, atomically.
+    async fn connect_block(&self, block: &IndexedBlock) -> Result<(), BridgeError>;
+
+    /// Removes a block that left the best chain from the index, with every
+    /// UTXO created or spent in it.
+    async fn disconnect_block(&self, block_hash: BlockHash) -> Result<(), BridgeError>;
+
+    /// Adds a deposit script to watch for. Adding an already watched script is
+    /// a no-op.
+    async fn add_watched_deposit_script(
+        &self,
+        script_pubkey: &ScriptBuf,
+    ) -> Result<(), BridgeError>;
+
+    async fn get_watched_deposit_scripts(&self) -> Result<HashSet<ScriptBuf>, BridgeError>;
+
+    async fn get_unspent_bridge_utxos(&self) -> Result<Vec<(OutPoint, Amount)>, BridgeError>;
+
+    /// Returns the total amount and the number of unspent bridge UTXOs.
+    async fn get_bridge_balance(&self) -> Result<(Amount, usize), BridgeError>;
+
+    /// Checks if a withdrawal that spends given bridge UTXO is signed.
+    /// Signatures that are not in the withdrawal ledger spend the first
+    /// output of the move tx.
+    async fn is_withdrawal_signed(&self, bridge_utxo: OutPoint) -> Result<bool, BridgeError>;
+
+    /// Returns the signing policy of the verifier, which is the default one if
+    /// it is never set.
+    async fn get_signing_policy(&self) -> Result<SigningPolicy, BridgeError>;
+
+    async fn set_signing_policy(&self, policy: &SigningPolicy) -> Result<(), BridgeError>;
+
+    /// Returns every address in the allow and deny lists of the policy.
+    async fn get_policy_addresses(&self) -> Result<Vec<ListedAddress>, BridgeError>;
+
+    /// Adds an address to a policy list. Adding an already listed address is
+    /// a no-op.
+    async fn add_policy_address(&self, listed: &ListedAddress) -> Result<(), BridgeError>;
+
+    /// Removes an address from a policy list. Returns false if it is not
+    /// listed.
+    async fn remove_policy_address(&self, listed: &ListedAddress) -> Result<bool, BridgeError>;
+
+    /// Saves deposits that are signed by the verifier, for daily limits.
+    /// Deposits that are already saved keep their first signing time.
+    async fn add_signed_deposits(&self, deposits: &[SignedDeposit]) -> Result<(), BridgeError>;
+
+    /// Returns deposits that are signed in the last `window_secs` seconds.
+    async fn get_signed_deposits(
+        &self,
+        window_secs: u64,
+    ) -> Result<Vec<SignedDeposit>, BridgeError>;
+
+    /// Adds a deposit to the approval queue, as pending. Adding an already
+    /// queued deposit is a no-op.
+    async fn add_deposit_approval(&self, deposit: &DepositRequest) -> Result<(), BridgeError>;
+
+    /// Returns the approval status of a deposit, or nothing if it is not in
+    /// the approval queue.
+    async fn get_deposit_approval_status(
+        &self,
+        start_utxo: OutPoint,
+    ) -> Result<Option<ApprovalStatus>, BridgeError>;
+
+    /// Approves or rejects a pending deposit.
+    async fn set_deposit_approval_status(
+        &self,
+        start_utxo: OutPoint,
+        status: ApprovalStatus,
+    ) -> Result<(), BridgeError>;
+
+    /// Returns deposits in the approval queue with given status, or all of
+    /// them if status is not given, oldest first.
+    async fn list_deposit_approvals(
+        &self,
+        status: Option<ApprovalStatus>,
+    ) -> Result<Vec<DepositApproval>, BridgeError>;
+
+    /// Checks if the verifier signed a withdrawal with given index.
+    async fn is_withdrawal_idx_signed(&self, idx: usize) -> Result<bool, BridgeError>;
+
+    /// Returns the number of withdrawals that are signed by the verifier but
+    /// didn't spend their bridge funds yet.
+    async fn get_outstanding_withdrawal_count(&self) -> Result<usize, BridgeError>;
+
+    /// Adds a withdrawal to the ledger, unless its index or bridge UTXO is
+    /// already in it.
+    async fn add_withdrawal_ledger_entry(
+        &self,
+        entry: &WithdrawalLedgerEntry,
+    ) -> Result<(), BridgeError>;
+
+    /// Returns withdrawals in the ledger that have given index or bridge UTXO.
+    async fn get_withdrawal_ledger_entries(
+        &self,
+        idx: usize,
+        bridge_utxo: OutPoint,
+    ) -> Result<Vec<WithdrawalLedgerEntry>, BridgeError>;
+
+    /// Returns every withdrawal in the ledger, in the order of their indexes.
+    async fn get_withdrawal_ledger(&self) -> Result<Vec<WithdrawalLedgerEntry>, BridgeError>;
+}
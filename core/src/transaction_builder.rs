@@ -1,12 +1,15 @@
 //! # Transaction Builder
 
+use crate::config::BridgeConfig;
 use crate::errors::BridgeError;
+use crate::fee;
+use crate::musig2::KeyAggContext;
 use crate::{script_builder, utils, EVMAddress};
 use bitcoin::address::NetworkUnchecked;
 use bitcoin::Network;
 use bitcoin::{
     absolute,
-    taproot::{TaprootBuilder, TaprootSpendInfo},
+    taproot::{LeafVersion, TaprootBuilder, TaprootSpendInfo},
     Address, Amount, OutPoint, ScriptBuf, TxIn, TxOut, Witness,
 };
 use clementine_circuits::constants::BRIDGE_AMOUNT_SATS;
@@ -26,17 +29,64 @@ pub type CreateAddressOutputs = (Address, TaprootSpendInfo);
 pub struct TransactionBuilder {
     verifiers_pks: Vec<XOnlyPublicKey>,
     network: Network,
+    /// If set, bridge and deposit scripts use this MuSig2 aggregated key
+    /// instead of N-of-N signatures.
+    musig2_aggregated_pk: Option<XOnlyPublicKey>,
 }
 
-pub const MOVE_TX_MIN_RELAY_FEE: u64 = 305;
-pub const WITHDRAWAL_TX_MIN_RELAY_FEE: u64 = 305;
-
 impl TransactionBuilder {
     /// Creates a new `TransactionBuilder`.
     pub fn new(verifiers_pks: Vec<XOnlyPublicKey>, network: Network) -> Self {
         Self {
             verifiers_pks,
             network,
+            musig2_aggregated_pk: None,
+        }
+    }
+
+    /// Creates a new `TransactionBuilder` that locks bridge and deposit funds
+    /// to the MuSig2 aggregated key of `verifiers_pks`.
+    pub fn new_musig2(
+        verifiers_pks: Vec<XOnlyPublicKey>,
+        network: Network,
+    ) -> Result<Self, BridgeError> {
+        let key_agg_ctx = KeyAggContext::from_xonly(&verifiers_pks)?;
+
+        Ok(Self {
+            verifiers_pks,
+            network,
+            musig2_aggregated_pk: Some(key_agg_ctx.aggregated_xonly_public_key()),
+        })
+    }
+
+    /// Creates a new `TransactionBuilder` in the signing mode given by the
+    /// configuration.
+    pub fn from_config(config: &BridgeConfig) -> Result<Self, BridgeError> {
+        if config.musig2 {
+            TransactionBuilder::new_musig2(config.verifiers_public_keys.clone(), config.network)
+        } else {
+            Ok(TransactionBuilder::new(
+                config.verifiers_public_keys.clone(),
+                config.network,
+            ))
+        }
+    }
+
+    /// Script that spends bridge funds: either N-of-N or MuSig2.
+    pub fn bridge_spend_script(&self) -> ScriptBuf {
+        match &self.musig2_aggregated_pk {
+            Some(aggregated_pk) => script_builder::generate_script_musig2(aggregated_pk),
+            None => script_builder::generate_script_n_of_n(&self.verifiers_pks),
+        }
+    }
+
+    /// Script that moves deposit funds to the bridge: either N-of-N or MuSig2.
+    pub fn deposit_script(&self, evm_address: &EVMAddress, amount: u64) -> ScriptBuf {
+        match &self.musig2_aggregated_pk {
+            Some(aggregated_pk) => {
+                script_builder::create_musig2_deposit_script(aggregated_pk, evm_address, amount)
+            }
+            None => script_builder::create_deposit_script(&self.verifiers_pks, evm_address, amount),
         }
     }
 
@@ -49,8 +99,7 @@ impl TransactionBuilder {
         amount: u64,
         user_takes_after: u32,
     ) -> Result<CreateAddressOutputs, BridgeError> {
-        let deposit_script =
-            script_builder::create_deposit_script(&self.verifiers_pks, user_evm_address, amount);
+        let deposit_script = self.deposit_script(user_evm_address, amount);
 
         let script_timelock =
             script_builder::generate_timelock_script(recovery_taproot_address, user_takes_after);
@@ -70,11 +119,12 @@ impl TransactionBuilder {
         Ok((address, tree_info))
     }
 
-    /// Generates bridge address. N-of-N script can be used to spend the funds.
+    /// Generates bridge address. N-of-N or MuSig2 script can be used to spend
+    /// the funds.
     pub fn generate_bridge_address(&self) -> Result<CreateAddressOutputs, BridgeError> {
-        let script_n_of_n = script_builder::generate_script_n_of_n(&self.verifiers_pks);
+        let bridge_spend_script = self.bridge_spend_script();
 
-        let taproot = TaprootBuilder::new().add_leaf(0, script_n_of_n.clone())?;
+        let taproot = TaprootBuilder::new().add_leaf(0, bridge_spend_script)?;
         let tree_info = taproot.finalize(&utils::SECP, *utils::UNSPENDABLE_XONLY_PUBKEY)?;
 
         let address = Address::p2tr(
@@ -87,6 +137,123 @@ impl TransactionBuilder {
         Ok((address, tree_info))
     }
 
+    /// Number of signatures needed to spend bridge or deposit funds.
+    fn num_signatures(&self) -> usize {
+        match self.musig2_aggregated_pk {
+            Some(_) => 1,
+            None => self.verifiers_pks.len(),
+        }
+    }
+
+    /// Calculates the fee of a presigned tx, that spends every input with the
+    /// first of its scripts. Witnesses are filled with dummy signatures of the
+    /// same size, so the tx doesn't need to be signed.
+    fn presigned_tx_fee(&self, tx: &CreateTxOutputs) -> Result<Amount, BridgeError> {
+        let mut dummy_tx = tx.tx.clone();
+
+        for (i, input) in dummy_tx.input.iter_mut().enumerate() {
+            let script = &tx.scripts[i][0];
+            let control_block = tx.taproot_spend_infos[i]
+                .control_block(&(script.clone(), LeafVersion::TapScript))
+                .ok_or(BridgeError::ControlBlockError)?;
+
+            let mut witness = Witness::new();
+            for _ in 0..self.num_signatures() {
+                witness.push([0u8; 64]);
+            }
+            witness.push(script);
+            witness.push(control_block.serialize());
+
+            input.witness = witness;
+        }
+
+        fee::fee_for_vsize(fee::PRESIGNED_TX_FEE_RATE, dummy_tx.vsize() as u64)
+    }
+
+    /// Fee of a move tx of `num_deposits` deposits. It doesn't depend on the
+    /// deposits, because every deposit script has the same size, whatever the
+    /// amount is.
+    pub fn move_tx_fee(&self, num_deposits: usize) -> Result<Amount, BridgeError> {
+        let recovery_taproot_address = Address::p2tr(
+            &utils::SECP,
+            *utils::UNSPENDABLE_XONLY_PUBKEY,
+            None,
+            self.network,
+        );
+
+        let move_tx = self.create_zero_value_batch_move_tx(
+            &vec![
+                (
+                    OutPoint::null(),
+                    EVMAddress([0u8; 20]),
+                    recovery_taproot_address.as_unchecked().clone(),
+                    BRIDGE_AMOUNT_SATS,
+                );
+                num_deposits
+            ],
+            0,
+        )?;
+
+        self.presigned_tx_fee(&move_tx)
+    }
+
+    /// Part of the move tx fee and its anyone-can-spend output that every
+    /// deposit of a move tx with given fee pays.
+    fn move_tx_fee_share(move_tx_fee: Amount, num_deposits: usize) -> Amount {
+        let total_fee = move_tx_fee + script_builder::anyone_can_spend_txout().value;
+
+        Amount::from_sat(total_fee.to_sat().div_ceil(num_deposits as u64))
+    }
+
+    /// Bridge output of a deposit of `amount` sats in a move tx of
+    /// `num_deposits` deposits, which is the input of a withdrawal tx.
+    pub fn bridge_txout(&self, amount: u64, num_deposits: usize) -> Result<TxOut, BridgeError> {
+        let (bridge_address, _) = self.generate_bridge_address()?;
+        let fee_share = Self::move_tx_fee_share(self.move_tx_fee(num_deposits)?, num_deposits);
+
+        Ok(TxOut {
+            value: Amount::from_sat(amount)
+                .checked_sub(fee_share)
+                .ok_or(BridgeError::InvalidDepositAmount(amount))?,
+            script_pubkey: bridge_address.script_pubkey(),
+        })
+    }
+
+    /// Returns the deposit amount that given bridge output of a move tx of
+    /// `num_deposits` deposits is created for, if it is one of
+    /// `deposit_denominations`.
+    pub fn bridge_txout_amount(
+        &self,
+        bridge_txout: &TxOut,
+        num_deposits: usize,
+        deposit_denominations: &[u64],
+    ) -> Result<u64, BridgeError> {
+        for amount in deposit_denominations {
+            if self.bridge_txout(*amount, num_deposits)? == *bridge_txout {
+                return Ok(*amount);
+            }
+        }
+
+        Err(BridgeError::InvalidBridgeUTXO)
+    }
+
+    /// Returns the index of the bridge output of a deposit in its move tx.
+    pub fn move_vout(
+        start_utxo: OutPoint,
+        move_tx: &bitcoin::Transaction,
+    ) -> Result<u32, BridgeError> {
+        // Bridge output of a deposit has the same index with its input.
+        move_tx
+            .input
+            .iter()
+            .position(|input| input.previous_output == start_utxo)
+            .map(|vout| vout as u32)
+            .ok_or(BridgeError::InvalidBatch(format!(
+                "Move tx doesn't spend deposit {}",
+                start_utxo
+            )))
+    }
+
     /// Creates the move tx, it's prevouts for signing and the script to be used
     /// for the signature.
     pub fn create_move_tx(
@@ -95,77 +262,169 @@ impl TransactionBuilder {
         evm_address: &EVMAddress,
         recovery_taproot_address: &Address<NetworkUnchecked>,
         deposit_user_takes_after: u32,
+        amount: u64,
     ) -> Result<CreateTxOutputs, BridgeError> {
+        self.create_batch_move_tx(
+            &[(
+                deposit_utxo,
+                *evm_address,
+                recovery_taproot_address.clone(),
+                amount,
+            )],
+            deposit_user_takes_after,
+        )
+    }
+
+    /// Creates a move tx that spends every given deposit UTXO. Output `i` is
+    /// the bridge output of input `i`, and fee of the tx is shared equally
+    /// between deposits.
+    ///
+    /// Deposits are given as deposit UTXO, EVM address, recovery taproot
+    /// address and amount.
+    pub fn create_batch_move_tx(
+        &self,
+        deposits: &[(OutPoint, EVMAddress, Address<NetworkUnchecked>, u64)],
+        deposit_user_takes_after: u32,
+    ) -> Result<CreateTxOutputs, BridgeError> {
+        let mut move_tx =
+            self.create_zero_value_batch_move_tx(deposits, deposit_user_takes_after)?;
+
+        let fee_share = Self::move_tx_fee_share(self.presigned_tx_fee(&move_tx)?, deposits.len());
+
+        for (output, (_, _, _, amount)) in move_tx.tx.output.iter_mut().zip(deposits) {
+            output.value = Amount::from_sat(*amount)
+                .checked_sub(fee_share)
+                .ok_or(BridgeError::InvalidDepositAmount(*amount))?;
+        }
+
+        Ok(move_tx)
+    }
+
+    /// Creates a move tx whose bridge outputs have no value yet, so that its
+    /// fee can be calculated.
+    fn create_zero_value_batch_move_tx(
+        &self,
+        deposits: &[(OutPoint, EVMAddress, Address<NetworkUnchecked>, u64)],
+        deposit_user_takes_after: u32,
+    ) -> Result<CreateTxOutputs, BridgeError> {
+        if deposits.is_empty() {
+            return Err(BridgeError::InvalidBatch(
+                "No deposits are given".to_string(),
+            ));
+        }
+
         let anyone_can_spend_txout = script_builder::anyone_can_spend_txout();
 
         let (bridge_address, _) = self.generate_bridge_address()?;
-        let (deposit_address, deposit_taproot_spend_info) = self.generate_deposit_address(
-            recovery_taproot_address,
-            evm_address,
-            BRIDGE_AMOUNT_SATS,
-            deposit_user_takes_after,
-        )?;
 
-        let tx_ins = TransactionBuilder::create_tx_ins(vec![deposit_utxo]);
-        let bridge_txout = TxOut {
-            value: Amount::from_sat(BRIDGE_AMOUNT_SATS)
-                - Amount::from_sat(MOVE_TX_MIN_RELAY_FEE)
-                - anyone_can_spend_txout.value,
-            script_pubkey: bridge_address.script_pubkey(),
-        };
-        let move_tx =
-            TransactionBuilder::create_btc_tx(tx_ins, vec![bridge_txout, anyone_can_spend_txout]);
+        let mut prevouts = Vec::new();
+        let mut scripts = Vec::new();
+        let mut taproot_spend_infos = Vec::new();
+        for (_, evm_address, recovery_taproot_address, amount) in deposits {
+            let (deposit_address, deposit_taproot_spend_info) = self.generate_deposit_address(
+                recovery_taproot_address,
+                evm_address,
+                *amount,
+                deposit_user_takes_after,
+            )?;
 
-        let prevouts = vec![TxOut {
-            script_pubkey: deposit_address.script_pubkey(),
-            value: Amount::from_sat(BRIDGE_AMOUNT_SATS),
-        }];
+            prevouts.push(TxOut {
+                script_pubkey: deposit_address.script_pubkey(),
+                value: Amount::from_sat(*amount),
+            });
+            scripts.push(vec![self.deposit_script(evm_address, *amount)]);
+            taproot_spend_infos.push(deposit_taproot_spend_info);
+        }
 
-        let deposit_script = vec![script_builder::create_deposit_script(
-            &self.verifiers_pks,
-            evm_address,
-            BRIDGE_AMOUNT_SATS,
-        )];
+        let tx_ins = TransactionBuilder::create_tx_ins(
+            deposits.iter().map(|(utxo, _, _, _)| *utxo).collect(),
+        );
+        let mut tx_outs: Vec<TxOut> = deposits
+            .iter()
+            .map(|_| TxOut {
+                value: Amount::ZERO,
+                script_pubkey: bridge_address.script_pubkey(),
+            })
+            .collect();
+        tx_outs.push(anyone_can_spend_txout);
+
+        let move_tx = TransactionBuilder::create_btc_tx(tx_ins, tx_outs);
 
         Ok(CreateTxOutputs {
             tx: move_tx,
             prevouts,
-            scripts: vec![deposit_script],
-            taproot_spend_infos: vec![deposit_taproot_spend_info],
+            scripts,
+            taproot_spend_infos,
         })
     }
 
+    /// Creates the withdrawal tx. Its fee is calculated from its size, which
+    /// depends on the type of the withdrawal address.
     pub fn create_withdraw_tx(
         &self,
         deposit_utxo: OutPoint,
         deposit_txout: TxOut,
         withdraw_address: &Address,
     ) -> Result<CreateTxOutputs, BridgeError> {
+        self.create_batch_withdraw_tx(&[(deposit_utxo, deposit_txout, withdraw_address.clone())])
+    }
+
+    /// Creates a withdrawal tx that spends every given bridge UTXO to its
+    /// withdrawal address. Output `i` pays the withdrawal of input `i`, and
+    /// fee of the tx is shared equally between withdrawals.
+    pub fn create_batch_withdraw_tx(
+        &self,
+        withdrawals: &[(OutPoint, TxOut, Address)],
+    ) -> Result<CreateTxOutputs, BridgeError> {
+        if withdrawals.is_empty() {
+            return Err(BridgeError::InvalidBatch(
+                "No withdrawals are given".to_string(),
+            ));
+        }
+
         let anyone_can_spend_txout = script_builder::anyone_can_spend_txout();
 
         let (_, bridge_spend_info) = self.generate_bridge_address()?;
 
-        let tx_ins = TransactionBuilder::create_tx_ins(vec![deposit_utxo]);
-        let bridge_txout = TxOut {
-            value: deposit_txout.value
-                - Amount::from_sat(WITHDRAWAL_TX_MIN_RELAY_FEE)
-                - anyone_can_spend_txout.value,
-            script_pubkey: withdraw_address.script_pubkey(),
-        };
-
-        let withdraw_tx =
-            TransactionBuilder::create_btc_tx(tx_ins, vec![bridge_txout, anyone_can_spend_txout]);
+        let tx_ins = TransactionBuilder::create_tx_ins(
+            withdrawals.iter().map(|(utxo, _, _)| *utxo).collect(),
+        );
+        let mut tx_outs: Vec<TxOut> = withdrawals
+            .iter()
+            .map(|(_, _, withdraw_address)| TxOut {
+                value: Amount::ZERO,
+                script_pubkey: withdraw_address.script_pubkey(),
+            })
+            .collect();
+        tx_outs.push(anyone_can_spend_txout.clone());
 
-        let prevouts = vec![deposit_txout];
+        let withdraw_tx = TransactionBuilder::create_btc_tx(tx_ins, tx_outs);
 
-        let bridge_spend_script = vec![script_builder::generate_script_n_of_n(&self.verifiers_pks)];
+        let prevouts = withdrawals
+            .iter()
+            .map(|(_, txout, _)| txout.clone())
+            .collect();
 
-        Ok(CreateTxOutputs {
+        let mut withdraw_tx = CreateTxOutputs {
             tx: withdraw_tx,
             prevouts,
-            scripts: vec![bridge_spend_script],
-            taproot_spend_infos: vec![bridge_spend_info],
-        })
+            scripts: vec![vec![self.bridge_spend_script()]; withdrawals.len()],
+            taproot_spend_infos: vec![bridge_spend_info; withdrawals.len()],
+        };
+
+        let total_fee = self.presigned_tx_fee(&withdraw_tx)? + anyone_can_spend_txout.value;
+        let fee_share = Amount::from_sat(total_fee.to_sat().div_ceil(withdrawals.len() as u64));
+
+        for (output, (_, txout, _)) in withdraw_tx.tx.output.iter_mut().zip(withdrawals) {
+            output.value = txout
+                .value
+                .checked_sub(fee_share)
+                .ok_or(BridgeError::FeeError(
+                    "Bridge funds can't pay withdrawal fee".to_string(),
+                ))?;
+        }
+
+        Ok(withdraw_tx)
     }
 
     pub fn create_btc_tx(tx_ins: Vec<TxIn>, tx_outs: Vec<TxOut>) -> bitcoin::Transaction {
@@ -275,7 +534,8 @@ impl TransactionBuilder {
 #[cfg(test)]
 mod tests {
     use crate::{config::BridgeConfig, transaction_builder::TransactionBuilder};
-    use bitcoin::{Address, XOnlyPublicKey};
+    use bitcoin::hashes::Hash;
+    use bitcoin::{Address, Amount, XOnlyPublicKey};
     use std::str::FromStr;
 
     #[test]
@@ -326,6 +586,218 @@ mod tests {
             "bcrt1prqxsjz7h5wt40w54vhmpvn6l2hu8mefmez6ld4p59vksllumskvqs8wvkh" // check this later
         ) // Comparing it to the taproot address generated in bridge backend repo (using js)
     }
+
+    #[test]
+    fn musig2_bridge_address() {
+        let verifier_pks: Vec<XOnlyPublicKey> = [
+            "9bef8d556d80e43ae7e0becb3a7e6838b95defe45896ed6075bb9035d06c9964",
+            "e37d58a1aae4ba059fd2503712d998470d3a2522f7e2335f544ef384d2199e02",
+            "688466442a134ee312299bafb37058e385c98dd6005eaaf0f538f533efe5f91f",
+        ]
+        .iter()
+        .map(|pk| XOnlyPublicKey::from_str(pk).unwrap())
+        .collect();
+
+        let n_of_n = TransactionBuilder::new(verifier_pks.clone(), bitcoin::Network::Regtest);
+        let musig2 =
+            TransactionBuilder::new_musig2(verifier_pks, bitcoin::Network::Regtest).unwrap();
+
+        // MuSig2 bridge script is a single key and OP_CHECKSIG.
+        assert_eq!(musig2.bridge_spend_script().len(), 34);
+        assert!(musig2.bridge_spend_script().len() < n_of_n.bridge_spend_script().len());
+
+        assert_ne!(
+            musig2.generate_bridge_address().unwrap().0,
+            n_of_n.generate_bridge_address().unwrap().0
+        );
+    }
+
+    #[test]
+    fn deposit_denominations() {
+        let config = BridgeConfig::new();
+        let verifier_pks: Vec<XOnlyPublicKey> = [
+            "9bef8d556d80e43ae7e0becb3a7e6838b95defe45896ed6075bb9035d06c9964",
+            "e37d58a1aae4ba059fd2503712d998470d3a2522f7e2335f544ef384d2199e02",
+        ]
+        .iter()
+        .map(|pk| XOnlyPublicKey::from_str(pk).unwrap())
+        .collect();
+        let tx_builder = TransactionBuilder::new(verifier_pks, config.network);
+
+        let recovery_taproot_address = Address::p2tr(
+            &crate::utils::SECP,
+            *crate::utils::UNSPENDABLE_XONLY_PUBKEY,
+            None,
+            config.network,
+        );
+        let evm_address = crate::EVMAddress([1u8; 20]);
+
+        // Amount is committed in the deposit script.
+        let (big_deposit_address, _) = tx_builder
+            .generate_deposit_address(
+                recovery_taproot_address.as_unchecked(),
+                &evm_address,
+                100_000_000,
+                200,
+            )
+            .unwrap();
+        let (small_deposit_address, _) = tx_builder
+            .generate_deposit_address(
+                recovery_taproot_address.as_unchecked(),
+                &evm_address,
+                10_000_000,
+                200,
+            )
+            .unwrap();
+        assert_ne!(big_deposit_address, small_deposit_address);
+
+        // Move tx fee is the same for every amount.
+        let big_bridge_txout = tx_builder.bridge_txout(100_000_000, 1).unwrap();
+        let small_bridge_txout = tx_builder.bridge_txout(10_000_000, 1).unwrap();
+        assert_eq!(
+            big_bridge_txout.script_pubkey,
+            small_bridge_txout.script_pubkey
+        );
+        assert_eq!(
+            big_bridge_txout.value - small_bridge_txout.value,
+            bitcoin::Amount::from_sat(90_000_000)
+        );
+
+        assert!(tx_builder.bridge_txout(100, 1).is_err());
+    }
+
+    #[test]
+    fn batch_move_tx() {
+        let config = BridgeConfig::new();
+        let verifier_pks: Vec<XOnlyPublicKey> = [
+            "9bef8d556d80e43ae7e0becb3a7e6838b95defe45896ed6075bb9035d06c9964",
+            "e37d58a1aae4ba059fd2503712d998470d3a2522f7e2335f544ef384d2199e02",
+        ]
+        .iter()
+        .map(|pk| XOnlyPublicKey::from_str(pk).unwrap())
+        .collect();
+        let tx_builder = TransactionBuilder::new(verifier_pks, config.network);
+
+        let recovery_taproot_address = Address::p2tr(
+            &crate::utils::SECP,
+            *crate::utils::UNSPENDABLE_XONLY_PUBKEY,
+            None,
+            config.network,
+        );
+        let deposits: Vec<_> = [100_000_000, 10_000_000]
+            .iter()
+            .enumerate()
+            .map(|(i, amount)| {
+                (
+                    bitcoin::OutPoint {
+                        txid: bitcoin::Txid::all_zeros(),
+                        vout: i as u32,
+                    },
+                    crate::EVMAddress([i as u8; 20]),
+                    recovery_taproot_address.as_unchecked().clone(),
+                    *amount,
+                )
+            })
+            .collect();
+
+        let move_tx = tx_builder.create_batch_move_tx(&deposits, 200).unwrap();
+
+        assert_eq!(move_tx.tx.input.len(), 2);
+        assert_eq!(move_tx.tx.output.len(), 3);
+        assert_ne!(move_tx.scripts[0], move_tx.scripts[1]);
+
+        // Fee is shared, so every deposit pays less than it would alone.
+        for (i, (utxo, evm_address, recovery_taproot_address, amount)) in
+            deposits.iter().enumerate()
+        {
+            let single_tx = tx_builder
+                .create_move_tx(*utxo, evm_address, recovery_taproot_address, 200, *amount)
+                .unwrap();
+
+            assert_eq!(move_tx.tx.input[i].previous_output, *utxo);
+            assert_eq!(move_tx.prevouts[i], single_tx.prevouts[0]);
+            assert_eq!(
+                move_tx.tx.output[i].script_pubkey,
+                single_tx.tx.output[0].script_pubkey
+            );
+            assert!(move_tx.tx.output[i].value > single_tx.tx.output[0].value);
+            assert_eq!(
+                move_tx.tx.output[i],
+                tx_builder.bridge_txout(*amount, deposits.len()).unwrap()
+            );
+            assert_eq!(
+                tx_builder
+                    .bridge_txout_amount(
+                        &move_tx.tx.output[i],
+                        deposits.len(),
+                        &[10_000_000, 100_000_000]
+                    )
+                    .unwrap(),
+                *amount
+            );
+        }
+
+        // Deposits pay the fee of the tx for its size, rounded up to share it
+        // equally.
+        let fee = Amount::from_sat(110_000_000)
+            - move_tx.tx.output.iter().map(|output| output.value).sum();
+        let move_tx_fee = tx_builder.move_tx_fee(deposits.len()).unwrap();
+        assert!(fee >= move_tx_fee);
+        assert!(fee < move_tx_fee + Amount::from_sat(deposits.len() as u64));
+    }
+
+    #[test]
+    fn batch_withdraw_tx() {
+        let config = BridgeConfig::new();
+        let verifier_pks: Vec<XOnlyPublicKey> = [
+            "9bef8d556d80e43ae7e0becb3a7e6838b95defe45896ed6075bb9035d06c9964",
+            "e37d58a1aae4ba059fd2503712d998470d3a2522f7e2335f544ef384d2199e02",
+        ]
+        .iter()
+        .map(|pk| XOnlyPublicKey::from_str(pk).unwrap())
+        .collect();
+        let tx_builder = TransactionBuilder::new(verifier_pks, config.network);
+
+        let withdrawal_address = Address::p2tr(
+            &crate::utils::SECP,
+            *crate::utils::UNSPENDABLE_XONLY_PUBKEY,
+            None,
+            config.network,
+        );
+        let bridge_txout = tx_builder.bridge_txout(100_000_000, 1).unwrap();
+        let withdrawals: Vec<_> = (0..3)
+            .map(|i| {
+                (
+                    bitcoin::OutPoint {
+                        txid: bitcoin::Txid::all_zeros(),
+                        vout: i,
+                    },
+                    bridge_txout.clone(),
+                    withdrawal_address.clone(),
+                )
+            })
+            .collect();
+
+        let single_tx = tx_builder
+            .create_withdraw_tx(withdrawals[0].0, bridge_txout.clone(), &withdrawal_address)
+            .unwrap();
+        let batch_tx = tx_builder.create_batch_withdraw_tx(&withdrawals).unwrap();
+
+        assert_eq!(batch_tx.tx.input.len(), 3);
+        assert_eq!(batch_tx.tx.output.len(), 4);
+        assert_eq!(batch_tx.prevouts.len(), 3);
+        assert_eq!(batch_tx.scripts.len(), 3);
+        assert_eq!(batch_tx.taproot_spend_infos.len(), 3);
+
+        // Fee is shared, so every withdrawal pays less than it would alone.
+        for output in batch_tx.tx.output[..3].iter() {
+            assert_eq!(output.script_pubkey, withdrawal_address.script_pubkey());
+            assert_eq!(output.value, batch_tx.tx.output[0].value);
+            assert!(output.value > single_tx.tx.output[0].value);
+        }
+
+        assert!(tx_builder.create_batch_withdraw_tx(&[]).is_err());
+    }
 }
 
 #[cfg(feature = "poc")]
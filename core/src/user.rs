@@ -8,7 +8,6 @@ use bitcoin::Address;
 use bitcoin::OutPoint;
 use bitcoin::XOnlyPublicKey;
 use bitcoin_mock_rpc::RpcApiWrapper;
-use clementine_circuits::constants::BRIDGE_AMOUNT_SATS;
 use secp256k1::SecretKey;
 
 #[derive(Debug)]
@@ -24,6 +23,11 @@ where
     R: RpcApiWrapper,
 {
     /// Creates a new `User`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if MuSig2 mode is enabled and given public keys can't be
+    /// aggregated.
     pub fn new(
         rpc: ExtendedRpc<R>,
         all_xonly_pks: Vec<XOnlyPublicKey>,
@@ -32,7 +36,12 @@ where
     ) -> Self {
         let signer = Actor::new(sk, config.network);
 
-        let transaction_builder = TransactionBuilder::new(all_xonly_pks.clone(), config.network);
+        let transaction_builder = if config.musig2 {
+            TransactionBuilder::new_musig2(all_xonly_pks.clone(), config.network)
+                .unwrap_or_else(|e| panic!("Failed to aggregate public keys: {}", e))
+        } else {
+            TransactionBuilder::new(all_xonly_pks.clone(), config.network)
+        };
 
         User {
             rpc,
@@ -42,29 +51,29 @@ where
         }
     }
 
+    /// Deposits `amount` sats, which should be one of the deposit
+    /// denominations of the bridge.
     pub fn deposit_tx(
         &self,
         evm_address: EVMAddress,
+        amount: u64,
     ) -> Result<(OutPoint, XOnlyPublicKey, EVMAddress), BridgeError> {
-        let (deposit_address, _) = self.transaction_builder.generate_deposit_address(
-            self.signer.address.as_unchecked(),
-            &evm_address,
-            BRIDGE_AMOUNT_SATS,
-            self.user_takes_after,
-        )?;
+        let deposit_address = self.get_deposit_address(evm_address, amount)?;
 
-        let deposit_utxo = self
-            .rpc
-            .send_to_address(&deposit_address, BRIDGE_AMOUNT_SATS)?;
+        let deposit_utxo = self.rpc.send_to_address(&deposit_address, amount)?;
 
         Ok((deposit_utxo, self.signer.xonly_public_key, evm_address))
     }
 
-    pub fn get_deposit_address(&self, evm_address: EVMAddress) -> Result<Address, BridgeError> {
+    pub fn get_deposit_address(
+        &self,
+        evm_address: EVMAddress,
+        amount: u64,
+    ) -> Result<Address, BridgeError> {
         let (deposit_address, _) = self.transaction_builder.generate_deposit_address(
             self.signer.address.as_unchecked(),
             &evm_address,
-            BRIDGE_AMOUNT_SATS,
+            amount,
             self.user_takes_after,
         )?;
 
@@ -1,19 +1,59 @@
+use crate::chain_watcher::ChainEvent;
 use crate::config::BridgeConfig;
+use crate::database::common::{DepositRequest, WithdrawalLedgerEntry};
 use crate::database::verifier::VerifierDB;
 use crate::errors::BridgeError;
 use crate::extended_rpc::ExtendedRpc;
-use crate::traits::rpc::VerifierRpcServer;
-use crate::transaction_builder::TransactionBuilder;
-use crate::{actor::Actor, operator::DepositPresigns};
-use crate::{script_builder, EVMAddress};
+use crate::musig2::{
+    KeyAggContext, MuSigAggNonce, MuSigPartialSignature, MuSigPubNonce, MuSigSecNonce,
+};
+use crate::policy::{
+    self, AddressList, ApprovalStatus, DepositApproval, ListedAddress, PolicyAddress, PolicyUsage,
+    SignedDeposit, SigningPolicy,
+};
+use crate::proof_of_reserves::{self, ReservesReport};
+use crate::rollup;
+use crate::traits::rollup::RollupClient;
+use crate::traits::rpc::{VerifierAdminRpcServer, VerifierRpcServer};
+use crate::transaction_builder::{CreateTxOutputs, TransactionBuilder};
+use crate::EVMAddress;
+use crate::{
+    actor::Actor,
+    operator::{BatchWithdrawal, DepositPresigns},
+    utils::SECP,
+};
 use bitcoin::address::{NetworkChecked, NetworkUnchecked};
-use bitcoin::{secp256k1, secp256k1::Secp256k1, OutPoint};
-use bitcoin::{Address, Amount, Network, TxOut, Txid};
+use bitcoin::hashes::Hash;
+use bitcoin::secp256k1::Message;
+use bitcoin::{secp256k1, OutPoint};
+use bitcoin::{Address, Network, TapSighash, Txid};
 use bitcoin_mock_rpc::RpcApiWrapper;
-use clementine_circuits::constants::BRIDGE_AMOUNT_SATS;
 use jsonrpsee::core::async_trait;
 use secp256k1::schnorr;
 use secp256k1::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+/// Time that a MuSig2 secret nonce waits for its partial signature request.
+const MUSIG2_NONCE_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Maximum number of MuSig2 secret nonces that wait for their partial
+/// signature requests at the same time.
+const MAX_MUSIG2_SEC_NONCES: usize = 10_000;
+
+/// Health of a verifier, as reported by itself.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifierHealth {
+    pub xonly_public_key: XOnlyPublicKey,
+    /// Best block height of verifier's Bitcoin node.
+    pub block_height: u64,
+    /// Height of the last block that is indexed in verifier's database, if
+    /// any.
+    pub indexed_height: Option<u64>,
+}
 
 #[derive(Debug, Clone)]
 pub struct Verifier<R>
@@ -26,8 +66,19 @@ where
     db: VerifierDB,
     network: Network,
     confirmation_treshold: u32,
-    min_relay_fee: u64,
     user_takes_after: u32,
+    /// Deposit amounts that this verifier accepts.
+    deposit_denominations: Vec<u64>,
+    /// Set if verifiers sign with a MuSig2 aggregated key.
+    key_agg_ctx: Option<KeyAggContext>,
+    /// MuSig2 secret nonces waiting for their partial signature requests,
+    /// with the time they are generated at.
+    musig2_sec_nonces: Arc<Mutex<HashMap<TapSighash, (MuSigSecNonce, Instant)>>>,
+    /// Source of withdrawal evidence. Withdrawals are not signed without it.
+    rollup_client: Option<Arc<dyn RollupClient>>,
+    /// Held from the policy checks of a signature until it is recorded, so
+    /// that concurrent requests can't go over the policy limits together.
+    policy_lock: Arc<tokio::sync::Mutex<()>>,
 }
 
 impl<R> Verifier<R>
@@ -35,22 +86,27 @@ where
     R: RpcApiWrapper,
 {
     pub async fn new(rpc: ExtendedRpc<R>, config: BridgeConfig) -> Result<Self, BridgeError> {
-        let signer = Actor::new(config.secret_key, config.network);
+        let signer = Actor::from_config(&config)?;
 
-        let secp: Secp256k1<secp256k1::All> = Secp256k1::new();
-
-        let pk: secp256k1::PublicKey = config.secret_key.public_key(&secp);
-        let xonly_pk = XOnlyPublicKey::from(pk);
-
-        // Generated public key must be in given public key list.
-        if !config.verifiers_public_keys.contains(&xonly_pk) {
+        // Signer's public key must be in given public key list.
+        if !config
+            .verifiers_public_keys
+            .contains(&signer.xonly_public_key)
+        {
             return Err(BridgeError::PublicKeyNotFound);
         }
 
-        let db = VerifierDB::new(config.clone()).await;
+        let db = VerifierDB::new(config.clone()).await?;
 
-        let transaction_builder =
-            TransactionBuilder::new(config.verifiers_public_keys.clone(), config.network);
+        let transaction_builder = TransactionBuilder::from_config(&config)?;
+
+        let key_agg_ctx = if config.musig2 {
+            Some(KeyAggContext::from_xonly(&config.verifiers_public_keys)?)
+        } else {
+            None
+        };
+
+        let rollup_client = rollup::new_rollup_client(&config)?;
 
         Ok(Verifier {
             rpc,
@@ -59,41 +115,275 @@ where
             db,
             network: config.network,
             confirmation_treshold: config.confirmation_treshold,
-            min_relay_fee: config.min_relay_fee,
             user_takes_after: config.user_takes_after,
+            deposit_denominations: config.deposit_denominations,
+            key_agg_ctx,
+            musig2_sec_nonces: Arc::new(Mutex::new(HashMap::new())),
+            rollup_client,
+            policy_lock: Arc::new(tokio::sync::Mutex::new(())),
         })
     }
 
-    /// Operator only endpoint for verifier.
-    ///
-    /// 1. Check if the deposit UTXO is valid and finalized (6 blocks confirmation)
-    /// 2. Check if the UTXO is not already spent
-    /// 3. Give move signature and operator claim signatures
-    async fn new_deposit(
+    /// Checks the deposit amount and UTXO. Amounts that are not one of the
+    /// deposit denominations are refused.
+    fn check_deposit(
         &self,
         start_utxo: OutPoint,
         recovery_taproot_address: &Address<NetworkUnchecked>,
-        _deposit_index: u32,
         evm_address: &EVMAddress,
-        _operator_address: &Address,
-    ) -> Result<DepositPresigns, BridgeError> {
+        amount: u64,
+    ) -> Result<(), BridgeError> {
+        if !self.deposit_denominations.contains(&amount) {
+            return Err(BridgeError::InvalidDepositAmount(amount));
+        }
+
         self.rpc.check_deposit_utxo(
             &self.transaction_builder,
             &start_utxo,
             recovery_taproot_address,
             evm_address,
-            BRIDGE_AMOUNT_SATS,
+            amount,
             self.user_takes_after,
             self.confirmation_treshold,
-        )?;
+        )
+    }
 
-        let mut move_tx = self.transaction_builder.create_move_tx(
+    /// Checks the deposit, then creates the move tx that spends it.
+    fn create_checked_move_tx(
+        &self,
+        start_utxo: OutPoint,
+        recovery_taproot_address: &Address<NetworkUnchecked>,
+        evm_address: &EVMAddress,
+        amount: u64,
+    ) -> Result<CreateTxOutputs, BridgeError> {
+        self.check_deposit(start_utxo, recovery_taproot_address, evm_address, amount)?;
+
+        self.transaction_builder.create_move_tx(
             start_utxo,
             evm_address,
             recovery_taproot_address,
             self.user_takes_after,
+            amount,
+        )
+    }
+
+    /// Checks every deposit of a batch, then creates the move tx that spends
+    /// them all.
+    fn create_checked_batch_move_tx(
+        &self,
+        deposits: &[DepositRequest],
+    ) -> Result<CreateTxOutputs, BridgeError> {
+        let mut start_utxos = HashSet::new();
+        for deposit in deposits {
+            if !start_utxos.insert(deposit.start_utxo) {
+                return Err(BridgeError::InvalidBatch(format!(
+                    "Deposit {} is given twice",
+                    deposit.start_utxo
+                )));
+            }
+
+            self.check_deposit(
+                deposit.start_utxo,
+                &deposit.recovery_taproot_address,
+                &deposit.evm_address,
+                deposit.amount,
+            )?;
+        }
+
+        let deposits: Vec<_> = deposits
+            .iter()
+            .map(|deposit| {
+                (
+                    deposit.start_utxo,
+                    deposit.evm_address,
+                    deposit.recovery_taproot_address.clone(),
+                    deposit.amount,
+                )
+            })
+            .collect();
+
+        self.transaction_builder
+            .create_batch_move_tx(&deposits, self.user_takes_after)
+    }
+
+    /// Creates the withdrawal tx that spends given bridge funds.
+    fn create_withdrawal_tx(
+        &self,
+        bridge_fund_txid: Txid,
+        withdrawal_address: &Address<NetworkChecked>,
+    ) -> Result<CreateTxOutputs, BridgeError> {
+        let bridge_utxo = OutPoint {
+            txid: bridge_fund_txid,
+            vout: 0,
+        };
+
+        let bridge_txout = self.rpc.get_bridge_txout(
+            &self.transaction_builder,
+            &bridge_utxo,
+            &self.deposit_denominations,
         )?;
+
+        self.transaction_builder
+            .create_withdraw_tx(bridge_utxo, bridge_txout, withdrawal_address)
+    }
+
+    /// Creates the withdrawal tx of a batch, after checking that no withdrawal
+    /// or bridge UTXO is given twice.
+    fn create_batch_withdrawal_tx(
+        &self,
+        withdrawals: &[BatchWithdrawal],
+    ) -> Result<CreateTxOutputs, BridgeError> {
+        let mut idxs = HashSet::new();
+        let mut bridge_utxos = HashSet::new();
+        for withdrawal in withdrawals {
+            if !idxs.insert(withdrawal.idx) || !bridge_utxos.insert(withdrawal.bridge_utxo) {
+                return Err(BridgeError::InvalidBatch(format!(
+                    "Withdrawal {} is given twice",
+                    withdrawal.idx
+                )));
+            }
+        }
+
+        let withdrawals = withdrawals
+            .iter()
+            .map(|withdrawal| {
+                let bridge_txout = self.rpc.get_bridge_txout(
+                    &self.transaction_builder,
+                    &withdrawal.bridge_utxo,
+                    &self.deposit_denominations,
+                )?;
+                let withdrawal_address = withdrawal
+                    .withdrawal_address
+                    .clone()
+                    .require_network(self.network)?;
+
+                Ok((withdrawal.bridge_utxo, bridge_txout, withdrawal_address))
+            })
+            .collect::<Result<Vec<_>, BridgeError>>()?;
+
+        self.transaction_builder
+            .create_batch_withdraw_tx(&withdrawals)
+    }
+
+    fn key_agg_ctx(&self) -> Result<&KeyAggContext, BridgeError> {
+        self.key_agg_ctx
+            .as_ref()
+            .ok_or(BridgeError::MuSig2ModeMismatch)
+    }
+
+    /// Generates and stores a MuSig2 nonce for given input of the tx. Nonces
+    /// that are not used in [`MUSIG2_NONCE_TIMEOUT`] are dropped, and no more
+    /// than [`MAX_MUSIG2_SEC_NONCES`] of them are kept.
+    fn musig2_nonce(
+        &self,
+        tx: &mut CreateTxOutputs,
+        txin_index: usize,
+    ) -> Result<MuSigPubNonce, BridgeError> {
+        let key_agg_ctx = self.key_agg_ctx()?;
+        let sighash = self
+            .signer
+            .sighash_taproot_script_spend(tx, txin_index, 0)?;
+
+        let (sec_nonce, pub_nonce) = self.signer.musig2_nonce(key_agg_ctx, sighash)?;
+
+        let mut sec_nonces = self.musig2_sec_nonces.lock().unwrap();
+        sec_nonces.retain(|_, (_, created_at)| created_at.elapsed() < MUSIG2_NONCE_TIMEOUT);
+        if sec_nonces.len() >= MAX_MUSIG2_SEC_NONCES && !sec_nonces.contains_key(&sighash) {
+            return Err(BridgeError::MuSig2Error(
+                "Too many nonces are waiting for partial signature requests".to_string(),
+            ));
+        }
+        sec_nonces.insert(sighash, (sec_nonce, Instant::now()));
+
+        Ok(pub_nonce)
+    }
+
+    /// Partially signs given input of the tx with the nonce generated by
+    /// [`Verifier::musig2_nonce`]. The nonce is removed, so that it can't be
+    /// used again.
+    fn musig2_partial_sign(
+        &self,
+        tx: &mut CreateTxOutputs,
+        txin_index: usize,
+        agg_nonce: &MuSigAggNonce,
+    ) -> Result<MuSigPartialSignature, BridgeError> {
+        let key_agg_ctx = self.key_agg_ctx()?;
+        let sighash = self
+            .signer
+            .sighash_taproot_script_spend(tx, txin_index, 0)?;
+
+        let sec_nonce = match self.musig2_sec_nonces.lock().unwrap().remove(&sighash) {
+            Some((sec_nonce, created_at)) if created_at.elapsed() < MUSIG2_NONCE_TIMEOUT => {
+                sec_nonce
+            }
+            _ => return Err(BridgeError::MuSig2NonceNotFound),
+        };
+
+        self.signer
+            .musig2_partial_sign(key_agg_ctx, sec_nonce, agg_nonce, sighash)
+    }
+
+    /// Generates and stores a MuSig2 nonce for every input of given tx.
+    fn musig2_batch_nonces(
+        &self,
+        tx: &mut CreateTxOutputs,
+    ) -> Result<Vec<MuSigPubNonce>, BridgeError> {
+        (0..tx.tx.input.len())
+            .map(|i| self.musig2_nonce(tx, i))
+            .collect()
+    }
+
+    /// Partially signs every input of given tx, with an aggregated nonce for
+    /// each.
+    fn musig2_batch_partial_sign(
+        &self,
+        tx: &mut CreateTxOutputs,
+        agg_nonces: &[MuSigAggNonce],
+    ) -> Result<Vec<MuSigPartialSignature>, BridgeError> {
+        if agg_nonces.len() != tx.tx.input.len() {
+            return Err(BridgeError::InvalidBatch(format!(
+                "Expected {} aggregated nonces, got {}",
+                tx.tx.input.len(),
+                agg_nonces.len()
+            )));
+        }
+
+        agg_nonces
+            .iter()
+            .enumerate()
+            .map(|(i, agg_nonce)| self.musig2_partial_sign(tx, i, agg_nonce))
+            .collect()
+    }
+
+    /// Operator only endpoint for verifier.
+    ///
+    /// 1. Check if the deposit UTXO is valid and finalized (6 blocks confirmation)
+    /// 2. Check if the UTXO is not already spent
+    /// 3. Give move signature and operator claim signatures
+    async fn new_deposit(
+        &self,
+        start_utxo: OutPoint,
+        recovery_taproot_address: &Address<NetworkUnchecked>,
+        _deposit_index: u32,
+        evm_address: &EVMAddress,
+        amount: u64,
+        _operator_address: &Address,
+    ) -> Result<DepositPresigns, BridgeError> {
+        if self.key_agg_ctx.is_some() {
+            return Err(BridgeError::MuSig2ModeMismatch);
+        }
+
+        let mut move_tx =
+            self.create_checked_move_tx(start_utxo, recovery_taproot_address, evm_address, amount)?;
         let move_txid = move_tx.tx.compute_txid();
+        let deposit = DepositRequest {
+            start_utxo,
+            recovery_taproot_address: recovery_taproot_address.clone(),
+            evm_address: *evm_address,
+            amount,
+        };
+        let _policy_lock = self.policy_lock.lock().await;
+        policy::check_deposits(&self.db, std::slice::from_ref(&deposit)).await?;
 
         tracing::info!(
             "Verifier with public key {:?} is signing {:?}.",
@@ -104,6 +394,9 @@ where
         let move_sig = self
             .signer
             .sign_taproot_script_spend_tx_new(&mut move_tx, 0, 0)?;
+        self.db
+            .add_signed_deposits(&[SignedDeposit::from(&deposit)])
+            .await?;
 
         Ok(DepositPresigns {
             move_sign: move_sig,
@@ -111,57 +404,549 @@ where
         })
     }
 
+    /// Operator only endpoint for verifier. Does the same checks as
+    /// [`Verifier::new_deposit`] for every deposit of a batch and signs every
+    /// input of their move tx.
+    async fn new_deposit_batch(
+        &self,
+        deposits: &[DepositRequest],
+    ) -> Result<Vec<schnorr::Signature>, BridgeError> {
+        if self.key_agg_ctx.is_some() {
+            return Err(BridgeError::MuSig2ModeMismatch);
+        }
+
+        let mut move_tx = self.create_checked_batch_move_tx(deposits)?;
+        let _policy_lock = self.policy_lock.lock().await;
+        policy::check_deposits(&self.db, deposits).await?;
+
+        tracing::info!(
+            "Verifier with public key {:?} is signing batch move tx {:?}.",
+            self.signer.xonly_public_key.to_string(),
+            move_tx.tx.compute_txid()
+        );
+
+        let sigs = (0..deposits.len())
+            .map(|i| {
+                self.signer
+                    .sign_taproot_script_spend_tx_new(&mut move_tx, i, 0)
+            })
+            .collect::<Result<Vec<_>, BridgeError>>()?;
+        self.db
+            .add_signed_deposits(&deposits.iter().map(SignedDeposit::from).collect::<Vec<_>>())
+            .await?;
+
+        Ok(sigs)
+    }
+
+    async fn new_deposit_batch_nonce(
+        &self,
+        deposits: &[DepositRequest],
+    ) -> Result<Vec<MuSigPubNonce>, BridgeError> {
+        let mut move_tx = self.create_checked_batch_move_tx(deposits)?;
+        policy::check_deposits(&self.db, deposits).await?;
+
+        self.musig2_batch_nonces(&mut move_tx)
+    }
+
+    async fn new_deposit_batch_partial_sig(
+        &self,
+        deposits: &[DepositRequest],
+        agg_nonces: &[MuSigAggNonce],
+    ) -> Result<Vec<MuSigPartialSignature>, BridgeError> {
+        let mut move_tx = self.create_checked_batch_move_tx(deposits)?;
+        let _policy_lock = self.policy_lock.lock().await;
+        policy::check_deposits(&self.db, deposits).await?;
+
+        tracing::info!(
+            "Verifier with public key {:?} is partially signing batch move tx {:?}.",
+            self.signer.xonly_public_key.to_string(),
+            move_tx.tx.compute_txid()
+        );
+
+        let partial_sigs = self.musig2_batch_partial_sign(&mut move_tx, agg_nonces)?;
+        self.db
+            .add_signed_deposits(&deposits.iter().map(SignedDeposit::from).collect::<Vec<_>>())
+            .await?;
+
+        Ok(partial_sigs)
+    }
+
     async fn new_withdrawal_direct(
         &self,
         withdrawal_idx: usize,
         bridge_fund_txid: Txid,
         withdrawal_address: &Address<NetworkChecked>,
     ) -> Result<schnorr::Signature, BridgeError> {
-        // TODO: Check Citrea RPC if the withdrawal is already been made or not.
+        if self.key_agg_ctx.is_some() {
+            return Err(BridgeError::MuSig2ModeMismatch);
+        }
+
+        let entry = WithdrawalLedgerEntry {
+            idx: withdrawal_idx,
+            bridge_utxo: OutPoint {
+                txid: bridge_fund_txid,
+                vout: 0,
+            },
+            withdrawal_address: withdrawal_address.as_unchecked().clone(),
+        };
+        let _policy_lock = self.policy_lock.lock().await;
+        self.check_withdrawal_ledger(&entry).await?;
 
         if let Ok((db_bridge_fund_txid, sig)) =
             self.db.get_withdrawal_sig_by_idx(withdrawal_idx).await
         {
-            if db_bridge_fund_txid == bridge_fund_txid {
-                return Ok(sig);
-            } else {
+            if db_bridge_fund_txid != bridge_fund_txid {
+                return Err(BridgeError::AlreadySpentWithdrawal);
+            }
+
+            // Withdrawals that were signed before the ledger existed are
+            // bound to the address that their signature is for.
+            let mut withdrawal_tx =
+                self.create_withdrawal_tx(bridge_fund_txid, withdrawal_address)?;
+            let sighash = self
+                .signer
+                .sighash_taproot_script_spend(&mut withdrawal_tx, 0, 0)?;
+            if SECP
+                .verify_schnorr(
+                    &sig,
+                    &Message::from_digest(sighash.to_byte_array()),
+                    &self.signer.xonly_public_key,
+                )
+                .is_err()
+            {
+                tracing::error!(
+                    "ALERT: Withdrawal {} of bridge funds {} is asked to {}, but it is signed for another address",
+                    withdrawal_idx,
+                    bridge_fund_txid,
+                    withdrawal_address
+                );
                 return Err(BridgeError::AlreadySpentWithdrawal);
             }
+
+            self.record_withdrawal(&entry).await?;
+            return Ok(sig);
         };
+        policy::check_withdrawals(&self.db, &[withdrawal_idx]).await?;
+
+        self.check_rollup_withdrawal(withdrawal_idx, entry.bridge_utxo, &entry.withdrawal_address)
+            .await?;
 
         tracing::info!(
             "Verifier is signing withdrawal transaction with TXID: {:?}",
             bridge_fund_txid
         );
 
-        let bridge_utxo = OutPoint {
-            txid: bridge_fund_txid,
-            vout: 0,
+        let mut withdrawal_tx = self.create_withdrawal_tx(bridge_fund_txid, withdrawal_address)?;
+
+        let sig = self
+            .signer
+            .sign_taproot_script_spend_tx_new(&mut withdrawal_tx, 0, 0)?;
+
+        self.record_withdrawal(&entry).await?;
+        self.db
+            .save_withdrawal_sig(withdrawal_idx, bridge_fund_txid, sig)
+            .await?;
+
+        Ok(sig)
+    }
+
+    /// Operator only endpoint for verifier, for MuSig2 mode. Does the same
+    /// checks as [`Verifier::new_deposit`] and returns a nonce for the move tx.
+    async fn new_deposit_nonce(
+        &self,
+        start_utxo: OutPoint,
+        recovery_taproot_address: &Address<NetworkUnchecked>,
+        evm_address: &EVMAddress,
+        amount: u64,
+    ) -> Result<MuSigPubNonce, BridgeError> {
+        let mut move_tx =
+            self.create_checked_move_tx(start_utxo, recovery_taproot_address, evm_address, amount)?;
+        let deposit = DepositRequest {
+            start_utxo,
+            recovery_taproot_address: recovery_taproot_address.clone(),
+            evm_address: *evm_address,
+            amount,
         };
+        policy::check_deposits(&self.db, &[deposit]).await?;
 
-        let (bridge_address, _) = self.transaction_builder.generate_bridge_address()?;
+        self.musig2_nonce(&mut move_tx, 0)
+    }
 
-        let dust_value = script_builder::anyone_can_spend_txout().value;
-        let bridge_txout = TxOut {
-            value: Amount::from_sat(BRIDGE_AMOUNT_SATS - self.min_relay_fee) - dust_value,
-            script_pubkey: bridge_address.script_pubkey(),
+    async fn new_deposit_partial_sig(
+        &self,
+        start_utxo: OutPoint,
+        recovery_taproot_address: &Address<NetworkUnchecked>,
+        evm_address: &EVMAddress,
+        amount: u64,
+        agg_nonce: &MuSigAggNonce,
+    ) -> Result<MuSigPartialSignature, BridgeError> {
+        let mut move_tx =
+            self.create_checked_move_tx(start_utxo, recovery_taproot_address, evm_address, amount)?;
+        let deposit = DepositRequest {
+            start_utxo,
+            recovery_taproot_address: recovery_taproot_address.clone(),
+            evm_address: *evm_address,
+            amount,
         };
+        let _policy_lock = self.policy_lock.lock().await;
+        policy::check_deposits(&self.db, std::slice::from_ref(&deposit)).await?;
 
-        let mut withdrawal_tx = self.transaction_builder.create_withdraw_tx(
-            bridge_utxo,
-            bridge_txout,
-            withdrawal_address,
+        tracing::info!(
+            "Verifier with public key {:?} is partially signing {:?}.",
+            self.signer.xonly_public_key.to_string(),
+            move_tx.tx.compute_txid()
+        );
+
+        let partial_sig = self.musig2_partial_sign(&mut move_tx, 0, agg_nonce)?;
+        self.db
+            .add_signed_deposits(&[SignedDeposit::from(&deposit)])
+            .await?;
+
+        Ok(partial_sig)
+    }
+
+    /// Checks if the withdrawal is made and finalized on rollup, for the
+    /// deposit amount of the bridge funds that it spends.
+    async fn check_rollup_withdrawal(
+        &self,
+        withdrawal_idx: usize,
+        bridge_utxo: OutPoint,
+        withdrawal_address: &Address<NetworkUnchecked>,
+    ) -> Result<(), BridgeError> {
+        let rollup_client = self
+            .rollup_client
+            .as_ref()
+            .ok_or(BridgeError::RollupClientNotConfigured)?;
+
+        let (_, amount) = self.rpc.get_bridge_deposit(
+            &self.transaction_builder,
+            &bridge_utxo,
+            &self.deposit_denominations,
         )?;
 
-        let sig = self
-            .signer
-            .sign_taproot_script_spend_tx_new(&mut withdrawal_tx, 0, 0)?;
+        if !rollup_client
+            .is_withdrawal_finalized(withdrawal_idx, withdrawal_address, amount)
+            .await?
+        {
+            return Err(BridgeError::WithdrawalNotFinalized(withdrawal_idx));
+        }
+
+        Ok(())
+    }
+
+    /// Checks if the withdrawal index is already bound to other bridge funds.
+    /// Single withdrawals always spend the first output of the move tx.
+    async fn check_musig2_withdrawal(
+        &self,
+        withdrawal_idx: usize,
+        bridge_utxo: OutPoint,
+    ) -> Result<(), BridgeError> {
+        if let Ok(db_bridge_fund_txid) = self.db.get_musig2_withdrawal_txid(withdrawal_idx).await {
+            if OutPoint::new(db_bridge_fund_txid, 0) != bridge_utxo {
+                return Err(BridgeError::AlreadySpentWithdrawal);
+            }
+        };
+
+        Ok(())
+    }
+
+    /// Checks that the withdrawal index and the bridge UTXO are not bound to
+    /// other withdrawals in the ledger. A conflict means that the bridge UTXO
+    /// is asked to be spent twice, so it raises an alert.
+    async fn check_withdrawal_ledger(
+        &self,
+        entry: &WithdrawalLedgerEntry,
+    ) -> Result<(), BridgeError> {
+        let conflict = self
+            .db
+            .get_withdrawal_ledger_entries(entry.idx, entry.bridge_utxo)
+            .await?
+            .into_iter()
+            .find(|ledger_entry| ledger_entry != entry);
+
+        match conflict {
+            Some(conflict) => {
+                let message = format!(
+                    "Withdrawal {} of bridge UTXO {} to {} conflicts with withdrawal {} of bridge UTXO {} to {}",
+                    entry.idx,
+                    entry.bridge_utxo,
+                    entry.withdrawal_address.assume_checked_ref(),
+                    conflict.idx,
+                    conflict.bridge_utxo,
+                    conflict.withdrawal_address.assume_checked_ref()
+                );
+                tracing::error!("ALERT: {}", message);
+
+                Err(BridgeError::WithdrawalConflict(message))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Binds the withdrawal index, bridge UTXO and withdrawal address to each
+    /// other in the ledger, before the withdrawal is signed.
+    async fn record_withdrawal(&self, entry: &WithdrawalLedgerEntry) -> Result<(), BridgeError> {
+        self.db.add_withdrawal_ledger_entry(entry).await?;
+
+        self.check_withdrawal_ledger(entry).await
+    }
+
+    async fn new_withdrawal_nonce(
+        &self,
+        withdrawal_idx: usize,
+        bridge_fund_txid: Txid,
+        withdrawal_address: &Address<NetworkChecked>,
+    ) -> Result<MuSigPubNonce, BridgeError> {
+        let entry = WithdrawalLedgerEntry {
+            idx: withdrawal_idx,
+            bridge_utxo: OutPoint {
+                txid: bridge_fund_txid,
+                vout: 0,
+            },
+            withdrawal_address: withdrawal_address.as_unchecked().clone(),
+        };
+        self.check_musig2_withdrawal(withdrawal_idx, entry.bridge_utxo)
+            .await?;
+        self.check_withdrawal_ledger(&entry).await?;
+        policy::check_withdrawals(&self.db, &[withdrawal_idx]).await?;
+        self.check_rollup_withdrawal(withdrawal_idx, entry.bridge_utxo, &entry.withdrawal_address)
+            .await?;
 
+        let mut withdrawal_tx = self.create_withdrawal_tx(bridge_fund_txid, withdrawal_address)?;
+
+        self.musig2_nonce(&mut withdrawal_tx, 0)
+    }
+
+    async fn new_withdrawal_partial_sig(
+        &self,
+        withdrawal_idx: usize,
+        bridge_fund_txid: Txid,
+        withdrawal_address: &Address<NetworkChecked>,
+        agg_nonce: &MuSigAggNonce,
+    ) -> Result<MuSigPartialSignature, BridgeError> {
+        let entry = WithdrawalLedgerEntry {
+            idx: withdrawal_idx,
+            bridge_utxo: OutPoint {
+                txid: bridge_fund_txid,
+                vout: 0,
+            },
+            withdrawal_address: withdrawal_address.as_unchecked().clone(),
+        };
+        let _policy_lock = self.policy_lock.lock().await;
+        self.check_musig2_withdrawal(withdrawal_idx, entry.bridge_utxo)
+            .await?;
+        self.check_withdrawal_ledger(&entry).await?;
+        policy::check_withdrawals(&self.db, &[withdrawal_idx]).await?;
+        self.check_rollup_withdrawal(withdrawal_idx, entry.bridge_utxo, &entry.withdrawal_address)
+            .await?;
+
+        tracing::info!(
+            "Verifier is partially signing withdrawal transaction with TXID: {:?}",
+            bridge_fund_txid
+        );
+
+        let mut withdrawal_tx = self.create_withdrawal_tx(bridge_fund_txid, withdrawal_address)?;
+
+        let partial_sig = self.musig2_partial_sign(&mut withdrawal_tx, 0, agg_nonce)?;
+        self.record_withdrawal(&entry).await?;
         self.db
-            .save_withdrawal_sig(withdrawal_idx, bridge_fund_txid, sig)
+            .save_musig2_withdrawal(withdrawal_idx, bridge_fund_txid)
             .await?;
 
-        Ok(sig)
+        Ok(partial_sig)
+    }
+
+    /// Operator only endpoint for verifier. Signs every input of a batch
+    /// withdrawal tx, after doing the same checks as
+    /// [`Verifier::new_withdrawal_direct`] for each withdrawal. Signatures
+    /// are only valid for this batch, so they are not saved; withdrawals
+    /// are recorded in the ledger instead.
+    async fn new_withdrawal_batch(
+        &self,
+        withdrawals: &[BatchWithdrawal],
+    ) -> Result<Vec<schnorr::Signature>, BridgeError> {
+        if self.key_agg_ctx.is_some() {
+            return Err(BridgeError::MuSig2ModeMismatch);
+        }
+
+        let mut withdrawal_tx = self.create_batch_withdrawal_tx(withdrawals)?;
+        let _policy_lock = self.policy_lock.lock().await;
+
+        // Withdrawals that are already signed stay bound to the same bridge
+        // UTXO and address, so they can be batched again if their tx is not
+        // mined.
+        for withdrawal in withdrawals {
+            self.check_withdrawal_ledger(&WithdrawalLedgerEntry::from(withdrawal))
+                .await?;
+            if let Ok((db_bridge_fund_txid, _)) =
+                self.db.get_withdrawal_sig_by_idx(withdrawal.idx).await
+            {
+                if OutPoint::new(db_bridge_fund_txid, 0) != withdrawal.bridge_utxo {
+                    return Err(BridgeError::AlreadySpentWithdrawal);
+                }
+            }
+
+            self.check_rollup_withdrawal(
+                withdrawal.idx,
+                withdrawal.bridge_utxo,
+                &withdrawal.withdrawal_address,
+            )
+            .await?;
+        }
+        let idxs = withdrawals
+            .iter()
+            .map(|withdrawal| withdrawal.idx)
+            .collect::<Vec<_>>();
+        policy::check_withdrawals(&self.db, &idxs).await?;
+
+        tracing::info!(
+            "Verifier is signing batch withdrawal transaction with TXID: {:?}",
+            withdrawal_tx.tx.compute_txid()
+        );
+
+        let sigs = (0..withdrawals.len())
+            .map(|i| {
+                self.signer
+                    .sign_taproot_script_spend_tx_new(&mut withdrawal_tx, i, 0)
+            })
+            .collect::<Result<Vec<_>, BridgeError>>()?;
+        for withdrawal in withdrawals {
+            self.record_withdrawal(&WithdrawalLedgerEntry::from(withdrawal))
+                .await?;
+        }
+
+        Ok(sigs)
+    }
+
+    async fn new_withdrawal_batch_nonce(
+        &self,
+        withdrawals: &[BatchWithdrawal],
+    ) -> Result<Vec<MuSigPubNonce>, BridgeError> {
+        for withdrawal in withdrawals {
+            self.check_musig2_withdrawal(withdrawal.idx, withdrawal.bridge_utxo)
+                .await?;
+            self.check_withdrawal_ledger(&WithdrawalLedgerEntry::from(withdrawal))
+                .await?;
+            self.check_rollup_withdrawal(
+                withdrawal.idx,
+                withdrawal.bridge_utxo,
+                &withdrawal.withdrawal_address,
+            )
+            .await?;
+        }
+        let idxs = withdrawals
+            .iter()
+            .map(|withdrawal| withdrawal.idx)
+            .collect::<Vec<_>>();
+        policy::check_withdrawals(&self.db, &idxs).await?;
+
+        let mut withdrawal_tx = self.create_batch_withdrawal_tx(withdrawals)?;
+
+        self.musig2_batch_nonces(&mut withdrawal_tx)
+    }
+
+    async fn new_withdrawal_batch_partial_sig(
+        &self,
+        withdrawals: &[BatchWithdrawal],
+        agg_nonces: &[MuSigAggNonce],
+    ) -> Result<Vec<MuSigPartialSignature>, BridgeError> {
+        let _policy_lock = self.policy_lock.lock().await;
+        for withdrawal in withdrawals {
+            self.check_musig2_withdrawal(withdrawal.idx, withdrawal.bridge_utxo)
+                .await?;
+            self.check_withdrawal_ledger(&WithdrawalLedgerEntry::from(withdrawal))
+                .await?;
+            self.check_rollup_withdrawal(
+                withdrawal.idx,
+                withdrawal.bridge_utxo,
+                &withdrawal.withdrawal_address,
+            )
+            .await?;
+        }
+        let idxs = withdrawals
+            .iter()
+            .map(|withdrawal| withdrawal.idx)
+            .collect::<Vec<_>>();
+        policy::check_withdrawals(&self.db, &idxs).await?;
+
+        let mut withdrawal_tx = self.create_batch_withdrawal_tx(withdrawals)?;
+
+        tracing::info!(
+            "Verifier is partially signing batch withdrawal transaction with TXID: {:?}",
+            withdrawal_tx.tx.compute_txid()
+        );
+
+        let partial_sigs = self.musig2_batch_partial_sign(&mut withdrawal_tx, agg_nonces)?;
+        for withdrawal in withdrawals {
+            self.record_withdrawal(&WithdrawalLedgerEntry::from(withdrawal))
+                .await?;
+        }
+
+        Ok(partial_sigs)
+    }
+
+    /// Raises an alert for every bridge UTXO that is spent without a
+    /// withdrawal signature from this verifier. Runs until chain watcher is
+    /// gone.
+    pub async fn watch_bridge_spends(self, mut chain_events: broadcast::Receiver<ChainEvent>) {
+        loop {
+            match chain_events.recv().await {
+                Ok(ChainEvent::BridgeUtxoSpent {
+                    outpoint,
+                    spending_txid,
+                    ..
+                }) => match self.db.is_withdrawal_signed(outpoint).await {
+                    Ok(true) => {}
+                    Ok(false) => tracing::error!(
+                        "ALERT: Bridge UTXO {} is spent by {} without a withdrawal signature from this verifier",
+                        outpoint,
+                        spending_txid
+                    ),
+                    Err(e) => tracing::error!(
+                        "Failed to check withdrawal signature of bridge UTXO {}: {}",
+                        outpoint,
+                        e
+                    ),
+                },
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(count)) => {
+                    tracing::warn!("Missed {} chain events", count)
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            }
+        }
+    }
+
+    /// Co-signs operator's proof of reserves report after checking every
+    /// listed UTXO with verifier's own node.
+    pub fn sign_reserves_report(
+        &self,
+        report: &ReservesReport,
+    ) -> Result<schnorr::Signature, BridgeError> {
+        proof_of_reserves::check_report(&self.rpc, &self.transaction_builder, report)?;
+
+        tracing::info!(
+            "Signing proof of reserves of {} sats at block {}",
+            report.total,
+            report.block_hash
+        );
+        self.signer.sign_digest(report.digest())
+    }
+
+    /// Checks that verifier can reach its Bitcoin node and database.
+    pub async fn health(&self) -> Result<VerifierHealth, BridgeError> {
+        let block_height = self.rpc.get_block_count()?;
+        let indexed_height = self
+            .db
+            .get_last_indexed_block()
+            .await?
+            .map(|(height, _)| height);
+
+        Ok(VerifierHealth {
+            xonly_public_key: self.signer.xonly_public_key,
+            block_height,
+            indexed_height,
+        })
     }
 }
 
@@ -176,6 +961,7 @@ where
         recovery_taproot_address: Address<NetworkUnchecked>,
         deposit_index: u32,
         evm_address: EVMAddress,
+        amount: u64,
         operator_address: Address<NetworkUnchecked>,
     ) -> Result<DepositPresigns, BridgeError> {
         let operator_address = operator_address.require_network(self.network)?;
@@ -185,6 +971,7 @@ where
             &recovery_taproot_address,
             deposit_index,
             &evm_address,
+            amount,
             &operator_address,
         )
         .await
@@ -201,6 +988,193 @@ where
         self.new_withdrawal_direct(withdrawal_idx, bridge_fund_txid, &withdrawal_address)
             .await
     }
+
+    async fn new_deposit_nonce_rpc(
+        &self,
+        start_utxo: OutPoint,
+        recovery_taproot_address: Address<NetworkUnchecked>,
+        evm_address: EVMAddress,
+        amount: u64,
+    ) -> Result<MuSigPubNonce, BridgeError> {
+        self.new_deposit_nonce(start_utxo, &recovery_taproot_address, &evm_address, amount)
+            .await
+    }
+
+    async fn new_deposit_partial_sig_rpc(
+        &self,
+        start_utxo: OutPoint,
+        recovery_taproot_address: Address<NetworkUnchecked>,
+        evm_address: EVMAddress,
+        amount: u64,
+        agg_nonce: MuSigAggNonce,
+    ) -> Result<MuSigPartialSignature, BridgeError> {
+        self.new_deposit_partial_sig(
+            start_utxo,
+            &recovery_taproot_address,
+            &evm_address,
+            amount,
+            &agg_nonce,
+        )
+        .await
+    }
+
+    async fn new_withdrawal_nonce_rpc(
+        &self,
+        withdrawal_idx: usize,
+        bridge_fund_txid: Txid,
+        withdrawal_address: Address<NetworkUnchecked>,
+    ) -> Result<MuSigPubNonce, BridgeError> {
+        let withdrawal_address = withdrawal_address.require_network(self.network)?;
+
+        self.new_withdrawal_nonce(withdrawal_idx, bridge_fund_txid, &withdrawal_address)
+            .await
+    }
+
+    async fn new_withdrawal_partial_sig_rpc(
+        &self,
+        withdrawal_idx: usize,
+        bridge_fund_txid: Txid,
+        withdrawal_address: Address<NetworkUnchecked>,
+        agg_nonce: MuSigAggNonce,
+    ) -> Result<MuSigPartialSignature, BridgeError> {
+        let withdrawal_address = withdrawal_address.require_network(self.network)?;
+
+        self.new_withdrawal_partial_sig(
+            withdrawal_idx,
+            bridge_fund_txid,
+            &withdrawal_address,
+            &agg_nonce,
+        )
+        .await
+    }
+
+    async fn new_deposit_batch_rpc(
+        &self,
+        deposits: Vec<DepositRequest>,
+    ) -> Result<Vec<schnorr::Signature>, BridgeError> {
+        self.new_deposit_batch(&deposits).await
+    }
+
+    async fn new_deposit_batch_nonce_rpc(
+        &self,
+        deposits: Vec<DepositRequest>,
+    ) -> Result<Vec<MuSigPubNonce>, BridgeError> {
+        self.new_deposit_batch_nonce(&deposits).await
+    }
+
+    async fn new_deposit_batch_partial_sig_rpc(
+        &self,
+        deposits: Vec<DepositRequest>,
+        agg_nonces: Vec<MuSigAggNonce>,
+    ) -> Result<Vec<MuSigPartialSignature>, BridgeError> {
+        self.new_deposit_batch_partial_sig(&deposits, &agg_nonces)
+            .await
+    }
+
+    async fn new_withdrawal_batch_rpc(
+        &self,
+        withdrawals: Vec<BatchWithdrawal>,
+    ) -> Result<Vec<schnorr::Signature>, BridgeError> {
+        self.new_withdrawal_batch(&withdrawals).await
+    }
+
+    async fn new_withdrawal_batch_nonce_rpc(
+        &self,
+        withdrawals: Vec<BatchWithdrawal>,
+    ) -> Result<Vec<MuSigPubNonce>, BridgeError> {
+        self.new_withdrawal_batch_nonce(&withdrawals).await
+    }
+
+    async fn new_withdrawal_batch_partial_sig_rpc(
+        &self,
+        withdrawals: Vec<BatchWithdrawal>,
+        agg_nonces: Vec<MuSigAggNonce>,
+    ) -> Result<Vec<MuSigPartialSignature>, BridgeError> {
+        self.new_withdrawal_batch_partial_sig(&withdrawals, &agg_nonces)
+            .await
+    }
+
+    async fn sign_reserves_report_rpc(
+        &self,
+        report: ReservesReport,
+    ) -> Result<schnorr::Signature, BridgeError> {
+        self.sign_reserves_report(&report)
+    }
+
+    async fn health_rpc(&self) -> Result<VerifierHealth, BridgeError> {
+        self.health().await
+    }
+}
+
+#[async_trait]
+impl<R> VerifierAdminRpcServer for Verifier<R>
+where
+    R: RpcApiWrapper,
+{
+    async fn get_policy_rpc(&self) -> Result<SigningPolicy, BridgeError> {
+        self.db.get_signing_policy().await
+    }
+
+    async fn set_policy_rpc(&self, policy: SigningPolicy) -> Result<(), BridgeError> {
+        tracing::info!("Signing policy is set to {:?}", policy);
+
+        self.db.set_signing_policy(&policy).await
+    }
+
+    async fn policy_usage_rpc(&self) -> Result<PolicyUsage, BridgeError> {
+        policy::usage(&self.db).await
+    }
+
+    async fn list_addresses_rpc(&self) -> Result<Vec<ListedAddress>, BridgeError> {
+        self.db.get_policy_addresses().await
+    }
+
+    async fn add_address_rpc(
+        &self,
+        list: AddressList,
+        address: PolicyAddress,
+    ) -> Result<(), BridgeError> {
+        tracing::info!("Adding {:?} to {} list", address, list.as_str());
+
+        self.db
+            .add_policy_address(&ListedAddress { list, address })
+            .await
+    }
+
+    async fn remove_address_rpc(
+        &self,
+        list: AddressList,
+        address: PolicyAddress,
+    ) -> Result<bool, BridgeError> {
+        tracing::info!("Removing {:?} from {} list", address, list.as_str());
+
+        self.db
+            .remove_policy_address(&ListedAddress { list, address })
+            .await
+    }
+
+    async fn list_approvals_rpc(
+        &self,
+        status: Option<ApprovalStatus>,
+    ) -> Result<Vec<DepositApproval>, BridgeError> {
+        self.db.list_deposit_approvals(status).await
+    }
+
+    async fn approve_deposit_rpc(&self, start_utxo: OutPoint) -> Result<(), BridgeError> {
+        tracing::info!("Deposit {} is approved", start_utxo);
+
+        self.db
+            .set_deposit_approval_status(start_utxo, ApprovalStatus::Approved)
+            .await
+    }
+
+    async fn reject_deposit_rpc(&self, start_utxo: OutPoint) -> Result<(), BridgeError> {
+        tracing::info!("Deposit {} is rejected", start_utxo);
+
+        self.db
+            .set_deposit_approval_status(start_utxo, ApprovalStatus::Rejected)
+            .await
+    }
 }
 
 #[cfg(feature = "poc")]
@@ -0,0 +1,316 @@
+//! # Verifier Pool
+//!
+//! Operator's connections to verifiers. Requests are sent to every verifier
+//! in parallel; a request that times out or can't reach its verifier is
+//! retried with exponential backoff. A verifier that still fails is named in
+//! the returned [`BridgeError::VerifierError`] by its index.
+//!
+//! Verifiers are also checked with their `health` method on a schedule, and
+//! the result of the last check is kept for operator's `verifier_status`
+//! method and metrics.
+
+use crate::config::BridgeConfig;
+use crate::errors::BridgeError;
+use crate::metrics;
+use crate::rpc_auth::VerifierClient;
+use crate::traits::rpc::VerifierRpcClient;
+use crate::verifier::VerifierHealth;
+use futures::stream::FuturesOrdered;
+use futures::{StreamExt, TryStreamExt};
+use jsonrpsee::core::client::Error as ClientError;
+use secp256k1::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Timeout of a request to a verifier in seconds, if not configured.
+pub const DEFAULT_VERIFIER_TIMEOUT_SECS: u64 = 60;
+/// Number of retries of a request to a verifier, if not configured.
+pub const DEFAULT_VERIFIER_RETRIES: u32 = 2;
+/// Delay before the first retry in milliseconds, if not configured.
+pub const DEFAULT_VERIFIER_RETRY_BACKOFF_MS: u64 = 500;
+/// Time between health checks of verifiers in seconds, if not configured.
+pub const DEFAULT_HEALTH_CHECK_INTERVAL_SECS: u64 = 30;
+
+/// Status of a verifier, as of operator's last health check.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifierStatus {
+    /// Index of the verifier in operator's verifier list.
+    pub idx: usize,
+    /// Whether the verifier passed the last health check.
+    pub up: bool,
+    /// Report of the last passed health check.
+    pub health: Option<VerifierHealth>,
+    /// Error of the last failed health check.
+    pub error: Option<String>,
+    /// Unix time of the last health check, or nothing if verifier is not
+    /// checked yet.
+    pub checked_at: Option<u64>,
+}
+
+/// Verifier clients of the operator. Clones share the health status.
+#[derive(Debug, Clone)]
+pub struct VerifierPool {
+    verifiers: Vec<VerifierClient>,
+    /// Public keys of verifiers, in the same order with `verifiers`.
+    verifiers_public_keys: Vec<XOnlyPublicKey>,
+    retries: u32,
+    retry_backoff: Duration,
+    status: Arc<RwLock<Vec<VerifierStatus>>>,
+}
+
+impl VerifierPool {
+    pub fn new(verifiers: Vec<VerifierClient>, config: &BridgeConfig) -> Self {
+        let status = (0..verifiers.len())
+            .map(|idx| VerifierStatus {
+                idx,
+                up: false,
+                health: None,
+                error: None,
+                checked_at: None,
+            })
+            .collect();
+
+        Self {
+            verifiers,
+            verifiers_public_keys: config.verifiers_public_keys.clone(),
+            retries: config.verifier_retries.unwrap_or(DEFAULT_VERIFIER_RETRIES),
+            retry_backoff: Duration::from_millis(
+                config
+                    .verifier_retry_backoff_ms
+                    .unwrap_or(DEFAULT_VERIFIER_RETRY_BACKOFF_MS),
+            ),
+            status: Arc::new(RwLock::new(status)),
+        }
+    }
+
+    /// Sends a request to every verifier in parallel and returns their
+    /// responses, in the same order with verifiers. Fails with the first
+    /// verifier that fails, after retries.
+    pub async fn request<'a, T, F, Fut>(&'a self, request: F) -> Result<Vec<T>, BridgeError>
+    where
+        F: Fn(&'a VerifierClient) -> Fut,
+        Fut: Future<Output = Result<T, ClientError>>,
+    {
+        let request = &request;
+
+        self.verifiers
+            .iter()
+            .enumerate()
+            .map(|(idx, verifier)| async move {
+                retry(self.retries, self.retry_backoff, || request(verifier))
+                    .await
+                    .map_err(|e| {
+                        tracing::error!("Verifier {} failed: {}", idx, e);
+                        BridgeError::VerifierError(idx, e)
+                    })
+            })
+            .collect::<FuturesOrdered<_>>()
+            .try_collect()
+            .await
+    }
+
+    /// Returns the status of every verifier.
+    pub fn status(&self) -> Vec<VerifierStatus> {
+        self.status.read().unwrap().clone()
+    }
+
+    /// Checks the health of every verifier, in parallel.
+    pub async fn check_health(&self) {
+        let checks = self
+            .verifiers
+            .iter()
+            .enumerate()
+            .map(|(idx, verifier)| async move { (idx, self.check_verifier(idx, verifier).await) })
+            .collect::<FuturesOrdered<_>>()
+            .collect::<Vec<_>>()
+            .await;
+
+        let checked_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or_default();
+
+        let mut status = self.status.write().unwrap();
+        for (idx, res) in checks {
+            let status = &mut status[idx];
+            match res {
+                Ok(health) => {
+                    if !status.up {
+                        tracing::info!("Verifier {} is up", idx);
+                    }
+                    status.up = true;
+                    status.health = Some(health);
+                    status.error = None;
+                }
+                Err(e) => {
+                    if status.up || status.checked_at.is_none() {
+                        tracing::warn!("Verifier {} is down: {}", idx, e);
+                    }
+                    status.up = false;
+                    status.error = Some(e.to_string());
+                }
+            }
+            status.checked_at = Some(checked_at);
+
+            metrics::VERIFIER_UP.set(&[&idx.to_string()], if status.up { 1.0 } else { 0.0 });
+        }
+    }
+
+    /// Checks the health of a verifier and that it is the expected one.
+    async fn check_verifier(
+        &self,
+        idx: usize,
+        verifier: &VerifierClient,
+    ) -> Result<VerifierHealth, BridgeError> {
+        let health = verifier
+            .health_rpc()
+            .await
+            .map_err(|e| BridgeError::VerifierError(idx, e))?;
+
+        match self.verifiers_public_keys.get(idx) {
+            Some(xonly_pk) if *xonly_pk != health.xonly_public_key => {
+                Err(BridgeError::VerifierError(
+                    idx,
+                    ClientError::Custom(format!(
+                        "Unexpected public key {}",
+                        health.xonly_public_key
+                    )),
+                ))
+            }
+            _ => Ok(health),
+        }
+    }
+
+    /// Checks the health of every verifier forever, once in every
+    /// `interval_secs` seconds.
+    pub async fn run_health_checks(self, interval_secs: u64) {
+        loop {
+            self.check_health().await;
+
+            tokio::time::sleep(Duration::from_secs(interval_secs)).await;
+        }
+    }
+}
+
+/// Checks if a failed request might succeed when it is sent again: It timed
+/// out or didn't reach the verifier. Errors that are returned by the verifier
+/// are not retried.
+fn is_retryable(error: &ClientError) -> bool {
+    matches!(
+        error,
+        ClientError::Transport(_) | ClientError::RequestTimeout | ClientError::RestartNeeded(_)
+    )
+}
+
+/// Sends a request until it succeeds, fails with an error that is not
+/// retryable or is retried `retries` times. Delay between attempts starts
+/// with `backoff` and is doubled after every attempt.
+async fn retry<T, F, Fut>(retries: u32, backoff: Duration, request: F) -> Result<T, ClientError>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<T, ClientError>>,
+{
+    let mut backoff = backoff;
+
+    for _ in 0..retries {
+        match request().await {
+            Err(e) if is_retryable(&e) => {
+                tracing::warn!("Retrying verifier request in {:?}: {}", backoff, e);
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+            }
+            res => return res,
+        }
+    }
+
+    request().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{retry, VerifierPool};
+    use crate::actor::Actor;
+    use crate::config::BridgeConfig;
+    use crate::errors::BridgeError;
+    use crate::rpc_auth;
+    use crate::traits::rpc::VerifierRpcClient;
+    use jsonrpsee::core::client::Error as ClientError;
+    use jsonrpsee::types::ErrorObject;
+    use secp256k1::SecretKey;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn retry_requests() {
+        let attempts = AtomicU32::new(0);
+        let res = retry(3, Duration::from_millis(1), || async {
+            match attempts.fetch_add(1, Ordering::SeqCst) {
+                0 | 1 => Err(ClientError::RequestTimeout),
+                _ => Ok(0x45),
+            }
+        })
+        .await;
+        assert_eq!(res.unwrap(), 0x45);
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+        // Gives up after retries.
+        attempts.store(0, Ordering::SeqCst);
+        let res: Result<(), _> = retry(2, Duration::from_millis(1), || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err(ClientError::RequestTimeout)
+        })
+        .await;
+        assert!(matches!(res, Err(ClientError::RequestTimeout)));
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+        // Errors of the verifier are not retried.
+        attempts.store(0, Ordering::SeqCst);
+        let res: Result<(), _> = retry(2, Duration::from_millis(1), || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err(ClientError::Call(ErrorObject::owned(
+                -30000,
+                "UTXOSpent",
+                None::<()>,
+            )))
+        })
+        .await;
+        assert!(matches!(res, Err(ClientError::Call(_))));
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn unreachable_verifier() {
+        let config = BridgeConfig {
+            verifier_retries: Some(1),
+            verifier_retry_backoff_ms: Some(1),
+            ..BridgeConfig::default()
+        };
+        let signer = Actor::new(SecretKey::from_slice(&[1u8; 32]).unwrap(), config.network);
+
+        // Nothing listens on the discard port.
+        let verifier = rpc_auth::verifier_client("http://127.0.0.1:9/", signer, &config).unwrap();
+        let pool = VerifierPool::new(vec![verifier.clone(), verifier], &config);
+
+        let res = pool
+            .request(|verifier| async { verifier.health_rpc().await })
+            .await;
+        assert!(matches!(
+            res,
+            Err(BridgeError::VerifierError(0, ClientError::Transport(_)))
+        ));
+
+        assert!(pool
+            .status()
+            .iter()
+            .all(|status| status.checked_at.is_none()));
+        pool.check_health().await;
+        let status = pool.status();
+        assert_eq!(status.len(), 2);
+        assert_eq!(status[1].idx, 1);
+        assert!(!status[1].up);
+        assert!(status[1].error.is_some());
+        assert!(status[1].checked_at.is_some());
+    }
+}
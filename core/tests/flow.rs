@@ -2,12 +2,16 @@
 //!
 //! This testss checks if basic deposit and withdraw operations are OK or not.
 
-use bitcoin::{Address, Amount};
+use bitcoin::{Address, Amount, Transaction};
+use bitcoin_mock_rpc::RpcApiWrapper;
 use clementine_circuits::constants::BRIDGE_AMOUNT_SATS;
 use clementine_core::actor::Actor;
-use clementine_core::database::common::Database;
+use clementine_core::config::BridgeConfig;
+use clementine_core::database::common::{Database, DepositStatus};
 use clementine_core::extended_rpc::ExtendedRpc;
 use clementine_core::mock::common;
+use clementine_core::mock::rollup::MockRollup;
+use clementine_core::rollup::RollupWithdrawal;
 use clementine_core::script_builder;
 use clementine_core::servers::*;
 use clementine_core::traits::rpc::OperatorRpcClient;
@@ -18,8 +22,48 @@ use clementine_core::EVMAddress;
 use clementine_core::{
     create_extended_rpc, create_test_config, create_test_config_with_thread_name,
 };
+use jsonrpsee::server::ServerHandle;
 use std::thread;
 
+/// Withdrawal amount is what's left from a deposit after its shares of move
+/// and withdrawal tx fees and their anyone-can-spend outputs. Both txs are
+/// presigned, so they pay 1 sat/vB for their size on chain, shared equally
+/// between their inputs.
+fn expected_withdraw_amount<R: RpcApiWrapper>(
+    rpc: &ExtendedRpc<R>,
+    withdraw_tx: &Transaction,
+) -> Amount {
+    let move_txid = withdraw_tx.input[0].previous_output.txid;
+    let move_tx = rpc.get_raw_transaction(&move_txid, None).unwrap();
+
+    let fee_share = |tx: &Transaction| {
+        let total_fee = tx.vsize() as u64 + script_builder::anyone_can_spend_txout().value.to_sat();
+
+        Amount::from_sat(total_fee.div_ceil(tx.input.len() as u64))
+    };
+
+    Amount::from_sat(BRIDGE_AMOUNT_SATS) - fee_share(&move_tx) - fee_share(withdraw_tx)
+}
+
+/// Starts a mock rollup and points verifiers of the given config to it.
+async fn start_mock_rollup(config: &mut BridgeConfig) -> (MockRollup, ServerHandle) {
+    let rollup = MockRollup::default();
+    let (url, handle) = rollup.start_server().await.unwrap();
+    config.rollup_rpc_url = Some(url);
+
+    (rollup, handle)
+}
+
+/// Makes the withdrawal of a deposit with given index final on the mock rollup.
+fn finalize_rollup_withdrawal(rollup: &MockRollup, idx: usize, withdrawal_address: &Address) {
+    rollup.add_withdrawal(RollupWithdrawal {
+        idx,
+        withdrawal_address: withdrawal_address.as_unchecked().clone(),
+        amount: BRIDGE_AMOUNT_SATS,
+        finalized: true,
+    });
+}
+
 #[tokio::test]
 async fn test_flow_1() {
     let mut config = create_test_config_with_thread_name!("test_config_flow_1.toml");
@@ -39,11 +83,16 @@ async fn test_flow_1() {
             "test_config_flow_1.toml"
         );
     }
+    let (rollup, _rollup_handle) = start_mock_rollup(&mut config).await;
 
     let (operator_client, _operator_handler, _results) =
         create_operator_and_verifiers(config.clone(), rpc.clone()).await;
     let secp = bitcoin::secp256k1::Secp256k1::new();
-    let (xonly_pk, _) = config.secret_key.public_key(&secp).x_only_public_key();
+    let (xonly_pk, _) = config
+        .secret_key
+        .unwrap()
+        .public_key(&secp)
+        .x_only_public_key();
     let taproot_address = Address::p2tr(&secp, xonly_pk, None, config.network);
     let tx_builder = TransactionBuilder::new(config.verifiers_public_keys.clone(), config.network);
 
@@ -83,13 +132,39 @@ async fn test_flow_1() {
                 deposit_utxo,
                 taproot_address.as_unchecked().clone(),
                 evm_addresses[idx],
+                BRIDGE_AMOUNT_SATS,
             )
             .await
             .unwrap();
         tracing::debug!("Output #{}: {:#?}", idx, output);
+
+        let status = operator_client
+            .deposit_status_rpc(deposit_utxo)
+            .await
+            .unwrap();
+        assert_eq!(status, DepositStatus::MoveBroadcast);
+
+        let deposit = operator_client.get_deposit_rpc(deposit_utxo).await.unwrap();
+        assert_eq!(deposit.evm_address, evm_addresses[idx]);
+        assert_eq!(
+            deposit.move_tx.map(|move_tx| move_tx.compute_txid()),
+            output
+        );
     }
 
+    let deposits = operator_client
+        .list_deposits_rpc(None, Some(DepositStatus::MoveBroadcast), 0, 100)
+        .await
+        .unwrap();
+    assert_eq!(deposits.len(), deposit_addresses.len());
+
+    // Operator checks verifiers' health as soon as it starts.
+    let verifiers = operator_client.verifier_status_rpc().await.unwrap();
+    assert_eq!(verifiers.len(), config.verifiers_public_keys.len() - 1);
+    assert!(verifiers.iter().all(|status| status.up));
+
     let withdrawal_address = Address::p2tr(&secp, xonly_pk, None, config.network);
+    finalize_rollup_withdrawal(&rollup, 0, &withdrawal_address);
 
     // This index is 3 since when testing the unit tests complete first and the index=1,2 is not sane
     let withdraw_txid = operator_client
@@ -108,12 +183,17 @@ async fn test_flow_1() {
     let rpc_withdraw_amount = tx.output[0].value;
     let expected_withdraw_script = withdrawal_address.script_pubkey();
     assert_eq!(rpc_withdraw_script, expected_withdraw_script);
-    let anyone_can_spend_amount = script_builder::anyone_can_spend_txout().value;
 
     // check if the amounts match
-    let expected_withdraw_amount = Amount::from_sat(BRIDGE_AMOUNT_SATS - 2 * config.min_relay_fee)
-        - anyone_can_spend_amount * 2;
+    let expected_withdraw_amount = expected_withdraw_amount(&rpc, &tx);
     assert_eq!(expected_withdraw_amount, rpc_withdraw_amount);
+
+    let withdrawals = operator_client
+        .list_withdrawals_rpc(None, 0, 100)
+        .await
+        .unwrap();
+    assert_eq!(withdrawals.len(), 1);
+    assert_eq!(withdrawals[0].withdrawal_tx.compute_txid(), withdraw_txid);
 }
 
 #[tokio::test]
@@ -139,7 +219,11 @@ async fn test_flow_2() {
     let (_operator_client, _operator_handler, _results) =
         create_operator_and_verifiers(config.clone(), rpc.clone()).await;
     let secp = bitcoin::secp256k1::Secp256k1::new();
-    let (xonly_pk, _) = config.secret_key.public_key(&secp).x_only_public_key();
+    let (xonly_pk, _) = config
+        .secret_key
+        .unwrap()
+        .public_key(&secp)
+        .x_only_public_key();
     let taproot_address = Address::p2tr(&secp, xonly_pk, None, config.network);
     tracing::debug!(
         "Taproot address script pubkey: {:#?}",
@@ -169,7 +253,7 @@ async fn test_flow_2() {
         .unwrap();
     tracing::debug!("Deposit UTXO: {:#?}", deposit_utxo);
     rpc.mine_blocks(config.user_takes_after as u64 + 2).unwrap();
-    let signer = Actor::new(config.secret_key, config.network);
+    let signer = Actor::new(config.secret_key.unwrap(), config.network);
     let anyone_can_spend_txout = script_builder::anyone_can_spend_txout();
     let tx_ins = TransactionBuilder::create_tx_ins_with_sequence(
         vec![deposit_utxo],
@@ -241,8 +325,13 @@ async fn verifier_down_for_withdrawal_signature() {
             "test_config_verifier_down_for_withdrawal_signature.toml"
         );
     }
+    let (rollup, _rollup_handle) = start_mock_rollup(&mut config).await;
 
-    let (xonly_pk, _) = config.secret_key.public_key(&SECP).x_only_public_key();
+    let (xonly_pk, _) = config
+        .secret_key
+        .unwrap()
+        .public_key(&SECP)
+        .x_only_public_key();
     let taproot_address = Address::p2tr(&SECP, xonly_pk, None, config.network);
 
     let tx_builder = TransactionBuilder::new(config.verifiers_public_keys.clone(), config.network);
@@ -285,6 +374,7 @@ async fn verifier_down_for_withdrawal_signature() {
                 deposit_utxo,
                 taproot_address.as_unchecked().clone(),
                 evm_addresses[idx],
+                BRIDGE_AMOUNT_SATS,
             )
             .await
             .unwrap();
@@ -296,6 +386,7 @@ async fn verifier_down_for_withdrawal_signature() {
     results.get(VERIFIER_IDX).unwrap().1.stop().unwrap();
 
     let withdrawal_address = Address::p2tr(&SECP, xonly_pk, None, config.network);
+    finalize_rollup_withdrawal(&rollup, 0, &withdrawal_address);
 
     if let Ok(_withdraw_txid) = operator_client
         .new_withdrawal_direct_rpc(0, withdrawal_address.as_unchecked().clone())
@@ -330,8 +421,87 @@ async fn verifier_down_for_withdrawal_signature() {
     assert_eq!(rpc_withdraw_script, expected_withdraw_script);
 
     // check if the amounts match
-    let anyone_can_spend_amount = script_builder::anyone_can_spend_txout().value;
-    let expected_withdraw_amount = Amount::from_sat(BRIDGE_AMOUNT_SATS - 2 * config.min_relay_fee)
-        - anyone_can_spend_amount * 2;
+    let expected_withdraw_amount = expected_withdraw_amount(&rpc, &tx);
     assert_eq!(expected_withdraw_amount, rpc_withdraw_amount);
 }
+
+#[tokio::test]
+async fn test_musig2_flow() {
+    let mut config = create_test_config_with_thread_name!("test_config_flow_musig2.toml");
+    let rpc = create_extended_rpc!(config);
+
+    // Create temporary databases for testing.
+    let handle = thread::current()
+        .name()
+        .unwrap()
+        .split(':')
+        .last()
+        .unwrap()
+        .to_owned();
+    for i in 0..4 {
+        create_test_config!(
+            handle.clone() + i.to_string().as_str(),
+            "test_config_flow_musig2.toml"
+        );
+    }
+    let (rollup, _rollup_handle) = start_mock_rollup(&mut config).await;
+
+    let (operator_client, _operator_handler, _results) =
+        create_operator_and_verifiers(config.clone(), rpc.clone()).await;
+    let (xonly_pk, _) = config
+        .secret_key
+        .unwrap()
+        .public_key(&SECP)
+        .x_only_public_key();
+    let taproot_address = Address::p2tr(&SECP, xonly_pk, None, config.network);
+    let tx_builder = TransactionBuilder::from_config(&config).unwrap();
+
+    let evm_address = EVMAddress([1u8; 20]);
+    let (deposit_address, _) = tx_builder
+        .generate_deposit_address(
+            taproot_address.as_unchecked(),
+            &evm_address,
+            BRIDGE_AMOUNT_SATS,
+            config.user_takes_after,
+        )
+        .unwrap();
+
+    let deposit_utxo = rpc
+        .send_to_address(&deposit_address, BRIDGE_AMOUNT_SATS)
+        .unwrap();
+    rpc.mine_blocks(18).unwrap();
+
+    let move_txid = operator_client
+        .new_deposit_rpc(
+            deposit_utxo,
+            taproot_address.as_unchecked().clone(),
+            evm_address,
+            BRIDGE_AMOUNT_SATS,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+    tracing::debug!("Move TXID: {:#?}", move_txid);
+
+    // A MuSig2 spend carries a single aggregated signature.
+    let move_tx = rpc.get_raw_transaction(&move_txid, None).unwrap();
+    assert_eq!(move_tx.input[0].witness.len(), 3);
+
+    let withdrawal_address = Address::p2tr(&SECP, xonly_pk, None, config.network);
+    finalize_rollup_withdrawal(&rollup, 0, &withdrawal_address);
+    let withdraw_txid = operator_client
+        .new_withdrawal_direct_rpc(0, withdrawal_address.as_unchecked().clone())
+        .await
+        .unwrap();
+    tracing::debug!("Withdrawal TXID: {:#?}", withdraw_txid);
+
+    let tx = rpc.get_raw_transaction(&withdraw_txid, None).unwrap();
+    assert_eq!(tx.input[0].witness.len(), 3);
+    assert_eq!(
+        tx.output[0].script_pubkey,
+        withdrawal_address.script_pubkey()
+    );
+
+    let expected_withdraw_amount = expected_withdraw_amount(&rpc, &tx);
+    assert_eq!(expected_withdraw_amount, tx.output[0].value);
+}
@@ -22,7 +22,11 @@ async fn run() {
     let mut config = create_test_config_with_thread_name!("test_config_taproot.toml");
     let rpc = create_extended_rpc!(config);
 
-    let (xonly_pk, _) = config.secret_key.public_key(&secp).x_only_public_key();
+    let (xonly_pk, _) = config
+        .secret_key
+        .unwrap()
+        .public_key(&secp)
+        .x_only_public_key();
     println!("x only pub key: {:?}", xonly_pk);
 
     let address = Address::p2tr(&secp, xonly_pk, None, config.network);
@@ -71,7 +75,7 @@ async fn run() {
 
     let tx = TransactionBuilder::create_btc_tx(ins, tx_outs.clone());
 
-    let signer = Actor::new(config.secret_key, config.network);
+    let signer = Actor::new(config.secret_key.unwrap(), config.network);
 
     let mut tx_details = CreateTxOutputs {
         tx: tx.clone(),
@@ -133,8 +137,12 @@ async fn taproot_key_path_spend() {
     let mut config = create_test_config_with_thread_name!("test_config_taproot.toml");
     let rpc = create_extended_rpc!(config);
 
-    let (xonly_pk, _) = config.secret_key.public_key(&secp).x_only_public_key();
-    let actor = Actor::new(config.secret_key, config.network);
+    let (xonly_pk, _) = config
+        .secret_key
+        .unwrap()
+        .public_key(&secp)
+        .x_only_public_key();
+    let actor = Actor::new(config.secret_key.unwrap(), config.network);
 
     let address = Address::p2tr(&secp, xonly_pk, None, config.network);
     const INPUT_AMOUNT: u64 = 600;
@@ -188,7 +196,7 @@ async fn taproot_key_path_spend_2() {
     let mut config = create_test_config_with_thread_name!("test_config_taproot.toml");
     let rpc = create_extended_rpc!(config);
 
-    let actor = Actor::new(config.secret_key, config.network);
+    let actor = Actor::new(config.secret_key.unwrap(), config.network);
 
     let address = actor.address.clone();
 
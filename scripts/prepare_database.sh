@@ -3,11 +3,12 @@
 # This script isn't a strict requirement for preparing database. One can take
 # this only as a reference.
 
-SCRIPT_DIR=$( cd -- "$( dirname -- "${BASH_SOURCE[0]}" )" &> /dev/null && pwd )
-
 echo "Preparing database for $PGDATABASE"
 
 dropdb -U $PGUSER $PGDATABASE
 createdb -U $PGUSER -O $PGUSER $PGDATABASE
 
-cat $SCRIPT_DIR/schema.sql | psql -U $PGUSER $PGDATABASE
+# Schema is created by migrations in core/migrations. Binaries apply them with
+# the migrate subcommand, e.g.:
+#
+#   cargo run --bin verifier -- config.toml migrate